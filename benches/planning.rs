@@ -0,0 +1,54 @@
+//! Benchmarks of planning a sort, with a dry run of 100k synthetic files.
+//!
+//! Run with `cargo bench`. The files are generated with a [`Workload`] in the
+//! temporary directory the first time, and reused after that.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use sorterylib::prelude::*;
+use sorterylib::testing::Workload;
+use std::{env, fs, time::Duration};
+
+/// The number of files to plan.
+const FILES: usize = 100_000;
+
+/// The number of files that share each modification time, so that their names
+/// collide and they get sequential names.
+const PER_SECOND: usize = 10;
+
+/// Return the source directory with the synthetic files in it, creating them if
+/// they aren't there yet.
+fn source() -> File {
+    let source = File::from(env::temp_dir()).join(String::from("sorterylib_bench_planning"));
+    if fs::read_dir(source.to_path_buf()).map_or(0, |d| d.count()) == FILES {
+        return source;
+    }
+
+    let _ = fs::remove_dir_all(source.to_path_buf());
+    let workload = Workload {
+        files: FILES,
+        start: 1_689_330_612,
+        spread: (FILES / PER_SECOND) as u64,
+        per_timestamp: PER_SECOND,
+        ..Default::default()
+    };
+    workload.generate(&source).expect("Failed to generate bench files.");
+    source
+}
+
+/// Benchmark a dry run of the synthetic files.
+fn bench_planning(c: &mut Criterion) {
+    let sorter = Sorter {
+        source: source(),
+        target: File::from(env::temp_dir()).join(String::from("sorterylib_bench_planning_target")),
+        ..Default::default()
+    };
+    fs::create_dir_all(sorter.target.to_path_buf()).expect("Failed to create bench dir.");
+
+    let mut group = c.benchmark_group("planning");
+    group.sample_size(10).measurement_time(Duration::from_secs(30));
+    group.bench_function("dry_run_100k", |b| b.iter(|| sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.")));
+    group.finish();
+}
+
+criterion_group!(benches, bench_planning);
+criterion_main!(benches);
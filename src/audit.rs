@@ -0,0 +1,167 @@
+//! Spotting files whose dates can't be right. See [`audit`] and
+//! [`Sorter::audit_dates`](crate::Sorter::audit_dates).
+//!
+//! A file whose timestamp was lost or reset usually gets the Unix epoch, or the
+//! DOS one (midnight, 1 January 1980) of ZIP archives, and a camera whose clock
+//! was never set can date its photos years before it was even made, or far into
+//! the future. Sorted by those dates, the files are buried in folders like
+//! `1970/01/`, where nobody thinks to look for them.
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Timelike};
+use crate::structs::File;
+
+#[cfg(test)]
+/// Tests for auditing dates. Each test is named after the function it tests,
+/// prefixed with test.
+mod tests {
+
+    use chrono::{Duration, Local, TimeZone};
+    use super::{check, release_year, SuspiciousDate};
+
+    #[test]
+    /// Test [`release_year`]
+    fn test_release_year() {
+        assert_eq!(release_year("iPhone 12 Pro Max"), Some(2020));
+        assert_eq!(release_year("iphone xs"), Some(2018));
+        assert_eq!(release_year("Canon EOS 5D Mark III"), Some(2012));
+        assert_eq!(release_year("Canon EOS 5D"), Some(2005));
+        assert_eq!(release_year("iPhone 120"), Some(2007));
+        assert_eq!(release_year("Kodak DC40"), None);
+    }
+
+    #[test]
+    /// Test [`check`]
+    fn test_check() {
+        let now = Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let date = |y, mo, d, h| Local.with_ymd_and_hms(y, mo, d, h, 0, 0).unwrap();
+
+        // The epochs, and anything before the Unix one
+        assert_eq!(check(&Local.timestamp_opt(0, 0).unwrap(), &now, None), Some(SuspiciousDate::Epoch));
+        assert_eq!(check(&date(1965, 3, 1, 12), &now, None), Some(SuspiciousDate::Epoch));
+        assert_eq!(check(&date(1980, 1, 1, 0), &now, None), Some(SuspiciousDate::Epoch));
+        assert_eq!(check(&date(1980, 1, 1, 9), &now, None), None);
+
+        // Dates in the future, beyond the differences between time zones
+        assert_eq!(check(&(now + Duration::hours(12)), &now, None), None);
+        assert_eq!(check(&date(2031, 1, 1, 0), &now, None), Some(SuspiciousDate::Future));
+
+        // Dates before the camera was made, or before any camera wrote EXIF
+        assert_eq!(check(&date(2019, 6, 1, 12), &now, Some("iPhone 12")), Some(SuspiciousDate::BeforeCamera(2020)));
+        assert_eq!(check(&date(2021, 6, 1, 12), &now, Some("iPhone 12")), None);
+        assert_eq!(check(&date(1990, 6, 1, 12), &now, Some("Kodak DC40")), Some(SuspiciousDate::BeforeCamera(1995)));
+        assert_eq!(check(&date(1990, 6, 1, 12), &now, None), None);
+    }
+}
+
+/// Why the date of a file can't be right. See [`audit`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SuspiciousDate {
+    /// The date is the Unix epoch, or earlier, or the DOS epoch, which is what
+    /// lost or reset timestamps usually get.
+    Epoch,
+    /// The date is in the future.
+    Future,
+    /// The date is before this year, in which the camera named in the file's
+    /// EXIF metadata came out (see [`CAMERA_YEARS`]). Cameras that aren't
+    /// listed came out in [`EXIF_YEAR`] at the earliest.
+    BeforeCamera(i32)
+}
+
+/// The year the first cameras wrote EXIF metadata, which no photo with a camera
+/// in its metadata can be older than.
+pub const EXIF_YEAR: i32 = 1995;
+
+/// How far into the future a date can be before it is suspicious, to allow for
+/// files from other time zones.
+pub const FUTURE_MARGIN_HOURS: i64 = 24;
+
+/// The years that some common cameras and phones came out, by the model names
+/// in their EXIF metadata. Longer models with the same start, as in
+/// `iPhone 12 Pro`, are taken to have come out with them.
+pub const CAMERA_YEARS: [(&str, i32); 36] = [
+    ("iPhone", 2007),
+    ("iPhone 3G", 2008),
+    ("iPhone 3GS", 2009),
+    ("iPhone 4", 2010),
+    ("iPhone 4S", 2011),
+    ("iPhone 5", 2012),
+    ("iPhone 5s", 2013),
+    ("iPhone 6", 2014),
+    ("iPhone 6s", 2015),
+    ("iPhone 7", 2016),
+    ("iPhone 8", 2017),
+    ("iPhone X", 2017),
+    ("iPhone XS", 2018),
+    ("iPhone XR", 2018),
+    ("iPhone 11", 2019),
+    ("iPhone 12", 2020),
+    ("iPhone 13", 2021),
+    ("iPhone 14", 2022),
+    ("iPhone 15", 2023),
+    ("iPhone 16", 2024),
+    ("Pixel", 2016),
+    ("Pixel 2", 2017),
+    ("Pixel 3", 2018),
+    ("Pixel 4", 2019),
+    ("Pixel 5", 2020),
+    ("Pixel 6", 2021),
+    ("Pixel 7", 2022),
+    ("Pixel 8", 2023),
+    ("Canon EOS 5D", 2005),
+    ("Canon EOS 5D Mark II", 2008),
+    ("Canon EOS 5D Mark III", 2012),
+    ("Canon EOS 5D Mark IV", 2016),
+    ("NIKON D700", 2008),
+    ("NIKON D800", 2012),
+    ("NIKON D850", 2017),
+    ("ILCE-7M3", 2018),
+];
+
+/// Return the year the camera `model` came out, from the longest of
+/// [`CAMERA_YEARS`] that it starts with, as a whole word, ignoring case.
+pub fn release_year(model: &str) -> Option<i32> {
+    let model = model.trim().to_lowercase();
+    CAMERA_YEARS.iter()
+        .filter(|(name, _)| {
+            let name = name.to_lowercase();
+            model.strip_prefix(&name).is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+        })
+        .max_by_key(|(name, _)| name.len())
+        .map(|(_, year)| *year)
+}
+
+/// Return why `date` can't be the date of a file, if it can't be, at the time
+/// `now`. `camera` is the model of the camera named in the file's EXIF
+/// metadata, if it has one.
+pub fn check(date: &DateTime<Local>, now: &DateTime<Local>, camera: Option<&str>) -> Option<SuspiciousDate> {
+    let dos_epoch = NaiveDate::from_ymd_opt(1980, 1, 1);
+    if date.timestamp() < 24 * 60 * 60
+        || (Some(date.date_naive()) == dos_epoch && date.num_seconds_from_midnight() == 0) {
+        return Some(SuspiciousDate::Epoch);
+    }
+    if *date > *now + Duration::hours(FUTURE_MARGIN_HOURS) {
+        return Some(SuspiciousDate::Future);
+    }
+    let released = release_year(camera?).unwrap_or(EXIF_YEAR);
+    (date.year() < released).then_some(SuspiciousDate::BeforeCamera(released))
+}
+
+/// Return why `date` can't be the date of `path`, if it can't be. The EXIF
+/// metadata of `path` is only read if the date could be before its camera.
+pub fn audit(path: &File, date: &DateTime<Local>) -> Option<SuspiciousDate> {
+    let latest = CAMERA_YEARS.iter().map(|(_, year)| *year).max().unwrap_or(EXIF_YEAR);
+    let camera = if date.year() < latest { camera_model(path) } else { None };
+    check(date, &Local::now(), camera.as_deref())
+}
+
+/// Return the model of the camera named in the EXIF metadata of `path`.
+#[cfg(feature = "exif")]
+fn camera_model(path: &File) -> Option<String> {
+    crate::exif::read(path)?.model
+}
+
+/// Return no camera, since it can't be read without the `exif` feature.
+#[cfg(not(feature = "exif"))]
+fn camera_model(_path: &File) -> Option<String> {
+    None
+}
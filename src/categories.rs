@@ -0,0 +1,188 @@
+//! Classifying files into named categories by their extensions.
+//!
+//! The `{category}` template placeholder is filled in with the name of the first
+//! [`Category`] that lists a file's extension, so one [`Sorter`](crate::Sorter)
+//! can sort images, documents, and so on into their own trees, with a `dir_format`
+//! like `"{category}/%Y/%m/"`.
+//!
+//! Extensions that aren't in any of the user's categories are looked up in
+//! [`DEFAULT_CATEGORIES`], so `{category}` works without any configuration. To
+//! override a built-in category, list the extension in a category of your own;
+//! to extend one, add a category of the same name.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+/// Tests for categories. Each test is named after the function or struct it tests,
+/// prefixed with test.
+mod tests {
+
+    use std::collections::HashSet;
+    use super::{category_name, default_categories, Category, DEFAULT_CATEGORIES};
+
+    #[test]
+    /// Test [`Category`] and [`category_name`]
+    fn test_category_name() {
+        let categories = vec![
+            Category::new("Images", &["jpg", "png", "heic"]),
+            Category::new("Documents", &["pdf", "docx"]),
+            Category::new("Graphics", &["png", "svg"])
+        ];
+        assert!(categories[0].contains("JPG"));
+        assert_eq!(category_name("heic", &categories), "Images");
+        assert_eq!(category_name("PDF", &categories), "Documents");
+        assert_eq!(category_name("png", &categories), "Images");
+        assert_eq!(category_name("exe", &categories), "Installers");
+        assert_eq!(category_name("MP4", &categories), "Videos");
+        assert_eq!(category_name("unknownext", &categories), "Other");
+        assert_eq!(category_name("", &categories), "Other");
+    }
+
+    #[test]
+    /// Test [`DEFAULT_CATEGORIES`] and [`default_categories`]
+    fn test_default_categories() {
+
+        // Each extension is in only one category, and is lowercase
+        let mut seen = HashSet::new();
+        for (_, extensions) in DEFAULT_CATEGORIES {
+            for extension in extensions {
+                assert!(seen.insert(*extension), "{} is in more than one category", extension);
+                assert_eq!(*extension, extension.to_lowercase());
+            }
+        }
+        assert!(seen.len() > 300);
+        assert_eq!(default_categories().len(), DEFAULT_CATEGORIES.len());
+    }
+}
+
+/// The value of the `{category}` placeholder for files that aren't in any category.
+pub const UNCATEGORIZED: &str = "Other";
+
+/// A named category of files, like `Images` or `Documents`.
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Category {
+    /// The name of the category, used as its folder name, as in `"Images"`.
+    pub name: String,
+    /// The extensions of the files in the category, without the dot, as in
+    /// `["jpg", "png", "heic"]`. Matched ignoring case.
+    pub extensions: Vec<String>
+}
+impl Category {
+
+    /// Return a new [`Category`] named `name`, with the extensions `extensions`.
+    pub fn new(name: &str, extensions: &[&str]) -> Category {
+        Category {
+            name: String::from(name),
+            extensions: extensions.iter().map(|e| String::from(*e)).collect()
+        }
+    }
+
+    /// Return [`true`] if `extension` is one of our extensions.
+    pub fn contains(&self, extension: &str) -> bool {
+        self.extensions.iter().any(|e| e.eq_ignore_ascii_case(extension))
+    }
+}
+
+/// The built-in categories, used for extensions that aren't in any of the user's
+/// categories. Each is a category name and its lowercase extensions.
+pub const DEFAULT_CATEGORIES: [(&str, &[&str]); 19] = [
+    ("Images", &[
+        "jpg", "jpeg", "jpe", "jfif", "png", "gif", "bmp", "dib", "tif", "tiff", "webp",
+        "heic", "heif", "avif", "jxl", "ico", "icns", "svg", "svgz", "psd", "psb", "xcf",
+        "kra", "ora", "ai", "eps", "tga", "dds", "exr", "hdr", "pcx", "ppm", "pgm",
+        "pbm", "pnm", "qoi", "jp2", "j2k", "wbmp"
+    ]),
+    ("Raw Photos", &[
+        "raw", "dng", "cr2", "cr3", "crw", "nef", "nrw", "arw", "srf", "sr2", "orf",
+        "rw2", "raf", "pef", "ptx", "srw", "x3f", "3fr", "fff", "iiq", "mef", "mos",
+        "mrw", "rwl", "erf", "kdc", "dcr"
+    ]),
+    ("Videos", &[
+        "mp4", "m4v", "mov", "avi", "mkv", "webm", "wmv", "flv", "f4v", "mpg", "mpeg",
+        "mpe", "m2v", "m2ts", "mts", "vob", "3gp", "3g2", "ogv", "asf", "rm", "rmvb",
+        "divx", "mxf", "dv", "insv", "lrv"
+    ]),
+    ("Audio", &[
+        "mp3", "wav", "flac", "aac", "m4a", "m4b", "ogg", "oga", "opus", "wma", "aif",
+        "aiff", "aifc", "alac", "ape", "mid", "midi", "amr", "au", "caf", "ra", "wv",
+        "dsf", "dff", "mka", "ac3", "dts"
+    ]),
+    ("Documents", &[
+        "pdf", "doc", "docx", "docm", "dot", "dotx", "odt", "ott", "rtf", "txt", "md",
+        "markdown", "rst", "tex", "pages", "wpd", "wps", "xps", "oxps", "djvu", "log"
+    ]),
+    ("Spreadsheets", &[
+        "xls", "xlsx", "xlsm", "xlsb", "xlt", "xltx", "ods", "ots", "csv", "tsv",
+        "numbers"
+    ]),
+    ("Presentations", &[
+        "ppt", "pptx", "pptm", "pps", "ppsx", "pot", "potx", "odp", "otp", "key"
+    ]),
+    ("Ebooks", &[
+        "epub", "mobi", "azw", "azw3", "kf8", "fb2", "lit", "cbr", "cbz", "cb7",
+        "ibooks"
+    ]),
+    ("Archives", &[
+        "zip", "rar", "7z", "tar", "gz", "tgz", "bz2", "tbz2", "xz", "txz", "zst", "lz",
+        "lzma", "lz4", "cab", "arj", "ace", "z", "cpio", "sit", "sitx", "war"
+    ]),
+    ("Disk Images", &[
+        "iso", "img", "dmg", "vhd", "vhdx", "vmdk", "qcow2", "vdi", "toast"
+    ]),
+    ("Installers", &[
+        "exe", "msi", "msix", "appx", "apk", "aab", "deb", "rpm", "pkg", "mpkg",
+        "appimage", "snap", "flatpakref"
+    ]),
+    ("Code", &[
+        "c", "h", "cpp", "cc", "cxx", "hpp", "hh", "cs", "java", "kt", "kts", "scala",
+        "go", "rs", "py", "pyw", "rb", "php", "pl", "pm", "lua", "swift", "m", "mm", "r",
+        "jl", "dart", "ex", "exs", "erl", "hs", "ml", "fs", "clj", "js", "mjs", "cjs",
+        "ts", "tsx", "jsx", "vue", "svelte", "html", "htm", "css", "scss", "sass",
+        "less", "sh", "bash", "zsh", "fish", "ps1", "bat", "cmd", "sql", "ipynb"
+    ]),
+    ("Data", &[
+        "json", "jsonl", "xml", "yaml", "yml", "toml", "ini", "cfg", "conf", "db",
+        "sqlite", "sqlite3", "mdb", "accdb", "parquet", "avro", "feather", "h5", "hdf5",
+        "nc", "sav", "dta"
+    ]),
+    ("Fonts", &[
+        "ttf", "otf", "woff", "woff2", "eot", "fon", "pfb", "pfm"
+    ]),
+    ("3D Models", &[
+        "obj", "fbx", "stl", "blend", "dae", "3ds", "gltf", "glb", "ply", "usd", "usdz",
+        "max", "ma", "mb", "c4d", "skp", "step", "stp", "iges", "igs"
+    ]),
+    ("Email", &[
+        "eml", "msg", "mbox", "pst", "ost", "emlx", "vcf", "ics"
+    ]),
+    ("Torrents", &[
+        "torrent"
+    ]),
+    ("Subtitles", &[
+        "srt", "sub", "ass", "ssa", "vtt", "sbv"
+    ]),
+    ("GPS Tracks", &[
+        "gpx", "kml", "kmz", "fit", "tcx"
+    ])
+];
+
+/// Return [`DEFAULT_CATEGORIES`] as a [`Vec<Category>`], as a base for building
+/// customized categories.
+pub fn default_categories() -> Vec<Category> {
+    DEFAULT_CATEGORIES.iter().map(|(name, extensions)| Category::new(name, extensions)).collect()
+}
+
+/// Return the name of the first of `categories` that contains `extension`, or of
+/// its category in [`DEFAULT_CATEGORIES`] if none of them do. Return
+/// [`UNCATEGORIZED`] if it isn't in any category.
+pub fn category_name<'c>(extension: &str, categories: &'c [Category]) -> &'c str {
+    let extension = extension.to_lowercase();
+    categories.iter()
+        .find(|c| c.contains(&extension))
+        .map(|c| c.name.as_str())
+        .or_else(|| DEFAULT_CATEGORIES.iter()
+            .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+            .map(|(name, _)| *name))
+        .unwrap_or(UNCATEGORIZED)
+}
@@ -0,0 +1,274 @@
+//! Hashing the contents of files, with a choice of algorithms. See [`HashAlgorithm`].
+//!
+//! Hashing many files is slow, and mostly waiting on reads. A [`HashQueue`]
+//! hashes them on a pool of threads of its own, ahead of whatever is done with
+//! them, so that hashing overlaps with moving the files rather than holding it
+//! up. See [`Sorter::hash_workers`](crate::Sorter::hash_workers).
+
+use crate::structs::File;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "sha256")]
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::sync::{Condvar, Mutex, PoisonError};
+use std::thread;
+use xxhash_rust::xxh3::Xxh3;
+
+#[cfg(test)]
+/// Tests for hashing. Each test is named after the function it tests, prefixed
+/// with test.
+mod tests {
+
+    use std::{env, fs, thread};
+    use super::{hash_file, hash_files, HashAlgorithm, HashQueue};
+    use crate::structs::{File, Join};
+    use crate::testing::{Entry, Sandbox};
+
+    #[test]
+    /// Test [`hash_file`]
+    fn test_hash_file() {
+        let path = File::from(env::temp_dir()).join(String::from("sorterylib_test_hash_file.txt"));
+        fs::write(path.to_path_buf(), "abc").expect("Failed to write test file.");
+
+        #[cfg(feature = "sha256")]
+        assert_eq!(
+            hash_file(&path, HashAlgorithm::Sha256).expect("Failed to hash file."),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        #[cfg(not(feature = "sha256"))]
+        assert!(hash_file(&path, HashAlgorithm::Sha256).is_err());
+        assert_eq!(
+            hash_file(&path, HashAlgorithm::Xxh3).expect("Failed to hash file."),
+            "78af5f94892f3950"
+        );
+        #[cfg(feature = "blake3")]
+        assert_eq!(
+            hash_file(&path, HashAlgorithm::Blake3).expect("Failed to hash file."),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+
+        fs::remove_file(path.to_path_buf()).expect("Failed to remove test file.");
+    }
+
+    #[test]
+    /// Test [`hash_files`] and [`HashQueue`]
+    fn test_hash_files() {
+        let entries: Vec<Entry> = (0..20).map(|n| Entry::file(&format!("source/{}.txt", n)).contents(n.to_string())).collect();
+        let sandbox = Sandbox::new(&entries).expect("Failed to create sandbox.");
+        let mut paths: Vec<File> = (0..20).map(|n| sandbox.path(&format!("source/{}.txt", n))).collect();
+        paths.push(sandbox.path("source/missing.txt"));
+        let expected: Vec<String> = paths[..20].iter().map(|path| hash_file(path, HashAlgorithm::Xxh3).unwrap()).collect();
+
+        // The hashes are in the order of the files, however many threads hash them
+        for workers in [0, 1, 4] {
+            let hashes = hash_files(&paths, HashAlgorithm::Xxh3, workers);
+            assert_eq!(hashes.len(), 21);
+            assert_eq!(hashes[..20].iter().map(|hash| hash.as_ref().unwrap().clone()).collect::<Vec<String>>(), expected);
+            assert!(hashes[20].is_err());
+        }
+
+        // Hashes can be taken in any order, and files that weren't queued are
+        // hashed on the spot
+        let queue = HashQueue::new(&paths[..20], HashAlgorithm::Xxh3);
+        thread::scope(|scope| {
+            scope.spawn(|| queue.work());
+            for i in (0..20).rev() {
+                assert_eq!(queue.take(&paths[i]).unwrap(), expected[i]);
+            }
+            assert_eq!(queue.take(&paths[3]).unwrap(), expected[3]);
+            assert!(queue.take(&paths[20]).is_err());
+            queue.stop();
+        });
+        queue.keep(&paths[2], String::from("b"));
+        queue.keep(&paths[1], String::from("a"));
+        assert_eq!(queue.kept(), vec![(paths[1].copy(), String::from("a")), (paths[2].copy(), String::from("b"))]);
+    }
+}
+
+/// How far along the hashing of a file of a [`HashQueue`] is.
+#[derive(Debug)]
+enum Slot {
+    Pending,
+    Hashing,
+    Done(io::Result<String>),
+    Taken
+}
+
+/// The state of a [`HashQueue`], shared by its threads.
+#[derive(Debug)]
+struct QueueState {
+    slots: Vec<Slot>,
+    next: usize,
+    stopped: bool
+}
+
+/// Files that are hashed ahead of time, in order, by any number of threads
+/// running [`HashQueue::work`], while their hashes are taken with
+/// [`HashQueue::take`] as they are needed. The hashes that the files turn out
+/// to have once they are sorted can be kept alongside, with
+/// [`HashQueue::keep`].
+#[derive(Debug)]
+pub struct HashQueue {
+    algorithm: HashAlgorithm,
+    paths: Vec<File>,
+    indices: HashMap<File, usize>,
+    state: Mutex<QueueState>,
+    ready: Condvar,
+    kept: Mutex<Vec<(File, String)>>
+}
+impl HashQueue {
+
+    /// Return a new [`HashQueue`] of `paths`, to hash with `algorithm`.
+    pub fn new(paths: &[File], algorithm: HashAlgorithm) -> HashQueue {
+        HashQueue {
+            algorithm,
+            paths: paths.to_vec(),
+            indices: paths.iter().enumerate().map(|(i, path)| (path.copy(), i)).collect(),
+            state: Mutex::new(QueueState {
+                slots: paths.iter().map(|_| Slot::Pending).collect(),
+                next: 0,
+                stopped: false
+            }),
+            ready: Condvar::new(),
+            kept: Mutex::new(Vec::new())
+        }
+    }
+
+    /// Hash the files that no one has started on yet, in order, until there are
+    /// none left or the queue is stopped. Run this on each worker thread.
+    pub fn work(&self) {
+        loop {
+            let i = {
+                let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+                while state.next < state.slots.len() && !matches!(state.slots[state.next], Slot::Pending) {
+                    state.next += 1;
+                }
+                if state.stopped || state.next == state.slots.len() {
+                    return;
+                }
+                let i = state.next;
+                state.slots[i] = Slot::Hashing;
+                i
+            };
+            let hash = hash_file(&self.paths[i], self.algorithm);
+            self.state.lock().unwrap_or_else(PoisonError::into_inner).slots[i] = Slot::Done(hash);
+            self.ready.notify_all();
+        }
+    }
+
+    /// Return the hash of `path`: the one the workers made, waiting for it if it
+    /// is being made. Files that no worker has started on yet, that aren't in
+    /// the queue, or whose hashes were already taken, are hashed on the spot.
+    pub fn take(&self, path: &File) -> io::Result<String> {
+        let Some(&i) = self.indices.get(path) else {
+            return hash_file(path, self.algorithm);
+        };
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            match std::mem::replace(&mut state.slots[i], Slot::Taken) {
+                Slot::Done(hash) => return hash,
+                Slot::Hashing => {
+                    state.slots[i] = Slot::Hashing;
+                    state = self.ready.wait(state).unwrap_or_else(PoisonError::into_inner);
+                },
+                Slot::Pending | Slot::Taken => {
+                    drop(state);
+                    return hash_file(path, self.algorithm);
+                },
+            }
+        }
+    }
+
+    /// Stop the workers after the files they are hashing, as once the hashes
+    /// of the rest won't be needed.
+    pub fn stop(&self) {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).stopped = true;
+    }
+
+    /// Keep `hash` as the hash of `path`, which may be any file.
+    pub fn keep(&self, path: &File, hash: String) {
+        self.kept.lock().unwrap_or_else(PoisonError::into_inner).push((path.copy(), hash));
+    }
+
+    /// Return the hashes kept with [`HashQueue::keep`], sorted by path.
+    pub fn kept(&self) -> Vec<(File, String)> {
+        let mut kept = self.kept.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        kept.sort_by(|a, b| a.0.pathbuf.cmp(&b.0.pathbuf));
+        kept
+    }
+}
+
+/// Return the hashes of `paths` using `algorithm`, in order, hashing them on
+/// `workers` threads as well as the calling one. With `0`, they are all hashed
+/// on the calling thread.
+pub fn hash_files(paths: &[File], algorithm: HashAlgorithm, workers: usize) -> Vec<io::Result<String>> {
+    let queue = HashQueue::new(paths, algorithm);
+    thread::scope(|scope| {
+        for _ in 0..workers.min(paths.len()) {
+            scope.spawn(|| queue.work());
+        }
+        paths.iter().map(|path| queue.take(path)).collect()
+    })
+}
+
+/// The size of the buffer used to read files while hashing them.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// An algorithm for hashing the contents of files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashAlgorithm {
+    /// SHA-256: slower, but cryptographically secure, for when hashes have to
+    /// stand up to an audit. This is the default. Hashing with it needs the
+    /// `sha256` feature, which is on by default, and fails without it.
+    #[default]
+    Sha256,
+    /// The 64-bit XXH3 hash: much faster, and good enough for finding identical
+    /// files, but not secure against deliberate collisions.
+    Xxh3,
+    /// BLAKE3: fast and cryptographically secure. Needs the `blake3` feature.
+    #[cfg(feature = "blake3")]
+    Blake3
+}
+
+/// Call `update` with each chunk of the contents of `path`.
+fn read_chunks(path: &File, mut update: impl FnMut(&[u8])) -> io::Result<()> {
+    let mut file = fs::File::open(&path.pathbuf)?;
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(());
+        }
+        update(&buffer[..read]);
+    }
+}
+
+/// Return the hash of the contents of `path` using `algorithm`, as a lowercase
+/// hex [`String`].
+pub fn hash_file(path: &File, algorithm: HashAlgorithm) -> io::Result<String> {
+    match algorithm {
+        #[cfg(feature = "sha256")]
+        HashAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            read_chunks(path, |chunk| hasher.update(chunk))?;
+            Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+        },
+        #[cfg(not(feature = "sha256"))]
+        HashAlgorithm::Sha256 => Err(io::Error::new(io::ErrorKind::Unsupported, "hashing with SHA-256 needs the sha256 feature")),
+        HashAlgorithm::Xxh3 => {
+            let mut hasher = Xxh3::new();
+            read_chunks(path, |chunk| hasher.update(chunk))?;
+            Ok(format!("{:016x}", hasher.digest()))
+        },
+        #[cfg(feature = "blake3")]
+        HashAlgorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            read_chunks(path, |chunk| { hasher.update(chunk); })?;
+            Ok(hasher.finalize().to_hex().to_string())
+        },
+    }
+}
@@ -0,0 +1,134 @@
+//! Handling files whose destinations are already in the target. See
+//! [`IdenticalCheck`].
+//!
+//! By default, a file whose destination is taken gets a sequential name, as in
+//! `2023-07-14_2.jpg`. When the file in the way is a copy of the same file, as
+//! when a memory card is sorted into the archive twice, that just makes a
+//! redundant copy, so identical files can be recognized and left out instead.
+//! Files that are sorted into the same archive again and again, as from a live
+//! folder, can instead replace their older versions, like with rsync's
+//! `--update`. See [`ConflictPolicy`].
+
+use crate::checksum::{self, HashAlgorithm};
+use crate::structs::File;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+#[cfg(test)]
+/// Tests for handling conflicts. Each test is named after the function it tests,
+/// prefixed with test.
+mod tests {
+
+    use std::{env, fs};
+    use super::{is_identical, is_newer, IdenticalCheck};
+    use crate::checksum::HashAlgorithm;
+    use crate::structs::{File, Join};
+
+    #[test]
+    /// Test [`is_identical`]
+    fn test_is_identical() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_is_identical"));
+        fs::create_dir_all(dir.to_path_buf()).expect("Failed to create test dir.");
+        let [a, b, c] = ["a.jpg", "b.jpg", "c.jpg"].map(|name| dir.join(String::from(name)));
+        fs::write(a.to_path_buf(), "same").expect("Failed to write test file.");
+        fs::write(b.to_path_buf(), "same").expect("Failed to write test file.");
+        fs::write(c.to_path_buf(), "diff").expect("Failed to write test file.");
+
+        // Sizes can't tell files of the same size apart, but hashes can
+        assert!(!is_identical(&a, &b, IdenticalCheck::Off, HashAlgorithm::Xxh3));
+        assert!(is_identical(&a, &c, IdenticalCheck::Size, HashAlgorithm::Xxh3));
+        assert!(is_identical(&a, &b, IdenticalCheck::Hash, HashAlgorithm::Xxh3));
+        assert!(!is_identical(&a, &c, IdenticalCheck::Hash, HashAlgorithm::Xxh3));
+        assert!(!is_identical(&a, &dir.join(String::from("missing.jpg")), IdenticalCheck::Size, HashAlgorithm::Xxh3));
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    /// Test [`is_newer`]
+    fn test_is_newer() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_is_newer"));
+        fs::create_dir_all(dir.to_path_buf()).expect("Failed to create test dir.");
+        let [old, new, longer] = ["old.txt", "new.txt", "longer.txt"].map(|name| dir.join(String::from(name)));
+        fs::write(old.to_path_buf(), "old").expect("Failed to write test file.");
+        fs::write(new.to_path_buf(), "new").expect("Failed to write test file.");
+        fs::write(longer.to_path_buf(), "longer").expect("Failed to write test file.");
+        let time = |seconds| filetime::FileTime::from_unix_time(seconds, 0);
+        filetime::set_file_mtime(old.to_path_buf(), time(1_600_000_000)).unwrap();
+        filetime::set_file_mtime(new.to_path_buf(), time(1_700_000_000)).unwrap();
+        filetime::set_file_mtime(longer.to_path_buf(), time(1_600_000_000)).unwrap();
+
+        // Files of the same time are only newer if their sizes differ
+        assert!(is_newer(&new, &old));
+        assert!(!is_newer(&old, &new));
+        assert!(!is_newer(&old, &old));
+        assert!(is_newer(&longer, &old));
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+}
+
+/// What to do with a file whose destination is taken by a different file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Give the file a sequential name, as in `2023-07-14_2.jpg`, keeping both.
+    /// This is the default.
+    #[default]
+    Rename,
+    /// Replace the file in the target if the file being sorted is newer (see
+    /// [`is_newer`]), and leave the file being sorted where it is otherwise, as
+    /// already present.
+    Update
+}
+
+/// How to tell whether a file is identical to the file already at its
+/// destination.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdenticalCheck {
+    /// Never treat files as identical, and always give them sequential names.
+    /// This is the default.
+    #[default]
+    Off,
+    /// Files of the same size are identical. This is fast, but can mistake
+    /// different files of the same size, like edits that only change some
+    /// pixels, for copies.
+    Size,
+    /// Files of the same size with the same hash (see
+    /// [`Sorter::hash_algorithm`](crate::Sorter::hash_algorithm)) are identical.
+    Hash
+}
+
+/// Return [`true`] if `a` is newer than `b`, the way rsync's `--update` decides:
+/// if it was modified later, or at the same time but has a different size.
+/// Files that can't be read are never newer, and anything is newer than a file
+/// that can't be read.
+pub fn is_newer(a: &File, b: &File) -> bool {
+    let stamp = |file: &File| fs::metadata(file.as_path()).and_then(|m| Ok((m.modified()?, m.len())));
+    match (stamp(a), stamp(b)) {
+        (Ok((a_time, a_len)), Ok((b_time, b_len))) => a_time > b_time || (a_time == b_time && a_len != b_len),
+        (Ok(_), Err(_)) => true,
+        _ => false,
+    }
+}
+
+/// Return [`true`] if `a` and `b` are identical, according to `check`. Files
+/// that can't be read are never identical.
+pub fn is_identical(a: &File, b: &File, check: IdenticalCheck, algorithm: HashAlgorithm) -> bool {
+    if check == IdenticalCheck::Off {
+        return false;
+    }
+    let same_size = match (fs::metadata(a.as_path()), fs::metadata(b.as_path())) {
+        (Ok(a), Ok(b)) => a.is_file() && b.is_file() && a.len() == b.len(),
+        _ => false,
+    };
+    match check {
+        IdenticalCheck::Hash if same_size => {
+            matches!((checksum::hash_file(a, algorithm), checksum::hash_file(b, algorithm)), (Ok(a), Ok(b)) if a == b)
+        },
+        _ => same_size,
+    }
+}
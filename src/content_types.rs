@@ -0,0 +1,222 @@
+//! Detecting the content types of files from their first bytes.
+//!
+//! Many file formats start with a fixed "magic" signature, like the `%PDF-` at
+//! the start of every PDF, so the content of a file can be identified without
+//! trusting its extension. This is how files that were renamed, or exported
+//! without an extension (as messaging apps like to do), are still recognized.
+//!
+//! Content types are MIME types, as in `"image/jpeg"`. They can be filtered
+//! with the same wildcards as extensions (see [`filters`](crate::filters)), so
+//! `"image/*"` matches every image.
+//!
+//! Content types also give files without an extension one; see
+//! [`ExtensionlessPolicy`].
+
+use serde::{Deserialize, Serialize};
+use std::{fs, io::{self, Read}, path::Path};
+
+#[cfg(test)]
+/// Tests for content types. Each test is named after the function it tests,
+/// prefixed with test.
+mod tests {
+
+    use std::{env, fs};
+    use super::{content_type, extension, sniff};
+    use crate::structs::{File, Join};
+
+    #[test]
+    /// Test [`sniff`]
+    fn test_sniff() {
+        assert_eq!(sniff(b"\xFF\xD8\xFF\xE0\x00\x10JFIF"), Some("image/jpeg"));
+        assert_eq!(sniff(b"\x89PNG\r\n\x1A\n\x00\x00"), Some("image/png"));
+        assert_eq!(sniff(b"RIFF\x24\x00\x00\x00WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff(b"RIFF\x24\x00\x00\x00WAVEfmt "), Some("audio/wav"));
+        assert_eq!(sniff(b"\x00\x00\x00\x18ftypheic\x00\x00"), Some("image/heic"));
+        assert_eq!(sniff(b"\x00\x00\x00\x18ftypisom\x00\x00"), Some("video/mp4"));
+        assert_eq!(sniff(b"\x00\x00\x00\x14ftypqt  \x00\x00"), Some("video/quicktime"));
+        assert_eq!(sniff(b"%PDF-1.7"), Some("application/pdf"));
+        assert_eq!(sniff(b"RIFF"), None);
+        assert_eq!(sniff(b"hello, world"), None);
+        assert_eq!(sniff(b""), None);
+    }
+
+    #[test]
+    /// Test [`extension`]
+    fn test_extension() {
+        assert_eq!(extension("image/jpeg"), Some("jpg"));
+        assert_eq!(extension("video/quicktime"), Some("mov"));
+        assert_eq!(extension("application/x-ole-storage"), None);
+        assert_eq!(extension("text/plain"), None);
+    }
+
+    #[test]
+    /// Test [`content_type`]
+    fn test_content_type() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_content_type"));
+        fs::create_dir_all(dir.to_path_buf()).expect("Failed to create test dir.");
+        let (photo, notes) = (dir.join(String::from("photo")), dir.join(String::from("notes.jpg")));
+
+        // The extension doesn't matter, only the content does
+        fs::write(photo.to_path_buf(), b"\xFF\xD8\xFF\xE1\x00\x10Exif").expect("Failed to write test file.");
+        fs::write(notes.to_path_buf(), b"not a photo").expect("Failed to write test file.");
+        assert_eq!(content_type(photo.as_path()).expect("Failed to read test file."), Some("image/jpeg"));
+        assert_eq!(content_type(notes.as_path()).expect("Failed to read test file."), None);
+        assert!(content_type(dir.join(String::from("missing")).as_path()).is_err());
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+}
+
+/// The number of bytes at the start of a file that are enough to recognize it.
+/// The tar signature is the one that is furthest in.
+const SNIFF_LEN: u64 = 262;
+
+/// The signatures of the recognized content types, as `(offset, magic bytes,
+/// content type)`. Formats with more than one signature are listed once for
+/// each.
+const SIGNATURES: [(usize, &[u8], &str); 29] = [
+    // Images
+    (0, b"\xFF\xD8\xFF", "image/jpeg"),
+    (0, b"\x89PNG\r\n\x1A\n", "image/png"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (0, b"BM", "image/bmp"),
+    (0, b"II*\x00", "image/tiff"),
+    (0, b"MM\x00*", "image/tiff"),
+    (0, b"\x00\x00\x01\x00", "image/x-icon"),
+    (0, b"8BPS", "image/vnd.adobe.photoshop"),
+    // Audio
+    (0, b"ID3", "audio/mpeg"),
+    (0, b"\xFF\xFB", "audio/mpeg"),
+    (0, b"fLaC", "audio/flac"),
+    (0, b"OggS", "audio/ogg"),
+    (0, b"MThd", "audio/midi"),
+    // Video
+    (0, b"\x1A\x45\xDF\xA3", "video/x-matroska"),
+    (0, b"FLV", "video/x-flv"),
+    (0, b"\x00\x00\x01\xBA", "video/mpeg"),
+    // Documents
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"{\\rtf", "application/rtf"),
+    (0, b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1", "application/x-ole-storage"),
+    (0, b"SQLite format 3\x00", "application/vnd.sqlite3"),
+    // Archives
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"PK\x05\x06", "application/zip"),
+    (0, b"Rar!\x1A\x07", "application/vnd.rar"),
+    (0, b"7z\xBC\xAF\x27\x1C", "application/x-7z-compressed"),
+    (0, b"\x1F\x8B", "application/gzip"),
+    (0, b"BZh", "application/x-bzip2"),
+    (0, b"\xFD7zXZ\x00", "application/x-xz"),
+    (257, b"ustar", "application/x-tar"),
+];
+
+/// The content types of the RIFF container formats, by their form type.
+const RIFF_TYPES: [(&[u8], &str); 3] = [
+    (b"WEBP", "image/webp"),
+    (b"WAVE", "audio/wav"),
+    (b"AVI ", "video/x-msvideo"),
+];
+
+/// The content types of the ISO base media formats (like MP4 and HEIC), by their
+/// major brand. Brands that aren't listed are taken to be MP4 video.
+const FTYP_BRANDS: [(&[u8], &str); 9] = [
+    (b"heic", "image/heic"),
+    (b"heix", "image/heic"),
+    (b"mif1", "image/heif"),
+    (b"msf1", "image/heif"),
+    (b"avif", "image/avif"),
+    (b"qt  ", "video/quicktime"),
+    (b"M4A ", "audio/mp4"),
+    (b"3gp4", "video/3gpp"),
+    (b"3gp5", "video/3gpp"),
+];
+
+/// The usual extensions of the recognized content types. Content types that are
+/// shared by several formats, like OLE storage, have none.
+const EXTENSIONS: [(&str, &str); 33] = [
+    ("image/jpeg", "jpg"),
+    ("image/png", "png"),
+    ("image/gif", "gif"),
+    ("image/bmp", "bmp"),
+    ("image/tiff", "tif"),
+    ("image/x-icon", "ico"),
+    ("image/vnd.adobe.photoshop", "psd"),
+    ("image/webp", "webp"),
+    ("image/heic", "heic"),
+    ("image/heif", "heif"),
+    ("image/avif", "avif"),
+    ("audio/mpeg", "mp3"),
+    ("audio/flac", "flac"),
+    ("audio/ogg", "ogg"),
+    ("audio/midi", "mid"),
+    ("audio/wav", "wav"),
+    ("audio/mp4", "m4a"),
+    ("video/x-matroska", "mkv"),
+    ("video/x-flv", "flv"),
+    ("video/mpeg", "mpg"),
+    ("video/x-msvideo", "avi"),
+    ("video/mp4", "mp4"),
+    ("video/quicktime", "mov"),
+    ("video/3gpp", "3gp"),
+    ("application/pdf", "pdf"),
+    ("application/rtf", "rtf"),
+    ("application/vnd.sqlite3", "sqlite"),
+    ("application/zip", "zip"),
+    ("application/vnd.rar", "rar"),
+    ("application/x-7z-compressed", "7z"),
+    ("application/gzip", "gz"),
+    ("application/x-bzip2", "bz2"),
+    ("application/x-xz", "xz"),
+];
+
+/// What to do with files that have no extension, like `IMG_1234` or `Makefile`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtensionlessPolicy {
+    /// Sort them like any other file, and keep them without an extension.
+    #[default]
+    Keep,
+    /// Give them the extension of their content type (see [`content_type`]), if
+    /// it is recognized, and keep them without one otherwise.
+    Sniff,
+    /// Sort them into a `no_extension` directory in the target, as in
+    /// `target/no_extension/2023/07/`.
+    Folder,
+    /// Leave them where they are.
+    Skip
+}
+
+/// Return the usual extension of the content type `content_type`, as in `"jpg"`
+/// for `"image/jpeg"`, or [`None`] if it has none.
+pub fn extension(content_type: &str) -> Option<&'static str> {
+    EXTENSIONS.iter().find(|(t, _)| *t == content_type).map(|(_, ext)| *ext)
+}
+
+/// Return the content type of a file that starts with `bytes`, or [`None`] if it
+/// isn't recognized.
+pub fn sniff(bytes: &[u8]) -> Option<&'static str> {
+
+    // Container formats are told apart by the type of what they contain
+    if bytes.len() >= 12 && bytes.starts_with(b"RIFF") {
+        return RIFF_TYPES.iter().find(|(form, _)| &bytes[8..12] == *form).map(|(_, t)| *t);
+    }
+    if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" {
+        return Some(FTYP_BRANDS.iter()
+            .find(|(brand, _)| &bytes[8..12] == *brand)
+            .map_or("video/mp4", |(_, t)| *t));
+    }
+
+    SIGNATURES.iter()
+        .find(|(offset, magic, _)| bytes.get(*offset..).is_some_and(|b| b.starts_with(magic)))
+        .map(|(_, _, t)| *t)
+}
+
+/// Return the content type of the file at `path`, read from its first bytes, or
+/// [`None`] if it isn't recognized.
+pub fn content_type(path: &Path) -> io::Result<Option<&'static str>> {
+    let mut bytes = Vec::with_capacity(SNIFF_LEN as usize);
+    fs::File::open(path)?.take(SNIFF_LEN).read_to_end(&mut bytes)?;
+    Ok(sniff(&bytes))
+}
@@ -0,0 +1,134 @@
+//! Sequential names that carry on from one sort to the next. See [`Counters`].
+//!
+//! A file whose destination is taken gets a sequential name, as in
+//! `IMG_1234_2.jpg`. Each sort numbers from 2 again, only skipping the names
+//! that are in the target, so once a numbered file is moved on out of the
+//! target, a later sort gives its name to another file, and the two collide
+//! wherever they meet again. With [`Sorter::persist_counters`](crate::Sorter::persist_counters),
+//! the highest number given to each name in each folder is kept in
+//! [`COUNTERS_FILE_NAME`] in the target, and later sorts carry on from there.
+
+use crate::structs::File;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Component;
+use std::{fs, io};
+
+#[cfg(test)]
+/// Tests for counters. Each test is named after the struct it tests, prefixed
+/// with test.
+mod tests {
+
+    use super::{Counters, COUNTERS_FILE_NAME};
+    use crate::testing::Sandbox;
+
+    #[test]
+    /// Test [`Counters`]
+    fn test_counters() {
+        let sandbox = Sandbox::new(&[]).expect("Failed to create sandbox.");
+        let target = sandbox.target();
+        let mut counters = Counters::load(&target).expect("Failed to load counters.");
+        assert_eq!(counters, Counters::default());
+
+        // Only sequential names in the target are counted, by their highest number
+        for path in ["2023/07/IMG_1234_3.jpg", "2023/07/IMG_1234_2.jpg", "2023/07/notes.txt", "2023/IMG_1234_2.jpg", "a_1.txt"] {
+            counters.record(&target, &sandbox.path(&format!("target/{}", path)));
+        }
+        counters.record(&target, &sandbox.path("source/b_2.txt"));
+        assert_eq!(counters.last(&target, &sandbox.path("target/2023/07/IMG_1234.jpg")), 3);
+        assert_eq!(counters.last(&target, &sandbox.path("target/2023/IMG_1234.jpg")), 2);
+        assert_eq!(counters.last(&target, &sandbox.path("target/a.txt")), 1);
+        assert_eq!(counters.folders.len(), 2);
+
+        counters.save(&target).expect("Failed to save counters.");
+        assert_eq!(sandbox.files(), vec![format!("target/{}", COUNTERS_FILE_NAME)]);
+        assert_eq!(Counters::load(&target).expect("Failed to load counters."), counters);
+        let names: Vec<(String, usize)> = counters.iter(&target).map(|(path, last)| (path.file_name(), last)).collect();
+        assert_eq!(names, vec![(String::from("IMG_1234.jpg"), 2), (String::from("IMG_1234.jpg"), 3)]);
+    }
+}
+
+/// The name of the counters file in the target.
+pub const COUNTERS_FILE_NAME: &str = ".sortery.counters.json";
+
+/// The highest sequential numbers given to the names of files in a target.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub struct Counters {
+    /// The highest number given to each file name, as in `IMG_1234.jpg` for
+    /// `IMG_1234_3.jpg`, by the folders they are in, relative to the target,
+    /// with `/` separators.
+    pub folders: BTreeMap<String, BTreeMap<String, usize>>
+}
+impl Counters {
+
+    /// Return the counters kept in `target`, or none if there aren't any yet.
+    /// Fail with [`io::ErrorKind::InvalidData`] if the counters file is broken.
+    pub fn load(target: &File) -> io::Result<Counters> {
+        match fs::read_to_string(target.pathbuf.join(COUNTERS_FILE_NAME)) {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Counters::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Keep the counters in `target`, for later sorts.
+    pub fn save(&self, target: &File) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(target.pathbuf.join(COUNTERS_FILE_NAME), json)
+    }
+
+    /// Return the highest number given to the name of `path` in its folder in
+    /// `target`, or `1` if it hasn't been given any.
+    pub fn last(&self, target: &File, path: &File) -> usize {
+        Counters::key(target, path)
+            .and_then(|(folder, name)| self.folders.get(&folder)?.get(&name).copied())
+            .unwrap_or(1)
+    }
+
+    /// Count the name of `path`, if it is a sequential name (as in
+    /// `IMG_1234_2.jpg`) in `target`. Files that happen to be named like one
+    /// are counted too, which only makes later numbers start higher.
+    pub fn record(&mut self, target: &File, path: &File) {
+        let stem = path.file_stem();
+        let Some((base, digits)) = stem.rsplit_once('_') else {
+            return;
+        };
+        let num = match digits.parse::<usize>() {
+            Ok(num) if num >= 2 && !base.is_empty() && num.to_string() == digits => num,
+            _ => return,
+        };
+        let name = match path.extension_os().is_empty() {
+            true => String::from(base),
+            false => format!("{}.{}", base, path.extension()),
+        };
+        let Some((folder, _)) = Counters::key(target, path) else {
+            return;
+        };
+        let last = self.folders.entry(folder).or_default().entry(name).or_insert(1);
+        *last = num.max(*last);
+    }
+
+    /// Return each name that has been given numbers, as its path in `target`,
+    /// with the highest number given to it, in order.
+    pub fn iter<'a>(&'a self, target: &'a File) -> impl Iterator<Item = (File, usize)> + 'a {
+        self.folders.iter().flat_map(move |(folder, names)| {
+            let dir = folder.split('/').filter(|part| !part.is_empty()).fold(target.to_path_buf(), |dir, part| dir.join(part));
+            names.iter().map(move |(name, last)| (File::from(dir.join(name)), *last))
+        })
+    }
+
+    /// Return the folder of `path` relative to `target`, with `/` separators,
+    /// and its file name, or [`None`] if it isn't in `target`.
+    fn key(target: &File, path: &File) -> Option<(String, String)> {
+        let relative = path.pathbuf.strip_prefix(&target.pathbuf).ok()?;
+        let folder: Vec<String> = relative.parent()?
+            .components()
+            .map(|component| match component {
+                Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+        Some((folder.join("/"), path.file_name()))
+    }
+}
@@ -0,0 +1,576 @@
+//! Reading dates from the contents of files.
+//!
+//! Filesystem timestamps often say when a file was exported or downloaded, not
+//! when it was made. A [`DateSource`] reads the date embedded in the file itself
+//! instead. See [`Sorter::date_sources`](crate::Sorter::date_sources).
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone};
+use crate::structs::File;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+
+#[cfg(test)]
+/// Tests for the date sources. Each test is named after the function or enum it
+/// tests, prefixed with test.
+mod tests {
+
+    use chrono::{DateTime, Duration, Local, TimeZone};
+    use std::{env, fs};
+    use super::{email_date, filename_date, format_specifiers, from_unix_time, is_supported, office_metadata_date, parse_offset, pdf_date, pdf_metadata_date, DateSource};
+    use crate::structs::{File, Join};
+
+    #[test]
+    /// Test [`email_date`]
+    fn test_email_date() {
+
+        let expected = DateTime::parse_from_rfc3339("2021-04-22T10:32:02+02:00").unwrap();
+
+        // Plain headers, folded headers, and comments after the date
+        let eml = b"From: a@example.com\r\nDate: Thu, 22 Apr 2021 10:32:02 +0200\r\nSubject: Hi\r\n\r\nDate: Mon, 1 Jan 2001 00:00:00 +0000\r\n";
+        assert_eq!(email_date(eml), Some(expected));
+        let folded = b"Subject: Hi\nDate: Thu,\n 22 Apr 2021 10:32:02 +0200 (CEST)\n\nBody";
+        assert_eq!(email_date(folded), Some(expected));
+
+        // A header in the body doesn't count
+        assert_eq!(email_date(b"Subject: Hi\n\nDate: Thu, 22 Apr 2021 10:32:02 +0200\n"), None);
+
+        // Outlook .msg files keep the headers as UTF-16
+        let msg: Vec<u8> = "\0\0junkDate: Thu, 22 Apr 2021 10:32:02 +0200\r\nX: y"
+            .encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+        assert_eq!(email_date(&msg), Some(expected));
+    }
+
+    #[test]
+    /// Test [`filename_date`]
+    fn test_filename_date() {
+        let time = |y, mo, d, h, mi, s| Local.with_ymd_and_hms(y, mo, d, h, mi, s).single();
+
+        assert_eq!(filename_date("IMG_20230714_103012"), time(2023, 7, 14, 10, 30, 12));
+        assert_eq!(filename_date("PXL_20230714_103012345.MP"), time(2023, 7, 14, 10, 30, 12));
+        assert_eq!(filename_date("WhatsApp Image 2023-07-14 at 10.30.12"), time(2023, 7, 14, 10, 30, 12));
+        assert_eq!(filename_date("WhatsApp Image 2023-07-14"), time(2023, 7, 14, 0, 0, 0));
+        assert_eq!(filename_date("Screenshot_2023-07-14-10-30-12"), time(2023, 7, 14, 10, 30, 12));
+        assert_eq!(filename_date("Screenshot_20230714-103012_Chrome"), time(2023, 7, 14, 10, 30, 12));
+        assert_eq!(filename_date("VID-20230714-WA0001"), time(2023, 7, 14, 0, 0, 0));
+        assert_eq!(filename_date("IMG_1234"), None);
+        assert_eq!(filename_date("holiday"), None);
+    }
+
+    #[test]
+    /// Test [`parse_offset`]
+    fn test_parse_offset() {
+        assert_eq!(parse_offset(""), Some(Duration::zero()));
+        assert_eq!(parse_offset("+7h"), Some(Duration::hours(7)));
+        assert_eq!(parse_offset("-1h30m"), Some(-Duration::minutes(90)));
+        assert_eq!(parse_offset("2d 12s"), Some(Duration::days(2) + Duration::seconds(12)));
+        assert_eq!(parse_offset("7"), None);
+        assert_eq!(parse_offset("7x"), None);
+        assert_eq!(parse_offset("+"), None);
+    }
+
+    #[test]
+    /// Test [`format_specifiers`]
+    fn test_format_specifiers() {
+        assert_eq!(format_specifiers("%Y-%m-%d %Hh%Mm%Ss"), vec!['Y', 'm', 'd', 'H', 'M', 'S']);
+        assert_eq!(format_specifiers("%-d%% %.3f %:z"), vec!['d', 'f', 'z']);
+        assert_eq!(format_specifiers("photos%"), vec![]);
+    }
+
+    #[test]
+    /// Test [`from_unix_time`] and [`is_supported`]
+    fn test_from_unix_time() {
+        assert_eq!(from_unix_time(1689330612, 0), Some(Local.timestamp_opt(1689330612, 0).unwrap()));
+        assert_eq!(from_unix_time(1689330612, 500_000_000), Some(Local.timestamp_opt(1689330612, 500_000_000).unwrap()));
+        assert_eq!(from_unix_time(-1, 0), Some(Local.timestamp_opt(-1, 0).unwrap()));
+        assert_eq!(from_unix_time(-62135596800, 0), Some(Local.timestamp_opt(-62135596800, 0).unwrap()).filter(is_supported));
+        assert_eq!(from_unix_time(253402300799 + 86400 * 2, 0), None);
+        assert_eq!(from_unix_time(i64::MAX, 0), None);
+        assert_eq!(from_unix_time(i64::MIN, 0), None);
+        assert!(is_supported(&Local.with_ymd_and_hms(1920, 1, 1, 0, 0, 0).unwrap()));
+        assert!(is_supported(&Local::now()));
+        assert!(!is_supported(&Local.with_ymd_and_hms(1825, 12, 31, 0, 0, 0).unwrap()));
+        assert!(!is_supported(&Local.with_ymd_and_hms(2554, 1, 1, 0, 0, 0).unwrap()));
+        assert!(!is_supported(&Local.with_ymd_and_hms(10000, 1, 1, 0, 0, 0).unwrap()));
+    }
+
+    #[test]
+    /// Test [`pdf_date`]
+    fn test_pdf_date() {
+        let expected = DateTime::parse_from_rfc3339("2021-04-22T10:32:02+02:00").unwrap();
+        assert_eq!(pdf_date("D:20210422103202+02'00'"), Some(expected.with_timezone(&Local)));
+        assert_eq!(pdf_date("D:20210422083202Z"), Some(expected.with_timezone(&Local)));
+        assert_eq!(pdf_date("20210422"), Local.with_ymd_and_hms(2021, 4, 22, 0, 0, 0).single());
+        assert_eq!(pdf_date("D:2021"), Local.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).single());
+        assert_eq!(pdf_date("D:20211322"), None);
+        assert_eq!(pdf_date("D:20210422103202+99999999'"), None);
+        assert_eq!(pdf_date("D:20210422103202-02'75'"), None);
+        assert_eq!(pdf_date("garbage"), None);
+    }
+
+    #[test]
+    /// Test [`pdf_metadata_date`]
+    fn test_pdf_metadata_date() {
+        let expected = DateTime::parse_from_rfc3339("2021-04-22T10:32:02+02:00").unwrap().with_timezone(&Local);
+        let info = b"%PDF-1.4\n1 0 obj\n<< /Producer (Scanner) /CreationDate (D:20210422103202+02'00') >>";
+        assert_eq!(pdf_metadata_date(info), Some(expected));
+        let xmp = b"<x:xmpmeta><rdf:Description xmp:CreateDate=\"2021-04-22T10:32:02+02:00\"/></x:xmpmeta>";
+        assert_eq!(pdf_metadata_date(xmp), Some(expected));
+        let xmp = b"<xmp:CreateDate>2021-04-22T10:32:02+02:00</xmp:CreateDate>";
+        assert_eq!(pdf_metadata_date(xmp), Some(expected));
+        assert_eq!(pdf_metadata_date(b"%PDF-1.4\n"), None);
+    }
+
+    #[test]
+    /// Test [`office_metadata_date`]
+    fn test_office_metadata_date() {
+        let expected = DateTime::parse_from_rfc3339("2021-04-22T10:32:02+02:00").unwrap().with_timezone(&Local);
+
+        // OOXML core properties, with the created date first
+        let core = "<cp:coreProperties><dcterms:modified xsi:type=\"dcterms:W3CDTF\">2022-01-01T00:00:00Z</dcterms:modified>\
+            <dcterms:created xsi:type=\"dcterms:W3CDTF\">2021-04-22T08:32:02Z</dcterms:created></cp:coreProperties>";
+        assert_eq!(office_metadata_date(core), Some(expected));
+
+        // ODF metadata, with a local time, and falling back to the modified date
+        let meta = "<office:meta><meta:creation-date>2021-04-22T10:32:02</meta:creation-date></office:meta>";
+        assert_eq!(office_metadata_date(meta), Local.with_ymd_and_hms(2021, 4, 22, 10, 32, 2).single());
+        let meta = "<office:meta><dc:date>2021-04-22T08:32:02Z</dc:date></office:meta>";
+        assert_eq!(office_metadata_date(meta), Some(expected));
+        assert_eq!(office_metadata_date("<office:meta/>"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "office")]
+    /// Test [`super::office_date`] with a zipped document
+    fn test_office_date() {
+        use std::io::Write;
+
+        let path = File::from(env::temp_dir()).join(String::from("sorterylib_test_office_date.docx"));
+        let mut writer = zip::ZipWriter::new(fs::File::create(path.to_path_buf()).expect("Failed to create test file."));
+        writer.start_file("docProps/core.xml", zip::write::SimpleFileOptions::default()).expect("Failed to write zip.");
+        writer.write_all(b"<dcterms:created>2021-04-22T08:32:02Z</dcterms:created>").expect("Failed to write zip.");
+        writer.finish().expect("Failed to write zip.");
+
+        let expected = DateTime::parse_from_rfc3339("2021-04-22T10:32:02+02:00").unwrap().with_timezone(&Local);
+        assert_eq!(DateSource::Office.read(&path), Some(expected));
+
+        fs::remove_file(path.to_path_buf()).expect("Failed to remove test file.");
+    }
+
+    #[test]
+    /// Test [`DateSource::read`]
+    fn test_datesource() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_datesource"));
+        fs::create_dir_all(dir.to_path_buf()).expect("Failed to create test dir.");
+        let eml = dir.join(String::from("mail.EML"));
+        let txt = dir.join(String::from("mail.txt"));
+        for path in [&eml, &txt] {
+            fs::write(path.to_path_buf(), "Date: Thu, 22 Apr 2021 10:32:02 +0200\n\n").expect("Failed to write test file.");
+        }
+
+        // Only email files are read by the email source
+        assert!(DateSource::Email.read(&eml).is_some());
+        assert!(DateSource::Email.read(&txt).is_none());
+        assert!(DateSource::Pdf.read(&eml).is_none());
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+}
+
+/// How many bytes of a file to search for a date.
+const READ_LIMIT: u64 = 256 * 1024;
+
+/// A source of dates embedded in the contents of files. Each source only reads
+/// the types of files it understands, and gives no date for the rest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateSource {
+    /// The `Date:` header of `.eml` and `.msg` email files: when the message was
+    /// sent, rather than when it was exported.
+    Email,
+    /// The `/CreationDate` in the metadata of `.pdf` files (or the XMP `CreateDate`
+    /// if there isn't one): when a scanned document was scanned, rather than when
+    /// it was downloaded. The metadata is found by searching the start and end of
+    /// the file, so it can be missed in PDFs that compress all their objects.
+    Pdf,
+    /// The creation date (or the modification date, if there isn't one) in the
+    /// metadata of OOXML (`.docx`, `.xlsx`, `.pptx`, ...) and ODF (`.odt`, `.ods`,
+    /// `.odp`, ...) office documents. Needs the `office` feature.
+    #[cfg(feature = "office")]
+    Office,
+    /// The date in the file name, for files named by cameras, phones, and apps,
+    /// as in `IMG_20230714_103012.jpg` or `WhatsApp Image 2023-07-14 at 10.30.12.jpeg`.
+    /// See [`FILENAME_PATTERNS`] for the recognized naming schemes.
+    Filename
+}
+impl DateSource {
+
+    /// Return the date embedded in `path`, or [`None`] if it isn't a type of file
+    /// we read, or doesn't have a date we can read.
+    pub fn read(&self, path: &File) -> Option<DateTime<Local>> {
+        let extension = path.extension().to_lowercase();
+        match self {
+            DateSource::Email if extension == "eml" || extension == "msg" => {
+                email_date(&read_start(path)?).map(|d| d.with_timezone(&Local))
+            },
+            DateSource::Pdf if extension == "pdf" => {
+                pdf_metadata_date(&read_start(path)?).or_else(|| pdf_metadata_date(&read_end(path)?))
+            },
+            #[cfg(feature = "office")]
+            DateSource::Office if OFFICE_TYPES.contains(&extension.as_str()) => office_date(path),
+            DateSource::Filename => filename_date(&path.file_stem()),
+            _ => None,
+        }
+    }
+}
+
+/// The directory that files without dates are sorted into, in the target, with
+/// [`MissingDatePolicy::Folder`].
+pub const UNKNOWN_DATE: &str = "unknown-date";
+
+/// What to do with files that none of the
+/// [`Sorter::date_sources`](crate::Sorter::date_sources) have a date for, as
+/// photos without EXIF metadata, or files whose names have no date.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingDatePolicy {
+    /// Sort them by their filesystem timestamps, as in their modification
+    /// dates (see [`Sorter::date_type`](crate::Sorter::date_type)).
+    #[default]
+    Fallback,
+    /// Leave them where they are, and list them among the skipped files of the
+    /// report.
+    Skip,
+    /// Move them into [`Sorter::quarantine_dir`](crate::Sorter::quarantine_dir),
+    /// which there has to be, and list them among the quarantined files of the
+    /// report, with a [`SortError::TimestampUnavailable`](crate::errors::SortError::TimestampUnavailable).
+    Quarantine,
+    /// Sort them into an `unknown-date` directory in the target, keeping their
+    /// names, as in `target/unknown-date/IMG_1234.jpg`.
+    Folder
+}
+
+/// A naming scheme with a date in the file name, like the `IMG_YYYYMMDD_HHMMSS`
+/// names given by many phones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FilenamePattern {
+    /// The start of the file name, before the date. Matched ignoring case.
+    pub prefix: &'static str,
+    /// The format of the date after `prefix`, in the `strftime` format. The rest
+    /// of the file name after the date is ignored.
+    pub format: &'static str,
+    /// [`true`] if `format` has a time as well as a date.
+    pub has_time: bool
+}
+
+/// The naming schemes recognized by [`DateSource::Filename`], tried in order.
+pub const FILENAME_PATTERNS: [FilenamePattern; 16] = [
+    // Android cameras, and Google Pixel phones (PXL_20230714_103012345)
+    FilenamePattern { prefix: "IMG_", format: "%Y%m%d_%H%M%S", has_time: true },
+    FilenamePattern { prefix: "VID_", format: "%Y%m%d_%H%M%S", has_time: true },
+    FilenamePattern { prefix: "PXL_", format: "%Y%m%d_%H%M%S", has_time: true },
+    FilenamePattern { prefix: "PANO_", format: "%Y%m%d_%H%M%S", has_time: true },
+    // WhatsApp exports
+    FilenamePattern { prefix: "WhatsApp Image ", format: "%Y-%m-%d at %H.%M.%S", has_time: true },
+    FilenamePattern { prefix: "WhatsApp Video ", format: "%Y-%m-%d at %H.%M.%S", has_time: true },
+    FilenamePattern { prefix: "WhatsApp Image ", format: "%Y-%m-%d", has_time: false },
+    FilenamePattern { prefix: "WhatsApp Video ", format: "%Y-%m-%d", has_time: false },
+    FilenamePattern { prefix: "IMG-", format: "%Y%m%d", has_time: false },
+    FilenamePattern { prefix: "VID-", format: "%Y%m%d", has_time: false },
+    // Screenshots on Android and macOS
+    FilenamePattern { prefix: "Screenshot_", format: "%Y-%m-%d-%H-%M-%S", has_time: true },
+    FilenamePattern { prefix: "Screenshot_", format: "%Y%m%d-%H%M%S", has_time: true },
+    FilenamePattern { prefix: "Screenshot_", format: "%Y%m%d_%H%M%S", has_time: true },
+    FilenamePattern { prefix: "Screenshot ", format: "%Y-%m-%d at %H.%M.%S", has_time: true },
+    FilenamePattern { prefix: "Screen Shot ", format: "%Y-%m-%d at %H.%M.%S", has_time: true },
+    // Signal exports
+    FilenamePattern { prefix: "signal-", format: "%Y-%m-%d-%H%M%S", has_time: true }
+];
+
+/// Return the date in the file name `stem`, using the first of [`FILENAME_PATTERNS`]
+/// that matches it. The date is taken to be local time.
+pub fn filename_date(stem: &str) -> Option<DateTime<Local>> {
+    FILENAME_PATTERNS.iter().find_map(|pattern| {
+        let prefix = stem.get(..pattern.prefix.len())?;
+        if !prefix.eq_ignore_ascii_case(pattern.prefix) {
+            return None;
+        }
+        let rest = &stem[pattern.prefix.len()..];
+        let naive = if pattern.has_time {
+            NaiveDateTime::parse_and_remainder(rest, pattern.format).ok()?.0
+        } else {
+            NaiveDate::parse_and_remainder(rest, pattern.format).ok()?.0.and_hms_opt(0, 0, 0)?
+        };
+        Local.from_local_datetime(&naive).earliest()
+    })
+}
+
+/// The year of the oldest surviving photograph, which no file can be older than.
+pub const FIRST_PHOTO_YEAR: i32 = 1826;
+
+/// How many years after the current one a date can be, for clocks that were set
+/// wrong. Dates that far off are still flagged by
+/// [`Sorter::audit_dates`](crate::Sorter::audit_dates), if it is on.
+pub const FUTURE_YEARS: i32 = 10;
+
+/// Return the range of years of the dates that files can be sorted by: from
+/// [`FIRST_PHOTO_YEAR`] to [`FUTURE_YEARS`] after the current one. Dates outside
+/// of it can't be right, and come from corrupt metadata.
+pub fn supported_years() -> std::ops::RangeInclusive<i32> {
+    FIRST_PHOTO_YEAR..=Local::now().year() + FUTURE_YEARS
+}
+
+/// Return [`true`] if `date` is in one of the [`supported_years`].
+pub fn is_supported(date: &DateTime<Local>) -> bool {
+    supported_years().contains(&date.year())
+}
+
+/// Return the local date and time `secs` seconds and `nanos` nanoseconds after
+/// the Unix epoch, or [`None`] if it isn't a supported date (see [`is_supported`]).
+/// Garbage timestamps, as from corrupt metadata, often aren't.
+pub fn from_unix_time(secs: i64, nanos: u32) -> Option<DateTime<Local>> {
+    let date = DateTime::from_timestamp(secs, nanos)?.with_timezone(&Local);
+    if is_supported(&date) { Some(date) } else { None }
+}
+
+/// Return the [`Duration`] written in `offset` as a sign followed by amounts of
+/// days, hours, minutes, and seconds, as in `"+7h"`, `"-1h30m"`, or `"2d 12s"`.
+/// The sign is optional, and an empty string is no offset. Return [`None`] if
+/// `offset` isn't in this format.
+pub fn parse_offset(offset: &str) -> Option<Duration> {
+
+    let offset = offset.trim();
+    let (negative, mut rest) = match offset.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, offset.strip_prefix('+').unwrap_or(offset)),
+    };
+    if rest.is_empty() && !offset.is_empty() {
+        return None;
+    }
+
+    // Add up each amount and its unit
+    let mut total = Duration::zero();
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).filter(|&i| i > 0)?;
+        let amount: i64 = rest[..digits].parse().ok()?;
+        total += match rest[digits..].chars().next()? {
+            'd' => Duration::try_days(amount)?,
+            'h' => Duration::try_hours(amount)?,
+            'm' => Duration::try_minutes(amount)?,
+            's' => Duration::try_seconds(amount)?,
+            _ => return None,
+        };
+        rest = rest[digits + 1..].trim_start();
+    }
+    Some(if negative { -total } else { total })
+}
+
+/// The `strftime` conversion specifiers that depend on the date, rather than
+/// only on the time of day.
+pub const DATE_SPECIFIERS: &str = "YCymbBhdeaAwuUWGgVjDxFvcs+";
+
+/// The `strftime` conversion specifiers that tell apart times a second or less
+/// apart.
+pub const SECOND_SPECIFIERS: &str = "STXcrs+f";
+
+/// Return the conversion specifiers of the `strftime` format `format`, as in
+/// `['Y', 'm']` for `"%Y-%m"`, without their flags, widths, and modifiers. A
+/// `%%` is a literal `%`, not a specifier.
+pub fn format_specifiers(format: &str) -> Vec<char> {
+    let mut specifiers = Vec::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+        match chars.by_ref().find(|c| !"-_0^#.:123456789".contains(*c)) {
+            Some('%') | None => (),
+            Some(specifier) => specifiers.push(specifier),
+        }
+    }
+    specifiers
+}
+
+/// The extensions of the office documents read by `DateSource::Office`.
+pub const OFFICE_TYPES: [&str; 14] = [
+    "docx", "docm", "dotx", "xlsx", "xlsm", "xltx", "pptx", "pptm", "potx",
+    "odt", "ods", "odp", "odg", "odf"
+];
+
+/// Return the first [`READ_LIMIT`] bytes of `path`.
+fn read_start(path: &File) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    fs::File::open(&path.pathbuf).ok()?.take(READ_LIMIT).read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+/// Return the last [`READ_LIMIT`] bytes of `path`.
+fn read_end(path: &File) -> Option<Vec<u8>> {
+    let mut file = fs::File::open(&path.pathbuf).ok()?;
+    let length = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(length.saturating_sub(READ_LIMIT))).ok()?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+/// Return the date in the `Date:` header of the email `bytes`. Works on plain
+/// `.eml` messages and on Outlook `.msg` files, where the headers are stored as
+/// UTF-16 text somewhere inside the file.
+pub fn email_date(bytes: &[u8]) -> Option<DateTime<FixedOffset>> {
+
+    // Plain messages start with the headers, which end at the first blank line
+    let text = String::from_utf8_lossy(bytes);
+    let headers = text.split("\r\n\r\n").next()?.split("\n\n").next()?;
+    if let Some(date) = header_date(headers) {
+        return Some(date);
+    }
+
+    // Look for headers stored as UTF-16
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+    let wide = String::from_utf16_lossy(&units);
+    let start = wide.find("Date: ")?;
+    header_date(&wide[start..])
+}
+
+/// Return the date in the first `Date:` header line of `headers`, unfolding
+/// continuation lines and ignoring comments like `(UTC)`.
+fn header_date(headers: &str) -> Option<DateTime<FixedOffset>> {
+    let mut lines = headers.lines();
+    let mut value = loop {
+        let line = lines.next()?;
+        if line.get(..5).is_some_and(|h| h.eq_ignore_ascii_case("date:")) {
+            break String::from(&line[5..]);
+        }
+    };
+    for line in lines.take_while(|l| l.starts_with([' ', '\t'])) {
+        value.push(' ');
+        value.push_str(line);
+    }
+    let value = match value.find('(') {
+        Some(comment) => &value[..comment],
+        None => &value[..],
+    };
+    DateTime::parse_from_rfc2822(value.split_whitespace().collect::<Vec<&str>>().join(" ").as_str()).ok()
+}
+
+/// Return the creation date in the PDF metadata in `bytes`: the `/CreationDate`
+/// entry of the document information dictionary, or the `xmp:CreateDate` in the
+/// XMP metadata if there isn't one.
+pub fn pdf_metadata_date(bytes: &[u8]) -> Option<DateTime<Local>> {
+
+    let text = String::from_utf8_lossy(bytes);
+
+    // The information dictionary holds the date as a string, like (D:20210422103202+02'00')
+    if let Some(start) = text.find("/CreationDate") {
+        let rest = text[start + 13..].trim_start();
+        if let Some(rest) = rest.strip_prefix('(') {
+            if let Some(date) = rest.split(')').next().and_then(pdf_date) {
+                return Some(date);
+            }
+        }
+    }
+
+    // XMP holds it as an ISO 8601 date, in an attribute or an element
+    let start = text.find("xmp:CreateDate")? + 14;
+    let rest = text[start..].trim_start_matches(['=', '"', '\'', '>', ' ']);
+    let value: String = rest.chars().take_while(|c| c.is_ascii_alphanumeric() || "-:+.".contains(*c)).collect();
+    DateTime::parse_from_rfc3339(&value).ok().map(|d| d.with_timezone(&Local))
+}
+
+/// Return the date represented by the PDF date string `date`, which looks like
+/// `D:YYYYMMDDHHmmSSOHH'mm'`. Everything after the year is optional, and dates
+/// without a time zone are taken to be local time.
+pub fn pdf_date(date: &str) -> Option<DateTime<Local>> {
+
+    let date = date.trim().trim_start_matches("D:");
+    let digits: String = date.chars().take_while(|c| c.is_ascii_digit()).collect();
+    if digits.len() < 4 {
+        return None;
+    }
+
+    // Get each field, with its default if it's missing
+    let field = |start: usize, end: usize, default: u32| -> Option<u32> {
+        match digits.get(start..end) {
+            Some(value) => value.parse().ok(),
+            None => Some(default),
+        }
+    };
+    let naive = NaiveDate::from_ymd_opt(field(0, 4, 1)? as i32, field(4, 6, 1)?, field(6, 8, 1)?)?
+        .and_hms_opt(field(8, 10, 0)?, field(10, 12, 0)?, field(12, 14, 0)?)?;
+
+    // Apply the time zone, if there is one
+    let zone = &date[digits.len()..];
+    let offset = match zone.chars().next() {
+        Some('Z') => Some(0),
+        Some(sign @ ('+' | '-')) => {
+            // Offsets too large to be hours and minutes are rejected, rather
+            // than overflowing
+            let numbers: Vec<i32> = zone[1..].split('\'').filter_map(|n| n.parse().ok()).collect();
+            let (hours, minutes) = (*numbers.first()?, *numbers.get(1).unwrap_or(&0));
+            if !(0..=23).contains(&hours) || !(0..=59).contains(&minutes) {
+                return None;
+            }
+            let seconds = hours * 3600 + minutes * 60;
+            Some(if sign == '-' { -seconds } else { seconds })
+        },
+        _ => None,
+    };
+    match offset {
+        Some(offset) => FixedOffset::east_opt(offset)?
+            .from_local_datetime(&naive).single()
+            .map(|d| d.with_timezone(&Local)),
+        None => Local.from_local_datetime(&naive).earliest(),
+    }
+}
+
+/// Return the date in the metadata of the office document `path`, which is a zip
+/// archive holding either `docProps/core.xml` (OOXML) or `meta.xml` (ODF).
+#[cfg(feature = "office")]
+fn office_date(path: &File) -> Option<DateTime<Local>> {
+    let mut archive = zip::ZipArchive::new(fs::File::open(&path.pathbuf).ok()?).ok()?;
+    for name in ["docProps/core.xml", "meta.xml"] {
+        let mut xml = String::new();
+        if let Ok(entry) = archive.by_name(name) {
+            if entry.take(READ_LIMIT).read_to_string(&mut xml).is_ok() {
+                return office_metadata_date(&xml);
+            }
+        }
+    }
+    None
+}
+
+/// Return the creation date in the office document metadata `xml`, or its
+/// modification date if there isn't one. Understands the OOXML `dcterms:created`
+/// and `dcterms:modified` elements, and the ODF `meta:creation-date` and `dc:date`
+/// elements.
+pub fn office_metadata_date(xml: &str) -> Option<DateTime<Local>> {
+    ["dcterms:created", "meta:creation-date", "dcterms:modified", "dc:date"].iter()
+        .filter_map(|tag| xml_element_text(xml, tag))
+        .find_map(iso_date)
+}
+
+/// Return the text of the first `tag` element in `xml`.
+fn xml_element_text<'x>(xml: &'x str, tag: &str) -> Option<&'x str> {
+    let start = xml.find(&format!("<{}", tag))?;
+    let rest = &xml[start + tag.len() + 1..];
+    if !rest.starts_with(['>', ' ']) {
+        return None;
+    }
+    let rest = &rest[rest.find('>')? + 1..];
+    Some(rest[..rest.find('<')?].trim())
+}
+
+/// Return the date represented by the ISO 8601 `date`, taking dates without a
+/// time zone to be local time.
+fn iso_date(date: &str) -> Option<DateTime<Local>> {
+    match DateTime::parse_from_rfc3339(date) {
+        Ok(date) => Some(date.with_timezone(&Local)),
+        Err(_) => {
+            let naive = NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S%.f").ok()?;
+            Local.from_local_datetime(&naive).earliest()
+        },
+    }
+}
@@ -0,0 +1,117 @@
+//! Recognizing downloads that are still in progress.
+//!
+//! Browsers write downloads to a temporary file next to the final one, like
+//! `report.pdf.part` (Firefox) or `report.pdf.crdownload` (Chrome), and rename
+//! it when the download completes. Safari writes into a `report.pdf.download`
+//! bundle directory instead. Some browsers also create an empty placeholder at
+//! the final name while downloading. None of these should be sorted until the
+//! download is done. Others, like Edge, write to a `.tmp` file, but so do many
+//! applications that aren't downloading anything, so those are only taken for
+//! downloads when there are signs of one. See
+//! [`Sorter::skip_partial_downloads`](crate::Sorter::skip_partial_downloads).
+
+use crate::structs::File;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[cfg(test)]
+/// Tests for partial downloads. Each test is named after the function it tests,
+/// prefixed with test.
+mod tests {
+
+    use std::{env, fs};
+    use super::{is_partial_download, partial_downloads};
+    use crate::structs::{File, Join};
+
+    #[test]
+    /// Test [`is_partial_download`]
+    fn test_is_partial_download() {
+        assert!(is_partial_download(&File::from("/dl/report.pdf.part")));
+        assert!(is_partial_download(&File::from("/dl/Unconfirmed 1234.CRDOWNLOAD")));
+        assert!(is_partial_download(&File::from("/dl/report.pdf.download/Info.plist")));
+        assert!(!is_partial_download(&File::from("/dl/report.pdf")));
+        assert!(!is_partial_download(&File::from("/dl/partial.txt")));
+        assert!(!is_partial_download(&File::from("/dl/setup.tmp")));
+
+        // A `.tmp` file that is still being written is a download
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_is_partial_download"));
+        fs::create_dir_all(dir.to_path_buf()).expect("Failed to create test dir.");
+        let tmp = dir.join(String::from("setup.exe.tmp"));
+        fs::write(tmp.to_path_buf(), "").expect("Failed to write test file.");
+        assert!(is_partial_download(&tmp));
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    /// Test [`partial_downloads`]
+    fn test_partial_downloads() {
+        let files = vec![
+            File::from("/dl/report.pdf"),
+            File::from("/dl/report.pdf.part"),
+            File::from("/dl/photo.jpg"),
+            File::from("/dl/movie.mkv.crdownload"),
+            File::from("/dl/other/report.pdf"),
+            File::from("/dl/setup.tmp"),
+            File::from("/dl/notes.txt.tmp"),
+            File::from("/dl/notes.txt")
+        ];
+        assert_eq!(partial_downloads(&files), [0, 1, 3, 6, 7].into_iter().collect());
+    }
+}
+
+/// The lowercase extensions of the temporary files of downloads in progress.
+pub const PARTIAL_DOWNLOAD_TYPES: [&str; 5] = ["part", "partial", "crdownload", "download", "opdownload"];
+
+/// The lowercase extensions that the temporary files of downloads share with
+/// those of other applications. Files with these are only downloads in progress
+/// if they were modified in the last [`RECENT_DOWNLOAD`], or if the file at
+/// their final name is there too.
+pub const AMBIGUOUS_DOWNLOAD_TYPES: [&str; 1] = ["tmp"];
+
+/// How recently a file with one of the [`AMBIGUOUS_DOWNLOAD_TYPES`] has to have
+/// been modified to still be downloading.
+pub const RECENT_DOWNLOAD: Duration = Duration::from_secs(10 * 60);
+
+/// Return [`true`] if the extension of `path` is one of `types`.
+fn has_type(path: &Path, types: &[&str]) -> bool {
+    match path.extension() {
+        None => false,
+        Some(ext) => types.iter().any(|t| ext.eq_ignore_ascii_case(t)),
+    }
+}
+
+/// Return [`true`] if `path` was modified in the last [`RECENT_DOWNLOAD`].
+fn is_recent(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| modified.elapsed().is_ok_and(|age| age < RECENT_DOWNLOAD))
+}
+
+/// Return [`true`] if `path` is the temporary file of a download in progress, or
+/// is inside the bundle directory of one. Files with one of the
+/// [`AMBIGUOUS_DOWNLOAD_TYPES`] only are if they were modified recently.
+pub fn is_partial_download(path: &File) -> bool {
+    has_type(&path.pathbuf, &PARTIAL_DOWNLOAD_TYPES)
+        || path.pathbuf.parent().is_some_and(|parent| has_type(parent, &PARTIAL_DOWNLOAD_TYPES))
+        || (has_type(&path.pathbuf, &AMBIGUOUS_DOWNLOAD_TYPES) && is_recent(&path.pathbuf))
+}
+
+/// Return the indices of the files in `files` that belong to downloads in
+/// progress: the temporary files, and the files at the final names they will
+/// be renamed to.
+pub fn partial_downloads(files: &[File]) -> HashSet<usize> {
+    let names: HashSet<&Path> = files.iter().map(|f| f.as_path()).collect();
+    let temporary: HashSet<usize> = (0..files.len())
+        .filter(|i| {
+            let path = &files[*i].pathbuf;
+            has_type(path, &PARTIAL_DOWNLOAD_TYPES) || (has_type(path, &AMBIGUOUS_DOWNLOAD_TYPES)
+                && (is_recent(path) || names.contains(path.with_extension("").as_path())))
+        })
+        .collect();
+    let finals: HashSet<PathBuf> = temporary.iter().map(|i| files[*i].pathbuf.with_extension("")).collect();
+    (0..files.len())
+        .filter(|i| temporary.contains(i) || is_partial_download(&files[*i]) || finals.contains(files[*i].as_path()))
+        .collect()
+}
@@ -0,0 +1,177 @@
+//! Finding content-identical files. See [`Sorter::find_duplicates`](crate::Sorter::find_duplicates).
+//! With the `phash` feature, visually identical images can be found as well. See
+//! `Sorter::find_near_duplicates`.
+
+use crate::checksum::{self, HashAlgorithm};
+use crate::structs::File;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use walkdir::WalkDir;
+
+#[cfg(test)]
+/// Tests for finding duplicates. Each test is named after the function or struct
+/// it tests, prefixed with test.
+mod tests {
+
+    use std::{env, fs};
+    use super::find_duplicates;
+    use crate::checksum::HashAlgorithm;
+    use crate::structs::{File, Join};
+
+    #[test]
+    /// Test [`find_duplicates`] and [`super::DuplicateGroup`]
+    fn test_find_duplicates() {
+
+        // The directories and files to use for testing
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_find_duplicates"));
+        let other = dir.join(String::from("other"));
+        fs::create_dir_all(other.to_path_buf()).expect("Failed to create test dir.");
+        for (name, contents) in [("a.txt", "same"), ("b.txt", "same"), ("c.txt", "diff"), ("other/d.txt", "same")] {
+            fs::write(dir.join(String::from(name)).to_path_buf(), contents).expect("Failed to write test file.");
+        }
+
+        // Scanning the directory twice shouldn't report files twice
+        let groups = find_duplicates(&[&dir, &other], HashAlgorithm::Xxh3, 2).expect("Failed to find duplicates.");
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].size, 4);
+        assert_eq!(groups[0].files, vec![
+            dir.join(String::from("a.txt")),
+            dir.join(String::from("b.txt")),
+            other.join(String::from("d.txt"))
+        ]);
+        assert_eq!(groups[0].wasted_bytes(), 8);
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+}
+
+/// A group of images that look identical, but may be encoded differently.
+#[cfg(feature = "phash")]
+#[derive(Debug, PartialEq)]
+pub struct NearDuplicateGroup {
+    /// The paths of the images in the group, sorted.
+    pub files: Vec<File>,
+    /// The perceptual hashes of the images, index-wise with `files`.
+    pub hashes: Vec<u64>
+}
+
+/// A group of files with identical contents.
+#[derive(Debug, PartialEq)]
+pub struct DuplicateGroup {
+    /// The size of each file in the group, in bytes.
+    pub size: u64,
+    /// The hash of the contents of each file in the group, in the algorithm they
+    /// were hashed with.
+    pub hash: String,
+    /// The paths of the files in the group, sorted.
+    pub files: Vec<File>
+}
+impl DuplicateGroup {
+
+    /// Return the number of bytes that would be freed by keeping only one of the
+    /// files in the group.
+    pub fn wasted_bytes(&self) -> u64 {
+        self.size * (self.files.len() as u64 - 1)
+    }
+}
+
+/// Return the groups of content-identical files in `dirs` and their subdirectories,
+/// largest files first. Files found through more than one of `dirs` are only
+/// counted once.
+///
+/// Only files that share their size with another file are hashed, so this is much
+/// faster than hashing everything. The files are hashed with `algorithm`, on
+/// `workers` threads besides the calling one (see [`checksum::hash_files`]).
+pub fn find_duplicates(dirs: &[&File], algorithm: HashAlgorithm, workers: usize) -> io::Result<Vec<DuplicateGroup>> {
+
+    // Group all the files by size
+    let mut seen: HashSet<File> = HashSet::new();
+    let mut by_size: HashMap<u64, Vec<File>> = HashMap::new();
+    for dir in dirs {
+        for entry in WalkDir::new(&dir.pathbuf) {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let file = File::from(entry.path());
+            if seen.insert(file.copy()) {
+                by_size.entry(entry.metadata()?.len()).or_default().push(file);
+            }
+        }
+    }
+
+    // Hash the files that share their size, all at once, and group them by hash
+    let by_size: Vec<(u64, Vec<File>)> = by_size.into_iter().filter(|(_, files)| files.len() > 1).collect();
+    let paths: Vec<File> = by_size.iter().flat_map(|(_, files)| files).map(File::copy).collect();
+    let mut hashes = checksum::hash_files(&paths, algorithm, workers).into_iter();
+    let mut groups = Vec::new();
+    for (size, files) in by_size {
+        let mut by_hash: BTreeMap<String, Vec<File>> = BTreeMap::new();
+        for (file, hash) in files.into_iter().zip(hashes.by_ref()) {
+            by_hash.entry(hash?).or_default().push(file);
+        }
+        for (hash, mut files) in by_hash {
+            if files.len() > 1 {
+                files.sort_by(|a, b| a.pathbuf.cmp(&b.pathbuf));
+                groups.push(DuplicateGroup { size, hash, files });
+            }
+        }
+    }
+    groups.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.files[0].pathbuf.cmp(&b.files[0].pathbuf)));
+    Ok(groups)
+}
+
+/// Return the groups of visually identical images in `dirs` and their subdirectories.
+/// Two images are in the same group if the [`phash::distance`](crate::phash::distance)
+/// between their perceptual hashes is at most `max_distance`, or if they are both
+/// close enough to a third image in the group. Files that can't be read as images
+/// are ignored.
+///
+/// Every pair of images is compared, so this gets slow for very large collections.
+#[cfg(feature = "phash")]
+pub fn find_near_duplicates(dirs: &[&File], max_distance: u32) -> io::Result<Vec<NearDuplicateGroup>> {
+
+    // Hash all the images, skipping files found through more than one of dirs
+    let mut seen: HashSet<File> = HashSet::new();
+    let mut images: Vec<(File, u64)> = Vec::new();
+    for dir in dirs {
+        for entry in WalkDir::new(&dir.pathbuf) {
+            let entry = entry?;
+            let file = File::from(entry.path());
+            if entry.file_type().is_file() && seen.insert(file.copy()) {
+                if let Some(hash) = crate::phash::dhash(&file) {
+                    images.push((file, hash));
+                }
+            }
+        }
+    }
+    images.sort_by(|a, b| a.0.pathbuf.cmp(&b.0.pathbuf));
+
+    // Join up the images that are close enough, using a union-find
+    let mut parents: Vec<usize> = (0..images.len()).collect();
+    fn root(parents: &mut [usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+    for i in 0..images.len() {
+        for j in i + 1..images.len() {
+            if crate::phash::distance(images[i].1, images[j].1) <= max_distance {
+                let (a, b) = (root(&mut parents, i), root(&mut parents, j));
+                parents[b] = a;
+            }
+        }
+    }
+
+    // Collect the groups with more than one image
+    let mut by_root: BTreeMap<usize, NearDuplicateGroup> = BTreeMap::new();
+    for (i, (file, hash)) in images.into_iter().enumerate() {
+        let group = by_root.entry(root(&mut parents, i))
+            .or_insert_with(|| NearDuplicateGroup { files: Vec::new(), hashes: Vec::new() });
+        group.files.push(file);
+        group.hashes.push(hash);
+    }
+    Ok(by_root.into_values().filter(|g| g.files.len() > 1).collect())
+}
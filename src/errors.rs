@@ -1,5 +1,84 @@
+//! The errors returned while sorting. All of them are [`SortError`]s, which
+//! implement [`std::error::Error`], with the underlying error as their source
+//! where there is one.
+//!
+//! The messages are plain text, for logging and displaying in other interfaces.
+//! With the `colored` feature, they are colored for the terminal instead.
+
+use crate::structs::File;
+#[cfg(feature = "colored")]
 use colored::Colorize;
-use std::fmt;
+use serde::{Deserialize, Serialize};
+use std::{error, fmt, io};
+
+#[cfg(all(test, not(feature = "colored")))]
+/// Tests for errors. Each test is named after the struct or enum it tests,
+/// prefixed with test. The messages are only checked without colors.
+mod tests {
+
+    use std::{error::Error, io};
+    use super::{InvalidPathError, PathDoesNotExistError, SortError};
+    use crate::structs::File;
+
+    #[test]
+    /// Test [`SortError`]
+    fn test_sorterror() {
+        let error = SortError::from(PathDoesNotExistError { path: String::from("/source") });
+        assert_eq!(error.to_string(), "path \"/source\" does not exist");
+        // The message is the inner error's, so it isn't given again as the
+        // source, which error-chain printers would show twice
+        assert!(error.source().is_none());
+        let error = SortError::from(InvalidPathError { path: String::from("../a.txt"), reason: "the path leads out of its directory" });
+        assert!(error.source().is_none());
+
+        let error = SortError::from(io::Error::new(io::ErrorKind::NotFound, "gone"));
+        assert_eq!(error.to_string(), "I/O error: gone");
+        assert_eq!(error.source().unwrap().to_string(), "gone");
+
+        let error = SortError::ConflictUnresolved(File::from("/target/a.txt"));
+        assert_eq!(error.to_string(), "destination \"/target/a.txt\" already exists");
+        assert!(error.source().is_none());
+
+        let error = SortError::DirectoryNotCreated(File::from("/target/2023"), io::ErrorKind::PermissionDenied);
+        assert_eq!(error.to_string(), "directory \"/target/2023\" could not be created: permission denied");
+    }
+}
+
+/// Return `path` in bold, with the `colored` feature.
+fn highlight(path: &str) -> String {
+    #[cfg(feature = "colored")]
+    return path.bold().to_string();
+    #[cfg(not(feature = "colored"))]
+    return String::from(path);
+}
+
+/// Write the start of an error message to `f`: a red `Error:`, with the
+/// `colored` feature, and nothing otherwise.
+fn write_prefix(f: &mut fmt::Formatter) -> fmt::Result {
+    #[cfg(feature = "colored")]
+    write!(f, "{} ", "Error:".red())?;
+    #[cfg(not(feature = "colored"))]
+    let _ = f;
+    Ok(())
+}
+
+/// How strictly to handle anomalies while sorting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strictness {
+    /// Stop at the first anomaly. A destination that already exists in the
+    /// target stops the sort before anything is moved, and a file that fails to
+    /// move stops the sort right there. For archivists, who would rather fix
+    /// things by hand than have them worked around.
+    Strict,
+    /// Work around anomalies, and record them as warnings in the
+    /// [`SortReport`](crate::report::SortReport). Files whose destinations already
+    /// exist get sequential names, and files that fail to move are skipped. This
+    /// is the default.
+    #[default]
+    Lenient
+}
 
 /// The error message shown when the user tries to sort with source and/or target
 /// directories that don't exist
@@ -8,8 +87,131 @@ pub struct PathDoesNotExistError {
     pub path: String,
 }
 impl fmt::Display for PathDoesNotExistError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "{} path \"{}\" does not exist.", format!("Error:").red(), format!("{}", self.path).bold())
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_prefix(f)?;
+        write!(f, "path \"{}\" does not exist", highlight(&self.path))
+    }
+}
+impl error::Error for PathDoesNotExistError { }
+
+/// The error returned when a path passed to [`File::try_new`] isn't valid, or
+/// when a directory format would sort a file outside of where it should go.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidPathError {
+    /// The invalid path.
+    pub path: String,
+    /// Why the path isn't valid.
+    pub reason: &'static str
+}
+impl fmt::Display for InvalidPathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_prefix(f)?;
+        write!(f, "path \"{}\" is invalid: {}", highlight(&self.path), self.reason)
+    }
+}
+impl error::Error for InvalidPathError { }
+
+/// An error that stopped a sort from running, or stopped a single file from
+/// being sorted.
+#[derive(Debug)]
+pub enum SortError {
+    /// Reading, moving, or writing a file failed.
+    IoError(io::Error),
+    /// The configuration of the [`Sorter`](crate::Sorter) is invalid, for the
+    /// given reason.
+    InvalidConfig(String),
+    /// The source or target directory doesn't exist.
+    PathDoesNotExist(PathDoesNotExistError),
+    /// A path isn't valid.
+    InvalidPath(InvalidPathError),
+    /// The target directory is read-only.
+    NotWritable(File),
+    /// The timestamp of a file is outside the range of dates that can be sorted by.
+    UnsupportedTimestamp(File),
+    /// None of the date types to sort by are available for a file, as when the
+    /// filesystem doesn't record creation dates.
+    TimestampUnavailable(File),
+    /// The destination of a file already exists in the target, and strict mode
+    /// doesn't allow working around it.
+    ConflictUnresolved(File),
+    /// The contents of a file weren't the same after it was moved as before, in
+    /// [`ExecutionMode::ExecuteVerified`](crate::execution::ExecutionMode::ExecuteVerified)
+    /// mode, or in `ExecutionMode::ExecuteIntegrity` mode.
+    VerificationFailed(File),
+    /// The directory a file is sorted into couldn't be created, for the given
+    /// reason, as when a parent directory isn't writable.
+    DirectoryNotCreated(File, io::ErrorKind),
+    /// The sort was cancelled with its [`CancelToken`](crate::observers::CancelToken)
+    /// while the file was being copied.
+    Cancelled(File),
+    /// The file changed or disappeared after the sort was planned, with
+    /// [`StalePolicy::Error`](crate::stale::StalePolicy::Error).
+    FileChanged(File),
+    /// Another sort into the same target is running, holding this lock file
+    /// (see [`lock`](crate::lock)).
+    AlreadyRunning(File)
+}
+impl fmt::Display for SortError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SortError::PathDoesNotExist(error) => return error.fmt(f),
+            SortError::InvalidPath(error) => return error.fmt(f),
+            _ => (),
+        }
+        write_prefix(f)?;
+        match self {
+            SortError::IoError(error) => write!(f, "I/O error: {}", error),
+            SortError::InvalidConfig(reason) => write!(f, "invalid configuration: {}", reason),
+            SortError::PathDoesNotExist(_) | SortError::InvalidPath(_) => Ok(()),
+            SortError::NotWritable(path) => write!(f, "path \"{}\" is not writable", highlight(&path.to_string())),
+            SortError::UnsupportedTimestamp(path) => {
+                write!(f, "the timestamp of \"{}\" is out of range", highlight(&path.to_string()))
+            },
+            SortError::TimestampUnavailable(path) => {
+                write!(f, "none of the date types are available for \"{}\"", highlight(&path.to_string()))
+            },
+            SortError::ConflictUnresolved(path) => {
+                write!(f, "destination \"{}\" already exists", highlight(&path.to_string()))
+            },
+            SortError::VerificationFailed(path) => {
+                write!(f, "the contents of \"{}\" changed while it was moved", highlight(&path.to_string()))
+            },
+            SortError::Cancelled(path) => write!(f, "the sort was cancelled while copying \"{}\"", highlight(&path.to_string())),
+            SortError::DirectoryNotCreated(path, kind) => {
+                write!(f, "directory \"{}\" could not be created: {}", highlight(&path.to_string()), kind)
+            },
+            SortError::AlreadyRunning(path) => {
+                write!(f, "another sort is already running, holding \"{}\"", highlight(&path.to_string()))
+            },
+            SortError::FileChanged(path) => {
+                write!(f, "\"{}\" changed after the sort was planned", highlight(&path.to_string()))
+            },
+        }
+    }
+}
+impl error::Error for SortError {
+
+    /// The errors that are shown as they are, like [`PathDoesNotExistError`],
+    /// have no source, so that their messages aren't repeated.
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SortError::IoError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+impl From<io::Error> for SortError {
+    fn from(error: io::Error) -> SortError {
+        SortError::IoError(error)
+    }
+}
+impl From<InvalidPathError> for SortError {
+    fn from(error: InvalidPathError) -> SortError {
+        SortError::InvalidPath(error)
+    }
+}
+impl From<PathDoesNotExistError> for SortError {
+    fn from(error: PathDoesNotExistError) -> SortError {
+        SortError::PathDoesNotExist(error)
     }
 }
-impl std::error::Error for PathDoesNotExistError { }
\ No newline at end of file
@@ -0,0 +1,293 @@
+//! How a sort is carried out. See [`Sorter::sort_report`](crate::Sorter::sort_report).
+
+use crate::structs::File;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+#[cfg(test)]
+/// Tests for execution modes. Each test is named after the function or enum it
+/// tests, prefixed with test.
+mod tests {
+
+    use std::{collections::HashMap, env, fs, io::{Cursor, ErrorKind}};
+    use super::{confirm, create_dirs, destination_dirs, destination_groups, ExecutionMode};
+    use crate::structs::{File, Join};
+
+    #[test]
+    /// Test [`ExecutionMode`]
+    fn test_execution_mode() {
+        assert!(!ExecutionMode::DryRun.moves_files());
+        assert!(ExecutionMode::Execute.moves_files());
+        assert!(ExecutionMode::ExecuteVerified.moves_files());
+        assert!(ExecutionMode::ExecuteIntegrity.moves_files());
+        assert!(ExecutionMode::Interactive.moves_files());
+        assert_eq!(ExecutionMode::from(true), ExecutionMode::DryRun);
+        assert_eq!(ExecutionMode::from(false), ExecutionMode::Execute);
+    }
+
+    #[test]
+    /// Test [`confirm`]
+    fn test_confirm() {
+        let (old, new) = (File::from("a.jpg"), File::from("2023/a.jpg"));
+        let mut output = Vec::new();
+        assert!(confirm(&old, &new, &mut Cursor::new("y\n"), &mut output).unwrap());
+        assert_eq!(String::from_utf8(output).unwrap(), "Move a.jpg to 2023/a.jpg? [y/N] ");
+        assert!(confirm(&old, &new, &mut Cursor::new("Yes\n"), &mut Vec::new()).unwrap());
+        assert!(!confirm(&old, &new, &mut Cursor::new("n\n"), &mut Vec::new()).unwrap());
+        assert!(!confirm(&old, &new, &mut Cursor::new("\n"), &mut Vec::new()).unwrap());
+        assert!(!confirm(&old, &new, &mut Cursor::new(""), &mut Vec::new()).unwrap());
+    }
+
+    #[test]
+    /// Test [`destination_groups`]
+    fn test_destination_groups() {
+        let new: Vec<File> = ["2023/07/a.jpg", "2023/08/b.jpg", "2023/07/c.jpg", "d.jpg"].into_iter()
+            .map(File::from)
+            .collect();
+        assert_eq!(destination_groups(&new, &HashMap::new()), vec![vec![0, 2], vec![1], vec![3]]);
+        assert!(destination_groups(&[], &HashMap::new()).is_empty());
+
+        // Hard links are moved after the first file of their links
+        assert_eq!(destination_groups(&new, &HashMap::from([(3, 1)])), vec![vec![0, 2], vec![1, 3]]);
+    }
+
+    #[test]
+    /// Test [`destination_dirs`] and [`create_dirs`]
+    fn test_create_dirs() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_create_dirs"));
+        fs::create_dir_all(dir.to_path_buf()).expect("Failed to create test dir.");
+        fs::write(dir.join(String::from("blocked")).to_path_buf(), "").expect("Failed to write test file.");
+        let new: Vec<File> = ["2023/07/a.jpg", "2023/07/b.jpg", "2023/08/c.jpg", "blocked/d.jpg"].into_iter()
+            .map(|name| dir.join(String::from(name)))
+            .collect();
+
+        // Each directory is listed, and created, once
+        let dirs = destination_dirs(&new);
+        assert_eq!(dirs, vec![new[0].as_path().parent().unwrap(), new[2].as_path().parent().unwrap(), new[3].as_path().parent().unwrap()]);
+        let failed = create_dirs(&dirs, |_| Ok(()));
+        assert!(dir.join(String::from("2023/07")).pathbuf.is_dir());
+        assert!(dir.join(String::from("2023/08")).pathbuf.is_dir());
+        assert_eq!(failed.len(), 1);
+        assert!(failed.contains_key(new[3].as_path().parent().unwrap()));
+        assert_ne!(failed[new[3].as_path().parent().unwrap()], ErrorKind::NotFound);
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// Test [`create_dirs`] with [`set_mode`]
+    fn test_create_dirs_mode() {
+        use std::os::unix::fs::PermissionsExt;
+        use super::set_mode;
+
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_create_dirs_mode"));
+        let existing = dir.join(String::from("existing"));
+        fs::create_dir_all(existing.to_path_buf()).expect("Failed to create test dir.");
+        fs::set_permissions(existing.to_path_buf(), fs::Permissions::from_mode(0o755)).expect("Failed to set test dir mode.");
+
+        // Only the directories that are created get the mode
+        let (year, month) = (existing.join(String::from("2023")), existing.join(String::from("2023/07")));
+        assert!(create_dirs(&[month.as_path()], |dir| set_mode(dir, 0o700)).is_empty());
+        let mode = |dir: &File| fs::metadata(dir.to_path_buf()).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&existing), 0o755);
+        assert_eq!(mode(&year), 0o700);
+        assert_eq!(mode(&month), 0o700);
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// Test [`set_owner`]
+    fn test_set_owner() {
+        use std::os::unix::fs::MetadataExt;
+        use super::set_owner;
+
+        let path = File::from(env::temp_dir()).join(String::from("sorterylib_test_set_owner.txt"));
+        fs::write(path.to_path_buf(), "").expect("Failed to write test file.");
+        let metadata = fs::metadata(path.to_path_buf()).unwrap();
+
+        // Changing to the owner it already has is always allowed
+        set_owner(path.as_path(), None, None).expect("Failed to set owner.");
+        set_owner(path.as_path(), Some(metadata.uid()), Some(metadata.gid())).expect("Failed to set owner.");
+        let changed = fs::metadata(path.to_path_buf()).unwrap();
+        assert_eq!((changed.uid(), changed.gid()), (metadata.uid(), metadata.gid()));
+        assert!(set_owner(&path.as_path().join("missing"), Some(metadata.uid()), None).is_err());
+
+        fs::remove_file(path.to_path_buf()).expect("Failed to remove test file.");
+    }
+}
+
+/// How to carry out a sort.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExecutionMode {
+    /// Plan the sort without moving anything, to check what it would do.
+    DryRun,
+    /// Move the files.
+    #[default]
+    Execute,
+    /// Move the files, and make sure that each one has the same contents after
+    /// the move as before, by hashing it with the
+    /// [`Sorter::hash_algorithm`](crate::Sorter::hash_algorithm) on both sides.
+    /// Files that don't are listed as failures. This is slower, but worth it for
+    /// irreplaceable files.
+    ExecuteVerified,
+    /// Move the files with end-to-end integrity checks, for archives that need
+    /// a cryptographic guarantee that each sorted file matches its original
+    /// byte for byte: files are hashed with BLAKE3 as they are copied, read back
+    /// and hashed again once they are in the target, and recorded in the
+    /// target's integrity manifest. Files that don't match are listed as
+    /// failures. See the `integrity` module. Needs the `blake3` feature;
+    /// without it, sorts in this mode fail with
+    /// [`SortError::InvalidConfig`](crate::errors::SortError::InvalidConfig).
+    ExecuteIntegrity,
+    /// Ask on the terminal before moving each file, and leave the files that
+    /// aren't confirmed where they are.
+    Interactive
+}
+impl ExecutionMode {
+
+    /// Return [`true`] if this mode moves files, as all but
+    /// [`ExecutionMode::DryRun`] do.
+    pub fn moves_files(&self) -> bool {
+        *self != ExecutionMode::DryRun
+    }
+}
+impl From<bool> for ExecutionMode {
+
+    /// Return the mode of the old `dry_run` argument: [`ExecutionMode::DryRun`]
+    /// if `dry_run` is [`true`], and [`ExecutionMode::Execute`] otherwise.
+    fn from(dry_run: bool) -> ExecutionMode {
+        if dry_run { ExecutionMode::DryRun } else { ExecutionMode::Execute }
+    }
+}
+
+/// The system calls used to move files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    /// The standard library's, one call for each file. This is the default.
+    #[default]
+    Std,
+    /// io_uring, renaming files in batches, which is much faster for lots of
+    /// small files on fast storage (see the `uring` module). This is only used
+    /// to move files in [`ExecutionMode::Execute`] mode; copies, and verified
+    /// and interactive sorts, fall back to [`Backend::Std`], as do the files
+    /// that io_uring fails to rename. Needs Linux 5.11 or later, and the
+    /// `io-uring` feature; [`Sorter::validate`](crate::Sorter::validate)
+    /// rejects it anywhere else.
+    IoUring
+}
+
+/// Ask on `output` whether to move `old` to `new`, and return [`true`] if the
+/// answer read from `input` starts with `y` or `Y`. Anything else, including no
+/// answer at all, is a no.
+pub fn confirm(old: &File, new: &File, input: &mut impl BufRead, output: &mut impl Write) -> io::Result<bool> {
+    write!(output, "Move {} to {}? [y/N] ", old, new)?;
+    output.flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(answer.trim_start().starts_with(['y', 'Y']))
+}
+
+/// Return the indices of the files in `new` grouped by the directory they are
+/// sorted into, with the groups in the order their first files are planned, and
+/// the files of each group in plan order. Each group is moved by a single worker
+/// when [`Sorter::workers`](crate::Sorter::workers) is more than one, so that
+/// the files in each directory are still moved in the planned order. Files that
+/// are hard links, in `links` (see [`links::hard_links`](crate::links::hard_links)),
+/// go in the group of the first file of their links, after it, so that it is
+/// always moved before them.
+pub fn destination_groups(new: &[File], links: &HashMap<usize, usize>) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut by_dir: HashMap<&Path, usize> = HashMap::new();
+    let mut group_of: Vec<usize> = Vec::with_capacity(new.len());
+    for (i, file) in new.iter().enumerate() {
+        let dir = file.as_path().parent().unwrap_or(Path::new(""));
+        let group = match links.get(&i) {
+            Some(leader) => group_of[*leader],
+            None => *by_dir.entry(dir).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            }),
+        };
+        groups[group].push(i);
+        group_of.push(group);
+    }
+    groups
+}
+
+/// Return the distinct directories that the files in `new` are sorted into, in
+/// the order they are first planned.
+pub fn destination_dirs(new: &[File]) -> Vec<&Path> {
+    let mut seen: HashSet<&Path> = HashSet::new();
+    new.iter()
+        .filter_map(|file| file.as_path().parent())
+        .filter(|dir| !dir.as_os_str().is_empty() && seen.insert(dir))
+        .collect()
+}
+
+/// Create each of `dirs`, with any missing parents, and return the reasons the
+/// ones that couldn't be created weren't. Directories that already exist are
+/// left alone. This is done once for the whole plan before any file is moved,
+/// rather than for each file, so that the files of a directory that can't be
+/// created all fail for the same reason.
+/// 
+/// `on_created` is called with every directory that is created (but not with
+/// the ones that already existed), parents first, to set its permissions or
+/// owner; a directory for which it fails counts as not created.
+pub fn create_dirs<'a>(
+    dirs: &[&'a Path],
+    mut on_created: impl FnMut(&Path) -> io::Result<()>) -> HashMap<&'a Path, io::ErrorKind> {
+
+    dirs.iter()
+        .filter_map(|dir| create_dir(dir, &mut on_created).err().map(|error| (*dir, error.kind())))
+        .collect()
+}
+
+/// Create `dir` with any missing parents, and call `on_created` with each one
+/// that is created.
+fn create_dir(dir: &Path, on_created: &mut impl FnMut(&Path) -> io::Result<()>) -> io::Result<()> {
+    let missing: Vec<&Path> = dir.ancestors()
+        .take_while(|dir| !dir.as_os_str().is_empty() && !dir.is_dir())
+        .collect();
+    fs::create_dir_all(dir)?;
+    for dir in missing.into_iter().rev() {
+        on_created(dir)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+/// Set the permissions of `path` to `mode`, whatever the umask is.
+pub fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+/// Permission modes are not supported on this platform, so leave `path` alone.
+pub fn set_mode(_path: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+/// Change the owning user of `path` to `owner` and its group to `group`, leaving
+/// either one alone if it is [`None`]. Changing the owner usually needs root.
+pub fn set_owner(path: &Path, owner: Option<u32>, group: Option<u32>) -> io::Result<()> {
+    if owner.is_none() && group.is_none() {
+        return Ok(());
+    }
+    std::os::unix::fs::chown(path, owner, group)
+}
+
+#[cfg(not(unix))]
+/// Ownership is not supported on this platform, so leave `path` alone.
+pub fn set_owner(_path: &Path, _owner: Option<u32>, _group: Option<u32>) -> io::Result<()> {
+    Ok(())
+}
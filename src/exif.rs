@@ -0,0 +1,162 @@
+//! Reading EXIF metadata from photos. Needs the `exif` feature.
+
+use crate::structs::File;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use ::exif::{In, Reader, Tag, Value};
+use std::io::BufReader;
+
+#[cfg(test)]
+/// Tests for reading EXIF metadata. Each test is named after the function it
+/// tests, prefixed with test.
+mod tests {
+
+    use ::exif::{experimental::Writer, Field, In, Rational, Tag, Value};
+    use chrono::{Local, TimeZone};
+    use std::{env, fs, io::Cursor};
+    use super::{read, ExifData};
+    use crate::structs::{File, Join};
+
+    /// Return the [`Rational`]s for the degrees, minutes, and seconds of `deg`
+    fn dms(deg: u32, min: u32, sec: u32) -> Value {
+        Value::Rational(vec![
+            Rational { num: deg, denom: 1 },
+            Rational { num: min, denom: 1 },
+            Rational { num: sec, denom: 1 }
+        ])
+    }
+
+    #[test]
+    /// Test [`read`] using a TIFF file with GPS coordinates
+    fn test_read() {
+
+        // Write a TIFF file with the coordinates of Rome
+        let fields = [
+            Field { tag: Tag::GPSLatitudeRef, ifd_num: In::PRIMARY, value: Value::Ascii(vec![b"N".to_vec()]) },
+            Field { tag: Tag::GPSLatitude, ifd_num: In::PRIMARY, value: dms(41, 54, 0) },
+            Field { tag: Tag::GPSLongitudeRef, ifd_num: In::PRIMARY, value: Value::Ascii(vec![b"W".to_vec()]) },
+            Field { tag: Tag::GPSLongitude, ifd_num: In::PRIMARY, value: dms(12, 30, 0) },
+            Field { tag: Tag::Make, ifd_num: In::PRIMARY, value: Value::Ascii(vec![b"Canon".to_vec()]) },
+            Field { tag: Tag::Model, ifd_num: In::PRIMARY, value: Value::Ascii(vec![b"Canon EOS 5D ".to_vec()]) },
+            Field { tag: Tag::DateTimeOriginal, ifd_num: In::PRIMARY, value: Value::Ascii(vec![b"2023:07:14 10:30:12".to_vec()]) }
+        ];
+        let mut writer = Writer::new();
+        for field in &fields {
+            writer.push_field(field);
+        }
+        let mut buf = Cursor::new(Vec::new());
+        writer.write(&mut buf, false).expect("Failed to write EXIF data.");
+
+        let path = File::from(env::temp_dir()).join(String::from("sorterylib_test_exif_read.tif"));
+        fs::write(path.to_path_buf(), buf.into_inner()).expect("Failed to write test file.");
+
+        let data = read(&path).expect("Failed to read EXIF data.");
+        let (lat, lon) = data.gps.expect("No GPS coordinates.");
+        assert!((lat - 41.9).abs() < 1e-9);
+        assert!((lon + 12.5).abs() < 1e-9);
+        assert_eq!(data.make, Some(String::from("Canon")));
+        assert_eq!(data.model, Some(String::from("Canon EOS 5D")));
+        assert_eq!(data.camera(), Some(String::from("Canon EOS 5D")));
+        assert_eq!(data.date_taken, Some(Local.with_ymd_and_hms(2023, 7, 14, 10, 30, 12).unwrap()));
+
+        fs::remove_file(path.to_path_buf()).expect("Failed to remove test file.");
+
+        // Files without EXIF data don't have any
+        assert!(read(&File::from("Cargo.toml")).is_none());
+    }
+
+    #[test]
+    /// Test [`ExifData::camera`]
+    fn test_exifdata_camera() {
+        let data = |make: Option<&str>, model: Option<&str>| ExifData {
+            make: make.map(String::from),
+            model: model.map(String::from),
+            ..Default::default()
+        };
+        assert_eq!(data(Some("Apple"), Some("iPhone 12")).camera(), Some(String::from("Apple iPhone 12")));
+        assert_eq!(data(Some("Canon"), Some("Canon EOS R5")).camera(), Some(String::from("Canon EOS R5")));
+        assert_eq!(data(None, Some("X100V")).camera(), Some(String::from("X100V")));
+        assert_eq!(data(None, None).camera(), None);
+    }
+}
+
+/// The value of the `{camera}` placeholder for files without a camera make or model.
+pub const UNKNOWN_CAMERA: &str = "unknown_camera";
+
+/// The EXIF metadata of a file that SorteryLib uses.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExifData {
+    /// The GPS coordinates where the photo was taken, as `(latitude, longitude)`
+    /// in decimal degrees. South and west are negative.
+    pub gps: Option<(f64, f64)>,
+    /// The manufacturer of the camera, as in `"Canon"`.
+    pub make: Option<String>,
+    /// The model of the camera, as in `"Canon EOS 5D"`.
+    pub model: Option<String>,
+    /// The software that created or last edited the file.
+    pub software: Option<String>,
+    /// When the photo was taken, in the camera's time zone, which is taken to be
+    /// the local one.
+    pub date_taken: Option<DateTime<Local>>
+}
+impl ExifData {
+
+    /// Return the name of the camera, made from its make and model. The make is
+    /// left out if the model already starts with it, so that a Canon EOS 5D is
+    /// `"Canon EOS 5D"`, not `"Canon Canon EOS 5D"`.
+    pub fn camera(&self) -> Option<String> {
+        match (&self.make, &self.model) {
+            (Some(make), Some(model)) if model.to_lowercase().starts_with(&make.to_lowercase()) => {
+                Some(model.clone())
+            },
+            (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+            (make, model) => make.clone().or_else(|| model.clone()),
+        }
+    }
+}
+
+/// Return the EXIF metadata of `path`, or [`None`] if it can't be read or the file
+/// doesn't have any. Supports JPEG, TIFF (and TIFF-based RAW formats), HEIF, PNG,
+/// and WebP files.
+pub fn read(path: &File) -> Option<ExifData> {
+    let file = std::fs::File::open(&path.pathbuf).ok()?;
+    let exif = Reader::new().read_from_container(&mut BufReader::new(file)).ok()?;
+
+    Some(ExifData {
+        gps: read_coordinate(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S')
+            .zip(read_coordinate(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W')),
+        make: read_string(&exif, Tag::Make),
+        model: read_string(&exif, Tag::Model),
+        software: read_string(&exif, Tag::Software),
+        date_taken: read_string(&exif, Tag::DateTimeOriginal)
+            .and_then(|date| NaiveDateTime::parse_from_str(&date, "%Y:%m:%d %H:%M:%S").ok())
+            .and_then(|date| Local.from_local_datetime(&date).earliest())
+    })
+}
+
+/// Return the text in the `tag` field of `exif`, with surrounding whitespace
+/// removed, or [`None`] if it is missing or empty.
+fn read_string(exif: &::exif::Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => {
+            let text = String::from_utf8_lossy(values.first()?).trim().to_string();
+            if text.is_empty() { None } else { Some(text) }
+        },
+        _ => None,
+    }
+}
+
+/// Return the coordinate in the `tag` field of `exif` in decimal degrees. The
+/// coordinate is negative if the first letter of the `ref_tag` field is `negative`.
+fn read_coordinate(exif: &::exif::Exif, tag: Tag, ref_tag: Tag, negative: u8) -> Option<f64> {
+    let dms = match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Rational(dms) if dms.len() == 3 => dms.clone(),
+        _ => return None,
+    };
+    let degrees = dms[0].to_f64() + dms[1].to_f64() / 60.0 + dms[2].to_f64() / 3600.0;
+
+    let is_negative = match &exif.get_field(ref_tag, In::PRIMARY)?.value {
+        Value::Ascii(refs) => refs.first().and_then(|r| r.first()) == Some(&negative),
+        _ => false,
+    };
+    Some(if is_negative { -degrees } else { degrees })
+}
@@ -0,0 +1,176 @@
+//! Matching files against the type filters of a [`Sorter`](crate::Sorter), its
+//! `only_type` and `exclude_type`.
+//!
+//! A type is a file extension without the dot, as in `"jpg"`. Types can contain
+//! the wildcards `*`, matching any number of characters, and `?`, matching a
+//! single character, so `"jp*"` matches `jpg` and `jpeg`, and `"*"` matches
+//! every file.
+//!
+//! The types are collected into a [`TypeFilter`] once per sort, so that most
+//! files are matched with a single set lookup.
+
+use std::collections::HashSet;
+
+#[cfg(test)]
+/// Tests for filters. Each test is named after the function it tests, prefixed
+/// with test.
+mod tests {
+
+    use super::{matches_type, TypeFilter, TypeSet};
+
+    /// Return `types` as a [`Vec<String>`]
+    fn strings(types: &[&str]) -> Vec<String> {
+        types.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    /// Test [`matches_type`]
+    fn test_matches_type() {
+        assert!(matches_type("jpg", "jpg"));
+        assert!(!matches_type("jpg", "jpeg"));
+        assert!(matches_type("jp*", "jpg"));
+        assert!(matches_type("jp*", "jpeg"));
+        assert!(matches_type("jp*", "jp"));
+        assert!(!matches_type("jp*", "png"));
+        assert!(matches_type("*", "png"));
+        assert!(matches_type("*", ""));
+        assert!(matches_type("m?v", "mkv"));
+        assert!(!matches_type("m?v", "mv"));
+        assert!(matches_type("*.gz", "tar.gz"));
+        assert!(matches_type("", ""));
+        assert!(!matches_type("", "txt"));
+    }
+
+    #[test]
+    /// Test [`TypeSet`]
+    fn test_type_set() {
+        let set = TypeSet::new(&strings(&["jpg", "mp?", "", "tar-gz"]));
+        assert!(set.contains("jpg"));
+        assert!(set.contains("mp4"));
+        assert!(set.contains("tar-gz"));
+        assert!(!set.contains("png"));
+        assert!(!set.contains(""));
+        assert!(TypeSet::new(&strings(&[""])).is_empty());
+    }
+
+    #[test]
+    /// Test [`TypeFilter`]
+    fn test_type_filter() {
+        let exclude = TypeFilter::new(&strings(&["jp*"]), &[]);
+        assert!(!exclude.allows("jpg"));
+        assert!(exclude.allows("png"));
+        assert!(exclude.allows(""));
+
+        let only = TypeFilter::new(&strings(&["txt"]), &strings(&["jpg", "png"]));
+        assert!(only.allows("jpg"));
+        assert!(!only.allows("txt"));
+        assert!(!only.allows(""));
+
+        let everything = TypeFilter::new(&strings(&["txt"]), &strings(&["*"]));
+        assert!(everything.allows("jpg"));
+        assert!(!everything.allows("txt"));
+        assert!(TypeFilter::new(&[], &[]).is_empty());
+        assert!(!everything.is_empty());
+    }
+}
+
+/// Return [`true`] if the extension `extension` matches the type `pattern`,
+/// which can contain the wildcards `*` and `?`.
+pub fn matches_type(pattern: &str, extension: &str) -> bool {
+    let (pattern, extension): (Vec<char>, Vec<char>) = (pattern.chars().collect(), extension.chars().collect());
+
+    // Match greedily, going back to the last `*` when we get stuck
+    let (mut p, mut e) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while e < extension.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == extension[e]) {
+            p += 1;
+            e += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, e));
+            p += 1;
+        } else if let Some((star_p, star_e)) = star {
+            p = star_p + 1;
+            e = star_e + 1;
+            star = Some((star_p, star_e + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// A set of types, some of which can contain wildcards.
+#[derive(Clone, Debug, Default)]
+pub struct TypeSet {
+    /// The types without wildcards, matched with a single lookup
+    exact: HashSet<String>,
+    /// The types with wildcards, matched one by one
+    patterns: Vec<String>
+}
+impl TypeSet {
+
+    /// Return a new [`TypeSet`] of `types`. Empty types are ignored.
+    pub fn new(types: &[String]) -> TypeSet {
+        let mut set = TypeSet::default();
+        for t in types.iter().filter(|t| !t.is_empty()) {
+            if t.contains(['*', '?']) {
+                set.patterns.push(t.clone());
+            } else {
+                set.exact.insert(t.clone());
+            }
+        }
+        set
+    }
+
+    /// Return [`true`] if `extension` matches any of our types.
+    pub fn contains(&self, extension: &str) -> bool {
+        self.exact.contains(extension) || self.patterns.iter().any(|p| matches_type(p, extension))
+    }
+
+    /// Return [`true`] if we don't have any types.
+    pub fn is_empty(&self) -> bool {
+        self.exact.is_empty() && self.patterns.is_empty()
+    }
+}
+
+/// A pair of `exclude` and `only` types, as in a [`Sorter`](crate::Sorter)'s
+/// `exclude_type` and `only_type`.
+#[derive(Clone, Debug, Default)]
+pub struct TypeFilter {
+    /// The types to leave out
+    pub exclude: TypeSet,
+    /// The only types to let through, if [`Some`]. This overrides `exclude`.
+    pub only: Option<TypeSet>
+}
+impl TypeFilter {
+
+    /// Return a new [`TypeFilter`] that excludes the types `exclude`, or lets only
+    /// the types `only` through if it doesn't match everything (see
+    /// [`matches_everything`]).
+    pub fn new(exclude: &[String], only: &[String]) -> TypeFilter {
+        TypeFilter {
+            exclude: TypeSet::new(exclude),
+            only: if only.is_empty() || matches_everything(only) { None } else { Some(TypeSet::new(only)) },
+        }
+    }
+
+    /// Return [`true`] if `extension` gets through the filter.
+    pub fn allows(&self, extension: &str) -> bool {
+        match &self.only {
+            Some(only) => only.contains(extension),
+            None => !self.exclude.contains(extension),
+        }
+    }
+
+    /// Return [`true`] if the filter lets everything through.
+    pub fn is_empty(&self) -> bool {
+        self.only.is_none() && self.exclude.is_empty()
+    }
+}
+
+/// Return [`true`] if `types` matches every file: if it contains `"*"`, or an
+/// empty type.
+pub fn matches_everything(types: &[String]) -> bool {
+    types.iter().any(|t| t.is_empty() || t.chars().all(|c| c == '*'))
+}
@@ -0,0 +1,144 @@
+//! Grouping of files by their timestamps.
+//!
+//! Grouping puts files that belong together in time into a shared subfolder of
+//! the date directory, instead of scattering them among all the other files of
+//! the same month. See [`Sorter::burst_interval`](crate::Sorter::burst_interval)
+//! and [`Sorter::event_gap`](crate::Sorter::event_gap).
+
+use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
+
+#[cfg(test)]
+/// Tests for grouping. Each test is named after the function it tests, prefixed
+/// with test.
+mod tests {
+
+    use chrono::{DateTime, Local, TimeZone};
+    use super::{burst_groups, clusters, event_groups};
+
+    /// Return a [`DateTime`] at `secs` seconds after 2023-07-14 10:30:12 local time.
+    fn time(secs: i64) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2023, 7, 14, 10, 30, 12).unwrap() + chrono::Duration::seconds(secs)
+    }
+
+    #[test]
+    /// Test [`clusters`]
+    fn test_clusters() {
+        let times = vec![time(100), time(0), time(1), time(50), time(3)];
+        assert_eq!(clusters(&times, 2), vec![vec![1, 2, 4], vec![3], vec![0]]);
+        assert_eq!(clusters(&times, 60), vec![vec![1, 2, 4, 3, 0]]);
+        assert!(clusters(&[], 60).is_empty());
+    }
+
+    #[test]
+    /// Test [`burst_groups`]
+    fn test_burst_groups() {
+        let times = vec![time(100), time(0), time(1), time(50), time(3)];
+        let groups = burst_groups(&times, 2);
+
+        // The three files within two seconds of each other share a burst named
+        // after the first of them, and the other two aren't in a burst.
+        assert_eq!(groups[0], None);
+        assert_eq!(groups[3], None);
+        for i in [1, 2, 4] {
+            let group = groups[i].clone().unwrap();
+            assert_eq!(group.anchor, time(0));
+            assert_eq!(group.name, "burst_103012");
+        }
+    }
+
+    #[test]
+    /// Test [`event_groups`]
+    fn test_event_groups() {
+
+        // Two events on the first day, split by a three hour gap, and one on the next
+        let times = vec![time(0), time(3600), time(4 * 3600), time(4 * 3600 + 60), time(24 * 3600)];
+        let groups = event_groups(&times, 2 * 3600);
+
+        let names: Vec<String> = groups.iter().map(|g| g.name.clone()).collect();
+        assert_eq!(names, vec!["event_103012", "event_103012", "event_143012", "event_143012", "event_103012"]);
+        assert_eq!(groups[1].anchor, time(0));
+        assert_eq!(groups[3].anchor, time(4 * 3600));
+        assert_eq!(groups[4].anchor, time(24 * 3600));
+    }
+}
+
+/// Split the files with the timestamps `times` into clusters, so that within a
+/// cluster each file is at most `max_gap` seconds after the one before it. Returns
+/// the indices of the files in each cluster, in chronological order.
+pub fn clusters(times: &[DateTime<Local>], max_gap: u64) -> Vec<Vec<usize>> {
+
+    // Go through the files in chronological order
+    let mut order: Vec<usize> = (0..times.len()).collect();
+    order.sort_by_key(|i| times[*i]);
+
+    let mut clusters: Vec<Vec<usize>> = Vec::new();
+    let mut last: Option<DateTime<Local>> = None;
+    for i in order {
+        match (last, clusters.last_mut()) {
+            (Some(last), Some(cluster)) if (times[i] - last).num_seconds() as u64 <= max_gap => {
+                cluster.push(i);
+            },
+            _ => clusters.push(vec![i]),
+        }
+        last = Some(times[i]);
+    }
+    clusters
+}
+
+/// A group of files that belong together in time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Group {
+    /// The timestamp of the first file in the group. This decides the date
+    /// directory of the whole group, so that a group is never split up.
+    pub anchor: DateTime<Local>,
+    /// The name of the group's folder, as in `burst_103012`.
+    pub name: String
+}
+
+/// Return the burst that each of the files with the timestamps `times` belongs
+/// to. A burst is a cluster of two or more files taken at most `interval` seconds
+/// apart (see [`clusters`]), and is named after the time of its first file, as
+/// in `burst_103012`. Files that aren't part of a burst get [`None`].
+pub fn burst_groups(times: &[DateTime<Local>], interval: u64) -> Vec<Option<Group>> {
+
+    let mut groups = vec![None; times.len()];
+    for cluster in clusters(times, interval) {
+        if cluster.len() < 2 {
+            continue;
+        }
+        let anchor = times[cluster[0]];
+        let name = anchor.format("burst_%H%M%S").to_string();
+        for i in cluster {
+            groups[i] = Some(Group { anchor, name: name.clone() });
+        }
+    }
+    groups
+}
+
+/// Return the event that each of the files with the timestamps `times` belongs
+/// to. Each day's files are split into events wherever there is a gap of more
+/// than `gap` seconds between two consecutive files, the way photo managers build
+/// event albums. Events are named after the time of their first file, as in
+/// `event_103012`.
+pub fn event_groups(times: &[DateTime<Local>], gap: u64) -> Vec<Group> {
+
+    // Split the files up by day, and cluster each day's files separately
+    let mut days: BTreeMap<_, Vec<usize>> = BTreeMap::new();
+    for (i, time) in times.iter().enumerate() {
+        days.entry(time.date_naive()).or_default().push(i);
+    }
+
+    let mut groups: Vec<Option<Group>> = vec![None; times.len()];
+    for indices in days.values() {
+        let day_times: Vec<DateTime<Local>> = indices.iter().map(|i| times[*i]).collect();
+        for cluster in clusters(&day_times, gap) {
+            let anchor = day_times[cluster[0]];
+            let name = anchor.format("event_%H%M%S").to_string();
+            for n in cluster {
+                groups[indices[n]] = Some(Group { anchor, name: name.clone() });
+            }
+        }
+    }
+    groups.into_iter().map(|g| g.expect("Every file belongs to an event.")).collect()
+}
@@ -0,0 +1,196 @@
+//! The history of the sorts into a target, in one portable file. See [`History`].
+//!
+//! Every sort can be recorded in a [`History`], along with [`Manifest`]s of the
+//! target, and the whole history saved as a single JSON file. Paths in the
+//! target are stored relative to it, with `/` separators, like in manifests, so
+//! a history exported on one machine can be imported on another one that the
+//! archive has moved to, and resolved against the target's new location there.
+//!
+//! With [`Sorter::record_history`](crate::Sorter::record_history), every sort
+//! that moves files records itself in the [`HISTORY_FILE_NAME`] in the target.
+//! Otherwise, sorts are recorded by calling [`History::record`] with their
+//! reports, and manifests are only ever added by hand. Either way,
+//! [`History::files`] says where every sorted file came from and where it is
+//! now, which is what undoing the sorts takes: moving each file back to its
+//! original path.
+
+use chrono::{DateTime, Utc};
+use crate::manifest::{self, Manifest};
+use crate::report::SortReport;
+use crate::structs::{File, Join};
+use serde::{Deserialize, Serialize};
+use std::{fs, io};
+
+#[cfg(test)]
+/// Tests for sort histories. Each test is named after the function or struct it
+/// tests, prefixed with test.
+mod tests {
+
+    use std::{env, fs};
+    use super::{History, HistorySort};
+    use crate::checksum::HashAlgorithm;
+    use crate::manifest::Manifest;
+    use crate::report::SortReport;
+    use crate::structs::{File, Join};
+
+    #[test]
+    /// Test [`History`]
+    fn test_history() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_history"));
+        let (target, moved) = (dir.join(String::from("target")), dir.join(String::from("nas")));
+        fs::create_dir_all(target.join(String::from("2022")).to_path_buf()).expect("Failed to create test dir.");
+        fs::write(target.join(String::from("2022/a.txt")).to_path_buf(), "a").expect("Failed to write test file.");
+
+        // Record a sort, and a manifest of the target after it
+        let report = SortReport {
+            old: vec![File::from("/source/a.txt")],
+            new: vec![target.join(String::from("2022/a.txt"))],
+            ..Default::default()
+        };
+        let mut history = History::default();
+        history.record(&report, &target);
+        history.manifests.push(Manifest::of_dir(&target, HashAlgorithm::Xxh3, 0).expect("Failed to make manifest."));
+        assert_eq!(history.sorts[0].files[0].path, "2022/a.txt");
+
+        // Exporting and importing gives the same history, whose files are
+        // found wherever the target is now
+        let path = dir.join(String::from("history.json"));
+        history.export(&path).expect("Failed to export history.");
+        let imported = History::import(&path).expect("Failed to import history.");
+        assert_eq!(imported, history);
+        assert_eq!(imported.files(&moved), vec![(File::from("/source/a.txt"), moved.join(String::from("2022/a.txt")))]);
+
+        // Merging a history into itself adds nothing
+        let mut merged = imported.clone();
+        merged.merge(imported);
+        assert_eq!(merged, history);
+
+        // Sorts recorded in different time zones are merged in the order they
+        // happened, not in the order of their local times
+        let sort = |sorted_at: &str| HistorySort { sorted_at: String::from(sorted_at), files: Vec::new() };
+        let mut tokyo = History { sorts: vec![sort("2024-01-01T09:00:00+09:00")], ..Default::default() };
+        let london = History { sorts: vec![sort("2024-01-01T01:00:00+00:00")], ..Default::default() };
+        tokyo.merge(london);
+        assert_eq!(tokyo.sorts, vec![sort("2024-01-01T09:00:00+09:00"), sort("2024-01-01T01:00:00+00:00")]);
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+}
+
+/// The name of the file in the target that sorts are recorded in, with
+/// [`Sorter::record_history`](crate::Sorter::record_history).
+pub const HISTORY_FILE_NAME: &str = ".sortery.history.json";
+
+/// The version of the format of exported histories. Histories of newer
+/// versions can't be imported.
+pub const HISTORY_VERSION: u32 = 1;
+
+/// A file that was sorted, as recorded in a [`History`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub struct HistoryFile {
+    /// The path the file was sorted from.
+    pub original: String,
+    /// The path the file was sorted to, relative to the target, with `/`
+    /// separators.
+    pub path: String
+}
+
+/// One sort, as recorded in a [`History`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub struct HistorySort {
+    /// When the sort was recorded, in RFC 3339 format, in UTC.
+    pub sorted_at: String,
+    /// The files that were sorted without failing.
+    pub files: Vec<HistoryFile>
+}
+
+/// The sorts into a target, and manifests of it, in the order they were added.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub struct History {
+    /// The version of the format, [`HISTORY_VERSION`] for new histories.
+    pub version: u32,
+    /// The sorts into the target.
+    pub sorts: Vec<HistorySort>,
+    /// The manifests of the target.
+    pub manifests: Vec<Manifest>
+}
+impl Default for History {
+
+    /// Return an empty history of the current version.
+    fn default() -> History {
+        History { version: HISTORY_VERSION, sorts: Vec::new(), manifests: Vec::new() }
+    }
+}
+impl History {
+
+    /// Record the files sorted in `report` into `target`.
+    pub fn record(&mut self, report: &SortReport, target: &File) {
+        let files = report.sorted()
+            .map(|(old, new)| HistoryFile { original: old.to_string(), path: manifest::relative_path(target, new) })
+            .collect();
+        self.sorts.push(HistorySort { sorted_at: Utc::now().to_rfc3339(), files });
+    }
+
+    /// Add the sorts and manifests of `other` that aren't in this history yet,
+    /// as when importing the history of the same target from another machine.
+    /// The sorts are put in the order they happened in, whatever time zones
+    /// they were recorded in.
+    pub fn merge(&mut self, other: History) {
+        for sort in other.sorts {
+            if !self.sorts.contains(&sort) {
+                self.sorts.push(sort);
+            }
+        }
+        for manifest in other.manifests {
+            if !self.manifests.contains(&manifest) {
+                self.manifests.push(manifest);
+            }
+        }
+        self.sorts.sort_by_cached_key(|sort| DateTime::parse_from_rfc3339(&sort.sorted_at).ok());
+    }
+
+    /// Return the original paths of all the files sorted, with their paths in
+    /// `target`, wherever the target is now.
+    pub fn files(&self, target: &File) -> Vec<(File, File)> {
+        self.sorts.iter()
+            .flat_map(|sort| &sort.files)
+            .map(|file| (File::from(file.original.as_str()), target.join(file.path.clone())))
+            .collect()
+    }
+
+    /// Return the history recorded in `target` (see [`HISTORY_FILE_NAME`]), or
+    /// an empty one if there is none yet.
+    pub fn load(target: &File) -> io::Result<History> {
+        match History::import(&target.join(String::from(HISTORY_FILE_NAME))) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(History::default()),
+            result => result,
+        }
+    }
+
+    /// Record the history in `target`, for later sorts (see
+    /// [`HISTORY_FILE_NAME`]).
+    pub fn save(&self, target: &File) -> io::Result<()> {
+        self.export(&target.join(String::from(HISTORY_FILE_NAME)))
+    }
+
+    /// Save the history as JSON in `path`, to import it elsewhere.
+    pub fn export(&self, path: &File) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(&path.pathbuf, json)
+    }
+
+    /// Return the history saved as JSON in `path`. Fails with
+    /// [`io::ErrorKind::InvalidData`] if it isn't a history, or is of a newer
+    /// version than [`HISTORY_VERSION`].
+    pub fn import(path: &File) -> io::Result<History> {
+        let json = fs::read_to_string(&path.pathbuf)?;
+        let history: History = serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if history.version > HISTORY_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported history version {}", history.version)));
+        }
+        Ok(history)
+    }
+}
@@ -0,0 +1,146 @@
+//! The sizes and shapes of images, for sorting wallpapers and thumbnails apart
+//! from real photos. Needs the `image` feature. See [`ImageInfo`].
+//!
+//! Only the headers of the images are read, not their pixels. Sizes are as the
+//! images are shown, so a photo taken upright, which is stored sideways with an
+//! EXIF orientation saying so, is a portrait.
+
+use crate::structs::File;
+use image::{metadata::Orientation as Transform, ImageDecoder, ImageReader};
+
+#[cfg(test)]
+/// Tests for image properties. Each test is named after the function or struct
+/// it tests, prefixed with test.
+mod tests {
+
+    use image::{GrayImage, Luma};
+    use super::{ImageClass, ImageInfo, Orientation};
+    use crate::testing::{Entry, Sandbox};
+
+    #[test]
+    /// Test [`ImageInfo`]
+    fn test_imageinfo() {
+        let sandbox = Sandbox::new(&[Entry::file("source/notes.txt").contents("not an image")])
+            .expect("Failed to create sandbox.");
+        let path = sandbox.path("source/photo.png");
+        GrayImage::from_pixel(120, 200, Luma([0])).save(path.to_path_buf()).expect("Failed to save image.");
+
+        let info = ImageInfo::read(&path).expect("Failed to read image.");
+        assert_eq!(info, ImageInfo { width: 120, height: 200 });
+        assert_eq!((info.orientation(), info.class()), (Orientation::Portrait, ImageClass::Thumbnail));
+        assert_eq!(ImageInfo::read(&sandbox.path("source/notes.txt")), None);
+
+        // Wallpapers have the shapes of screens, and photos don't
+        let [wallpaper, photo] = [(2560, 1440), (4000, 3000)].map(|(width, height)| ImageInfo { width, height });
+        assert_eq!((wallpaper.orientation(), wallpaper.class()), (Orientation::Landscape, ImageClass::Wallpaper));
+        assert_eq!((photo.orientation(), photo.class()), (Orientation::Landscape, ImageClass::Photo));
+        assert_eq!(ImageInfo { width: 10, height: 10 }.orientation().name(), "square");
+    }
+}
+
+/// The value of the image placeholders for files that aren't images, or can't
+/// be read.
+pub const UNKNOWN_IMAGE: &str = "unknown";
+
+/// Images whose longest side is at most this many pixels are thumbnails.
+pub const THUMBNAIL_SIZE: u32 = 256;
+
+/// Images at least this many pixels wide, in the shape of a screen (see
+/// [`SCREEN_RATIOS`]), are wallpapers.
+pub const WALLPAPER_WIDTH: u32 = 1280;
+
+/// The aspect ratios of screens, as width to height: 16:9, 16:10, 21:9, and 32:9.
+pub const SCREEN_RATIOS: [(u32, u32); 4] = [(16, 9), (16, 10), (21, 9), (32, 9)];
+
+/// Whether an image is taller than it is wide, or the other way around.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    /// Taller than wide.
+    Portrait,
+    /// Wider than tall.
+    Landscape,
+    /// As wide as tall.
+    Square
+}
+impl Orientation {
+
+    /// Return the name of the orientation, as in `portrait`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Orientation::Portrait => "portrait",
+            Orientation::Landscape => "landscape",
+            Orientation::Square => "square",
+        }
+    }
+}
+
+/// What an image probably is, judging by its size and shape.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageClass {
+    /// A small image, like a thumbnail or an icon (see [`THUMBNAIL_SIZE`]).
+    Thumbnail,
+    /// A large image in the shape of a screen (see [`WALLPAPER_WIDTH`]).
+    Wallpaper,
+    /// Any other image, like a photo.
+    Photo
+}
+impl ImageClass {
+
+    /// Return the name of the class, as in `wallpaper`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImageClass::Thumbnail => "thumbnail",
+            ImageClass::Wallpaper => "wallpaper",
+            ImageClass::Photo => "photo",
+        }
+    }
+}
+
+/// The size of an image, in pixels, as it is shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageInfo {
+    /// The width of the image.
+    pub width: u32,
+    /// The height of the image.
+    pub height: u32
+}
+impl ImageInfo {
+
+    /// Return the size of the image at `path`, or [`None`] if it isn't an image
+    /// in one of the supported formats, or can't be read.
+    pub fn read(path: &File) -> Option<ImageInfo> {
+        let mut decoder = ImageReader::open(path.as_path()).ok()?.with_guessed_format().ok()?.into_decoder().ok()?;
+        let (width, height) = decoder.dimensions();
+        let sideways = matches!(
+            decoder.orientation().unwrap_or(Transform::NoTransforms),
+            Transform::Rotate90 | Transform::Rotate270 | Transform::Rotate90FlipH | Transform::Rotate270FlipH
+        );
+        match sideways {
+            true => Some(ImageInfo { width: height, height: width }),
+            false => Some(ImageInfo { width, height }),
+        }
+    }
+
+    /// Return whether the image is a portrait, a landscape, or square.
+    pub fn orientation(&self) -> Orientation {
+        match self.width.cmp(&self.height) {
+            std::cmp::Ordering::Less => Orientation::Portrait,
+            std::cmp::Ordering::Greater => Orientation::Landscape,
+            std::cmp::Ordering::Equal => Orientation::Square,
+        }
+    }
+
+    /// Return what the image probably is. Aspect ratios within 2% of those of
+    /// [`SCREEN_RATIOS`] count as the shape of a screen.
+    pub fn class(&self) -> ImageClass {
+        let ratio = self.width as f64 / self.height.max(1) as f64;
+        let screen = SCREEN_RATIOS.iter().any(|(w, h)| (ratio / (*w as f64 / *h as f64) - 1.0).abs() <= 0.02);
+        if self.width.max(self.height) <= THUMBNAIL_SIZE {
+            ImageClass::Thumbnail
+        } else if self.width >= WALLPAPER_WIDTH && screen {
+            ImageClass::Wallpaper
+        } else {
+            ImageClass::Photo
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! End-to-end integrity checks with BLAKE3, for archives that need a
+//! cryptographic guarantee that every sorted file matches its original byte for
+//! byte. Needs the `blake3` feature. See
+//! [`ExecutionMode::ExecuteIntegrity`](crate::execution::ExecutionMode::ExecuteIntegrity).
+//!
+//! Files that are copied are hashed as they are read, in the same pass as the
+//! copy, rather than read once more beforehand. Once a copy is flushed to disk,
+//! it is read back and hashed again, and the two hashes have to match. Files
+//! that are moved across filesystems are only removed from the source once their
+//! copies check out, and copies that don't are removed instead. Files that are
+//! renamed or linked aren't read or written at all, so they are only hashed
+//! once, in the target.
+//!
+//! The hashes of the files that check out are recorded in the integrity manifest
+//! of the target (see [`INTEGRITY_MANIFEST_NAME`]), a [`Manifest`] of every
+//! file sorted in this mode, which can be checked later with
+//! [`Manifest::verify`].
+
+use crate::checksum::{self, HashAlgorithm};
+use crate::manifest::{self, Manifest, ManifestEntry, INTEGRITY_MANIFEST_NAME};
+use crate::structs::{File, Join};
+use crate::transfer::{self, TransferMode};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::{fs, io};
+
+#[cfg(test)]
+/// Tests for integrity checks. Each test is named after the function it tests,
+/// prefixed with test.
+mod tests {
+
+    use std::fs;
+    use super::{load, record, transfer};
+    use crate::checksum::{self, HashAlgorithm};
+    use crate::testing::{Entry, Sandbox};
+    use crate::transfer::TransferMode;
+
+    #[test]
+    /// Test [`transfer`]
+    fn test_transfer() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/a.jpg").contents("a"),
+            Entry::file("source/b.jpg").contents("b"),
+            Entry::file("source/c.jpg").contents("c"),
+        ]).expect("Failed to create sandbox.");
+        let hash = |contents: &str| blake3::hash(contents.as_bytes()).to_hex().to_string();
+
+        // What was read and what was written hash the same, whichever way the
+        // files get there
+        for (name, mode) in [("a.jpg", TransferMode::Copy), ("b.jpg", TransferMode::Move), ("c.jpg", TransferMode::Hardlink)] {
+            let (old, new) = (sandbox.path(&format!("source/{}", name)), sandbox.path(&format!("target/{}", name)));
+            let hashes = transfer(old.as_path(), new.as_path(), mode, |_, _| true).expect("Failed to transfer file.");
+            assert_eq!(hashes, (hash(&name[..1]), hash(&name[..1])));
+            assert_eq!(checksum::hash_file(&new, HashAlgorithm::Blake3).unwrap(), hash(&name[..1]));
+            assert_eq!(old.exists(), mode != TransferMode::Move);
+        }
+        assert!(transfer(sandbox.path("source/missing.jpg").as_path(), sandbox.path("target/d.jpg").as_path(), TransferMode::Copy, |_, _| true).is_err());
+        assert!(!sandbox.path("target/d.jpg").exists());
+    }
+
+    #[test]
+    /// Test [`record`] and [`load`]
+    fn test_record() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("target/2023/a.jpg").contents("a"),
+            Entry::file("target/2023/b.jpg").contents("b"),
+        ]).expect("Failed to create sandbox.");
+        let target = sandbox.target();
+        assert!(load(&target).expect("Failed to load manifest.").entries.is_empty());
+
+        // Later records replace earlier ones of the same files
+        record(&target, &[(sandbox.path("target/2023/b.jpg"), String::from("old"))]).expect("Failed to record hashes.");
+        fs::write(sandbox.path("target/2023/b.jpg").to_path_buf(), "bb").unwrap();
+        let hashes = [(sandbox.path("target/2023/a.jpg"), String::from("a")), (sandbox.path("target/2023/b.jpg"), String::from("bb"))];
+        record(&target, &hashes).expect("Failed to record hashes.");
+        let manifest = load(&target).expect("Failed to load manifest.");
+        assert_eq!(manifest.algorithm, HashAlgorithm::Blake3);
+        let entries: Vec<(&str, u64, &str)> = manifest.entries.iter().map(|e| (e.path.as_str(), e.size, e.hash.as_str())).collect();
+        assert_eq!(entries, vec![("2023/a.jpg", 1, "a"), ("2023/b.jpg", 2, "bb")]);
+    }
+}
+
+/// Get `old` to `new` by `transfer`, hashing it with BLAKE3 on the way, and then
+/// hash `new`. Return the hashes of what was read from `old` and of what is in
+/// `new`. Files moved across filesystems are only removed from `old` if the two
+/// match, and copies that don't match are removed from `new`. See
+/// [`transfer::copy_file`] for `progress`.
+pub fn transfer(
+    old: &Path,
+    new: &Path,
+    transfer: TransferMode,
+    progress: impl FnMut(u64, u64) -> bool) -> io::Result<(String, String)> {
+
+    let linked = match transfer {
+        TransferMode::Move => match fs::rename(old, new) {
+            Err(error) if error.kind() == io::ErrorKind::CrossesDevices => false,
+            result => result.map(|()| true)?,
+        },
+        TransferMode::Hardlink => fs::hard_link(old, new).map(|()| true)?,
+        TransferMode::Copy => false,
+    };
+    if linked {
+        let hash = checksum::hash_file(&File::from(new), HashAlgorithm::Blake3)?;
+        return Ok((hash.clone(), hash));
+    }
+
+    let mut hasher = blake3::Hasher::new();
+    transfer::copy_file_inspected(old, new, |chunk| { hasher.update(chunk); }, progress)?;
+    let read = hasher.finalize().to_hex().to_string();
+    let written = checksum::hash_file(&File::from(new), HashAlgorithm::Blake3)?;
+    if read != written {
+        fs::remove_file(new)?;
+    } else if transfer == TransferMode::Move {
+        fs::remove_file(old)?;
+    }
+    Ok((read, written))
+}
+
+/// Return the integrity manifest of `target`, which is empty if nothing has been
+/// sorted into it in [`ExecutionMode::ExecuteIntegrity`](crate::execution::ExecutionMode::ExecuteIntegrity)
+/// mode yet.
+pub fn load(target: &File) -> io::Result<Manifest> {
+    match Manifest::load(&target.join(String::from(INTEGRITY_MANIFEST_NAME))) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            Ok(Manifest { algorithm: HashAlgorithm::Blake3, entries: Vec::new() })
+        },
+        result => result,
+    }
+}
+
+/// Record the BLAKE3 `hashes` of files in `target` in its integrity manifest,
+/// along with their sizes, replacing what was recorded for the same paths.
+pub fn record(target: &File, hashes: &[(File, String)]) -> io::Result<()> {
+    let mut manifest = load(target)?;
+    let mut entries: BTreeMap<String, ManifestEntry> = manifest.entries.into_iter()
+        .map(|entry| (entry.path.clone(), entry))
+        .collect();
+    for (path, hash) in hashes {
+        let relative = manifest::relative_path(target, path);
+        let size = path.pathbuf.metadata()?.len();
+        entries.insert(relative.clone(), ManifestEntry { path: relative, size, hash: hash.clone() });
+    }
+    manifest.entries = entries.into_values().collect();
+    manifest.save(&target.join(String::from(INTEGRITY_MANIFEST_NAME)))
+}
@@ -0,0 +1,165 @@
+//! The keywords that photos are tagged with, for sorting them by their tags.
+//! Needs the `metadata` feature. See [`read`] and [`Rule::tags`](crate::rules::Rule::tags).
+//!
+//! Keywords are read from the two places photo managers like Lightroom write
+//! them: the XMP packet embedded in the file (its `dc:subject`), and the IPTC
+//! keywords of JPEG files. Raw photos usually keep theirs in an XMP sidecar
+//! instead, as in `IMG_1234.xmp` next to `IMG_1234.cr2`, which is read too.
+
+use crate::structs::File;
+use std::fs;
+use std::io::Read;
+
+#[cfg(test)]
+/// Tests for keywords. Each test is named after the function it tests, prefixed
+/// with test.
+mod tests {
+
+    use super::{iptc_keywords, read, xmp_keywords};
+    use crate::testing::{Entry, Sandbox};
+
+    /// Return an XMP packet with the keywords `keywords`
+    fn xmp(keywords: &[&str]) -> String {
+        let items: String = keywords.iter().map(|keyword| format!("<rdf:li>{}</rdf:li>", keyword)).collect();
+        format!(
+            "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF><rdf:Description>\
+            <dc:subject><rdf:Bag>{}</rdf:Bag></dc:subject>\
+            </rdf:Description></rdf:RDF></x:xmpmeta>",
+            items
+        )
+    }
+
+    /// Return a Photoshop image resource block with the IPTC keywords `keywords`
+    fn iptc(keywords: &[&str]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for keyword in keywords {
+            data.extend_from_slice(&[0x1c, 2, 25]);
+            data.extend_from_slice(&(keyword.len() as u16).to_be_bytes());
+            data.extend_from_slice(keyword.as_bytes());
+        }
+        let mut bytes = b"Photoshop 3.0\08BIM\x04\x04\0\0".to_vec();
+        bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    #[test]
+    /// Test [`xmp_keywords`]
+    fn test_xmp_keywords() {
+        assert_eq!(xmp_keywords(&xmp(&["family", "Rock &amp; Roll"])), vec!["family", "Rock & Roll"]);
+        assert_eq!(xmp_keywords("<dc:subject><rdf:Bag><rdf:li/></rdf:Bag></dc:subject>"), Vec::<String>::new());
+        assert_eq!(xmp_keywords("<dc:title>Not keywords</dc:title>"), Vec::<String>::new());
+    }
+
+    #[test]
+    /// Test [`iptc_keywords`]
+    fn test_iptc_keywords() {
+        assert_eq!(iptc_keywords(&iptc(&["family", "beach"])), vec!["family", "beach"]);
+        assert_eq!(iptc_keywords(b"8BIM\x04\x04\0\0\xff\xff\xff\xff"), Vec::<String>::new());
+        assert_eq!(iptc_keywords(b"no keywords"), Vec::<String>::new());
+    }
+
+    #[test]
+    /// Test [`read`]
+    fn test_read() {
+        let mut jpeg = b"\xff\xd8\xff\xe1".to_vec();
+        jpeg.extend(xmp(&["family", "Beach"]).into_bytes());
+        jpeg.extend(iptc(&["beach", "2023"]));
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/a.jpg").contents(jpeg),
+            Entry::file("source/b.cr2").contents("raw"),
+            Entry::file("source/b.xmp").contents(xmp(&["holiday"])),
+            Entry::file("source/c.jpg").contents("no metadata"),
+        ]).expect("Failed to create sandbox.");
+
+        // Keywords are only listed once, whatever their case
+        assert_eq!(read(&sandbox.path("source/a.jpg")), vec!["family", "Beach", "2023"]);
+        assert_eq!(read(&sandbox.path("source/b.cr2")), vec!["holiday"]);
+        assert!(read(&sandbox.path("source/c.jpg")).is_empty());
+        assert!(read(&sandbox.path("source/missing.jpg")).is_empty());
+    }
+}
+
+/// How much of the start of each file is searched for metadata, in bytes.
+/// Photos keep their metadata before the image data.
+pub const SCAN_LENGTH: u64 = 4 << 20;
+
+/// Return the keywords that `path` is tagged with, from its XMP packet, its IPTC
+/// keywords, and its XMP sidecar, in that order, each only once (ignoring case).
+/// Files without any, or that can't be read, have none.
+pub fn read(path: &File) -> Vec<String> {
+    let mut bytes = Vec::new();
+    if let Ok(file) = fs::File::open(path.as_path()) {
+        let _ = file.take(SCAN_LENGTH).read_to_end(&mut bytes);
+    }
+    let text = String::from_utf8_lossy(&bytes);
+    let packet = text.find("<x:xmpmeta").map(|start| &text[start..]);
+    let sidecar = fs::read(path.pathbuf.with_extension("xmp")).ok().filter(|_| !path.extension().eq_ignore_ascii_case("xmp"));
+
+    let mut keywords: Vec<String> = Vec::new();
+    let found = packet.map(xmp_keywords).into_iter().flatten()
+        .chain(iptc_keywords(&bytes))
+        .chain(sidecar.map(|sidecar| xmp_keywords(&String::from_utf8_lossy(&sidecar))).into_iter().flatten());
+    for keyword in found {
+        if !keywords.iter().any(|k| k.eq_ignore_ascii_case(&keyword)) {
+            keywords.push(keyword);
+        }
+    }
+    keywords
+}
+
+/// Return the keywords in the `dc:subject` of the XMP packet `xmp`.
+pub fn xmp_keywords(xmp: &str) -> Vec<String> {
+    let Some(subject) = xmp.split_once("<dc:subject").and_then(|(_, rest)| rest.split_once("</dc:subject>")) else {
+        return Vec::new();
+    };
+    subject.0.split("<rdf:li")
+        .skip(1)
+        .filter_map(|item| item.split_once('>').and_then(|(_, rest)| rest.split_once("</rdf:li>")))
+        .map(|(keyword, _)| unescape(keyword.trim()))
+        .filter(|keyword| !keyword.is_empty())
+        .collect()
+}
+
+/// Return the IPTC keywords in `bytes`, from the first Photoshop image resource
+/// that holds IPTC data, as JPEG files have.
+pub fn iptc_keywords(bytes: &[u8]) -> Vec<String> {
+    let Some(start) = bytes.windows(6).position(|window| window == b"8BIM\x04\x04") else {
+        return Vec::new();
+    };
+
+    // The resource has a padded Pascal string for a name, and then the length
+    // of its data
+    let name_length = bytes.get(start + 6).map_or(0, |n| *n as usize);
+    let at = start + 6 + (name_length + 2) / 2 * 2;
+    let Some(length) = bytes.get(at..at + 4).map(|b| u32::from_be_bytes(b.try_into().unwrap()) as usize) else {
+        return Vec::new();
+    };
+    let data = &bytes[at + 4..bytes.len().min((at + 4).saturating_add(length))];
+
+    // Each dataset is a marker, a record and a dataset number, and a length;
+    // keywords are dataset 25 of record 2
+    let mut keywords = Vec::new();
+    let mut i = 0;
+    while let Some(&[0x1c, record, dataset, high, low]) = data.get(i..i + 5) {
+        let length = u16::from_be_bytes([high, low]) as usize;
+        if length & 0x8000 != 0 || i + 5 + length > data.len() {
+            break;
+        }
+        if (record, dataset) == (2, 25) {
+            keywords.push(String::from_utf8_lossy(&data[i + 5..i + 5 + length]).trim().to_string());
+        }
+        i += 5 + length;
+    }
+    keywords.retain(|keyword| !keyword.is_empty());
+    keywords
+}
+
+/// Return `text` with its XML entities replaced by the characters they stand for.
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
@@ -23,439 +23,4408 @@
 //!         date_type: date_type, // The date type to sort the files by
 //!         preserve_name: preserve_name, // Whether to include the old file name in the new name
 //!         exclude_type: exclude_type, // File type(s) to exclude
-//!         only_type: only_type // File type(s) to exclusively sort. Overrides `exclude_type`
+//!         only_type: only_type, // File type(s) to exclusively sort. Overrides `exclude_type`
+//!         ..Default::default() // Use the defaults for everything else
 //!     };
 //! 
 //!     // Run the sorting algorithm (uncomment line below)
-//!     // sorter.sort(false);
+//!     // sorter.sort_report(ExecutionMode::Execute);
 //! }
 //! ```
 //! 
 //! You can find more detailed descriptions of the fields on the [`Sorter`] page.
+//! 
+//! # Features
+//! 
+//! Sorting by date needs no features. Everything that needs more dependencies
+//! has a feature of its own, so that only what is used gets built:
+//! 
+//! - `sha256`: hash files with [SHA-256](checksum::HashAlgorithm), on by default
+//! - `blake3`: hash files with BLAKE3, and sort them with end-to-end integrity checks
+//! - `exif`: read EXIF metadata from photos
+//! - `image`: read the sizes of images, for the [image placeholders](Sorter::dir_format)
+//! - `phash`: find visually identical images, with `image`
+//! - `metadata`: read the XMP and IPTC keywords of photos, for [rules](rules::Rule::tags)
+//! - `office`: read dates from office documents
+//! - `colored`: color error messages for the terminal
+//! - `metrics`: emit metrics of sorts
+//! - `tracing`: trace sorts with `tracing` spans
+//! - `io-uring`: rename files in batches with io_uring, on Linux
+//! - `webhook`: post [notifications](notify) to webhooks
+//! - `full`: all of the above
+
+// The tests go at the top of each module, before the items they test.
+#![allow(clippy::items_after_test_module)]
 
-mod errors;
+pub mod audit;
+pub mod categories;
+pub mod checksum;
+pub mod conflicts;
+pub mod content_types;
+pub mod counters;
+pub mod dates;
+pub mod downloads;
+pub mod duplicates;
+pub mod errors;
+pub mod execution;
+#[cfg(feature = "exif")]
+pub mod exif;
+pub mod filters;
+pub mod grouping;
+pub mod history;
+#[cfg(feature = "image")]
+pub mod images;
+#[cfg(feature = "blake3")]
+pub mod integrity;
+#[cfg(feature = "metadata")]
+pub mod keywords;
+pub mod links;
+pub mod location;
+pub mod lock;
+pub mod manifest;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod mirror;
+pub mod mounts;
+pub mod notify;
+pub mod observers;
+pub mod open_files;
+pub mod origin;
+pub mod plan;
+pub mod preview;
+pub mod providers;
+pub mod reparse;
+pub mod report;
+pub mod rules;
+#[cfg(feature = "phash")]
+pub mod phash;
+pub mod screenshots;
+pub mod sidecars;
+pub mod stale;
 pub mod structs;
+pub mod template;
+pub mod testing;
+pub mod transfer;
+pub mod unsort;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub mod uring;
+pub mod video;
 
-use chrono::{DateTime, TimeZone, Utc, Local};
+use categories::Category;
+use checksum::HashAlgorithm;
+use chrono::{DateTime, Local};
+use conflicts::{ConflictPolicy, IdenticalCheck};
+use content_types::ExtensionlessPolicy;
+use dates::{DateSource, MissingDatePolicy};
+use duplicates::DuplicateGroup;
 use errors::*;
+use execution::ExecutionMode;
 use filetime::FileTime;
-use std::{fs, path::Path};
+use filters::TypeFilter;
+use links::SymlinkPolicy;
+use location::Region;
+use mounts::MountInfo;
+use observers::{CancelToken, Observer, Observers, ProgressInterval, ProgressThrottle};
+use origin::OriginRecord;
+use providers::{MetadataProvider, Providers};
+use reparse::ReparsePolicy;
+use stale::{Revalidation, Snapshot, StalePolicy};
+use rules::Rule;
+use report::{ByteProgress, ConfigWarning, Estimate, FileStatus, Progress, Risk, SortReport, SortWarning};
+use std::{borrow::Cow, collections::{HashMap, HashSet}, ffi::{OsStr, OsString}, fmt::Write, fs, io, path::{Component, Path, PathBuf}, thread, time::{Duration, SystemTime}};
+use std::sync::{Arc, PoisonError, mpsc, atomic::{AtomicBool, AtomicUsize, Ordering}};
 use structs::*;
-use walkdir::WalkDir;
+use template::Granularity;
+use transfer::TransferMode;
+use video::VideoFilter;
 
 /// Includes all the stuff needed for basic operations, in one neat module.
 #[allow(unused_imports)]
 pub mod prelude {
     pub use crate::{FromJson, Sorter};
+    pub use crate::categories::Category;
+    pub use crate::checksum::HashAlgorithm;
+    pub use crate::content_types::ExtensionlessPolicy;
+    pub use crate::dates::{DateSource, MissingDatePolicy};
+    pub use crate::links::SymlinkPolicy;
+    pub use crate::location::Region;
+    pub use crate::observers::{CancelToken, Observer, ProgressInterval};
+    pub use crate::errors::{SortError, Strictness};
+    pub use crate::execution::ExecutionMode;
+    pub use crate::origin::OriginRecord;
+    pub use crate::providers::MetadataProvider;
+    pub use crate::reparse::ReparsePolicy;
+    pub use crate::report::{FileStatus, Progress, SortEntry, SortReport};
     pub use crate::structs::{File, Join};
+    pub use crate::template::Granularity;
+    pub use crate::transfer::TransferMode;
 }
 
 /// Tests. Each test is named after the function or struct it tests, prefixed with `test_`.
 #[cfg(test)]
 mod tests {
     use crate::{FromJson, Sorter};
-    use std::{env, fs, path::Path};
+    use crate::audit::SuspiciousDate;
+    use crate::categories::Category;
+    use crate::content_types::ExtensionlessPolicy;
+    use crate::dates::{DateSource, MissingDatePolicy};
+    use crate::errors::{SortError, Strictness};
+    use crate::execution::ExecutionMode;
+    use crate::filters::TypeFilter;
+    use crate::observers::{CancelToken, Observer, ProgressInterval};
+    use crate::conflicts::{ConflictPolicy, IdenticalCheck};
+    use crate::links::SymlinkPolicy;
+    use crate::lock::{self, TargetLock};
+    use crate::mirror::OrphanPolicy;
+    use crate::origin::OriginRecord;
+    use crate::providers::MetadataProvider;
+    use crate::stale::StalePolicy;
+    use crate::origin;
+    use crate::rules::Rule;
+    use crate::unsort;
+    use crate::report::{ConfigWarning, Estimate, FileStatus, Progress, Risk, SortWarning};
+    use crate::testing::{Entry, Sandbox};
+    use crate::template::Granularity;
+    use crate::transfer::TransferMode;
+    use chrono::{Local, TimeZone};
+    use std::{collections::{HashMap, HashSet}, env, fs, path::Path, sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}}, time::Duration};
     use super::structs::*;
 
-    #[test]
-    fn test_sorter() {
+    #[test]
+    /// Test [`Sorter::get_template_vars`]
+    fn test_get_template_vars() {
+        let sorter = Sorter {
+            categories: vec![Category::new("Images", &["jpg"])],
+            ..Default::default()
+        };
+        let vars = |path, name| sorter.get_template_vars(&File::from(path), &[name])[name].clone();
+        assert_eq!(vars("a/IMG_1234.JPG", "extension"), "jpg");
+        assert_eq!(vars("a/Makefile", "extension"), "no_extension");
+        assert_eq!(vars("a/IMG_1234.JPG", "category"), "Images");
+        assert_eq!(vars("a/notes.txt", "category"), "Documents");
+        assert_eq!(vars("a/notes.unknownext", "category"), "Other");
+        assert_eq!(vars("a/Italy 2019/IMG_1234.JPG", "parent"), "Italy 2019");
+        assert_eq!(vars("a/Italy 2019/IMG_1234.JPG", "parent:2"), "a");
+        assert_eq!(vars("IMG_1234.JPG", "parent"), "no_parent");
+
+        #[cfg(feature = "image")]
+        {
+            let sandbox = Sandbox::new(&[]).expect("Failed to create sandbox.");
+            let path = sandbox.path("source/wallpaper.png");
+            image::GrayImage::new(1920, 1080).save(path.to_path_buf()).expect("Failed to save image.");
+            let names = ["width", "height", "orientation", "image_class"];
+            let vars = sorter.get_template_vars(&path, &names);
+            assert_eq!(names.map(|name| vars[name].as_str()), ["1920", "1080", "landscape", "wallpaper"]);
+            assert_eq!(sorter.get_template_vars(&File::from("a/notes.txt"), &["width"])["width"], "unknown");
+        }
+        assert_eq!(vars("a/notes.txt", "video_resolution"), "unknown");
+        assert_eq!(vars("a/clip.mp4", "video_class"), "unknown");
+    }
+
+    #[test]
+    /// Test [`Sorter::persist_counters`]
+    fn test_persist_counters() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/a.jpg").modified_at(1_656_676_800),
+            Entry::file("target/2022/2022 a.jpg"),
+        ]).expect("Failed to create sandbox.");
+        let sorter = |persist_counters| Sorter {
+            source: sandbox.source(),
+            target: sandbox.target(),
+            date_format: String::from("%Y"),
+            dir_format: String::from("%Y/"),
+            preserve_name: true,
+            persist_counters,
+            ..Default::default()
+        };
+        let report = sorter(true).sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        assert_eq!(report.new, vec![sandbox.path("target/2022/2022 a_2.jpg")]);
+
+        // Once the numbered file is moved on, its name isn't given out again
+        fs::remove_file(sandbox.path("target/2022/2022 a_2.jpg").to_path_buf()).unwrap();
+        fs::write(sandbox.path("source/a.jpg").to_path_buf(), "").unwrap();
+        filetime::set_file_mtime(sandbox.path("source/a.jpg").to_path_buf(), filetime::FileTime::from_unix_time(1_656_676_800, 0)).unwrap();
+        let report = sorter(false).sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        assert_eq!(report.new, vec![sandbox.path("target/2022/2022 a_2.jpg")]);
+        let report = sorter(true).sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        assert_eq!(report.new, vec![sandbox.path("target/2022/2022 a_3.jpg")]);
+    }
+
+    #[test]
+    /// Test [`Sorter::record_history`]
+    fn test_record_history() {
+        use crate::history::History;
+
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/a.jpg").modified_at(1_656_676_800),
+            Entry::file("source/b.png").modified_at(1_656_676_800),
+        ]).expect("Failed to create sandbox.");
+        let sorter = Sorter {
+            source: sandbox.source(),
+            target: sandbox.target(),
+            date_format: String::from("%Y"),
+            dir_format: String::from("%Y/"),
+            preserve_name: true,
+            only_type: vec![String::from("jpg")],
+            record_history: true,
+            ..Default::default()
+        };
+
+        // Dry runs aren't recorded, and each sort that moves files is
+        sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        assert_eq!(History::load(&sandbox.target()).expect("Failed to load history."), History::default());
+        sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        Sorter { only_type: Vec::new(), ..sorter }.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        let history = History::load(&sandbox.target()).expect("Failed to load history.");
+        assert_eq!(history.sorts.len(), 2);
+        assert_eq!(history.files(&sandbox.target()), vec![
+            (sandbox.path("source/a.jpg"), sandbox.path("target/2022/2022 a.jpg")),
+            (sandbox.path("source/b.png"), sandbox.path("target/2022/2022 b.png")),
+        ]);
+    }
+
+    #[test]
+    /// Test [`Sorter::unsort`]
+    fn test_unsort() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("target/2022/07/2022 a.jpg").contents("a"),
+            Entry::file("target/2023/01/b.jpg").contents("b"),
+            Entry::file("target/2023/b.jpg").contents("other b"),
+        ]).expect("Failed to create sandbox.");
+        let a = sandbox.path("target/2022/07/2022 a.jpg");
+        origin::record_origin(&sandbox.path("source/a.jpg"), &a, &OriginRecord::Sidecar).expect("Failed to record origin.");
+        let sorter = Sorter { source: sandbox.source(), target: sandbox.target(), ..Default::default() };
+        let flat = sandbox.path("flat");
+
+        // A dry run only plans, and taken names get sequential ones
+        let planned = sorter.unsort(&flat, unsort::UnsortNames::Original, ExecutionMode::DryRun).expect("Failed to unsort.");
+        let names: Vec<String> = planned.new.iter().map(File::file_name).collect();
+        assert_eq!(names, vec!["a.jpg", "b.jpg", "b_2.jpg"]);
+        assert!(!flat.exists());
+
+        let report = sorter.unsort(&flat, unsort::UnsortNames::Original, ExecutionMode::Execute).expect("Failed to unsort.");
+        assert!(report.is_ok());
+        assert_eq!(report.new, planned.new);
+        let lock = format!("target/{}", lock::LOCK_FILE_NAME);
+        assert_eq!(sandbox.files(), vec!["flat/a.jpg", "flat/a.jpg.origin.json", "flat/b.jpg", "flat/b_2.jpg", lock.as_str()]);
+        assert_eq!(fs::read_to_string(sandbox.path("flat/b_2.jpg").to_path_buf()).unwrap(), "other b");
+    }
+
+    #[test]
+    /// Test [`Sorter::rules`]
+    fn test_rules() {
+        let tagged = "<x:xmpmeta><dc:subject><rdf:Bag><rdf:li>Family</rdf:li></rdf:Bag></dc:subject></x:xmpmeta>";
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/a.jpg").contents(tagged).modified_at(1_656_676_800),
+            Entry::file("source/b.jpg").modified_at(1_656_676_800),
+            Entry::file("source/Receipt 42.pdf").modified_at(1_656_676_800),
+        ]).expect("Failed to create sandbox.");
+        let finance = Rule { name_keywords: vec![String::from("receipt")], ..Rule::new("Finance/%Y/", &[]) };
+        let sorter = Sorter {
+            source: sandbox.source(),
+            target: sandbox.target(),
+            date_format: String::from("%Y"),
+            dir_format: String::from("%Y/"),
+            preserve_name: true,
+            rules: vec![finance, Rule::new("Family/%Y/", &["family"])],
+            ..Default::default()
+        };
+        let report = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        let new = |name: &str| report.new[report.old.iter().position(|old| old.file_name() == name).unwrap()].copy();
+
+        // Files can only be tagged with the metadata feature
+        let family = if cfg!(feature = "metadata") { "target/Family/2022/2022 a.jpg" } else { "target/2022/2022 a.jpg" };
+        assert_eq!(new("a.jpg"), sandbox.path(family));
+        assert_eq!(new("b.jpg"), sandbox.path("target/2022/2022 b.jpg"));
+        assert_eq!(new("Receipt 42.pdf"), sandbox.path("target/Finance/2022/2022 Receipt 42.pdf"));
+    }
+
+    #[test]
+    /// Test [`Rule::transfer`]
+    fn test_rule_transfer() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/IMG_1234.jpg").modified_at(1_656_676_800).contents("photo"),
+            Entry::file("source/IMG_1234.xmp").modified_at(1_656_676_800).contents("sidecar"),
+            Entry::file("source/scan.pdf").modified_at(1_656_676_800).contents("scan"),
+            Entry::file("source/setup.exe").modified_at(1_656_676_800).contents("junk"),
+        ]).expect("Failed to create sandbox.");
+        let rule = |dir_format: &str, keyword: &str, transfer: TransferMode| Rule {
+            name_keywords: vec![String::from(keyword)],
+            transfer: Some(transfer),
+            ..Rule::new(dir_format, &[])
+        };
+        let sorter = Sorter {
+            source: sandbox.source(),
+            target: sandbox.target(),
+            date_format: String::from("%Y"),
+            dir_format: String::new(),
+            preserve_name: true,
+            rules: vec![rule("Photos/", "img", TransferMode::Copy), rule("Scans/", "scan", TransferMode::Hardlink)],
+            ..Default::default()
+        };
+        let report = sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        assert!(report.is_ok());
+
+        // Sidecars go the way of their primaries, and files without a rule go
+        // the sorter's way
+        assert_eq!(sandbox.files(), vec![
+            "source/IMG_1234.jpg",
+            "source/IMG_1234.xmp",
+            "source/scan.pdf",
+            "target/.sortery.lock",
+            "target/2022 setup.exe",
+            "target/Photos/2022 IMG_1234.jpg",
+            "target/Photos/2022 IMG_1234.xmp",
+            "target/Scans/2022 scan.pdf",
+        ]);
+        fs::write(sandbox.path("source/scan.pdf").to_path_buf(), "edited").unwrap();
+        assert_eq!(fs::read_to_string(sandbox.path("target/Scans/2022 scan.pdf").to_path_buf()).unwrap(), "edited");
+    }
+
+    #[test]
+    /// Test [`Sorter::keep_newest`]
+    fn test_keep_newest() {
+
+        // Three files, a day apart
+        let source = File::from(env::temp_dir()).join(String::from("sorterylib_test_keep_newest"));
+        let target = source.join(String::from("target"));
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+        for (day, name) in ["old.txt", "newer.txt", "newest.txt"].iter().enumerate() {
+            let path = source.join(String::from(*name));
+            fs::write(path.to_path_buf(), name).expect("Failed to write test file.");
+            let time = filetime::FileTime::from_unix_time(1_600_000_000 + day as i64 * 86_400, 0);
+            filetime::set_file_mtime(path.to_path_buf(), time).expect("Failed to set modification time.");
+        }
+
+        let sorter = Sorter { source: source.copy(), target, keep_newest: Some(2), ..Default::default() };
+        let report = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        assert_eq!(report.count(), 1);
+        assert_eq!(report.old, vec![source.join(String::from("old.txt"))]);
+
+        fs::remove_dir_all(source.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    /// Test [`Sorter::is_old_enough`]
+    fn test_is_old_enough() {
+        let path = File::from("Cargo.toml");
+        assert!(Sorter::default().is_old_enough(&path));
+        assert!(!Sorter { min_age: Some(Duration::from_secs(60 * 60 * 24 * 365 * 1000)), ..Default::default() }.is_old_enough(&path));
+        assert!(!Sorter { min_age: Some(Duration::from_secs(1)), ..Default::default() }.is_old_enough(&File::from("missing.txt")));
+    }
+
+    #[test]
+    /// Test [`Sorter::min_size`] and [`Sorter::tiny_dir`]
+    fn test_min_size() {
+
+        // An empty file and a bigger one
+        let source = File::from(env::temp_dir()).join(String::from("sorterylib_test_min_size"));
+        let target = source.join(String::from("target"));
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+        fs::write(source.join(String::from("empty.txt")).to_path_buf(), "").expect("Failed to write test file.");
+        fs::write(source.join(String::from("full.txt")).to_path_buf(), "full").expect("Failed to write test file.");
+
+        // Tiny files are skipped, or sorted into the tiny directory
+        let mut sorter = Sorter { source: source.copy(), target: target.copy(), min_size: Some(1), ..Default::default() };
+        let report = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        assert_eq!(report.old, vec![source.join(String::from("full.txt"))]);
+        sorter.tiny_dir = Some(String::from("Tiny"));
+        let report = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        let (old, new) = (report.old, report.new);
+        assert_eq!(old.len(), 2);
+        let empty = old.iter().position(|f| f.file_name() == "empty.txt").unwrap();
+        assert!(new[empty].pathbuf.starts_with(target.join(String::from("Tiny")).pathbuf));
+
+        fs::remove_dir_all(source.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    #[cfg_attr(not(feature = "sha256"), ignore = "hashes with SHA-256, the default hash_algorithm")]
+    /// Test [`Sorter::sort_report`]
+    fn test_sort_report() {
+
+        // A file to sort, into a target without the date directories it needs
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_sort_report"));
+        let (source, target) = (dir.join(String::from("source")), dir.join(String::from("target")));
+        fs::create_dir_all(source.to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+        let path = source.join(String::from("file.txt"));
+        fs::write(path.to_path_buf(), "file").expect("Failed to write test file.");
+
+        // The date directories are created, unless something is in the way, in
+        // which case the failure is recorded, instead of panicking
+        let sorter = Sorter { source: source.copy(), target, ..Default::default() };
+        let planned = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        let month = File::from(planned.new[0].as_path().parent().unwrap());
+        assert!(!month.exists());
+        fs::create_dir_all(month.as_path().parent().unwrap()).expect("Failed to create test dir.");
+        fs::write(month.to_path_buf(), "in the way").expect("Failed to write test file.");
+        let report = sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        assert_eq!(report.count(), 1);
+        assert!(!report.is_ok());
+        assert_eq!(report.failures[0].0, path);
+        assert!(matches!(&report.failures[0].1, SortError::DirectoryNotCreated(dir, _) if *dir == month));
+        fs::remove_file(month.to_path_buf()).expect("Failed to remove test file.");
+        let report = sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        assert!(report.is_ok());
+        assert!(month.pathbuf.is_dir());
+        fs::rename(report.new[0].to_path_buf(), path.to_path_buf()).expect("Failed to move test file back.");
+
+        // Unless we're not supposed to create them
+        fs::remove_dir(month.to_path_buf()).expect("Failed to remove test dir.");
+        let uncreated = Sorter { create_dirs: false, ..sorter };
+        let report = uncreated.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        assert!(matches!(&report.failures[0].1, SortError::PathDoesNotExist(error) if error.path == month.to_string()));
+        assert!(!month.exists());
+        let sorter = Sorter { create_dirs: true, ..uncreated };
+
+        // Destinations already in the target are worked around, or stop a strict sort
+        let existing = sorter.target.join(String::from("file.txt"));
+        fs::write(existing.to_path_buf(), "existing").expect("Failed to write test file.");
+        let sorter = Sorter { dir_format: String::new(), date_format: String::from("file"), ..sorter };
+        let report = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        assert_eq!(report.new, vec![sorter.target.join(String::from("file_2.txt"))]);
+        assert_eq!(report.warnings, vec![(path.copy(), SortWarning::DestinationExists(existing.copy()))]);
+        let strict = Sorter { strictness: Strictness::Strict, ..sorter };
+        assert!(matches!(strict.sort_report(ExecutionMode::DryRun), Err(SortError::ConflictUnresolved(p)) if p == existing));
+
+        // Verified moves check the contents on both sides
+        let verified = Sorter { date_format: String::from("verified"), ..strict };
+        let report = verified.sort_report(ExecutionMode::ExecuteVerified).expect("Failed to sort.");
+        assert!(report.is_ok());
+        assert_eq!(report.new, vec![verified.target.join(String::from("verified.txt"))]);
+        assert_eq!(fs::read_to_string(report.new[0].to_path_buf()).unwrap(), "file");
+
+        // The sort can't run at all without a source
+        let missing = Sorter { source: source.join(String::from("missing")), ..Default::default() };
+        assert!(matches!(missing.sort_report(ExecutionMode::DryRun), Err(SortError::PathDoesNotExist(_))));
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    /// Test [`Sorter::sort_report_with_callback`]
+    fn test_sort_report_with_callback() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_sort_report_with_callback"));
+        let (source, target) = (dir.join(String::from("source")), dir.join(String::from("target")));
+        fs::create_dir_all(source.to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+        for name in ["a.txt", "b.txt"] {
+            fs::write(source.join(String::from(name)).to_path_buf(), name).expect("Failed to write test file.");
+        }
+
+        // An observer that counts the files it is told about
+        #[derive(Default)]
+        struct Counter(AtomicUsize);
+        impl Observer for Counter {
+            fn on_file(&self, _progress: &Progress) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        // The callback gets the file that was just processed, and then the end of
+        // the sort, while observers get every file
+        let mut sorter = Sorter { source, target, ..Default::default() };
+        let counter = Arc::new(Counter::default());
+        sorter.register_observer(counter.clone());
+        let mut calls = Vec::new();
+        let report = sorter.sort_report_with_callback(ExecutionMode::DryRun, |progress| {
+            calls.push((progress.counters(), progress.current.map(|(old, new, status)| (old.copy(), new.copy(), status))));
+        }).expect("Failed to sort.");
+        assert_eq!(calls, vec![
+            ((1, 2, 50), Some((report.old[1].copy(), report.new[1].copy(), FileStatus::Planned))),
+            ((2, 2, 100), None)
+        ]);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 2);
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    /// Test [`Sorter::sort_with_callback`]
+    fn test_sort_with_callback() {
+        let sandbox = Sandbox::new(&[Entry::file("source/a.txt")]).expect("Failed to create sandbox.");
+        let sorter = Sorter { source: sandbox.source(), target: sandbox.target(), ..Default::default() };
+
+        // The old dry_run bool still works, as well as an ExecutionMode
+        let mut calls = Vec::new();
+        let (count, old, _) = sorter.sort_with_callback(true, |counters| calls.push(counters));
+        assert_eq!((count, calls), (1, vec![(1, 1, 100)]));
+        assert!(old[0].exists());
+        let (_, old, new) = sorter.sort_with_callback(ExecutionMode::Execute, |_| {});
+        assert!(!old[0].exists() && new[0].exists());
+    }
+
+    #[test]
+    #[cfg_attr(not(feature = "sha256"), ignore = "hashes with SHA-256, the default hash_algorithm")]
+    /// Test [`Sorter::transfer`]
+    fn test_transfer() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_transfer"));
+        let (source, target) = (dir.join(String::from("source")), dir.join(String::from("target")));
+        fs::create_dir_all(source.to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+        let path = source.join(String::from("a.txt"));
+        fs::write(path.to_path_buf(), "a").expect("Failed to write test file.");
+
+        // Copies leave the originals where they are
+        let sorter = Sorter { source, target, transfer: TransferMode::Copy, ..Default::default() };
+        let report = sorter.sort_report(ExecutionMode::ExecuteVerified).expect("Failed to sort.");
+        assert!(report.is_ok());
+        assert!(path.exists());
+        assert_eq!(fs::read_to_string(report.new[0].to_path_buf()).unwrap(), "a");
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// Test copying hard links with [`TransferMode::Copy`]
+    fn test_transfer_hard_links() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_transfer_hard_links"));
+        let (source, target) = (dir.join(String::from("source")), dir.join(String::from("target")));
+        fs::create_dir_all(source.join(String::from("album")).to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+        let (a, b) = (source.join(String::from("a.jpg")), source.join(String::from("album/b.jpg")));
+        fs::write(a.to_path_buf(), "a").expect("Failed to write test file.");
+        fs::hard_link(a.to_path_buf(), b.to_path_buf()).expect("Failed to link test file.");
+
+        // The second link is reported, and linked to the copy of the first
+        let sorter = Sorter { source, target, preserve_name: true, transfer: TransferMode::Copy, workers: 2, ..Default::default() };
+        let report = sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        assert!(report.is_ok());
+        assert_eq!(report.warnings.len(), 1);
+        assert!(matches!(&report.warnings[0].1, SortWarning::HardLinked(_)));
+        let inode = |file: &File| fs::metadata(file.to_path_buf()).unwrap().ino();
+        assert_eq!(inode(&report.new[0]), inode(&report.new[1]));
+        assert_ne!(inode(&report.new[0]), inode(&a));
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// Test [`Sorter::symlinks`]
+    fn test_symlinks() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_symlinks"));
+        let (source, target) = (dir.join(String::from("source")), dir.join(String::from("target")));
+        let link = source.join(String::from("link.txt"));
+
+        // Links are handled the same with io_uring, which only renames files
+        let mut backends = vec![crate::execution::Backend::Std];
+        if Sorter::io_uring_supported() {
+            backends.push(crate::execution::Backend::IoUring);
+        }
+        for (backend, symlinks) in backends.into_iter().flat_map(|backend| [SymlinkPolicy::Skip, SymlinkPolicy::Relink, SymlinkPolicy::Dereference].map(|symlinks| (backend, symlinks))) {
+            fs::create_dir_all(source.to_path_buf()).expect("Failed to create test dir.");
+            fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+            fs::write(dir.join(String::from("a.txt")).to_path_buf(), "a").expect("Failed to write test file.");
+            std::os::unix::fs::symlink("../a.txt", link.to_path_buf()).expect("Failed to create test link.");
+
+            let sorter = Sorter { source: source.clone(), target: target.clone(), preserve_name: true, symlinks, backend, ..Default::default() };
+            let report = sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+            assert!(report.is_ok());
+            match symlinks {
+                SymlinkPolicy::Skip => assert_eq!(report.count(), 0),
+                // Either way, the sorted file still reads the same
+                _ => {
+                    assert_eq!(fs::read_to_string(report.new[0].to_path_buf()).unwrap(), "a");
+                    let is_link = fs::symlink_metadata(report.new[0].to_path_buf()).unwrap().file_type().is_symlink();
+                    assert_eq!(is_link, symlinks == SymlinkPolicy::Relink);
+                },
+            }
+
+            fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+        }
+    }
+
+    #[test]
+    /// Test [`Sorter::mirror`]
+    fn test_mirror() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_mirror"));
+        let (source, target) = (dir.join(String::from("source")), dir.join(String::from("target")));
+        fs::create_dir_all(source.to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+        let (a, b) = (source.join(String::from("a.jpg")), source.join(String::from("b.jpg")));
+        fs::write(a.to_path_buf(), "a").expect("Failed to write test file.");
+        fs::write(b.to_path_buf(), "b").expect("Failed to write test file.");
+
+        // Mirroring moves nothing out of the source, so it has to copy
+        let moving = Sorter { source: source.copy(), target: target.copy(), ..Default::default() };
+        assert!(matches!(moving.mirror(ExecutionMode::Execute, OrphanPolicy::Flag), Err(SortError::InvalidConfig(_))));
+
+        // The second mirror has nothing to add
+        let sorter = Sorter {
+            source, target, transfer: TransferMode::Copy, origin_record: OriginRecord::Sidecar,
+            skip_identical: IdenticalCheck::Size, ..Default::default()
+        };
+        let report = sorter.mirror(ExecutionMode::Execute, OrphanPolicy::Flag).expect("Failed to mirror.");
+        assert!(report.is_ok());
+        assert_eq!(report.added().count(), 2);
+        let copy_of_b = report.added().find(|(old, _)| **old == b).unwrap().1.copy();
+        let report = sorter.mirror(ExecutionMode::Execute, OrphanPolicy::Flag).expect("Failed to mirror.");
+        assert_eq!((report.added().count(), report.unchanged().count()), (0, 2));
+
+        // Once b.jpg is gone from the source, its copy is an orphan, which is
+        // only removed when pruning
+        fs::remove_file(b.to_path_buf()).expect("Failed to remove test file.");
+        let report = sorter.mirror(ExecutionMode::Execute, OrphanPolicy::Flag).expect("Failed to mirror.");
+        assert_eq!(report.orphans, vec![(copy_of_b.copy(), b.copy())]);
+        assert!(report.pruned.is_empty() && copy_of_b.exists());
+        let report = sorter.mirror(ExecutionMode::DryRun, OrphanPolicy::Prune).expect("Failed to mirror.");
+        assert!(report.pruned.is_empty() && copy_of_b.exists());
+        let report = sorter.mirror(ExecutionMode::Execute, OrphanPolicy::Prune).expect("Failed to mirror.");
+        assert_eq!(report.pruned, vec![copy_of_b.copy()]);
+        assert!(!copy_of_b.exists() && a.exists());
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    #[cfg_attr(not(feature = "sha256"), ignore = "hashes with SHA-256, the default hash_algorithm")]
+    /// Test [`Sorter::skip_identical`] and [`Sorter::remove_identical`]
+    fn test_skip_identical() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_skip_identical"));
+        let (source, target) = (dir.join(String::from("source")), dir.join(String::from("target")));
+        fs::create_dir_all(source.to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+        let (a, b) = (source.join(String::from("a.jpg")), source.join(String::from("b.jpg")));
+        fs::write(a.to_path_buf(), "a").expect("Failed to write test file.");
+        fs::write(b.to_path_buf(), "b").expect("Failed to write test file.");
+
+        // a.jpg is in the target already, and so is a different b.jpg, so
+        // b.jpg's copy is found at its sequential name
+        let sorter = Sorter {
+            source, target, dir_format: String::new(), preserve_name: true,
+            skip_identical: IdenticalCheck::Hash, remove_identical: true, ..Default::default()
+        };
+        let planned = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        let destination = |path: &File| planned.sorted().find(|(old, _)| *old == path).unwrap().1.copy();
+        let (new_a, new_b) = (destination(&a), destination(&b));
+        let new_b_2 = sorter.get_sequential_name(&new_b, &HashSet::new());
+        fs::write(new_a.to_path_buf(), "a").expect("Failed to write test file.");
+        fs::write(new_b.to_path_buf(), "not b").expect("Failed to write test file.");
+        fs::write(new_b_2.to_path_buf(), "b").expect("Failed to write test file.");
+        let report = sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        assert!(report.is_ok());
+        assert_eq!(report.count(), 0);
+        assert_eq!(report.present.len(), 2);
+        assert!(report.present.contains(&(a.copy(), new_a)) && report.present.contains(&(b.copy(), new_b_2)));
+        assert!(!a.exists() && !b.exists());
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    /// Test [`Sorter::conflict_policy`]
+    fn test_conflict_policy() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_conflict_policy"));
+        let (source, target) = (dir.join(String::from("source")), dir.join(String::from("target")));
+        fs::create_dir_all(source.to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+        let (a, b) = (source.join(String::from("a.jpg")), source.join(String::from("b.jpg")));
+        fs::write(a.to_path_buf(), "new a").expect("Failed to write test file.");
+        fs::write(b.to_path_buf(), "old b").expect("Failed to write test file.");
+
+        // The a.jpg in the target is older, so it is replaced, but the b.jpg
+        // in the target is newer, so it is kept
+        let sorter = Sorter {
+            source, target, dir_format: String::new(), preserve_name: true,
+            conflict_policy: ConflictPolicy::Update, ..Default::default()
+        };
+        let planned = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        let destination = |path: &File| planned.sorted().find(|(old, _)| *old == path).unwrap().1.copy();
+        let (new_a, new_b) = (destination(&a), destination(&b));
+        fs::write(new_a.to_path_buf(), "old a").expect("Failed to write test file.");
+        fs::write(new_b.to_path_buf(), "new b").expect("Failed to write test file.");
+        let time = |seconds| filetime::FileTime::from_unix_time(seconds, 0);
+        filetime::set_file_mtime(new_a.to_path_buf(), time(1_600_000_000)).unwrap();
+        filetime::set_file_mtime(new_b.to_path_buf(), time(4_000_000_000)).unwrap();
+        let report = sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        assert!(report.is_ok());
+        assert_eq!(report.count(), 1);
+        assert_eq!(report.warnings, vec![(a.copy(), SortWarning::Replaced(new_a.copy()))]);
+        assert_eq!(report.present, vec![(b.copy(), new_b.copy())]);
+        assert_eq!(fs::read_to_string(new_a.to_path_buf()).unwrap(), "new a");
+        assert_eq!(fs::read_to_string(new_b.to_path_buf()).unwrap(), "new b");
+        assert!(!a.exists() && b.exists());
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    /// Test [`Sorter::stale_policy`]
+    fn test_stale_policy() {
+
+        // An observer that changes one file and removes another one once the
+        // first file is moved, after the sort was planned. Files are moved in
+        // chronological order, with time_tie_break.
+        struct Changer(File, File, AtomicUsize);
+        impl Observer for Changer {
+            fn on_file(&self, _progress: &Progress) {
+                if self.2.fetch_add(1, Ordering::SeqCst) == 0 {
+                    fs::write(self.0.to_path_buf(), "changed").unwrap();
+                    filetime::set_file_mtime(self.0.to_path_buf(), filetime::FileTime::from_unix_time(1_600_000_000, 0)).unwrap();
+                    fs::remove_file(self.1.to_path_buf()).unwrap();
+                }
+            }
+        }
+        let sort = |stale_policy| {
+            let sandbox = Sandbox::new(&[
+                Entry::file("source/a.jpg").modified_at(1_690_000_000),
+                Entry::file("source/b.jpg").modified_at(1_700_000_000),
+                Entry::file("source/b.xmp").modified_at(1_700_000_000),
+                Entry::file("source/c.jpg").modified_at(1_700_000_000),
+            ]).expect("Failed to create sandbox.");
+            let mut sorter = Sorter {
+                source: sandbox.source(),
+                target: sandbox.target(),
+                dir_format: String::from("%Y/"),
+                preserve_name: true,
+                sidecar_types: vec![String::from("xmp")],
+                time_tie_break: true,
+                stale_policy,
+                ..Default::default()
+            };
+            let changer = Changer(sandbox.path("source/b.jpg"), sandbox.path("source/c.jpg"), AtomicUsize::new(0));
+            sorter.register_observer(Arc::new(changer));
+            let report = sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+            (sandbox, report)
+        };
+        let year = |path: &File| path.pathbuf.parent().unwrap().file_name().unwrap().to_string_lossy().to_string();
+
+        // Changed files, and their sidecars, are left where they are
+        let (sandbox, report) = sort(StalePolicy::Skip);
+        let [b, b_xmp, c] = ["source/b.jpg", "source/b.xmp", "source/c.jpg"].map(|path| sandbox.path(path));
+        assert_eq!(report.old, vec![sandbox.path("source/a.jpg")]);
+        assert_eq!(report.skipped, vec![b.copy(), b_xmp.copy(), c.copy()]);
+        assert!(report.warnings.contains(&(b.copy(), SortWarning::Changed)));
+        assert!(b.exists() && b_xmp.exists());
+
+        // Or sorted again, by their new dates, with their sidecars
+        let (sandbox, report) = sort(StalePolicy::Replan);
+        let [b, b_xmp, c] = ["source/b.jpg", "source/b.xmp", "source/c.jpg"].map(|path| sandbox.path(path));
+        let years: Vec<String> = report.new.iter().map(year).collect();
+        assert_eq!(years, vec!["2023", "2020", "2020"]);
+        assert!(report.new.iter().all(File::exists));
+        assert!(matches!(&report.warnings[..], [.., (path, SortWarning::Replanned(planned))] if *path == b_xmp && year(planned) == "2023"));
+        assert_eq!(report.skipped, vec![c.copy()]);
+        assert!(!b.exists());
+
+        // Or they fail
+        let (sandbox, report) = sort(StalePolicy::Error);
+        let failed: Vec<&File> = report.failures.iter().map(|(path, _)| path).collect();
+        assert_eq!(failed, vec![&sandbox.path("source/b.jpg"), &sandbox.path("source/c.jpg")]);
+        assert!(matches!(report.failures[0].1, SortError::FileChanged(_)));
+    }
+
+    #[test]
+    /// Test [`Sorter::sort_report`] when a destination is taken after the sort
+    /// was planned
+    fn test_sort_report_taken_destination() {
+
+        // An observer that takes a destination once the first file is moved
+        struct Taker(File, AtomicUsize);
+        impl Observer for Taker {
+            fn on_file(&self, _progress: &Progress) {
+                if self.1.fetch_add(1, Ordering::SeqCst) == 0 {
+                    fs::write(self.0.to_path_buf(), "taken").unwrap();
+                }
+            }
+        }
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/a.jpg").modified_at(1_690_000_000),
+            Entry::file("source/b.jpg").modified_at(1_700_000_000),
+            Entry::file("source/b.xmp").modified_at(1_700_000_000),
+        ]).expect("Failed to create sandbox.");
+        let mut sorter = Sorter {
+            source: sandbox.source(),
+            target: sandbox.target(),
+            dir_format: String::new(),
+            preserve_name: true,
+            sidecar_types: vec![String::from("xmp")],
+            time_tie_break: true,
+            ..Default::default()
+        };
+        let planned = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.").new;
+        sorter.register_observer(Arc::new(Taker(planned[1].copy(), AtomicUsize::new(0))));
+
+        // The file gets a sequential name instead of overwriting the one in the
+        // way, and its sidecar goes along
+        let report = sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        assert_eq!(fs::read_to_string(planned[1].to_path_buf()).unwrap(), "taken");
+        assert_eq!(report.new[1].file_stem(), format!("{}_2", planned[1].file_stem()));
+        assert_eq!(report.new[2].file_stem(), report.new[1].file_stem());
+        assert!(report.new.iter().all(File::exists));
+        assert!(report.warnings.contains(&(sandbox.path("source/b.jpg"), SortWarning::DestinationExists(planned[1].copy()))));
+    }
+
+    #[test]
+    /// Test [`Sorter::sort_report`] when the destination of a sidecar is taken,
+    /// but not the one of its primary
+    fn test_sort_report_taken_sidecar() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/a.jpg").modified_at(1_656_676_800),
+            Entry::file("source/a.xmp").modified_at(1_656_676_800),
+            Entry::file("source/b.jpg").modified_at(1_656_676_800),
+            Entry::file("source/b.xmp").modified_at(1_656_676_800),
+            Entry::file("target/2022/2022 a.xmp"),
+            Entry::file("target/2022/2022 a_2.jpg"),
+        ]).expect("Failed to create sandbox.");
+        let mut sorter = Sorter {
+            source: sandbox.source(),
+            target: sandbox.target(),
+            date_format: String::from("%Y"),
+            dir_format: String::from("%Y/"),
+            preserve_name: true,
+            sidecar_types: vec![String::from("xmp")],
+            ..Default::default()
+        };
+
+        // The whole group gets the first sequential name that is free for all
+        // of its files, so the sidecars keep the stems of their primaries
+        let report = sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        let mut moved: Vec<(File, File)> = report.old.into_iter().zip(report.new).collect();
+        moved.sort_by(|(a, _), (b, _)| a.pathbuf.cmp(&b.pathbuf));
+        assert_eq!(moved, vec![
+            (sandbox.path("source/a.jpg"), sandbox.path("target/2022/2022 a_3.jpg")),
+            (sandbox.path("source/a.xmp"), sandbox.path("target/2022/2022 a_3.xmp")),
+            (sandbox.path("source/b.jpg"), sandbox.path("target/2022/2022 b.jpg")),
+            (sandbox.path("source/b.xmp"), sandbox.path("target/2022/2022 b.xmp")),
+        ]);
+        assert!(report.warnings.contains(&(sandbox.path("source/a.xmp"), SortWarning::DestinationExists(sandbox.path("target/2022/2022 a.xmp")))));
+
+        // Strict sorts stop instead
+        fs::write(sandbox.path("source/c.jpg").to_path_buf(), "").unwrap();
+        fs::write(sandbox.path("source/c.xmp").to_path_buf(), "").unwrap();
+        fs::write(sandbox.path("target/2022/2022 c.xmp").to_path_buf(), "").unwrap();
+        for path in ["source/c.jpg", "source/c.xmp"] {
+            filetime::set_file_mtime(sandbox.path(path).to_path_buf(), filetime::FileTime::from_unix_time(1_656_676_800, 0)).unwrap();
+        }
+        sorter.strictness = Strictness::Strict;
+        assert!(matches!(sorter.sort_report(ExecutionMode::DryRun), Err(SortError::ConflictUnresolved(_))));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    /// Test [`Sorter::skip_open_files`]
+    fn test_skip_open_files() {
+        use std::process::{Child, Command, Stdio};
+
+        // Start another process that holds on to `path`, until it is killed
+        fn hold(path: &File) -> Child {
+            Command::new("sleep")
+                .arg("60")
+                .stdin(Stdio::from(fs::File::open(path.as_path()).expect("Failed to open test file.")))
+                .spawn()
+                .expect("Failed to start test process.")
+        }
+
+        // An observer that opens a file once the sort is planned
+        struct Opener(File, Mutex<Option<Child>>);
+        impl Observer for Opener {
+            fn on_start(&self, _total: usize) {
+                *self.1.lock().unwrap() = Some(hold(&self.0));
+            }
+        }
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/a.jpg").modified_at(1_690_000_000),
+            Entry::file("source/b.jpg").modified_at(1_700_000_000),
+            Entry::file("source/b.xmp").modified_at(1_700_000_000),
+            Entry::file("source/c.jpg").modified_at(1_710_000_000),
+        ]).expect("Failed to create sandbox.");
+        let mut sorter = Sorter {
+            source: sandbox.source(),
+            target: sandbox.target(),
+            sidecar_types: vec![String::from("xmp")],
+            skip_open_files: true,
+            ..Default::default()
+        };
+        let opener = Arc::new(Opener(sandbox.path("source/b.jpg"), Mutex::new(None)));
+        sorter.register_observer(opener.clone());
+
+        // Files open while the sort is planned aren't planned at all, and files
+        // opened after that are left where they are, along with their sidecars
+        let mut held = hold(&sandbox.path("source/a.jpg"));
+        let report = sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        held.kill().expect("Failed to stop test process.");
+        held.wait().expect("Failed to stop test process.");
+        if let Some(mut child) = opener.1.lock().unwrap().take() {
+            child.kill().expect("Failed to stop test process.");
+            child.wait().expect("Failed to stop test process.");
+        }
+        assert_eq!(report.in_use, vec![sandbox.path("source/a.jpg"), sandbox.path("source/b.jpg")]);
+        assert_eq!(report.skipped, vec![sandbox.path("source/b.xmp")]);
+        assert_eq!(report.old, vec![sandbox.path("source/c.jpg")]);
+        assert!(sandbox.path("source/a.jpg").exists() && sandbox.path("source/b.jpg").exists());
+    }
+
+    #[test]
+    /// Test [`Sorter::sort_report`] while another sort into the target is running
+    fn test_sort_report_locked() {
+        let sandbox = Sandbox::new(&[Entry::file("source/a.jpg")]).expect("Failed to create sandbox.");
+        let sorter = Sorter { source: sandbox.source(), target: sandbox.target(), ..Default::default() };
+
+        // Dry runs don't need the lock, but sorts do, and leave the lock file
+        // out of the sort once the target is sorted again
+        let lock = TargetLock::acquire(&sandbox.target()).expect("Failed to lock target.");
+        assert_eq!(sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.").count(), 1);
+        assert!(matches!(sorter.sort_report(ExecutionMode::Execute), Err(SortError::AlreadyRunning(_))));
+        assert!(sandbox.path("source/a.jpg").exists());
+        drop(lock);
+        assert_eq!(sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.").count(), 1);
+        let resort = Sorter { source: sandbox.target(), target: sandbox.path("other"), ..Default::default() };
+        fs::create_dir(sandbox.path("other").to_path_buf()).unwrap();
+        assert_eq!(resort.sort_report(ExecutionMode::DryRun).expect("Failed to sort.").count(), 1);
+    }
+
+    #[test]
+    /// Test [`Sorter::cancel`]
+    fn test_cancel() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_cancel"));
+        let (source, target) = (dir.join(String::from("source")), dir.join(String::from("target")));
+        fs::create_dir_all(source.to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(source.join(String::from(name)).to_path_buf(), name).expect("Failed to write test file.");
+        }
+
+        // An observer that cancels the sort after the first file
+        struct Canceller(CancelToken);
+        impl Observer for Canceller {
+            fn on_file(&self, _progress: &Progress) {
+                self.0.cancel();
+            }
+        }
+        let mut sorter = Sorter { source, target, dir_format: String::new(), preserve_name: true, ..Default::default() };
+        sorter.register_observer(Arc::new(Canceller(sorter.cancel.clone())));
+        let report = sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        assert_eq!(report.count(), 1);
+        assert!(report.new[0].exists());
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    /// Test [`Sorter::audit_dates`] and [`Sorter::suspicious_dir`]
+    fn test_audit_dates() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/a.jpg").modified_at(1_656_676_800),
+            Entry::file("source/b.jpg").modified_at(0),
+            // Two years ahead: in the future, but still in the supported years
+            Entry::file("source/c.jpg").modified_at(Local::now().timestamp() + 2 * 365 * 86_400),
+        ]).expect("Failed to create sandbox.");
+        let mut sorter = Sorter {
+            source: sandbox.source(),
+            target: sandbox.target(),
+            date_format: String::from("%Y"),
+            dir_format: String::from("%Y/"),
+            preserve_name: true,
+            ..Default::default()
+        };
+        let report = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        assert!(report.warnings.is_empty());
+
+        // A dry run audits the dates, and lists the ones that can't be right
+        sorter.audit_dates = true;
+        let report = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        let mut warnings = report.warnings;
+        warnings.sort_by(|(a, _), (b, _)| a.pathbuf.cmp(&b.pathbuf));
+        assert_eq!(warnings, vec![
+            (sandbox.path("source/b.jpg"), SortWarning::SuspiciousDate(SuspiciousDate::Epoch)),
+            (sandbox.path("source/c.jpg"), SortWarning::SuspiciousDate(SuspiciousDate::Future)),
+        ]);
+
+        // Those files go to be reviewed, rather than into the date tree
+        sorter.suspicious_dir = Some(String::from("Review"));
+        sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        let files = sandbox.files();
+        assert_eq!(files.len(), 4);
+        assert!(files.contains(&String::from("target/2022/2022 a.jpg")));
+        assert_eq!(files.iter().filter(|file| file.starts_with("target/Review/")).count(), 2);
+    }
+
+    #[test]
+    /// Test [`Sorter::missing_date`]
+    fn test_missing_date() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/IMG_20230714_103012.jpg").modified_at(1_656_676_800),
+            Entry::file("source/holiday.jpg").modified_at(1_656_676_800),
+        ]).expect("Failed to create sandbox.");
+        let mut sorter = Sorter {
+            source: sandbox.source(),
+            target: sandbox.target(),
+            date_format: String::from("%Y"),
+            dir_format: String::from("%Y/"),
+            date_sources: vec![DateSource::Filename],
+            ..Default::default()
+        };
+        let destinations = |sorter: &Sorter| {
+            let report = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+            let mut new = report.new.clone();
+            new.sort_by(|a, b| a.pathbuf.cmp(&b.pathbuf));
+            (report, new)
+        };
+
+        // Files without dates in their names are sorted by their timestamps, or
+        // skipped, or sorted into a directory of their own
+        let (_, new) = destinations(&sorter);
+        assert_eq!(new, vec![sandbox.path("target/2022/2022.jpg"), sandbox.path("target/2023/2023.jpg")]);
+        sorter.missing_date = MissingDatePolicy::Skip;
+        let (report, new) = destinations(&sorter);
+        assert_eq!(new, vec![sandbox.path("target/2023/2023.jpg")]);
+        assert_eq!(report.skipped, vec![sandbox.path("source/holiday.jpg")]);
+        sorter.missing_date = MissingDatePolicy::Folder;
+        let (_, new) = destinations(&sorter);
+        assert_eq!(new, vec![sandbox.path("target/2023/2023.jpg"), sandbox.path("target/unknown-date/holiday.jpg")]);
+
+        // Or quarantined, if there is a quarantine
+        sorter.missing_date = MissingDatePolicy::Quarantine;
+        assert!(matches!(sorter.validate(), Err(SortError::InvalidConfig(_))));
+        sorter.quarantine_dir = Some(String::from("_unsorted"));
+        let (report, new) = destinations(&sorter);
+        assert_eq!(new, vec![sandbox.path("target/2023/2023.jpg"), sandbox.path("target/_unsorted/holiday.jpg")]);
+        assert!(matches!(&report.quarantined[..], [(path, SortError::TimestampUnavailable(_))] if path.file_name() == "holiday.jpg"));
+    }
+
+    #[test]
+    /// Test [`Sorter::quarantine_dir`]
+    fn test_quarantine_dir() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/a.jpg").modified_at(1_656_676_800),
+            Entry::file("target/_unsorted/a.jpg"),
+        ]).expect("Failed to create sandbox.");
+
+        // Files without dates are planned into the quarantine
+        let mut sorter = Sorter {
+            source: sandbox.source(),
+            target: sandbox.target(),
+            date_format: String::from("%Y"),
+            dir_format: String::from("%Y/"),
+            preserve_name: true,
+            clock_offset: String::from("+3000000d"),
+            ..Default::default()
+        };
+        let report = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        assert!(matches!(&report.failures[..], [(_, SortError::UnsupportedTimestamp(_))]));
+        sorter.quarantine_dir = Some(String::from("_unsorted"));
+        let report = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        assert!(report.failures.is_empty());
+        assert!(matches!(&report.quarantined[..], [(_, SortError::UnsupportedTimestamp(_))]));
+        assert_eq!(report.new, vec![sandbox.path("target/_unsorted/a_2.jpg")]);
+
+        // Files that fail to be moved are moved into the quarantine instead
+        sorter.clock_offset = String::new();
+        fs::write(sandbox.path("target/2022").to_path_buf(), "in the way").expect("Failed to write test file.");
+        let report = sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        assert!(report.failures.is_empty());
+        assert!(matches!(&report.quarantined[..], [(_, SortError::DirectoryNotCreated(..))]));
+        assert_eq!(sandbox.files(), vec![
+            String::from("target/.sortery.lock"),
+            String::from("target/2022"),
+            String::from("target/_unsorted/a.jpg"),
+            String::from("target/_unsorted/a_2.jpg"),
+        ]);
+    }
+
+    #[test]
+    /// Test [`Sorter::set_granularity`]
+    fn test_set_granularity() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/a.jpg").modified_at(1_656_676_800),
+        ]).expect("Failed to create sandbox.");
+        let mut sorter = Sorter {
+            source: sandbox.source(),
+            target: sandbox.target(),
+            ..Default::default()
+        };
+        let destination = |sorter: &Sorter| {
+            let report = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+            let path = report.new[0].pathbuf.strip_prefix(&sandbox.target().pathbuf).unwrap().to_path_buf();
+            path.to_string_lossy().replace('\\', "/")
+        };
+        let date = Local.timestamp_opt(1_656_676_800, 0).unwrap();
+        for (granularity, expected) in [
+            (Granularity::Year, date.format("%Y/%Y-%m-%d.jpg")),
+            (Granularity::YearMonth, date.format("%Y/%m/%Y-%m-%d.jpg")),
+            (Granularity::YearMonthDay, date.format("%Y/%m/%d/%Y-%m-%d %Hh%Mm%Ss.jpg")),
+            (Granularity::Flat, date.format("%Y-%m-%d %Hh%Mm%Ss.jpg")),
+        ] {
+            sorter.set_granularity(granularity);
+            assert_eq!(destination(&sorter), expected.to_string());
+        }
+    }
+
+    #[test]
+    /// Test [`Sorter::keep_top_folder`]
+    fn test_keep_top_folder() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/Alice/Phone/a.jpg").modified_at(1_656_676_800),
+            Entry::file("source/Bob/b.jpg").modified_at(1_656_676_800),
+            Entry::file("source/c.jpg").modified_at(1_656_676_800),
+            Entry::file("source/Bob/Invoice.pdf").modified_at(1_656_676_800),
+        ]).expect("Failed to create sandbox.");
+        let sorter = Sorter {
+            source: sandbox.source(),
+            target: sandbox.target(),
+            date_format: String::from("%Y"),
+            dir_format: String::from("%Y/"),
+            preserve_name: true,
+            keep_top_folder: true,
+            rules: vec![Rule { name_keywords: vec![String::from("invoice")], ..Rule::new("Finance/", &[]) }],
+            ..Default::default()
+        };
+        sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        assert_eq!(sandbox.files(), vec![
+            String::from("target/.sortery.lock"),
+            String::from("target/2022/2022 c.jpg"),
+            String::from("target/Alice/2022/2022 a.jpg"),
+            String::from("target/Bob/2022/2022 b.jpg"),
+            String::from("target/Finance/2022 Invoice.pdf"),
+        ]);
+    }
+
+    #[test]
+    /// Test [`Sorter::register_provider`]
+    fn test_register_provider() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/ACME_report.pdf").modified_at(1_656_676_800),
+            Entry::file("source/notes.txt").modified_at(1_656_676_800),
+            Entry::file("source/UP_notes.txt").modified_at(1_656_676_800),
+        ]).expect("Failed to create sandbox.");
+
+        // A provider that knows the clients by the prefixes of the file names,
+        // and would fill in the quarter too if it were asked. Clients named like
+        // a `..` folder stay inside the target.
+        struct Clients;
+        impl MetadataProvider for Clients {
+            fn variables(&self, path: &File, names: &[&str]) -> HashMap<String, String> {
+                let client = match path.file_name().split_once('_') {
+                    Some(("ACME", _)) => "Acme",
+                    Some(("UP", _)) => "..",
+                    _ => "unknown_client",
+                };
+                [("client", client), ("quarter", "Q9")].iter()
+                    .filter(|(name, _)| names.contains(name))
+                    .map(|(name, value)| (String::from(*name), String::from(*value)))
+                    .collect()
+            }
+        }
+        let mut sorter = Sorter {
+            source: sandbox.source(),
+            target: sandbox.target(),
+            date_format: String::from("%Y"),
+            dir_format: String::from("{client}/{extension}/{quarter}/{unknown}/"),
+            preserve_name: true,
+            ..Default::default()
+        };
+        sorter.register_provider(Arc::new(Clients));
+        let mut report = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        report.new.sort_by(|a, b| a.pathbuf.cmp(&b.pathbuf));
+        assert_eq!(report.new, vec![
+            sandbox.path("target/Acme/pdf/Q3/{unknown}/2022 ACME_report.pdf"),
+            sandbox.path("target/_/txt/Q3/{unknown}/2022 UP_notes.txt"),
+            sandbox.path("target/unknown_client/txt/Q3/{unknown}/2022 notes.txt"),
+        ]);
+    }
+
+    #[test]
+    /// Test [`Sorter::progress_interval`]
+    fn test_progress_interval() {
+        let entries: Vec<Entry> = (0..10).map(|n| Entry::file(&format!("source/{}.txt", n))).collect();
+        let sandbox = Sandbox::new(&entries).expect("Failed to create sandbox.");
+
+        // An observer that writes down the files it hears about
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<usize>>);
+        impl Observer for Recorder {
+            fn on_file(&self, progress: &Progress) {
+                self.0.lock().unwrap().push(progress.done);
+            }
+        }
+        let mut sorter = Sorter {
+            source: sandbox.source(),
+            target: sandbox.target(),
+            progress_interval: ProgressInterval { files: Some(4), millis: None },
+            ..Default::default()
+        };
+        let recorder = Arc::new(Recorder::default());
+        sorter.register_observer(recorder.clone());
+
+        // The last file and the end of the sort are always reported
+        let mut percents = Vec::new();
+        let report = sorter.sort_report_with_callback(ExecutionMode::DryRun, |progress| percents.push(progress.percent))
+            .expect("Failed to sort.");
+        assert_eq!(report.count(), 10);
+        assert_eq!(*recorder.0.lock().unwrap(), vec![0, 4, 8, 9]);
+        assert_eq!(percents, vec![40, 80, 90, 100]);
+    }
+
+    #[test]
+    #[cfg(feature = "blake3")]
+    /// Test sorting in [`ExecutionMode::ExecuteIntegrity`] mode
+    fn test_execute_integrity() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/a.jpg").contents("a").modified_at(1_656_676_800),
+            Entry::file("source/b.jpg").contents("b").modified_at(1_656_676_800),
+        ]).expect("Failed to create sandbox.");
+        let copy = Rule { name_keywords: vec![String::from("a")], transfer: Some(TransferMode::Copy), ..Rule::new("%Y/", &[]) };
+        let sorter = Sorter {
+            source: sandbox.source(),
+            target: sandbox.target(),
+            date_format: String::from("%Y"),
+            dir_format: String::from("%Y/"),
+            preserve_name: true,
+            rules: vec![copy],
+            ..Default::default()
+        };
+        let report = sorter.sort_report(ExecutionMode::ExecuteIntegrity).expect("Failed to sort.");
+        assert!(report.is_ok());
+
+        // Copied and renamed files are both recorded, and the integrity manifest
+        // isn't sorted itself
+        let manifest = crate::integrity::load(&sandbox.target()).expect("Failed to load manifest.");
+        let paths: Vec<&str> = manifest.entries.iter().map(|entry| entry.path.as_str()).collect();
+        assert_eq!(paths, vec!["2022/2022 a.jpg", "2022/2022 b.jpg"]);
+        assert_eq!(manifest.entries[0].hash, blake3::hash(b"a").to_hex().to_string());
+        assert!(manifest.verify(&sandbox.target(), 0).expect("Failed to verify.").is_ok());
+        let resort = Sorter { source: sandbox.target(), target: sandbox.path("other"), ..Default::default() };
+        fs::create_dir(sandbox.path("other").to_path_buf()).unwrap();
+        assert_eq!(resort.sort_report(ExecutionMode::DryRun).expect("Failed to sort.").count(), 2);
+    }
+
+    #[test]
+    #[cfg(not(feature = "blake3"))]
+    /// Test that sorting in [`ExecutionMode::ExecuteIntegrity`] mode is refused
+    fn test_execute_integrity() {
+        let sandbox = Sandbox::new(&[Entry::file("source/a.jpg")]).expect("Failed to create sandbox.");
+        let sorter = Sorter { source: sandbox.source(), target: sandbox.target(), ..Default::default() };
+        assert!(matches!(sorter.sort_report(ExecutionMode::ExecuteIntegrity), Err(SortError::InvalidConfig(_))));
+        assert!(sandbox.path("source/a.jpg").exists());
+    }
+
+    #[test]
+    #[cfg_attr(not(feature = "sha256"), ignore = "hashes with SHA-256, the default hash_algorithm")]
+    /// Test [`Sorter::hash_workers`]
+    fn test_hash_workers() {
+        let entries: Vec<Entry> = (0..20).map(|n| Entry::file(&format!("source/{}.txt", n)).contents(n.to_string())).collect();
+        let sandbox = Sandbox::new(&entries).expect("Failed to create sandbox.");
+        let sorter = Sorter {
+            source: sandbox.source(),
+            target: sandbox.target(),
+            dir_format: String::new(),
+            preserve_name: true,
+            hash_workers: 3,
+            ..Default::default()
+        };
+
+        // Files hashed ahead of their moves are still verified, and so are the
+        // ones hashed on several threads for a manifest
+        let report = sorter.sort_report(ExecutionMode::ExecuteVerified).expect("Failed to sort.");
+        assert!(report.is_ok());
+        assert_eq!(report.count(), 20);
+        let manifest = sorter.manifest().expect("Failed to make manifest.");
+        assert_eq!(manifest.entries.len(), 20);
+        assert!(sorter.verify(&manifest).expect("Failed to verify.").is_ok());
+    }
+
+    #[test]
+    /// Test [`Sorter::workers`]
+    fn test_workers() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_workers"));
+        let (source, target) = (dir.join(String::from("source")), dir.join(String::from("target")));
+        fs::create_dir_all(source.to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+        for n in 0..20 {
+            fs::write(source.join(format!("{}.txt", n)).to_path_buf(), n.to_string()).expect("Failed to write test file.");
+        }
+
+        // Every file is moved, and the observers hear about each of them once
+        #[derive(Default)]
+        struct Counter(AtomicUsize);
+        impl Observer for Counter {
+            fn on_file(&self, _progress: &Progress) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        let mut sorter = Sorter { source, target, dir_format: String::new(), preserve_name: true, workers: 4, ..Default::default() };
+        let counter = Arc::new(Counter::default());
+        sorter.register_observer(counter.clone());
+        let report = sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        assert!(report.is_ok());
+        assert_eq!(report.count(), 20);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 20);
+        for (old, new) in report.sorted() {
+            assert!(!old.exists());
+            assert_eq!(fs::read_to_string(new.to_path_buf()).unwrap(), old.file_stem());
+        }
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    /// Test [`crate::execution::Backend::IoUring`]
+    fn test_backend_io_uring() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_backend_io_uring"));
+        let (source, target) = (dir.join(String::from("source")), dir.join(String::from("target")));
+        fs::create_dir_all(source.to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+        for n in 0..(crate::uring::BATCH_SIZE + 10) {
+            fs::write(source.join(format!("{}.txt", n)).to_path_buf(), n.to_string()).expect("Failed to write test file.");
+        }
+
+        // Sorting with io_uring where it isn't available is refused
+        let mut sorter = Sorter { source, target, preserve_name: true, backend: crate::execution::Backend::IoUring, ..Default::default() };
+        if !crate::uring::is_supported() {
+            assert!(matches!(sorter.sort_report(ExecutionMode::Execute), Err(SortError::InvalidConfig(_))));
+            fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+            return;
+        }
+
+        // More than one batch, all moved, even the files that io_uring fails to
+        // rename, like the ones going to another filesystem
+        let shm = File::from("/dev/shm/sorterylib_test_backend_io_uring");
+        if shm.pathbuf.parent().is_some_and(Path::is_dir) {
+            sorter.min_size = Some(2);
+            sorter.tiny_dir = Some(shm.to_string());
+        }
+        let report = sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        assert!(report.is_ok());
+        assert_eq!(report.count(), crate::uring::BATCH_SIZE + 10);
+        for (old, new) in report.sorted() {
+            assert!(!old.exists());
+            assert_eq!(fs::read_to_string(new.to_path_buf()).unwrap(), old.file_stem());
+        }
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+        if shm.exists() {
+            fs::remove_dir_all(shm.to_path_buf()).expect("Failed to remove test dir.");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "tracing")]
+    #[tracing_test::traced_test]
+    /// Test the `tracing` spans of planning and moving files
+    fn test_tracing() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_tracing"));
+        let (source, target) = (dir.join(String::from("source")), dir.join(String::from("target")));
+        fs::create_dir_all(source.to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+        let path = source.join(String::from("a.txt"));
+        fs::write(path.to_path_buf(), "a").expect("Failed to write test file.");
+
+        // Each file's span has its path, timestamp, and destination
+        let sorter = Sorter { source, target, ..Default::default() };
+        let report = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        assert!(logs_contain(&format!("plan_file{{path={}", path)));
+        assert!(logs_contain(&format!("destination={}", report.new[0])));
+        assert!(logs_contain("timestamp="));
+        assert!(logs_contain("status=Planned"));
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    /// Test [`Sorter::time_tie_break`]
+    fn test_time_tie_break() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_time_tie_break"));
+        let (source, target) = (dir.join(String::from("source")), dir.join(String::from("target")));
+        fs::create_dir_all(source.to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+
+        // Three files from the same minute, two of them from the same second
+        let start = Local.with_ymd_and_hms(2023, 7, 14, 10, 30, 12).unwrap().timestamp();
+        for (name, secs, nanos) in [("a.jpg", start, 0), ("b.jpg", start + 1, 0), ("c.jpg", start + 1, 250_000_000)] {
+            let path = source.join(String::from(name));
+            fs::write(path.to_path_buf(), name).expect("Failed to write test file.");
+            filetime::set_file_mtime(path.to_path_buf(), filetime::FileTime::from_unix_time(secs, nanos))
+                .expect("Failed to set test file time.");
+        }
+
+        let sorter = Sorter {
+            source: source.copy(),
+            target: target.copy(),
+            date_format: String::from("%Y-%m-%d %Hh%M"),
+            dir_format: String::new(),
+            time_tie_break: true,
+            ..Default::default()
+        };
+        let mut new: Vec<String> = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.").new.iter()
+            .map(|path| path.file_name())
+            .collect();
+        new.sort();
+        assert_eq!(new, vec!["2023-07-14 10h30-13-250.jpg", "2023-07-14 10h30-13.jpg", "2023-07-14 10h30.jpg"]);
+
+        // Without it, they just get sequential names
+        let sequential = Sorter { time_tie_break: false, ..sorter };
+        let mut new: Vec<String> = sequential.sort_report(ExecutionMode::DryRun).expect("Failed to sort.").new.iter()
+            .map(|path| path.file_name())
+            .collect();
+        new.sort();
+        assert_eq!(new, vec!["2023-07-14 10h30.jpg", "2023-07-14 10h30_2.jpg", "2023-07-14 10h30_3.jpg"]);
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    /// Test sorting files from before 1970, which have negative timestamps
+    fn test_pre_1970() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_pre_1970"));
+        let (source, target) = (dir.join(String::from("source")), dir.join(String::from("target")));
+        fs::create_dir_all(source.to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+
+        // Just before, at, and just after the epoch, and long before it
+        let sorter = Sorter { source: source.copy(), target: target.copy(), date_format: String::from("%Y-%m-%d %H%M%S"), ..Default::default() };
+        for secs in [-1, 0, 1, -1_577_923_200] {
+            let path = source.join(format!("{}.txt", secs));
+            fs::write(path.to_path_buf(), "old").expect("Failed to write test file.");
+            filetime::set_file_mtime(path.to_path_buf(), filetime::FileTime::from_unix_time(secs, 0))
+                .expect("Failed to set test file time.");
+
+            let (datetime, _) = sorter.get_file_datetime(&path).expect("Failed to get file date.");
+            assert_eq!(datetime.timestamp(), secs);
+        }
+
+        // The files are sorted into the right year directories, in order
+        let report = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        let expected = |secs: i64| {
+            let datetime = Local.timestamp_opt(secs, 0).unwrap();
+            target.join(datetime.format("%Y/%m/%Y-%m-%d %H%M%S.txt").to_string())
+        };
+        for (old, new) in report.old.iter().zip(&report.new) {
+            let secs: i64 = old.file_stem().parse().unwrap();
+            assert_eq!(*new, expected(secs));
+        }
+        assert_eq!(report.count(), 4);
+        assert!(report.warnings.is_empty() && report.failures.is_empty());
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// Test [`Sorter::get_new_date_path`] and [`Sorter::get_sequential_name`] with
+    /// names that aren't valid UTF-8
+    fn test_get_new_date_path_non_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let sorter = Sorter { date_format: String::from("%Y"), preserve_name: true, ..Default::default() };
+        let old = File::from(Path::new(OsStr::from_bytes(b"caf\xe9.jpg")));
+        let time = Local.with_ymd_and_hms(2023, 7, 14, 12, 0, 0).unwrap();
+        let new = sorter.get_new_date_path(&File::from("dir"), &old, &time, &sorter.date_format);
+        assert_eq!(new.pathbuf.as_os_str().as_bytes(), b"dir/2023 caf\xe9.jpg");
+        let sequential = sorter.get_sequential_name(&new, &HashSet::from([new.copy()]));
+        assert_eq!(sequential.pathbuf.as_os_str().as_bytes(), b"dir/2023 caf\xe9_2.jpg");
+    }
+
+    #[test]
+    /// Test [`Sorter::get_new_date_path`] and [`Sorter::get_sequential_name`] with
+    /// files that have no extension
+    fn test_get_new_date_path_extensionless() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_get_new_date_path_extensionless"));
+        fs::create_dir_all(dir.to_path_buf()).expect("Failed to create test dir.");
+        let old = dir.join(String::from("test"));
+        fs::write(old.to_path_buf(), b"%PDF-1.7").expect("Failed to write test file.");
+        let time = Local.with_ymd_and_hms(2022, 5, 1, 0, 0, 0).unwrap();
+
+        let keep = Sorter { date_format: String::from("%Y"), preserve_name: true, ..Default::default() };
+        let new = keep.get_new_date_path(&File::from("dir"), &old, &time, &keep.date_format);
+        assert_eq!(new, File::from("dir/2022 test"));
+        assert_eq!(keep.get_sequential_name(&new, &HashSet::from([new.copy()])), File::from("dir/2022 test_2"));
+
+        let sniff = Sorter { extensionless: ExtensionlessPolicy::Sniff, ..keep };
+        assert_eq!(sniff.get_new_date_path(&File::from("dir"), &old, &time, &sniff.date_format), File::from("dir/2022 test.pdf"));
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    /// Test [`Sorter::is_content_sortable`]
+    fn test_is_content_sortable() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_is_content_sortable"));
+        fs::create_dir_all(dir.to_path_buf()).expect("Failed to create test dir.");
+        let (photo, text) = (dir.join(String::from("photo")), dir.join(String::from("text.jpg")));
+        fs::write(photo.to_path_buf(), b"\xFF\xD8\xFF\xE0\x00\x10JFIF").expect("Failed to write test file.");
+        fs::write(text.to_path_buf(), "not a photo").expect("Failed to write test file.");
+
+        let types = |exclude: &[&str], only: &[&str]| TypeFilter::new(
+            &exclude.iter().map(|t| t.to_string()).collect::<Vec<String>>(),
+            &only.iter().map(|t| t.to_string()).collect::<Vec<String>>());
+        let sorter = Sorter::default();
+        assert!(sorter.is_content_sortable(&text, &types(&[], &[])));
+        assert!(sorter.is_content_sortable(&photo, &types(&[], &["image/*"])));
+        assert!(!sorter.is_content_sortable(&text, &types(&[], &["image/*"])));
+        assert!(!sorter.is_content_sortable(&photo, &types(&["image/jpeg"], &[])));
+        assert!(sorter.is_content_sortable(&text, &types(&["image/jpeg"], &[])));
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    /// Test [`Sorter::config_warnings`]
+    fn test_config_warnings() {
+        let strings = |types: &[&str]| types.iter().map(|t| t.to_string()).collect::<Vec<String>>();
+        assert!(Sorter::default().config_warnings().is_empty());
+        assert!(Sorter { exclude_type: strings(&["png"]), only_type: strings(&["*"]), ..Default::default() }
+            .config_warnings().is_empty());
+
+        let sorter = Sorter {
+            exclude_type: strings(&["png", "jpg"]),
+            only_type: strings(&["jpg"]),
+            exclude_content_type: strings(&["image/*"]),
+            only_content_type: strings(&["video/*"]),
+            ..Default::default()
+        };
+        assert_eq!(sorter.config_warnings(), vec![
+            ConfigWarning::Overridden("exclude_type"),
+            ConfigWarning::Contradicts("only_type", "exclude_type", String::from("jpg")),
+            ConfigWarning::Overridden("exclude_content_type"),
+        ]);
+        assert_eq!(sorter.config_warnings()[1].to_string(), "\"jpg\" is in both only_type and exclude_type; only_type wins");
+    }
+
+    #[test]
+    /// Test [`Sorter::estimate`]
+    fn test_estimate() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/a.jpg").contents("aaaa"),
+            Entry::file("source/a.xmp").contents("xmp"),
+            Entry::file("source/nested/b.jpg").contents("bb"),
+            Entry::file("source/c.png").contents("c"),
+            Entry::file("source/d.jpg.part").contents("d"),
+        ]).expect("Failed to create sandbox.");
+        let sorter = Sorter {
+            source: sandbox.source(),
+            exclude_type: vec![String::from("png")],
+            sidecar_types: vec![String::from("xmp")],
+            ..Default::default()
+        };
+        assert_eq!(sorter.estimate().expect("Failed to estimate."), Estimate { files: 3, bytes: 9 });
+        assert_eq!(sorter.estimate().unwrap().to_string(), "about 3 files, 9 B");
+
+        let missing = Sorter { source: sandbox.path("missing"), ..Default::default() };
+        assert!(matches!(missing.estimate(), Err(SortError::PathDoesNotExist(_))));
+    }
+
+    #[test]
+    /// Test [`Sorter::check`]
+    fn test_check() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_check"));
+        let target = dir.join(String::from("target"));
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+
+        assert!(Sorter { source: target.copy(), target: dir.copy(), ..Default::default() }.check().is_empty());
+        let sorter = Sorter { source: dir.copy(), target: target.copy(), date_format: String::from("%Hh%Mm"), ..Default::default() };
+        assert_eq!(sorter.check(), vec![Risk::TargetInSource, Risk::NoDateInFormat, Risk::NameCollisions]);
+        let sorter = Sorter { date_format: String::from("%Y-%m-%d"), preserve_name: true, ..Default::default() };
+        assert!(sorter.check().is_empty());
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    /// Test [`Sorter::get_file_datetime`] with a fallback list of date types
+    fn test_get_file_datetime() {
+        let path = File::from("Cargo.toml");
+        let sorter = Sorter { date_type: String::from("cm"), ..Default::default() };
+        let (datetime, fallback) = sorter.get_file_datetime(&path).unwrap();
+
+        // Creation time isn't available everywhere, so check whichever was used
+        match sorter.get_filetime_creation(&path) {
+            Some(time) => {
+                assert_eq!(datetime.timestamp(), time.unix_seconds());
+                assert_eq!(fallback, None);
+            },
+            None => {
+                assert_eq!(Some(datetime.timestamp()), sorter.get_filetime_modified(&path).map(|t| t.unix_seconds()));
+                assert_eq!(fallback, Some(SortWarning::DateTypeFallback('m')));
+            },
+        }
+        assert!(matches!(
+            sorter.get_file_datetime(&File::from("missing.txt")),
+            Err(SortError::TimestampUnavailable(_))
+        ));
+
+        // The earliest date is never later than any of the others
+        let earliest = sorter.get_datetime(&path, 'e').unwrap().unwrap();
+        assert!(earliest <= sorter.get_datetime(&path, 'm').unwrap().unwrap());
+        if let Some(created) = sorter.get_datetime(&path, 'c') {
+            assert!(earliest <= created.unwrap());
+        }
+
+        // Dates that are pushed out of range by the offset can't be sorted by
+        let offset = Sorter { clock_offset: String::from("+4000000d"), ..Default::default() };
+        assert!(matches!(offset.get_file_datetime(&path), Err(SortError::UnsupportedTimestamp(_))));
+    }
+
+    #[test]
+    /// Test [`Sorter::validate`]
+    fn test_validate() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_validate"));
+        let (source, target) = (dir.join(String::from("source")), dir.join(String::from("target")));
+        fs::create_dir_all(source.to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+
+        let sorter = Sorter { source: source.copy(), target: target.copy(), ..Default::default() };
+        assert!(sorter.validate().is_ok());
+        let missing = Sorter { source: dir.join(String::from("missing")), target: target.copy(), ..Default::default() };
+        assert!(matches!(missing.validate(), Err(SortError::PathDoesNotExist(_))));
+
+        // A target that doesn't exist yet is created by the sort, if its
+        // nearest existing ancestor is a directory
+        let created = Sorter { source: source.copy(), target: dir.join(String::from("new/target")), ..Default::default() };
+        assert!(created.validate().is_ok());
+        created.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+        assert!(created.target.pathbuf.is_dir());
+        fs::write(dir.join(String::from("file")).to_path_buf(), "").expect("Failed to write test file.");
+        let in_file = Sorter { source: source.copy(), target: dir.join(String::from("file/target")), ..Default::default() };
+        assert!(matches!(in_file.validate(), Err(SortError::InvalidConfig(_))));
+
+        let same = Sorter { source: source.copy(), target: source.join(String::from(".")), ..Default::default() };
+        assert!(matches!(same.validate(), Err(SortError::InvalidConfig(_))));
+        let offset = Sorter { source: source.copy(), target: target.copy(), clock_offset: String::from("7 hours"), ..Default::default() };
+        assert!(matches!(offset.validate(), Err(SortError::InvalidConfig(_))));
+        let date_type = Sorter { source: source.copy(), target: target.copy(), date_type: String::from("cx"), ..Default::default() };
+        assert!(matches!(date_type.validate(), Err(SortError::InvalidConfig(_))));
+        for location_grid in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let grid = Sorter { source: source.copy(), target: target.copy(), location_grid, ..Default::default() };
+            assert!(matches!(grid.validate(), Err(SortError::InvalidConfig(_))));
+        }
+        for year_start in [0, 13] {
+            let months = Sorter { source: source.copy(), target: target.copy(), year_start, ..Default::default() };
+            assert!(matches!(months.validate(), Err(SortError::InvalidConfig(_))));
+        }
+        let io_uring = Sorter { source: source.copy(), target: target.copy(), backend: crate::execution::Backend::IoUring, ..Default::default() };
+        assert_eq!(io_uring.validate().is_ok(), Sorter::io_uring_supported());
+        let sha256 = Sorter { source: source.copy(), target: target.copy(), skip_identical: IdenticalCheck::Hash, ..Default::default() };
+        assert_eq!(sha256.validate().is_ok(), cfg!(feature = "sha256"));
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    /// Test [`Sorter::get_destination`] with directory formats that lead out of
+    /// the target
+    fn test_get_destination() {
+        let sandbox = Sandbox::new(&[Entry::file("source/a.txt").modified_at(1_656_676_800)]).expect("Failed to create sandbox.");
+
+        // Files that would be sorted outside of the target fail instead
+        let mut sorter = Sorter { source: sandbox.source(), target: sandbox.target(), dir_format: String::from("../%Y/"), ..Default::default() };
+        let report = sorter.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        assert!(report.new.is_empty());
+        assert!(matches!(&report.failures[..], [(_, SortError::InvalidPath(_))]));
+        sorter.strictness = Strictness::Strict;
+        assert!(matches!(sorter.sort_report(ExecutionMode::DryRun), Err(SortError::InvalidPath(_))));
+
+        // Camera models and regions that are a `..` folder stay inside it
+        #[cfg(feature = "exif")]
+        {
+            use ::exif::{experimental::Writer, Field, In, Rational, Tag, Value};
+            let degrees = |deg| Value::Rational(vec![Rational { num: deg, denom: 1 }, Rational { num: 0, denom: 1 }, Rational { num: 0, denom: 1 }]);
+            let fields = [
+                Field { tag: Tag::Model, ifd_num: In::PRIMARY, value: Value::Ascii(vec![b"..".to_vec()]) },
+                Field { tag: Tag::GPSLatitudeRef, ifd_num: In::PRIMARY, value: Value::Ascii(vec![b"N".to_vec()]) },
+                Field { tag: Tag::GPSLatitude, ifd_num: In::PRIMARY, value: degrees(41) },
+                Field { tag: Tag::GPSLongitudeRef, ifd_num: In::PRIMARY, value: Value::Ascii(vec![b"E".to_vec()]) },
+                Field { tag: Tag::GPSLongitude, ifd_num: In::PRIMARY, value: degrees(12) },
+            ];
+            let mut writer = Writer::new();
+            for field in &fields {
+                writer.push_field(field);
+            }
+            let mut buf = std::io::Cursor::new(Vec::new());
+            writer.write(&mut buf, false).expect("Failed to write EXIF data.");
+            fs::write(sandbox.path("source/b.tif").to_path_buf(), buf.into_inner()).expect("Failed to write test file.");
+
+            let sorter = Sorter {
+                source: sandbox.source(),
+                target: sandbox.target(),
+                dir_format: String::from("{camera}/{location}/"),
+                regions: vec![crate::location::Region { name: String::from(".."), polygon: vec![(40.0, 11.0), (40.0, 13.0), (42.0, 13.0), (42.0, 11.0)] }],
+                ..Default::default()
+            };
+            let report = sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+            assert!(report.is_ok());
+            let b = report.old.iter().position(|old| old.file_name() == "b.tif").expect("b.tif wasn't sorted.");
+            assert_eq!(report.new[b].pathbuf.parent(), Some(sandbox.path("target/_/_").as_path()));
+        }
+    }
+
+    #[test]
+    /// Test [`Sorter::get_new_dir`] with week- and quarter-based layouts
+    fn test_get_new_dir() {
+        let sorter = Sorter::default();
+        let target = File::from("target");
+        let time = |y, m, d| Local.with_ymd_and_hms(y, m, d, 12, 0, 0).unwrap();
+
+        // ISO weeks can start in the year before
+        assert_eq!(sorter.get_new_dir(&target, "%G/W%V/", &time(2021, 1, 1), None, None), target.join(String::from("2020/W53/")));
+        assert_eq!(sorter.get_new_dir(&target, "%G/W%V/", &time(2021, 1, 4), None, None), target.join(String::from("2021/W01/")));
+        assert_eq!(sorter.get_new_dir(&target, "%G/W%V/", &time(2024, 12, 30), None, None), target.join(String::from("2025/W01/")));
+
+        // Quarters, and fiscal quarters
+        assert_eq!(sorter.get_new_dir(&target, "%Y/{quarter}/", &time(2023, 8, 14), None, None), target.join(String::from("2023/Q3/")));
+        let fiscal = Sorter { year_start: 7, ..Default::default() };
+        assert_eq!(fiscal.get_new_dir(&target, "%Y/{quarter}/", &time(2023, 8, 14), None, None), target.join(String::from("2023/Q1/")));
+        assert_eq!(fiscal.get_new_dir(&target, "{fiscal_year}/%m/", &time(2024, 3, 1), None, None), target.join(String::from("2023-2024/03/")));
+    }
+
+    #[test]
+    fn test_sorter() {
+
+        // The paths to use for testing
+        let current_dir = env::current_dir().expect("Failed to get current dir.");
+        let json_path = current_dir.join(Path::new("template.json"));
+        let sandbox = Sandbox::new(&[Entry::file("source/test.json"), Entry::file("source/test.png")]).expect("Failed to create sandbox.");
+        let (source, target) = (sandbox.source(), sandbox.target());
+
+        // Get the string from the json file
+        let json_string = fs::read_to_string(json_path).expect("Failed to read json file.");
+        
+        // Create a Sorter instance for testing
+        let sorter1 = Sorter {
+            source: source.copy(),
+            target: target.copy(),
+            date_format: String::from("%Y-%m-%d %Hh%Mm%Ss"),
+            date_type: String::from("m"),
+            preserve_name: false,
+            exclude_type: vec![String::from("png")],
+            only_type: vec![String::from("json"), String::from("py")],
+            ..Default::default()
+        };
+
+        // Create a Sorter instance from the json string for testing
+        let sorter2 = Sorter::from_json(json_string, source.copy(), target.copy());
+
+        // Compare the two Sorter instances to make sure that the JSON was parsed
+        // correctly, and that the values match up.
+        assert_eq!(sorter1, sorter2);
+
+        // Test the sorting algorithm
+        let report = sorter1.sort_report(ExecutionMode::DryRun).expect("Failed to sort.");
+        assert_eq!(report.old, vec![source.join(String::from("test.json"))]);
+    }
+}
+
+/// This is a private function used in case the user doesn't supply a progress
+/// update callback.
+fn callback_fn(_progress: &Progress) { }
+
+/// The trait used by [`Sorter`] to either parse a JSON string or a JSON file
+pub trait FromJson<T> {
+    fn from_json(json: T, source: File, target: File) -> Sorter;
+}
+
+/// The sorter struct that sorts the files. There are two ways to create an instance
+/// of [`Sorter`]: passing the individual fields, and using [`Sorter::from_json`].
+/// 
+/// [`Sorter`] implements [`Default`], so only the fields that differ from the
+/// defaults need to be passed, using `..Default::default()`.
+#[derive(Debug, PartialEq)]
+pub struct Sorter {
+    /// An instance of [`File`] specifying the directory from which to get
+    /// all the files to sort
+    pub source: File,
+    /// An instance of [`File`] specifying the directory to sort all the
+    /// files into. It is created by the sort if it doesn't exist yet.
+    pub target: File,
+    /// A [`String`] representing the date format. Uses the standard `strftime` format.
+    /// See [`chrono::format::strftime`] for formatting information.
+    pub date_format: String,
+    /// A [`String`] representing the date type to sort by. Must be one of `String::from("a")`
+    /// (accessed) `String::from("c")` (created), or `String::from("m")` (modified).
+    /// Note that sorting by creation date is not available on all filesystems.
+    /// 
+    /// `String::from("e")` (earliest) sorts by the earliest of the creation date,
+    /// the modification date, and (with the `exif` feature) the date a photo was
+    /// taken. Copying and editing files only ever makes their timestamps later,
+    /// so the earliest one is usually the closest to when the file really came
+    /// from, which is what photo archives want.
+    /// 
+    /// Several date types can be given in order of priority, as a fallback list:
+    /// `String::from("cm")` sorts by creation date where it is available, and by
+    /// modification date otherwise. Files sorted by a fallback are listed in the
+    /// [`SortReport`] with a [`SortWarning::DateTypeFallback`]. Files for which
+    /// none of the date types are available, as when creation dates aren't
+    /// recorded by the filesystem, are left where they are (or moved into
+    /// `quarantine_dir`), and listed in its failures with a
+    /// [`SortError::TimestampUnavailable`]. The same goes for files
+    /// whose dates are out of the supported range (see [`dates::supported_years`]),
+    /// as from corrupt metadata, with a [`SortError::UnsupportedTimestamp`].
+    pub date_type: String,
+    /// If [`true`], then the sorter adds the old file name onto the end of the new
+    /// one. For example, `test.txt` would be renamed to something like `2021-04-22 test.txt`.
+    /// If [`false`], then ignores the old file name (would rename `test.txt` to `2021-04-22.txt`).
+    pub preserve_name: bool,
+    /// A [`Vec<String>`] containing all the file extensions to be ignored during
+    /// sorting. For example, if `vec![String::from("jpg")]` is passed, than all files
+    /// ending in `.jpg` won't be sorted. The extensions can contain the wildcards
+    /// `*` and `?`, so `String::from("jp*")` excludes `.jpg` and `.jpeg` files; see
+    /// [`filters`]. Empty extensions are ignored.
+    pub exclude_type: Vec<String>,
+    /// A [`Vec<String>`] containing all the file extensions to be exclusively sorted.
+    /// For example, if `vec![String::from("png")] is passed, than *only* files ending
+    /// in `.png` will be sorted. All other files will be ignored. This option overrides
+    /// `exclude_type`. The extensions can contain the wildcards `*` and `?`, like
+    /// `exclude_type`. An empty list, or one containing `String::from("*")` or an
+    /// empty extension, sorts every file.
+    pub only_type: Vec<String>,
+    /// A [`Vec<String>`] containing the content types of files to be ignored
+    /// during sorting, like `exclude_type`, but detected from the first bytes of
+    /// the files instead of their extensions (see [`content_types`]). Content types
+    /// are MIME types, and can contain wildcards, so `String::from("image/*")`
+    /// excludes every image, even one named `photo` or `photo.txt`. Files whose
+    /// content type isn't recognized are never excluded. Empty by default.
+    pub exclude_content_type: Vec<String>,
+    /// A [`Vec<String>`] containing the content types of files to be exclusively
+    /// sorted, like `only_type`, but detected from the first bytes of the files
+    /// (see `exclude_content_type`). Files whose content type isn't recognized are
+    /// ignored. This option overrides `exclude_content_type`, and applies on top
+    /// of the extension filters. Empty by default, which sorts every file.
+    pub only_content_type: Vec<String>,
+    /// The conditions videos have to meet to be sorted, like a minimum length or
+    /// resolution, so that short clips or 4K footage can be sorted separately,
+    /// in sorts of their own. Only MP4 and QuickTime videos are probed, and only
+    /// if there are any conditions; other files are never left out. See
+    /// [`VideoFilter`]. Defaults to no conditions.
+    pub video_filter: VideoFilter,
+    /// What to do with files that have no extension, as in `IMG_1234`: keep them
+    /// without one, give them the extension of their content type, sort them into
+    /// a `no_extension` directory, or leave them where they are. See
+    /// [`ExtensionlessPolicy`] for the options. Defaults to [`ExtensionlessPolicy::Keep`].
+    pub extensionless: ExtensionlessPolicy,
+    /// How to stamp each sorted file with its original location, so that its
+    /// provenance is never lost. See [`OriginRecord`] for the options. Defaults
+    /// to [`OriginRecord::Off`].
+    pub origin_record: OriginRecord,
+    /// A [`Vec<String>`] containing the extensions of metadata sidecar files, like
+    /// `.xmp` or `.aae` files. A sidecar that sits next to its primary file (as in
+    /// `IMG_1234.jpg` and `IMG_1234.xmp`, or `IMG_1234.jpg.json`) is moved along
+    /// with its primary, and gets the same new stem, instead of being sorted on its
+    /// own. See [`sidecars::DEFAULT_SIDECAR_TYPES`] for the common ones. Empty by
+    /// default, which turns sidecar pairing off.
+    pub sidecar_types: Vec<String>,
+    /// If [`Some`], files whose timestamps are at most this many seconds apart are
+    /// grouped together as a burst, and sorted into a shared subfolder named after
+    /// the first file in the burst, as in `2023/07/14/burst_103012/`. This keeps
+    /// rapid-fire shots together. Defaults to [`None`], which turns burst grouping off.
+    pub burst_interval: Option<u64>,
+    /// If [`Some`], each day's files are split into events wherever there is a gap
+    /// of more than this many seconds between two consecutive files, and each
+    /// event is sorted into its own subfolder named after its first file, as in
+    /// `2023/07/14/event_090000/`. Bursts (see [`Sorter::burst_interval`]) go inside
+    /// their event's folder. Defaults to [`None`], which turns event grouping off.
+    pub event_gap: Option<u64>,
+    /// A [`String`] representing the format of the directories to sort the files
+    /// into, inside `target`. Uses the standard `strftime` format, like `date_format`,
+    /// and can also contain these `{name}` placeholders, which are filled in with
+    /// each file's own values:
+    /// 
+    /// - `{location}`: the name of the first of `regions` that contains the GPS
+    ///   coordinates of the photo, or its cell in a latitude/longitude grid with
+    ///   `location_grid`-degree cells (as in `41N_12E`) if there is none. Files
+    ///   without GPS coordinates get `unknown_location`. Needs the `exif` feature.
+    /// - `{camera}`: the make and model of the camera that took the photo, as in
+    ///   `Canon EOS 5D`. Files without them get `unknown_camera`. Needs the `exif`
+    ///   feature.
+    /// - `{width}` and `{height}`: the size of the image, in pixels, as it is
+    ///   shown, so `{width}x{height}` gives names like `1920x1080`. Needs the
+    ///   `image` feature, like the other image placeholders, which files that
+    ///   aren't images get `unknown` for.
+    /// - `{orientation}`: `portrait`, `landscape`, or `square`, as the image is
+    ///   shown, with its EXIF orientation.
+    /// - `{image_class}`: `thumbnail` for small images, `wallpaper` for large ones
+    ///   in the shape of a screen, and `photo` for the rest, for routing
+    ///   downloaded wallpapers and thumbnails away from real photos (see
+    ///   `images::ImageClass`).
+    /// - `{video_resolution}`: `4k`, `1080p`, `720p`, or `sd`, by the shorter
+    ///   side of the video, like the other video placeholders, which files that
+    ///   aren't MP4 or QuickTime videos get `unknown` for (see [`video`]).
+    /// - `{video_length}`: `clip` for videos shorter than 30 seconds, `short` for
+    ///   ones shorter than 5 minutes, and `long` for the rest.
+    /// - `{video_class}`: `footage` for videos of the resolutions cameras
+    ///   record, and `screen_recording` for the rest, so something like
+    ///   `{video_class}/{video_resolution}/` keeps screen recordings apart.
+    /// - `{extension}`: the lowercase extension of the file, as in `jpg`. Files
+    ///   without one get `no_extension`.
+    /// - `{category}`: the name of the first of `categories` that lists the file's
+    ///   extension, or of its built-in category (see [`categories::DEFAULT_CATEGORIES`])
+    ///   if none of them do, as in `Images`. Files in no category get `Other`.
+    /// - `{parent}`: the name of the folder the file is in, inside `source`, so
+    ///   albums like `Italy 2019/` are carried into the new tree, as in
+    ///   `%Y/%m/{parent}/` for `2019/07/Italy 2019/`. `{parent:2}` is the folder
+    ///   above that, and so on. Files that aren't that deep in folders inside
+    ///   `source` get `no_parent`.
+    /// - `{quarter}`: the quarter of the year, from `Q1` to `Q4`. Quarters start
+    ///   in the `year_start` month, for fiscal quarters.
+    /// - `{fiscal_year}`: the year, starting in the `year_start` month. Years that
+    ///   don't start in January are named after both calendar years, so with a
+    ///   `year_start` of `8`, August 2023 to July 2024 is `2023-2024`, for
+    ///   academic or fiscal-year trees like `{fiscal_year}/%m/`.
+    /// - Any other `{name}`: filled in by the first of `providers` that knows it,
+    ///   as in `{client}` for the client a file belongs to (see
+    ///   [`MetadataProvider`]). Placeholders that nothing fills in are left as
+    ///   they are.
+    /// 
+    /// For example, `String::from("%Y/%m/{location}/")` sorts photos into trees
+    /// like `2023/07/Rome/`. Defaults to `String::from("%Y/%m/")`.
+    /// 
+    /// Each directory is a layer of classification, and the layers can be
+    /// stacked in any order, so `String::from("{category}/%Y/%m/")` sorts files
+    /// into `Images/2023/07/`, `Documents/2023/07/`, and so on, with a single [`Sorter`].
+    /// 
+    /// To archive by week, use the ISO 8601 week-numbering year and week, as in
+    /// `String::from("%G/W%V/")`. Use `%G` rather than `%Y` with `%V`: the first
+    /// days of January can belong to the last week of the year before, so
+    /// 1 January 2021 is sorted into `2020/W53/`, not `2021/W53/`.
+    pub dir_format: String,
+    /// A [`Vec<Rule>`] of rules for sorting some files into trees of their own,
+    /// with formats of their own instead of `dir_format`, as in photos tagged
+    /// `family` going to `Family/%Y/`, or files with `invoice` or `receipt` in
+    /// their names going to `Finance/%Y/`. The first rule that a file meets decides
+    /// where it goes, before `tiny_dir` and `screenshot_dir`. See [`Rule`] for
+    /// the conditions. Empty by default.
+    pub rules: Vec<Rule>,
+    /// A [`Vec<Region>`] of named regions, used for the `{location}` placeholder in
+    /// `dir_format`. See [`Region`] for more information. Empty by default.
+    pub regions: Vec<Region>,
+    /// The size of the cells of the latitude/longitude grid used for the `{location}`
+    /// placeholder in `dir_format`, in degrees, as in `0.1` or `0.5`. Cells are
+    /// named with as many decimal places as the size has. Defaults to `1.0`.
+    pub location_grid: f64,
+    /// If [`Some`], files that look like screenshots (see [`screenshots::is_screenshot`])
+    /// are sorted into this directory instead of `target`, keeping them out of the
+    /// photo archive. A relative path is relative to `target`, so
+    /// `Some(String::from("Screenshots"))` sorts them into `target/Screenshots/2023/07/`
+    /// and so on. Defaults to [`None`], which sorts screenshots like any other file.
+    pub screenshot_dir: Option<String>,
+    /// A [`Vec<DateSource>`] of sources of dates embedded in the files themselves,
+    /// like the `Date:` header of emails, in order of priority. The date of each file
+    /// is read from the first of these sources that has one, and from its filesystem
+    /// timestamp (see `date_type`) if none of them do. Empty by default, which only
+    /// uses the filesystem timestamps.
+    pub date_sources: Vec<DateSource>,
+    /// What to do with files that none of `date_sources` have a date for: sort
+    /// them by their filesystem timestamps, leave them where they are, move
+    /// them into `quarantine_dir`, or sort them into an `unknown-date`
+    /// directory. See [`MissingDatePolicy`] for the options. The same goes for
+    /// files that none of the date types in `date_type` are available for.
+    /// Defaults to [`MissingDatePolicy::Fallback`].
+    pub missing_date: MissingDatePolicy,
+    /// A [`String`] representing a time offset added to the date of every file,
+    /// wherever the date came from, in the format of [`dates::parse_offset`]. For
+    /// example, `String::from("+7h")` corrects the dates of photos from a camera
+    /// whose clock was never set to the right time zone, so that they land in the
+    /// right day folders. Empty by default, which leaves the dates alone.
+    pub clock_offset: String,
+    /// The month (from 1 to 12) that starts the year, for the `{quarter}` and
+    /// `{fiscal_year}` placeholders in `dir_format`. For example, `7` makes July
+    /// to September `Q1` of `2023-2024`, for a fiscal year starting in July.
+    /// Defaults to `1` (January). Values that aren't a month are rejected by
+    /// [`Sorter::validate`].
+    pub year_start: u32,
+    /// A [`Vec<Category>`] of named groups of extensions, used for the `{category}`
+    /// placeholder in `dir_format`, as in `jpg`, `png`, and `heic` for `Images`.
+    /// These take priority over the built-in categories. See [`Category`] for more
+    /// information. Empty by default.
+    pub categories: Vec<Category>,
+    /// If [`Some`], this many of the newest files (by the dates they would be
+    /// sorted by) are left where they are, and only the rest are sorted. This
+    /// keeps the files you're still working with in place, as when triaging a
+    /// downloads folder. Their sidecars stay with them. Defaults to [`None`], which
+    /// sorts every file.
+    pub keep_newest: Option<usize>,
+    /// If [`Some`], only files that haven't been created or modified for at least
+    /// this long are sorted, so that files that are still being downloaded or
+    /// written aren't grabbed. This uses the filesystem timestamps, whatever the
+    /// files are sorted by. In JSON configuration, it is given in seconds. Defaults
+    /// to [`None`], which sorts files of any age.
+    pub min_age: Option<Duration>,
+    /// If [`true`], files that are open in other processes are left where they
+    /// are, so that half-written files aren't moved. Files are checked while the
+    /// sort is planned, and again right before each is moved, and the ones left
+    /// are listed in [`SortReport::in_use`]. This is only detected on Linux and
+    /// Windows; see [`open_files`] for how. Defaults to [`false`].
+    pub skip_open_files: bool,
+    /// If [`true`], downloads that are still in progress are left where they
+    /// are: both the browser's temporary file (as in `report.pdf.part` or
+    /// `report.pdf.crdownload`) and the file at its final name. `.tmp` files,
+    /// which other applications leave behind too, only count while they are
+    /// being written, or when their final name is taken. See [`downloads`] for
+    /// the recognized types. Defaults to [`true`].
+    pub skip_partial_downloads: bool,
+    /// If [`true`], cloud placeholders, like OneDrive's "online-only" files, are
+    /// left where they are, since sorting them would download them all, as soon
+    /// as their contents are read or they are copied. If [`false`], they are
+    /// sorted like any other file. They are only detected on Windows; see
+    /// [`reparse`]. Defaults to [`true`].
+    pub skip_placeholders: bool,
+    /// What to do with directory junctions and other links to directories in
+    /// the source: leave them alone, sort them like files, or follow them. See
+    /// [`ReparsePolicy`] for the options. Defaults to [`ReparsePolicy::Skip`].
+    pub reparse_points: ReparsePolicy,
+    /// What to do with symbolic links to files in the source: leave them alone,
+    /// sort them and keep them leading to the same files, or sort the files
+    /// they lead to in their place. See [`SymlinkPolicy`] for the options.
+    /// Defaults to [`SymlinkPolicy::Relink`].
+    pub symlinks: SymlinkPolicy,
+    /// If [`Some`], files smaller than this many bytes are tiny, and are left
+    /// where they are instead of being sorted (or are sorted into `tiny_dir`, if
+    /// there is one). Tiny files are usually corrupt leftovers, and
+    /// `Some(1)` catches the zero-byte ones. Defaults to [`None`], which treats
+    /// files of any size the same.
+    pub min_size: Option<u64>,
+    /// If [`Some`], tiny files (see `min_size`) are sorted into this directory
+    /// instead of being left where they are, as a quarantine for reviewing them.
+    /// A relative path is relative to `target`, like `screenshot_dir`. Defaults to
+    /// [`None`].
+    pub tiny_dir: Option<String>,
+    /// The [`HashAlgorithm`] used wherever files are hashed, as in
+    /// [`Sorter::find_duplicates`] and [`Sorter::manifest`]. Defaults to
+    /// [`HashAlgorithm::Sha256`], which needs the `sha256` feature; without it,
+    /// pick another one.
+    pub hash_algorithm: HashAlgorithm,
+    /// The number of threads that hash files, besides the ones that move them,
+    /// so that hashing overlaps with moving rather than holding it up. In
+    /// [`ExecutionMode::ExecuteVerified`] sorts, the files are hashed ahead of
+    /// being moved, and [`Sorter::find_duplicates`], [`Sorter::manifest`], and
+    /// [`Sorter::verify`] hash several files at once. Defaults to `0`, which
+    /// hashes each file on the thread that needs its hash, when it needs it.
+    pub hash_workers: usize,
+    /// How to tell whether a file is already in the target, when its destination
+    /// is taken. Files that are identical to the file at their destination, or
+    /// at one of its sequential names, are left out of the sort, and listed in
+    /// the report as already present, instead of being given sequential names
+    /// as redundant copies. See [`IdenticalCheck`] for the options. Defaults to
+    /// [`IdenticalCheck::Off`].
+    pub skip_identical: IdenticalCheck,
+    /// If [`true`], files that are already in the target (see `skip_identical`)
+    /// are removed from the source, as if they had been moved. Defaults to
+    /// [`false`], which leaves them where they are.
+    pub remove_identical: bool,
+    /// What to do with files whose destinations are taken by different files:
+    /// give them sequential names, or replace the files in the target if they
+    /// are older, as when a live folder is sorted into the same archive again
+    /// and again. See [`ConflictPolicy`] for the options. Defaults to
+    /// [`ConflictPolicy::Rename`].
+    pub conflict_policy: ConflictPolicy,
+    /// What to do with files that changed or disappeared between planning the
+    /// sort and moving them, as on a source that is still being written to:
+    /// leave them where they are, sort them again, or fail them. Files are
+    /// checked right before they are moved, by their sizes and modification
+    /// times (see `stale_tolerance`). See [`StalePolicy`] for the options.
+    /// Defaults to [`StalePolicy::Skip`].
+    pub stale_policy: StalePolicy,
+    /// How far the modification time of a file may be from the one it had when
+    /// the sort was planned, before the file counts as changed (see
+    /// `stale_policy`), for filesystems that don't keep exact times, like
+    /// network shares. Sizes always have to be the same. In JSON configuration,
+    /// it is given in seconds. Defaults to no tolerance at all.
+    pub stale_tolerance: Duration,
+    /// How to handle anomalies, like a destination that already exists in the
+    /// target. See [`Strictness`] for the options. Defaults to [`Strictness::Lenient`].
+    pub strictness: Strictness,
+    /// If [`true`], files whose new names are already taken are told apart by
+    /// the finer parts of their dates before falling back to sequential names:
+    /// the seconds, if `date_format` doesn't have them, as in
+    /// `2023-07-14 10h30-13.jpg`, and then the milliseconds, as in
+    /// `2023-07-14 10h30-13-250.jpg`. This keeps the names of bursts of photos
+    /// in chronological order. If [`false`], they get sequential names right
+    /// away, as in `2023-07-14 10h30_2.jpg`. Defaults to [`false`].
+    pub time_tie_break: bool,
+    /// If [`true`], the highest sequential number given to each name in each
+    /// folder of the target is kept in the target, and later sorts carry on
+    /// numbering from there, rather than giving the names of numbered files
+    /// that have since been moved on to other files. See [`counters`].
+    /// Defaults to [`false`].
+    pub persist_counters: bool,
+    /// If [`true`], every sort that moves files is recorded in the
+    /// [`History`](history::History) kept in the target, with where each of its
+    /// files came from, so that it can be undone or exported later. See
+    /// [`history`]. Defaults to [`false`].
+    pub record_history: bool,
+    /// Whether the files are moved into the target, or copied, leaving the
+    /// originals in the source. Copies keep the dates of the originals, and
+    /// sparse files stay sparse. See [`TransferMode`] for the options. Files that
+    /// meet one of `rules` get there the rule's way, if it has one (see
+    /// [`Rule::transfer`](rules::Rule::transfer)). Defaults to [`TransferMode::Move`].
+    pub transfer: TransferMode,
+    /// If [`true`], the directories the files are sorted into, like
+    /// `target/2023/07/`, are created before any file is moved, each of them
+    /// once. The files of a directory that can't be created fail with a
+    /// [`SortError::DirectoryNotCreated`], saying why. If [`false`], nothing is
+    /// created, and the files of directories that don't exist yet fail with a
+    /// [`SortError::PathDoesNotExist`], for targets whose layout is managed by
+    /// something else. Either way, a `target` that doesn't exist or is read-only
+    /// stops the sort before anything is moved (see [`Sorter::validate`]).
+    /// Defaults to [`true`].
+    pub create_dirs: bool,
+    /// If [`Some`], the permissions of the directories created by the sorter
+    /// (see `create_dirs`) are set to this mode, as in `Some(0o755)` to share
+    /// them with every user, or `Some(0o700)` to keep them private, instead of
+    /// whatever the umask gives. Directories that already exist are left alone.
+    /// This is only supported on Unix, and is ignored elsewhere. In JSON
+    /// configuration, it is given as a number, so use `493` for `0o755`.
+    /// Defaults to [`None`].
+    pub dir_mode: Option<u32>,
+    /// If [`Some`], the files that are sorted (with their origin sidecars, see
+    /// `origin_record`) and the directories created for them are given to the
+    /// user with this ID, as when sorting into a user's archive as root on a
+    /// file server, so that the sorted files are theirs right away. Changing the
+    /// owner usually needs root. This is only supported on Unix, and is ignored
+    /// elsewhere. Defaults to [`None`], which leaves the owner alone.
+    pub owner: Option<u32>,
+    /// If [`Some`], the files that are sorted and the directories created for
+    /// them are given to the group with this ID, like `owner`. Defaults to
+    /// [`None`], which leaves the group alone.
+    pub group: Option<u32>,
+    /// If [`true`], the marks that downloaded files get are removed from the
+    /// sorted files: `com.apple.quarantine` on macOS, and the `Zone.Identifier`
+    /// stream on Windows, so that the files in the archive open without any
+    /// warnings. If [`false`], they are kept, whether the files are moved or
+    /// copied, so that they are checked as downloads when they are first opened.
+    /// Defaults to [`false`].
+    pub strip_quarantine: bool,
+    /// The number of threads that move files at once. Copies to network storage
+    /// are bound by latency rather than bandwidth, so moving several files at
+    /// once can make them much faster. The files of each destination directory
+    /// are moved by a single thread, in order. Dry runs and
+    /// [`ExecutionMode::Interactive`] sorts are never run on more than one.
+    /// Defaults to `1`, which moves the files one at a time.
+    pub workers: usize,
+    /// The system calls used to move the files. See [`execution::Backend`] for
+    /// the options. Defaults to [`execution::Backend::Std`].
+    pub backend: execution::Backend,
+    /// The [`Notification`](notify::Notification)s sent once a sort that moves
+    /// files is over, or couldn't run, as to alert someone when a scheduled sort
+    /// runs into problems. Dry runs send none. The ones that can't be sent are
+    /// listed in the report. Defaults to none.
+    pub notifications: Vec<notify::Notification>,
+    /// How often the progress of a sort is reported, to the observers and to the
+    /// callback, as every 1,000 files, or at most every 250 milliseconds, so
+    /// that sorts of millions of files don't spend their time reporting it. See
+    /// [`ProgressInterval`]. Defaults to no limits, which reports every file to
+    /// the observers, and every percent to the callback.
+    pub progress_interval: ProgressInterval,
+    /// If [`true`], the folders directly inside `source` are kept as the first
+    /// level of the target, above the `dir_format` tree, for sources that are
+    /// already split up by person or by device: `source/Alice/IMG_1234.jpg` is
+    /// sorted into `target/Alice/2023/07/`. Files directly in `source`, and the
+    /// ones that meet one of `rules`, are sorted as usual. Defaults to [`false`].
+    pub keep_top_folder: bool,
+    /// If [`true`], the date of each file is checked before it is sorted, and
+    /// files whose dates can't be right, like the Unix epoch, dates in the
+    /// future, or dates before their camera came out (with the `exif` feature),
+    /// are listed in the report with a [`SortWarning::SuspiciousDate`], instead
+    /// of being quietly buried in folders like `1970/01/`. A dry run makes it
+    /// an audit of the source. See [`audit::SuspiciousDate`]. Defaults to
+    /// [`false`].
+    pub audit_dates: bool,
+    /// If [`Some`], files with suspicious dates (see `audit_dates`) are sorted
+    /// into this directory, without the `dir_format` tree, for reviewing them.
+    /// A relative path is relative to `target`, like `tiny_dir`. Defaults to
+    /// [`None`], which sorts them by their dates anyway.
+    pub suspicious_dir: Option<String>,
+    /// If [`Some`], files that can't be sorted are moved into this directory,
+    /// keeping their names, rather than being left behind where they might go
+    /// unnoticed: the ones whose dates can't be found are planned into it, and
+    /// the ones that fail to be moved to their destinations are moved into it
+    /// instead. Why each file couldn't be sorted is listed in
+    /// [`SortReport::quarantined`]. A relative path is relative to `target`,
+    /// like `tiny_dir`, as in `Some(String::from("_unsorted"))`. Defaults to
+    /// [`None`], which leaves them where they are, and lists them among the
+    /// failures.
+    pub quarantine_dir: Option<String>,
+    /// The [`CancelToken`] that cancels the sort from another thread. Keep a
+    /// clone of it, and call [`CancelToken::cancel`] on it to stop the sort
+    /// between two files, or between two chunks of a large file. This isn't part
+    /// of the JSON configuration. Defaults to a new token.
+    pub cancel: CancelToken,
+    /// The [`Observer`]s that follow each sort, added with
+    /// [`Sorter::register_observer`]. This isn't part of the JSON configuration.
+    /// Defaults to none.
+    pub observers: Observers,
+    /// The [`MetadataProvider`]s that fill in placeholders of their own in
+    /// `dir_format`, added with [`Sorter::register_provider`]. This isn't part
+    /// of the JSON configuration. Defaults to none.
+    pub providers: Providers
+}
+impl Default for Sorter {
+
+    /// Return a [`Sorter`] with empty source and target paths, the
+    /// `"%Y-%m-%d %Hh%Mm%Ss"` date format, sorting by modification date, and
+    /// no type filters.
+    fn default() -> Sorter {
+        Sorter {
+            source: File::from(""),
+            target: File::from(""),
+            date_format: String::from("%Y-%m-%d %Hh%Mm%Ss"),
+            date_type: String::from("m"),
+            preserve_name: false,
+            exclude_type: Vec::new(),
+            only_type: Vec::new(),
+            exclude_content_type: Vec::new(),
+            only_content_type: Vec::new(),
+            video_filter: VideoFilter::default(),
+            extensionless: ExtensionlessPolicy::Keep,
+            origin_record: OriginRecord::Off,
+            sidecar_types: Vec::new(),
+            burst_interval: None,
+            event_gap: None,
+            dir_format: String::from("%Y/%m/"),
+            rules: Vec::new(),
+            regions: Vec::new(),
+            location_grid: 1.0,
+            screenshot_dir: None,
+            date_sources: Vec::new(),
+            missing_date: MissingDatePolicy::Fallback,
+            clock_offset: String::new(),
+            year_start: 1,
+            categories: Vec::new(),
+            keep_newest: None,
+            min_age: None,
+            skip_open_files: false,
+            skip_partial_downloads: true,
+            skip_placeholders: true,
+            reparse_points: ReparsePolicy::Skip,
+            symlinks: SymlinkPolicy::Relink,
+            min_size: None,
+            tiny_dir: None,
+            hash_algorithm: HashAlgorithm::default(),
+            hash_workers: 0,
+            skip_identical: IdenticalCheck::Off,
+            remove_identical: false,
+            conflict_policy: ConflictPolicy::Rename,
+            stale_policy: StalePolicy::Skip,
+            stale_tolerance: Duration::ZERO,
+            strictness: Strictness::Lenient,
+            time_tie_break: false,
+            persist_counters: false,
+            record_history: false,
+            transfer: TransferMode::Move,
+            create_dirs: true,
+            dir_mode: None,
+            owner: None,
+            group: None,
+            strip_quarantine: false,
+            workers: 1,
+            backend: execution::Backend::Std,
+            notifications: Vec::new(),
+            progress_interval: ProgressInterval::default(),
+            keep_top_folder: false,
+            audit_dates: false,
+            suspicious_dir: None,
+            quarantine_dir: None,
+            cancel: CancelToken::default(),
+            observers: Observers::default(),
+            providers: Providers::default()
+        }
+    }
+}
+impl Sorter {
+
+    /// Return a new [`Sorter`] from `source`, `target`, and the configuration
+    /// data in `data`. Used by both [`FromJson`] implementations.
+    fn from_config(data: ConfigData, source: File, target: File) -> Sorter {
+        Sorter {
+            source,
+            target,
+            date_format: data.date_format,
+            date_type: data.date_type,
+            preserve_name: data.preserve_name,
+            exclude_type: data.exclude_type,
+            only_type: data.only_type,
+            exclude_content_type: data.exclude_content_type,
+            only_content_type: data.only_content_type,
+            video_filter: data.video_filter,
+            extensionless: data.extensionless,
+            origin_record: data.origin_record,
+            sidecar_types: data.sidecar_types,
+            burst_interval: data.burst_interval,
+            event_gap: data.event_gap,
+            dir_format: data.dir_format,
+            rules: data.rules,
+            regions: data.regions,
+            location_grid: data.location_grid,
+            screenshot_dir: data.screenshot_dir,
+            date_sources: data.date_sources,
+            missing_date: data.missing_date,
+            clock_offset: data.clock_offset,
+            year_start: data.year_start,
+            categories: data.categories,
+            keep_newest: data.keep_newest,
+            min_age: data.min_age.map(Duration::from_secs),
+            skip_open_files: data.skip_open_files,
+            skip_partial_downloads: data.skip_partial_downloads,
+            skip_placeholders: data.skip_placeholders,
+            reparse_points: data.reparse_points,
+            symlinks: data.symlinks,
+            min_size: data.min_size,
+            tiny_dir: data.tiny_dir,
+            hash_algorithm: data.hash_algorithm,
+            hash_workers: data.hash_workers,
+            skip_identical: data.skip_identical,
+            remove_identical: data.remove_identical,
+            conflict_policy: data.conflict_policy,
+            stale_policy: data.stale_policy,
+            stale_tolerance: Duration::from_secs(data.stale_tolerance),
+            strictness: data.strictness,
+            time_tie_break: data.time_tie_break,
+            persist_counters: data.persist_counters,
+            record_history: data.record_history,
+            transfer: data.transfer,
+            create_dirs: data.create_dirs,
+            dir_mode: data.dir_mode,
+            owner: data.owner,
+            group: data.group,
+            strip_quarantine: data.strip_quarantine,
+            workers: data.workers,
+            backend: data.backend,
+            notifications: data.notifications,
+            progress_interval: data.progress_interval,
+            keep_top_folder: data.keep_top_folder,
+            audit_dates: data.audit_dates,
+            suspicious_dir: data.suspicious_dir,
+            quarantine_dir: data.quarantine_dir,
+            cancel: CancelToken::default(),
+            observers: Observers::default(),
+            providers: Providers::default()
+        }
+    }
+
+    /// Return the groups of content-identical files in the source directory, and
+    /// in the target directory as well if `include_target` is [`true`], without
+    /// moving anything. Each [`DuplicateGroup`] holds the size, hash, and paths of
+    /// its files, so that duplication can be audited before deciding what to do
+    /// about it. For example:
+    /// 
+    /// ```ignore
+    /// use sorterylib::prelude::*;
+    /// 
+    /// fn main() {
+    /// 
+    ///     // The sorter instance
+    ///     let sorter = Sorter { ... };
+    /// 
+    ///     for group in sorter.find_duplicates(true).expect("Failed to find duplicates.") {
+    ///         println!("{} bytes wasted by {:?}", group.wasted_bytes(), group.files);
+    ///     }
+    /// }
+    /// ```
+    pub fn find_duplicates(&self, include_target: bool) -> io::Result<Vec<DuplicateGroup>> {
+        if include_target {
+            duplicates::find_duplicates(&[&self.source, &self.target], self.hash_algorithm, self.hash_workers)
+        } else {
+            duplicates::find_duplicates(&[&self.source], self.hash_algorithm, self.hash_workers)
+        }
+    }
+
+    /// Return a [`Manifest`](manifest::Manifest) of all the files in the target
+    /// directory, hashed with `self.hash_algorithm`. Save it after sorting, and
+    /// check the target against it later with [`Sorter::verify`].
+    pub fn manifest(&self) -> io::Result<manifest::Manifest> {
+        manifest::Manifest::of_dir(&self.target, self.hash_algorithm, self.hash_workers)
+    }
+
+    /// Check the target directory against `manifest`: that every file in it still
+    /// exists with the same size and hash, and that no other files have appeared.
+    /// Return a [`VerificationReport`](manifest::VerificationReport) of everything
+    /// that doesn't match. For example:
+    /// 
+    /// ```ignore
+    /// use sorterylib::prelude::*;
+    /// use sorterylib::manifest::Manifest;
+    /// 
+    /// fn main() {
+    /// 
+    ///     // The sorter instance
+    ///     let sorter = Sorter { ... };
+    /// 
+    ///     // Sort the files, and save a manifest of the target
+    ///     sorter.sort(false);
+    ///     sorter.manifest().unwrap().save(&File::from("manifest.json")).unwrap();
+    /// 
+    ///     // Later on, make sure nothing has changed
+    ///     let manifest = Manifest::load(&File::from("manifest.json")).unwrap();
+    ///     assert!(sorter.verify(&manifest).unwrap().is_ok());
+    /// }
+    /// ```
+    pub fn verify(&self, manifest: &manifest::Manifest) -> io::Result<manifest::VerificationReport> {
+        manifest.verify(&self.target, self.hash_workers)
+    }
+
+    /// Make the target's dated tree reflect the source: copy the files of the
+    /// source that aren't in the target yet into it, and find the orphans, the
+    /// files in the target whose originals have disappeared from the source,
+    /// handling them according to `orphans`. Return a
+    /// [`MirrorReport`](mirror::MirrorReport) of what was added, updated, left
+    /// unchanged, and orphaned. See [`mirror`] for more.
+    /// 
+    /// Mirroring needs `transfer` to be [`TransferMode::Copy`], so that the
+    /// source is left as it is, `origin_record` to be on, so that orphans can be
+    /// found, and `skip_identical` to be on, so that files aren't copied again.
+    /// Otherwise, it returns [`SortError::InvalidConfig`]. For example:
+    /// 
+    /// ```ignore
+    /// use sorterylib::prelude::*;
+    /// use sorterylib::conflicts::IdenticalCheck;
+    /// use sorterylib::mirror::OrphanPolicy;
+    /// 
+    /// fn main() {
+    /// 
+    ///     // The sorter instance
+    ///     let sorter = Sorter {
+    ///         transfer: TransferMode::Copy,
+    ///         origin_record: OriginRecord::Auto,
+    ///         skip_identical: IdenticalCheck::Size,
+    ///         ...
+    ///     };
+    /// 
+    ///     // Copy the new files, and remove the ones deleted from the source
+    ///     let report = sorter.mirror(ExecutionMode::Execute, OrphanPolicy::Prune).unwrap();
+    ///     println!("{} added, {} pruned", report.added().count(), report.pruned.len());
+    /// }
+    /// ```
+    pub fn mirror(&self, mode: ExecutionMode, orphans: mirror::OrphanPolicy) -> Result<mirror::MirrorReport, SortError> {
+        if self.transfer != TransferMode::Copy || self.rules.iter().any(|rule| rule.transfer.is_some_and(|t| t != TransferMode::Copy)) {
+            return Err(SortError::InvalidConfig(String::from("mirroring needs transfer to be \"copy\"")));
+        }
+        if self.origin_record == OriginRecord::Off {
+            return Err(SortError::InvalidConfig(String::from("mirroring needs an origin_record")));
+        }
+        if self.skip_identical == IdenticalCheck::Off {
+            return Err(SortError::InvalidConfig(String::from("mirroring needs skip_identical")));
+        }
+        let mut report = mirror::MirrorReport { sort: self.sort_report(mode)?, ..Default::default() };
+
+        // Prune the orphans, if we're supposed to and this isn't a dry run
+        report.orphans = mirror::find_orphans(&self.source, &self.target)?;
+        if orphans == mirror::OrphanPolicy::Prune && mode.moves_files() {
+            for (file, _) in &report.orphans {
+                match mirror::prune(file) {
+                    Ok(()) => report.pruned.push(file.copy()),
+                    Err(error) => report.failures.push((file.copy(), SortError::from(error))),
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Move every file in the target's tree back into the flat directory `into`
+    /// (which can be the target itself), and remove the directories of the tree
+    /// that are left empty, in `mode`. The files are named according to `names`,
+    /// and names that are taken get sequential names, in the order of the files'
+    /// paths. Their origin sidecars go along with them. Return a [`SortReport`]
+    /// of the files unsorted, from their paths in the target to their paths in
+    /// `into`. See [`unsort`] for more.
+    /// 
+    /// The files are always moved, whatever `transfer` is, and aren't stamped
+    /// with their origins again. Return [`SortError::PathDoesNotExist`] if the
+    /// target doesn't exist, and [`SortError::AlreadyRunning`] if a sort into it
+    /// is running. For example:
+    /// 
+    /// ```ignore
+    /// use sorterylib::prelude::*;
+    /// use sorterylib::unsort::UnsortNames;
+    /// 
+    /// fn main() {
+    ///     let sorter = Sorter { ... };
+    ///     let flat = File::from("/home/user/Pictures/Unsorted");
+    ///     sorter.unsort(&flat, UnsortNames::Original, ExecutionMode::Execute).unwrap();
+    /// }
+    /// ```
+    pub fn unsort(&self, into: &File, names: unsort::UnsortNames, mode: ExecutionMode) -> Result<SortReport, SortError> {
+        if !self.target.pathbuf.is_dir() {
+            return Err(SortError::from(PathDoesNotExistError { path: self.target.to_string() }));
+        }
+        let _lock = self.lock_target(mode)?;
+
+        // Plan where each file goes, leaving the ones that are there already
+        let mut report = SortReport::default();
+        let mut taken: HashSet<File> = HashSet::new();
+        for old in unsort::target_files(&self.target)? {
+            let mut new = File::from(into.pathbuf.join(unsort::flat_name(&old, names)));
+            if new != old && (taken.contains(&new) || new.exists()) {
+                new = self.get_sequential_name(&new, &taken);
+            }
+            taken.insert(new.copy());
+            report.old.push(old);
+            report.new.push(new);
+        }
+        if !mode.moves_files() {
+            return Ok(report);
+        }
+
+        // Move the files, stopping early if the unsort is cancelled, or if it is
+        // strict and a file fails
+        fs::create_dir_all(&into.pathbuf)?;
+        let mut handled = 0;
+        for (old, new) in report.old.iter().zip(&report.new) {
+            if self.cancel.is_cancelled() {
+                break;
+            }
+            handled += 1;
+            if old == new {
+                continue;
+            }
+            if mode == ExecutionMode::Interactive
+                && !execution::confirm(old, new, &mut io::stdin().lock(), &mut io::stdout()).unwrap_or(false) {
+                report.skipped.push(old.copy());
+                continue;
+            }
+            if let Err(error) = self.unsort_file(old, new, mode) {
+                report.failures.push((old.copy(), error));
+                if self.strictness == Strictness::Strict {
+                    break;
+                }
+            }
+        }
+        unsort::remove_empty_dirs(&self.target);
+
+        // Files that weren't confirmed aren't unsorted at all, and neither are
+        // the ones left after the unsort stopped
+        let skipped: HashSet<&File> = report.skipped.iter().collect();
+        (report.old, report.new) = report.old.iter().zip(&report.new)
+            .take(handled)
+            .filter(|(old, _)| !skipped.contains(old))
+            .map(|(old, new)| (old.copy(), new.copy()))
+            .unzip();
+        Ok(report)
+    }
+
+    /// Move `old` in the target to `new` in the flat directory of an unsort in
+    /// `mode`, along with its origin sidecar. See [`Sorter::unsort`].
+    fn unsort_file(&self, old: &File, new: &File, mode: ExecutionMode) -> Result<(), SortError> {
+        let hash = match mode {
+            ExecutionMode::ExecuteVerified => Some(checksum::hash_file(old, self.hash_algorithm)?),
+            _ => None,
+        };
+        transfer::move_file(old.as_path(), new.as_path(), |_, _| !self.cancel.is_cancelled())?;
+        let sidecar = origin::sidecar_path(old);
+        if sidecar.exists() {
+            transfer::move_file(sidecar.as_path(), origin::sidecar_path(new).as_path(), |_, _| true)?;
+        }
+        if let Some(hash) = hash {
+            if checksum::hash_file(new, self.hash_algorithm)? != hash {
+                return Err(SortError::VerificationFailed(new.copy()));
+            }
+        }
+        Ok(())
+    }
+
+    /// The same as [`Sorter::find_duplicates`], but finds images that look identical
+    /// even when they are encoded differently, like re-saved or resized copies,
+    /// which exact hashing misses. Images whose perceptual hashes differ in at most
+    /// `max_distance` bits are grouped together; [`phash::DEFAULT_MAX_DISTANCE`]
+    /// is a good start. Needs the `phash` feature.
+    #[cfg(feature = "phash")]
+    pub fn find_near_duplicates(
+        &self,
+        include_target: bool,
+        max_distance: u32) -> io::Result<Vec<duplicates::NearDuplicateGroup>> {
+
+        if include_target {
+            duplicates::find_near_duplicates(&[&self.source, &self.target], max_distance)
+        } else {
+            duplicates::find_near_duplicates(&[&self.source], max_distance)
+        }
+    }
+
+    /// Return a [`DateTime`] instance representing the creation, modification,
+    /// or access time of `path` according to `date_type`, or [`None`] if it isn't
+    /// available. Return [`SortError::UnsupportedTimestamp`] if it is available,
+    /// but isn't a date we can sort by (see [`dates::is_supported`]).
+    /// 
+    /// `date_type` must be one of `'c'` (created), `'a'` (accessed), `'m'` (modified),
+    /// or `'e'` (earliest). Note that creation time is not available on all filesystems.
+    fn get_datetime(&self, path: &File, date_type: char) -> Option<Result<DateTime<Local>, SortError>> {
+        if date_type == 'e' {
+            return self.get_earliest_datetime(path);
+        }
+        let time = match date_type {
+            'm' => self.get_filetime_modified(path),
+            'a' => self.get_filetime_access(path),
+            _ => self.get_filetime_creation(path),
+        }?;
+        Some(dates::from_unix_time(time.unix_seconds(), time.nanoseconds())
+            .ok_or_else(|| SortError::UnsupportedTimestamp(path.copy())))
+    }
+
+    /// Return the earliest of the creation date, the modification date, and the
+    /// EXIF date taken (with the `exif` feature) of `path`, or [`None`] if none of
+    /// them are available. Unsupported dates are left out, unless they all are.
+    fn get_earliest_datetime(&self, path: &File) -> Option<Result<DateTime<Local>, SortError>> {
+        #[allow(unused_mut)]
+        let mut dates: Vec<Result<DateTime<Local>, SortError>> = [self.get_datetime(path, 'c'), self.get_datetime(path, 'm')]
+            .into_iter()
+            .flatten()
+            .collect();
+        #[cfg(feature = "exif")]
+        if let Some(date) = crate::exif::read(path).and_then(|data| data.date_taken) {
+            dates.push(Ok(date));
+        }
+        let earliest = dates.iter().filter_map(|date| date.as_ref().ok()).min().copied();
+        match earliest {
+            Some(earliest) => Some(Ok(earliest)),
+            None => dates.pop(),
+        }
+    }
+
+    /// Return a [`DateTime`] instance representing the date of `path`: the date
+    /// from the first of `self.date_sources` that has one, or the first available
+    /// filesystem timestamp in `self.date_type` otherwise (unless
+    /// `self.missing_date` says not to fall back to them), shifted by
+    /// `self.clock_offset`. Also return a [`SortWarning::DateTypeFallback`] if
+    /// the first date type wasn't available. Return
+    /// [`SortError::TimestampUnavailable`] if none of them are, and
+    /// [`SortError::UnsupportedTimestamp`] if the date isn't one we can sort by,
+    /// with or without the offset.
+    fn get_file_datetime(&self, path: &File) -> Result<(DateTime<Local>, Option<SortWarning>), SortError> {
+        let offset = dates::parse_offset(&self.clock_offset).unwrap_or_default();
+        let shift = |datetime: DateTime<Local>| datetime.checked_add_signed(offset)
+            .filter(dates::is_supported)
+            .ok_or_else(|| SortError::UnsupportedTimestamp(path.copy()));
+        if let Some(datetime) = self.date_sources.iter().find_map(|source| source.read(path)) {
+            return Ok((shift(datetime)?, None));
+        }
+        if !self.date_sources.is_empty() && self.missing_date != MissingDatePolicy::Fallback {
+            return Err(SortError::TimestampUnavailable(path.copy()));
+        }
+        let (n, date_type, datetime) = self.date_type.chars().enumerate()
+            .find_map(|(n, date_type)| Some((n, date_type, self.get_datetime(path, date_type)?)))
+            .ok_or_else(|| SortError::TimestampUnavailable(path.copy()))?;
+        Ok((shift(datetime?)?, if n > 0 { Some(SortWarning::DateTypeFallback(date_type)) } else { None }))
+    }
+
+    /// Return the access date and time of `path` as a [`FileTime`].
+    /// Now works cross-platform.
+    fn get_filetime_access(&self, path: &File) -> Option<FileTime> {
+        let metadata = path.pathbuf.metadata().ok()?;
+        Some(FileTime::from_last_access_time(&metadata))
+    }
+    
+    /// Return the creation date and time of `path` as a [`FileTime`], or [`None`]
+    /// if the filesystem doesn't record it.
+    fn get_filetime_creation(&self, path: &File) -> Option<FileTime> {
+        let metadata = path.pathbuf.metadata().ok()?;
+        FileTime::from_creation_time(&metadata)
+    }
+
+    /// Return the modification date and time of `path` as a [`FileTime`].
+    /// Now works cross-platform.
+    fn get_filetime_modified(&self, path: &File) -> Option<FileTime> {
+        let metadata = path.pathbuf.metadata().ok()?;
+        Some(FileTime::from_last_modification_time(&metadata))
+    }
+
+    /// Get the new directory of a file with the date and time `ctime`. If the file
+    /// is part of an `event` and/or a `burst`, their folders go inside a day
+    /// folder in the date directory, as in `2023/07/14/event_090000/burst_103012/`.
+    /// 
+    /// `dir_format` is `self.dir_format`, with the file's placeholders filled in.
+    /// The placeholders that only depend on the date are filled in here.
+    fn get_new_dir(
+        &self,
+        target: &File,
+        dir_format: &str,
+        ctime: &DateTime<Local>,
+        event: Option<&grouping::Group>,
+        burst: Option<&grouping::Group>) -> File {
+
+        // Groups are never split up, so their first file decides the date directory
+        let anchor = event.or(burst).map(|g| &g.anchor).unwrap_or(ctime);
+        let dir_format = match dir_format.contains('{') {
+            true => Cow::Owned(template::expand(dir_format, &template::date_vars(anchor, self.year_start))),
+            false => Cow::Borrowed(dir_format),
+        };
+
+        // Build the path in place, rather than joining a new one for each part
+        let mut part = OsString::new();
+        let mut dir = target.to_path_buf();
+        let _ = write!(part, "{}", anchor.format(&dir_format));
+        dir.push(&part);
+        if event.is_some() || burst.is_some() {
+            part.clear();
+            let _ = write!(part, "{}", anchor.format("%d"));
+            dir.push(&part);
+        }
+        for group in [event, burst].into_iter().flatten() {
+            dir.push(&group.name);
+        }
+        File::from(dir)
+    }
+
+    /// Get the new path of `old_file` inside `dir`, according to the sorting
+    /// algorithm. `ctime` is the date and time of `old_file`, and `date_format`
+    /// is the format to name it with, which is usually `self.date_format`.
+    fn get_new_date_path(&self, dir: &File, old_file: &File, ctime: &DateTime<Local>, date_format: &str) -> File {
+
+        // Create the new file name, preserving the original file name if we're
+        // supposed to. Names are built as OsStrings, so that names that aren't
+        // valid UTF-8 are kept as they are, and the date is written straight
+        // into the name rather than into a String first.
+        let mut name = OsString::with_capacity(date_format.len() + old_file.pathbuf.as_os_str().len());
+        let _ = write!(name, "{}", ctime.format(date_format));
+        if self.preserve_name {
+            name.push(" ");
+            name.push(old_file.file_stem_os());
+        }
+
+        // Files without an extension get none, unless we're supposed to find one
+        let mut extension = old_file.extension_os();
+        if extension.is_empty() && self.extensionless == ExtensionlessPolicy::Sniff {
+            if let Ok(Some(content_type)) = content_types::content_type(&old_file.pathbuf) {
+                extension = OsStr::new(content_types::extension(content_type).unwrap_or_default());
+            }
+        }
+        if !extension.is_empty() {
+            name.push(".");
+            name.push(extension);
+        }
+        let mut pathbuf = PathBuf::with_capacity(dir.pathbuf.as_os_str().len() + name.len() + 1);
+        pathbuf.push(&dir.pathbuf);
+        pathbuf.push(name);
+        File::from(pathbuf)
+    }
+
+    /// Return the values of the template placeholders in `names` for `path`.
+    /// See [`Sorter::dir_format`] for the available placeholders. The ones that
+    /// none of them are, nor the ones filled in by date, are asked of
+    /// `self.providers`.
+    fn get_template_vars<'a>(&self, path: &File, names: &[&'a str]) -> HashMap<&'a str, String> {
+
+        let mut vars = HashMap::new();
+        if names.contains(&"extension") {
+            let extension = path.extension().to_lowercase();
+            vars.insert("extension", if extension.is_empty() { String::from(template::NO_EXTENSION) } else { extension });
+        }
+        if names.contains(&"category") {
+            vars.insert("category", String::from(categories::category_name(&path.extension(), &self.categories)));
+        }
+        for name in names {
+            if let Some(level) = template::parent_level(name) {
+                vars.insert(*name, template::parent_name(path, &self.source, level).unwrap_or_else(|| String::from(template::NO_PARENT)));
+            }
+        }
+
+        // The placeholders that need EXIF metadata
+        #[cfg(feature = "exif")]
+        {
+            let data = crate::exif::read(path).unwrap_or_default();
+            if names.contains(&"location") {
+                vars.insert("location", match data.gps {
+                    Some((lat, lon)) => location::location_name(lat, lon, &self.regions, self.location_grid),
+                    None => String::from(location::UNKNOWN_LOCATION),
+                });
+            }
+            if names.contains(&"camera") {
+                vars.insert("camera", data.camera().unwrap_or_else(|| String::from(crate::exif::UNKNOWN_CAMERA)));
+            }
+        }
+
+        // The placeholders that need the size of the image
+        #[cfg(feature = "image")]
+        if ["width", "height", "orientation", "image_class"].iter().any(|name| names.contains(name)) {
+            let info = images::ImageInfo::read(path);
+            let value = |get: fn(&images::ImageInfo) -> String| info.as_ref().map_or_else(|| String::from(images::UNKNOWN_IMAGE), get);
+            vars.insert("width", value(|info| info.width.to_string()));
+            vars.insert("height", value(|info| info.height.to_string()));
+            vars.insert("orientation", value(|info| String::from(info.orientation().name())));
+            vars.insert("image_class", value(|info| String::from(info.class().name())));
+        }
+
+        // The placeholders that need the length and resolution of the video
+        if ["video_resolution", "video_length", "video_class"].iter().any(|name| names.contains(name)) {
+            let info = video::VideoInfo::read(path);
+            let value = |get: fn(&video::VideoInfo) -> &'static str| String::from(info.as_ref().map_or(video::UNKNOWN_VIDEO, get));
+            vars.insert("video_resolution", value(video::VideoInfo::resolution));
+            vars.insert("video_length", value(video::VideoInfo::length));
+            vars.insert("video_class", value(video::VideoInfo::class));
+        }
+
+        // The placeholders of our own providers
+        let custom: Vec<&str> = names.iter()
+            .copied()
+            .filter(|name| !vars.contains_key(name) && !template::DATE_PLACEHOLDERS.contains(name))
+            .collect();
+        if !self.providers.is_empty() && !custom.is_empty() {
+            let mut provided = self.providers.variables(path, &custom);
+            for name in custom {
+                if let Some(value) = provided.remove(name) {
+                    vars.insert(name, value);
+                }
+            }
+        }
+        vars
+    }
+
+    /// Return the new path of `old_file` inside `dir` with the finer parts of its
+    /// date and time `ctime` added to the name: first the seconds (if
+    /// `self.date_format` doesn't have them already), then the milliseconds.
+    /// Return [`None`] if these paths exist too, in `taken` or on disk.
+    fn get_tie_broken_path(&self, dir: &File, old_file: &File, ctime: &DateTime<Local>, taken: &HashSet<File>) -> Option<File> {
+        let has_seconds = ["%S", "%T", "%X", "%s", "%c", "%+", "%r"].iter().any(|s| self.date_format.contains(s));
+        let suffixes: &[&str] = if has_seconds { &["-%3f"] } else { &["-%S", "-%S-%3f"] };
+        suffixes.iter()
+            .map(|suffix| self.get_new_date_path(dir, old_file, ctime, &format!("{}{}", self.date_format, suffix)))
+            .find(|path| !taken.contains(path) && !path.exists())
+    }
+
+    /// Return the path to move `path` to in the directory `dir` in the target,
+    /// as the quarantine, keeping its name, or with a sequential name if that
+    /// exists, or is in `taken`.
+    fn get_named_path(&self, dir: &str, path: &File, taken: &HashSet<File>) -> File {
+        let dir = self.target.join(String::from(dir));
+        let new_file = File::from(dir.pathbuf.join(path.pathbuf.file_name().unwrap_or_default()));
+        match taken.contains(&new_file) || new_file.exists() {
+            true => self.get_sequential_name(&new_file, taken),
+            false => new_file,
+        }
+    }
+
+    /// Return a [`File`] representing the renamed version of `path`.
+    /// 
+    /// This function is called only if `path` already exists, but can't/shouldn't
+    /// be replaced. The naming logic: if `/path/to/file` already exists, return
+    /// `/path/to/file_2`. If `/path/to/file_2` already exists, return `/path/to/file_3`, etc.
+    /// A path exists if it's in `taken`, or on disk.
+    fn get_sequential_name(&self, path: &File, taken: &HashSet<File>) -> File {
+
+        let mut num = 2;
+
+        // The name and the path are reused for every number we try
+        let mut name = OsString::new();
+        let mut new_pathbuf = path.to_path_buf();
+
+        loop {
+
+            // Create the new path name
+            Sorter::set_sequential_name(&mut new_pathbuf, &mut name, path, num);
+
+            // Check if it exists, and if so, continue the loop
+            if !taken.contains(new_pathbuf.as_path()) && !new_pathbuf.exists() {
+                return File::from(new_pathbuf);
+            }
+            num += 1;
+        }
+    }
+
+    /// Return the destination of `primary`, planned to go to `new_primary`, and
+    /// the destinations of its `sidecars`, sent along with it. If the
+    /// destination of any sidecar is in `taken` or in the target already, the
+    /// whole group gets the first sequential name of `planned`, the destination
+    /// before any renaming, that is free for all of its files, so that the
+    /// sidecars keep the same new stem as their primary.
+    fn get_group_destinations(
+        primary: &File,
+        sidecars: &[&File],
+        planned: &File,
+        new_primary: File,
+        taken: &HashSet<File>) -> (File, Vec<File>) {
+
+        let destinations = |new_primary: &File| -> Vec<File> {
+            sidecars.iter().map(|sidecar| sidecars::sidecar_destination(sidecar, primary, new_primary)).collect()
+        };
+        let free = |file: &File| !taken.contains(file) && !file.exists();
+        let new_sidecars = destinations(&new_primary);
+        if new_sidecars.iter().all(free) {
+            return (new_primary, new_sidecars);
+        }
+        let (mut name, mut pathbuf) = (OsString::new(), planned.to_path_buf());
+        let mut num = 2;
+        loop {
+            Sorter::set_sequential_name(&mut pathbuf, &mut name, planned, num);
+            let candidate = File::from(pathbuf.clone());
+            let new_sidecars = destinations(&candidate);
+            if free(&candidate) && new_sidecars.iter().all(free) {
+                return (candidate, new_sidecars);
+            }
+            num += 1;
+        }
+    }
+
+    /// Set the file name of `buf` to the `num`th sequential name of `path`, as in
+    /// `file_2.txt`, using `name` to build it.
+    fn set_sequential_name(buf: &mut PathBuf, name: &mut OsString, path: &File, num: usize) {
+        let extension = path.extension_os();
+        name.clear();
+        name.push(path.file_stem_os());
+        let _ = write!(name, "_{}", num);
+        if !extension.is_empty() {
+            name.push(".");
+            name.push(extension);
+        }
+        buf.set_file_name(&*name);
+    }
+
+    /// Return the file in the target that `path` is identical to (see
+    /// `self.skip_identical`): the file at its destination `new_file`, or one
+    /// of its sequential names, if it has any.
+    fn find_identical(&self, path: &File, new_file: &File) -> Option<File> {
+        let mut candidate = new_file.to_path_buf();
+        let mut name = OsString::new();
+        let mut num = 2;
+        while candidate.exists() {
+            let existing = File::from(candidate.as_path());
+            if conflicts::is_identical(path, &existing, self.skip_identical, self.hash_algorithm) {
+                return Some(existing);
+            }
+            Sorter::set_sequential_name(&mut candidate, &mut name, new_file, num);
+            num += 1;
+        }
+        None
+    }
+
+    /// Get the full sorting results for all the files according to the sorting
+    /// algorithm, with the snapshots of the files to sort as they were planned
+    /// (see [`StalePolicy`]).
+    fn get_sorting_results(&self) -> Result<(SortReport, Vec<Option<Snapshot>>), SortError> {
+
+        let source = &self.source;
+
+        // Build the type filters once, rather than for every file
+        let types = TypeFilter::new(&self.exclude_type, &self.only_type);
+        let content_types = TypeFilter::new(&self.exclude_content_type, &self.only_content_type);
+
+        // The vector to return: a tuple of (old_filename, new_filename). The
+        // new names are also kept in a set, to look them up quickly.
+        let mut vec_old: Vec<File> = Vec::new();
+        let mut vec_new: Vec<File> = Vec::new();
+        let mut snapshots: Vec<Option<Snapshot>> = Vec::new();
+        let mut taken: HashSet<File> = HashSet::new();
+
+        // The sequential names given by earlier sorts are taken, so that
+        // numbering carries on from them
+        if self.persist_counters {
+            let mut name = OsString::new();
+            for (path, last) in counters::Counters::load(&self.target)?.iter(&self.target) {
+                let mut pathbuf = path.to_path_buf();
+                taken.extend((2..=last).map(|num| {
+                    Sorter::set_sequential_name(&mut pathbuf, &mut name, &path, num);
+                    File::from(pathbuf.clone())
+                }));
+            }
+        }
+        let mut warnings = Vec::new();
+        let mut present = Vec::new();
+        let mut failures = Vec::new();
+
+        // Get all the files in the source, excluding the directories. The parts
+        // of it that can't be read fail, or stop the sort if it is strict.
+        let (files, special, unreadable) = reparse::source_files(source.as_path(), self.reparse_points);
+        for (path, error) in unreadable {
+            if self.strictness == Strictness::Strict {
+                return Err(SortError::from(error));
+            }
+            failures.push((path, SortError::from(error)));
+        }
+
+        // Pair up the sidecar files with their primaries, so that they can be
+        // sorted together
+        let pairs = sidecars::pair_sidecars(&files, &self.sidecar_types);
+        let paired: HashSet<usize> = pairs.values().flatten().copied().collect();
+
+        // Leave downloads in progress alone until they are done
+        let downloading = if self.skip_partial_downloads { downloads::partial_downloads(&files) } else { HashSet::new() };
+
+        // Get the date and time of all the files we're sorting. Sidecars are
+        // sorted along with their primaries, not on their own. Also make sure
+        // that we sort according to the exclude-type and only-type arguments.
+        let open_files = if self.skip_open_files { open_files::OpenFiles::scan() } else { Default::default() };
+        let mut in_use = Vec::new();
+        let to_sort: Vec<usize> = (0..files.len())
+            .filter(|i| !paired.contains(i) && !downloading.contains(i))
+            .filter(|i| self.is_cheaply_sortable(&files[*i], &types))
+            .filter(|i| self.is_content_sortable(&files[*i], &content_types))
+            .filter(|i| self.is_video_sortable(&files[*i]))
+            .filter(|i| match self.skip_open_files && open_files.contains(&files[*i]) {
+                true => {
+                    in_use.push(files[*i].copy());
+                    false
+                },
+                false => true,
+            })
+            .collect();
+        // Files without any of the date types we sort by are left where they
+        // are, or stop the sort if it is strict, unless `missing_date` says
+        // otherwise
+        let mut skipped = Vec::new();
+        let mut undated = Vec::new();
+        let mut fallbacks: HashMap<usize, SortWarning> = HashMap::new();
+        let mut times: Vec<DateTime<Local>> = Vec::with_capacity(to_sort.len());
+        let mut dated: Vec<usize> = Vec::with_capacity(to_sort.len());
+        for i in to_sort {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::debug_span!("date_file", path = %files[i]).entered();
+            match self.get_file_datetime(&files[i]) {
+                Ok((datetime, fallback)) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(timestamp = %datetime, fallback = ?fallback, "dated file");
+                    if let Some(fallback) = fallback {
+                        fallbacks.insert(i, fallback);
+                    }
+                    times.push(datetime);
+                    dated.push(i);
+                },
+                Err(SortError::TimestampUnavailable(_)) if self.missing_date == MissingDatePolicy::Skip => {
+                    skipped.push(files[i].copy());
+                },
+                Err(SortError::TimestampUnavailable(_)) if self.missing_date == MissingDatePolicy::Folder => {
+                    undated.push((i, dates::UNKNOWN_DATE, None));
+                },
+                Err(error @ SortError::TimestampUnavailable(_)) if self.missing_date == MissingDatePolicy::Quarantine => {
+                    undated.push((i, self.quarantine_dir.as_deref().unwrap_or_default(), Some(error)));
+                },
+                Err(error) if self.strictness == Strictness::Strict => return Err(error),
+                Err(error) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(%error, "failed to date file");
+                    match &self.quarantine_dir {
+                        Some(dir) => undated.push((i, dir, Some(error))),
+                        None => failures.push((files[i].copy(), error)),
+                    }
+                },
+            }
+        }
+        let mut to_sort = dated;
+
+        // Leave the newest files where they are, if we're supposed to
+        if let Some(keep) = self.keep_newest {
+            let mut newest: Vec<usize> = (0..to_sort.len()).collect();
+            newest.sort_by(|a, b| times[*b].cmp(&times[*a])
+                .then_with(|| files[to_sort[*a]].pathbuf.cmp(&files[to_sort[*b]].pathbuf)));
+            let kept: HashSet<usize> = newest.into_iter().take(keep).collect();
+            (to_sort, times) = to_sort.into_iter().zip(times).enumerate()
+                .filter(|(n, _)| !kept.contains(n))
+                .map(|(_, pair)| pair)
+                .unzip();
+        }
+
+        // Name the files in chronological order when ties are broken by time, so
+        // that the earliest file of a tie gets the plain name
+        if self.time_tie_break {
+            let mut order: Vec<(usize, DateTime<Local>)> = to_sort.into_iter().zip(times).collect();
+            order.sort_by(|(a, a_time), (b, b_time)| a_time.cmp(b_time)
+                .then_with(|| files[*a].pathbuf.cmp(&files[*b].pathbuf)));
+            (to_sort, times) = order.into_iter().unzip();
+        }
+
+        // Group the files into events and bursts, if we're supposed to
+        let events = match self.event_gap {
+            Some(gap) => grouping::event_groups(&times, gap).into_iter().map(Some).collect(),
+            None => vec![None; times.len()],
+        };
+        let bursts = match self.burst_interval {
+            Some(interval) => grouping::burst_groups(&times, interval),
+            None => vec![None; times.len()],
+        };
+
+        // The placeholders to fill in for each file
+        let placeholders = template::placeholders(&self.dir_format);
+
+        // Sort the everything
+        for (n, i) in to_sort.into_iter().enumerate() {
+
+            let path = &files[i];
+            #[cfg(feature = "tracing")]
+            let span = tracing::debug_span!(
+                "plan_file", path = %path, timestamp = %times[n], destination = tracing::field::Empty
+            ).entered();
+
+            let suspicious = self.audit_date(path, &times[n]);
+            let destination = self.get_destination(
+                path, &times[n], &placeholders, events[n].as_ref(), bursts[n].as_ref(), suspicious.is_some()
+            );
+            let (dir, mut new_file) = match destination {
+                Ok(destination) => destination,
+                Err(error) if self.strictness == Strictness::Strict => return Err(error),
+                Err(error) => {
+                    failures.push((path.copy(), error));
+                    continue;
+                },
+            };
+            #[cfg(feature = "tracing")]
+            tracing::debug!(dir = %dir, event = ?events[n].as_ref().map(|g| &g.name),
+                burst = ?bursts[n].as_ref().map(|g| &g.name), "chose directory");
+
+            // Leave out files that are already in the target, if we're supposed
+            // to check for them
+            let exists = new_file.exists();
+            if exists && self.skip_identical != IdenticalCheck::Off {
+                if let Some(existing) = self.find_identical(path, &new_file) {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(existing = %existing, "already present");
+                    present.push((path.copy(), existing));
+                    continue;
+                }
+            }
+
+            // Replace older files in the target, and leave out the files they
+            // are as new as, if we're supposed to. A destination that another
+            // file is already planned for is left to sequential names.
+            let replace = exists && self.conflict_policy == ConflictPolicy::Update && !taken.contains(&new_file);
+            if replace {
+                if !conflicts::is_newer(path, &new_file) {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(existing = %new_file, "already present, as new");
+                    present.push((path.copy(), new_file));
+                    continue;
+                }
+                #[cfg(feature = "tracing")]
+                tracing::debug!(existing = %new_file, "replacing older file");
+                warnings.push((path.copy(), SortWarning::Replaced(new_file.copy())));
+            }
+
+            // Get the sequential file name if new_file already exists. Files
+            // that were already in the target, and aren't replaced, are an
+            // anomaly, though.
+            let exists = exists && !replace;
+            let planned = new_file.copy();
+            if exists {
+                if self.strictness == Strictness::Strict {
+                    return Err(SortError::ConflictUnresolved(new_file));
+                }
+                warnings.push((path.copy(), SortWarning::DestinationExists(new_file.copy())));
+            }
+            if exists || taken.contains(&new_file) {
+                new_file = match self.time_tie_break {
+                    true => self.get_tie_broken_path(&dir, path, &times[n], &taken),
+                    false => None,
+                }.unwrap_or_else(|| self.get_sequential_name(&new_file, &taken));
+                #[cfg(feature = "tracing")]
+                tracing::debug!(renamed = %new_file, "destination taken");
+            }
+            #[cfg(feature = "tracing")]
+            {
+                span.record("destination", tracing::field::display(&new_file));
+                tracing::debug!("planned file");
+            }
+
+            // Send the sidecars along to the same place, with the same new stem.
+            // Sidecars that would replace files in the target are an anomaly,
+            // like their primaries, unless those are replaced too.
+            let sidecar_files = pairs.get(&i).map(|s| s.as_slice()).unwrap_or_default();
+            let group: Vec<&File> = sidecar_files.iter().map(|s| &files[*s]).collect();
+            let new_sidecars: Vec<File> = group.iter().map(|s| sidecars::sidecar_destination(s, path, &new_file)).collect();
+            for (s, new_sidecar) in sidecar_files.iter().zip(&new_sidecars).filter(|(_, new_sidecar)| new_sidecar.exists()) {
+                match replace {
+                    true => warnings.push((files[*s].copy(), SortWarning::Replaced(new_sidecar.copy()))),
+                    false if self.strictness == Strictness::Strict => return Err(SortError::ConflictUnresolved(new_sidecar.copy())),
+                    false => warnings.push((files[*s].copy(), SortWarning::DestinationExists(new_sidecar.copy()))),
+                }
+            }
+
+            // The group is renamed together if any sidecar's destination is
+            // taken, unless it is replacing what is there
+            let (new_file, new_sidecars) = match replace {
+                true => (new_file, new_sidecars),
+                false => Sorter::get_group_destinations(path, &group, &planned, new_file, &taken),
+            };
+
+            // Push the new and old file names to their respective vectors
+            if let Some(fallback) = fallbacks.remove(&i) {
+                warnings.push((path.copy(), fallback));
+            }
+            if let Some(suspicious) = suspicious {
+                warnings.push((path.copy(), SortWarning::SuspiciousDate(suspicious)));
+            }
+            vec_old.push(path.copy());
+            snapshots.push(Snapshot::of(path));
+            taken.insert(new_file.copy());
+            vec_new.push(new_file);
+            for (s, new_sidecar) in sidecar_files.iter().zip(new_sidecars) {
+                vec_old.push(files[*s].copy());
+                snapshots.push(Snapshot::of(&files[*s]));
+                taken.insert(new_sidecar.copy());
+                vec_new.push(new_sidecar);
+            }
+        }
+
+        // Files without dates go into the quarantine, if there is one, or the
+        // unknown date directory, along with their sidecars
+        let mut quarantined = Vec::with_capacity(undated.len());
+        for (i, dir, error) in undated {
+            let path = &files[i];
+            let new_file = self.get_named_path(dir, path, &taken);
+            let sidecar_files = pairs.get(&i).map(|s| s.as_slice()).unwrap_or_default();
+            let group: Vec<&File> = sidecar_files.iter().map(|s| &files[*s]).collect();
+            let (new_file, new_sidecars) = Sorter::get_group_destinations(path, &group, &new_file, new_file.copy(), &taken);
+            vec_old.push(path.copy());
+            snapshots.push(Snapshot::of(path));
+            taken.insert(new_file.copy());
+            vec_new.push(new_file);
+            for (s, new_sidecar) in sidecar_files.iter().zip(new_sidecars) {
+                vec_old.push(files[*s].copy());
+                snapshots.push(Snapshot::of(&files[*s]));
+                taken.insert(new_sidecar.copy());
+                vec_new.push(new_sidecar);
+            }
+            quarantined.extend(error.map(|error| (path.copy(), error)));
+        }
+
+        // Hard links are listed, since they are sorted differently
+        let mut links: Vec<(usize, usize)> = links::hard_links(&vec_old).into_iter().collect();
+        links.sort_unstable();
+        for (i, leader) in links {
+            warnings.push((vec_old[i].copy(), SortWarning::HardLinked(vec_old[leader].copy())));
+        }
+        let report = SortReport {
+            old: vec_old,
+            new: vec_new,
+            failures,
+            warnings,
+            skipped,
+            in_use,
+            special,
+            present,
+            quarantined,
+            config_warnings: self.config_warnings(),
+            notification_failures: Vec::new()
+        };
+        Ok((report, snapshots))
+    }
+
+    /// Return the directory to sort `path` into, and its new path there, if its
+    /// date and time is `time`: the usual date directory, with the event's and
+    /// the burst's folders below it if it's part of an `event` or a `burst`.
+    /// `placeholders` are the placeholders of `self.dir_format`. Files that meet
+    /// one of `self.rules` go into its tree instead, and `suspicious` ones into
+    /// `self.suspicious_dir`, if there is one.
+    fn get_destination(
+        &self,
+        path: &File,
+        time: &DateTime<Local>,
+        placeholders: &[&str],
+        event: Option<&grouping::Group>,
+        burst: Option<&grouping::Group>,
+        suspicious: bool) -> Result<(File, File), SortError> {
+
+        if let (true, Some(dir)) = (suspicious, &self.suspicious_dir) {
+            let dir = self.target.join(dir.clone());
+            let new_file = self.get_new_date_path(&dir, path, time, &self.date_format);
+            return Ok((dir, new_file));
+        }
+        let rule = rules::matching_rule(path, &self.rules);
+        let (dir_format, placeholders) = match rule {
+            Some(rule) => (rule.dir_format.as_str(), Cow::Owned(template::placeholders(&rule.dir_format))),
+            None => (self.dir_format.as_str(), Cow::Borrowed(placeholders)),
+        };
+        let dir_format = if placeholders.is_empty() {
+            Cow::Borrowed(dir_format)
+        } else {
+            Cow::Owned(template::expand(dir_format, &self.get_template_vars(path, &placeholders)))
+        };
+        let target = match self.top_folder(path) {
+            Some(folder) if rule.is_none() => File::from(self.target.pathbuf.join(folder)),
+            _ => self.target.copy(),
+        };
+        let root = match (&self.tiny_dir, &self.screenshot_dir) {
+            _ if rule.is_some() => target,
+            (Some(dir), _) if self.is_tiny(path) => target.join(dir.clone()),
+            (_, Some(dir)) if screenshots::is_screenshot(path) => target.join(dir.clone()),
+            _ if self.extensionless == ExtensionlessPolicy::Folder && path.extension_os().is_empty() => {
+                target.join(String::from(template::NO_EXTENSION))
+            },
+            _ => target,
+        };
+        let dir = Sorter::inside(self.get_new_dir(&root, &dir_format, time, event, burst), &root)?;
+        let new_file = self.get_new_date_path(&dir, path, time, &self.date_format);
+        Ok((dir, new_file))
+    }
+
+    /// Return `dir` if it is inside `root`, the directory that its date tree was
+    /// built in, or an error if it leads out of it, as through a `..` folder in
+    /// a directory format, so that nothing is ever sorted outside of where it
+    /// was meant to go.
+    fn inside(dir: File, root: &File) -> Result<File, SortError> {
+        let inside = dir.pathbuf.strip_prefix(&root.pathbuf)
+            .is_ok_and(|rest| rest.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)));
+        match inside {
+            true => Ok(dir),
+            false => Err(SortError::from(InvalidPathError { path: dir.to_string(), reason: "it leads out of the directory it is sorted into" })),
+        }
+    }
+
+    /// Return the folder directly inside `self.source` that `path` is in, if
+    /// `self.keep_top_folder` is on and it is in one.
+    fn top_folder<'a>(&self, path: &'a File) -> Option<&'a OsStr> {
+        if !self.keep_top_folder {
+            return None;
+        }
+        let mut components = path.pathbuf.strip_prefix(&self.source.pathbuf).ok()?.components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(folder)), Some(_)) => Some(folder),
+            _ => None,
+        }
+    }
+
+    /// Return the parts of our configuration that contradict each other, and so
+    /// probably don't do what was meant: an `exclude_type` that is ignored because
+    /// `only_type` overrides it, and types in both (and the same for the content
+    /// type filters). Unlike the problems found by [`Sorter::validate`], these
+    /// don't stop sorting; they are also in the [`SortReport`] of every sort.
+    pub fn config_warnings(&self) -> Vec<ConfigWarning> {
+        let mut warnings = Vec::new();
+        let filters = [
+            ("exclude_type", &self.exclude_type, "only_type", &self.only_type),
+            ("exclude_content_type", &self.exclude_content_type, "only_content_type", &self.only_content_type),
+        ];
+        for (exclude_name, exclude, only_name, only) in filters {
+            let exclude: Vec<&String> = exclude.iter().filter(|t| !t.is_empty()).collect();
+            if exclude.is_empty() || only.is_empty() || filters::matches_everything(only) {
+                continue;
+            }
+            warnings.push(ConfigWarning::Overridden(exclude_name));
+            for t in exclude.into_iter().filter(|t| only.contains(t)) {
+                warnings.push(ConfigWarning::Contradicts(only_name, exclude_name, t.clone()));
+            }
+        }
+        warnings
+    }
+
+    /// Return about how many files a sort would process, and how big they are
+    /// together, without planning it: the source is walked, but only the
+    /// filters that don't read the files are applied, so files filtered by
+    /// content type or `video_filter`, or left alone for being open, are still
+    /// counted. This is
+    /// fast enough to show something like "about 42,000 files, 380 GB" before
+    /// committing to a sort. Return [`SortError::PathDoesNotExist`] if the
+    /// source doesn't exist.
+    pub fn estimate(&self) -> Result<Estimate, SortError> {
+        if !self.source.pathbuf.is_dir() {
+            return Err(SortError::from(PathDoesNotExistError { path: self.source.to_string() }));
+        }
+        let types = TypeFilter::new(&self.exclude_type, &self.only_type);
+        let (files, _, _) = reparse::source_files(self.source.as_path(), self.reparse_points);
+        let pairs = sidecars::pair_sidecars(&files, &self.sidecar_types);
+        let paired: HashSet<usize> = pairs.values().flatten().copied().collect();
+        let downloading = if self.skip_partial_downloads { downloads::partial_downloads(&files) } else { HashSet::new() };
+
+        // Sidecars go along with their primaries
+        let mut estimate = Estimate::default();
+        let sorted = (0..files.len())
+            .filter(|i| !paired.contains(i) && !downloading.contains(i))
+            .filter(|i| self.is_cheaply_sortable(&files[*i], &types))
+            .flat_map(|i| std::iter::once(i).chain(pairs.get(&i).into_iter().flatten().copied()));
+        for i in sorted {
+            estimate.files += 1;
+            estimate.bytes += files[i].pathbuf.symlink_metadata().map_or(0, |m| m.len());
+        }
+        Ok(estimate)
+    }
+
+    /// Return the risky parts of our configuration: ones that work, but probably
+    /// not as well as meant, like a target inside the source, a `date_format`
+    /// without a date, a `date_format` that gives many files the same name, or
+    /// copies over the network. See [`Risk`] for all of them. Unlike the
+    /// problems found by [`Sorter::validate`], these don't stop sorting, and
+    /// unlike [`Sorter::config_warnings`], they aren't in the [`SortReport`].
+    pub fn check(&self) -> Vec<Risk> {
+        let mut risks = Vec::new();
+        if let (Ok(source), Ok(target)) = (fs::canonicalize(&self.source.pathbuf), fs::canonicalize(&self.target.pathbuf)) {
+            if target != source && target.starts_with(&source) {
+                risks.push(Risk::TargetInSource);
+            }
+        }
+        let specifiers = dates::format_specifiers(&self.date_format);
+        if !specifiers.iter().any(|c| dates::DATE_SPECIFIERS.contains(*c)) {
+            risks.push(Risk::NoDateInFormat);
+        }
+        if !self.preserve_name && !specifiers.iter().any(|c| dates::SECOND_SPECIFIERS.contains(*c)) {
+            risks.push(Risk::NameCollisions);
+        }
+
+        // Files moved to another device are copied too
+        let mounts = self.mounts();
+        let copies = self.transfer == TransferMode::Copy || self.rules.iter().any(|rule| rule.transfer == Some(TransferMode::Copy));
+        if (copies || !mounts.same_device) && mounts.either_is(mounts::MountKind::Network) {
+            risks.push(Risk::UnverifiedNetworkCopy);
+        }
+        risks
+    }
+
+    /// Check that sorting can run: that the source directory exists, that the
+    /// target directory exists or can be created, that they aren't the same
+    /// directory, that the target is writable, and that the configuration is
+    /// valid. Return the first problem found as a [`SortError`]. This is done at
+    /// the start of every sort, so that nothing is moved when sorting can't
+    /// succeed, but can be called before that too.
+    pub fn validate(&self) -> Result<(), SortError> {
+
+        // Return error messages if the source doesn't exist, or if the target
+        // doesn't and can't be created, in its nearest existing ancestor
+        if !self.source.exists() {
+            return Err(SortError::from(PathDoesNotExistError { path: self.source.to_string() }));
+        }
+        let writable = self.target.pathbuf.ancestors()
+            .find(|path| path.exists())
+            .ok_or_else(|| SortError::from(PathDoesNotExistError { path: self.target.to_string() }))?;
+        for path in [self.source.as_path(), writable] {
+            if !path.is_dir() {
+                return Err(SortError::InvalidConfig(format!("\"{}\" is not a directory", path.display())));
+            }
+        }
+        if self.target.exists() && fs::canonicalize(&self.source.pathbuf)? == fs::canonicalize(&self.target.pathbuf)? {
+            return Err(SortError::InvalidConfig(String::from("the source and target are the same directory")));
+        }
+        if !Sorter::is_writable(writable) {
+            return Err(SortError::NotWritable(File::from(writable)));
+        }
+
+        // The options that have to be parsed
+        if self.date_type.is_empty() || !self.date_type.chars().all(|c| "acem".contains(c)) {
+            return Err(SortError::InvalidConfig(format!("invalid date_type \"{}\"", self.date_type)));
+        }
+        if dates::parse_offset(&self.clock_offset).is_none() {
+            return Err(SortError::InvalidConfig(format!("invalid clock_offset \"{}\"", self.clock_offset)));
+        }
+        if !self.location_grid.is_finite() || self.location_grid <= 0.0 {
+            return Err(SortError::InvalidConfig(format!("invalid location_grid {}, which isn't a size in degrees", self.location_grid)));
+        }
+        if !(1..=12).contains(&self.year_start) {
+            return Err(SortError::InvalidConfig(format!("invalid year_start {}, which isn't a month from 1 to 12", self.year_start)));
+        }
+        if self.missing_date == MissingDatePolicy::Quarantine && self.quarantine_dir.is_none() {
+            return Err(SortError::InvalidConfig(String::from("missing_date is quarantine, but there is no quarantine_dir")));
+        }
+        if self.backend == execution::Backend::IoUring && !Sorter::io_uring_supported() {
+            return Err(SortError::InvalidConfig(String::from("backend is io_uring, but io_uring can't rename files here")));
+        }
+        if !cfg!(feature = "sha256") && self.hash_algorithm == HashAlgorithm::Sha256 && self.skip_identical == IdenticalCheck::Hash {
+            return Err(SortError::InvalidConfig(String::from("hash_algorithm is sha256, but the sha256 feature is off")));
+        }
+        Ok(())
+    }
+
+    /// Check that sorting can run in `mode`, which it can't in
+    /// [`ExecutionMode::ExecuteIntegrity`] mode without the `blake3` feature.
+    fn validate_mode(mode: ExecutionMode) -> Result<(), SortError> {
+        if mode == ExecutionMode::ExecuteIntegrity && !cfg!(feature = "blake3") {
+            return Err(SortError::InvalidConfig(String::from("ExecuteIntegrity mode needs the blake3 feature")));
+        }
+        Ok(())
+    }
+
+    /// Return [`true`] if io_uring can rename files here (see [`uring::is_supported`]).
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    fn io_uring_supported() -> bool {
+        uring::is_supported()
+    }
+
+    /// Return [`false`], since io_uring is only used on Linux, with the
+    /// `io-uring` feature.
+    #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+    fn io_uring_supported() -> bool {
+        false
+    }
+
+    /// Return [`true`] if `path` was last created or modified at least `self.min_age`
+    /// ago, or if there is no `self.min_age`.
+    fn is_old_enough(&self, path: &File) -> bool {
+        let min_age = match self.min_age {
+            Some(min_age) => min_age,
+            None => return true,
+        };
+        let metadata = match path.pathbuf.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => return false,
+        };
+        let changed = [metadata.modified(), metadata.created()].into_iter()
+            .flatten()
+            .max()
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        SystemTime::now().duration_since(changed).map(|age| age >= min_age).unwrap_or(false)
+    }
+
+    /// Return why `time` can't be the date of `path`, if `self.audit_dates` is
+    /// on and it can't be.
+    fn audit_date(&self, path: &File, time: &DateTime<Local>) -> Option<audit::SuspiciousDate> {
+        match self.audit_dates {
+            true => audit::audit(path, time),
+            false => None,
+        }
+    }
+
+    /// Return [`true`] if `path` is smaller than `self.min_size`.
+    fn is_tiny(&self, path: &File) -> bool {
+        match self.min_size {
+            Some(min_size) => path.pathbuf.metadata().map(|m| m.len() < min_size).unwrap_or(false),
+            None => false,
+        }
+    }
+
+    /// Return [`true`] if `path` gets through the filters that don't read the
+    /// file: its type (see `types`), whether it is a placeholder, a symbolic
+    /// link, or without an extension, its size, and its age.
+    fn is_cheaply_sortable(&self, path: &File, types: &TypeFilter) -> bool {
+        types.allows(&path.extension())
+            && (!self.skip_placeholders || !reparse::is_placeholder(path.as_path()))
+            && (self.symlinks != SymlinkPolicy::Skip || !links::is_file_symlink(path.as_path()))
+            && (self.extensionless != ExtensionlessPolicy::Skip || !path.extension_os().is_empty())
+            && (self.tiny_dir.is_some() || !self.is_tiny(path))
+            && self.is_old_enough(path)
+    }
+
+    /// Return [`true`] if `path`'s content type gets through `content_types`, the
+    /// filter of `exclude_content_type` and `only_content_type`. The content is
+    /// only read if there is something to filter, and files whose content type
+    /// isn't recognized (or that can't be read) are only let through if there
+    /// are no `only` types.
+    fn is_content_sortable(&self, path: &File, content_types: &TypeFilter) -> bool {
+        if content_types.is_empty() {
+            return true;
+        }
+        match content_types::content_type(&path.pathbuf) {
+            Ok(Some(content_type)) => content_types.allows(content_type),
+            _ => content_types.only.is_none(),
+        }
+    }
+
+    /// Return [`true`] if `path` isn't a video, or meets the conditions of
+    /// `video_filter`. The video is only probed if there are any conditions.
+    fn is_video_sortable(&self, path: &File) -> bool {
+        self.video_filter.is_empty() || video::VideoInfo::read(path).is_none_or(|info| self.video_filter.allows(&info))
+    }
+
+    /// The method that runs the sorting algorithm. Returns the sorting results as
+    /// a tuple of ([`usize`], [`Vec<String>`], [`Vec<String>`]), where `results.0`
+    /// is the number of items sorted, `results.1` contains all the old file names,
+    /// and `results.2` contains all the new file names. The two vectors correspond
+    /// index-wise, so `results.1[0]` is renamed to `results.2[0]`, etc.
+    /// 
+    /// If `dry_run` is [`true`], return the results as usual, but without acutally
+    /// sorting the files.
+    /// 
+    /// Panics if the sort can't run, or if any file fails to sort. Use
+    /// [`Sorter::sort_report`] to handle these errors instead, and to get a
+    /// [`SortReport`] rather than a tuple. This will be removed in the next release.
+    #[deprecated(since = "0.4.0", note = "use `Sorter::sort_report` with an `ExecutionMode` instead")]
+    pub fn sort(&self, dry_run: bool) -> (usize, Vec<File>, Vec<File>) {
+        Sorter::into_tuple(self.sort_base(ExecutionMode::from(dry_run), callback_fn))
+    }
+
+    /// The same as [`Sorter::sort`], but returns a [`SortReport`], which also holds
+    /// the files that failed to sort and why, instead of panicking. The other files
+    /// are still sorted. Returns a [`SortError`] if the sort can't run at all,
+    /// as when the source directory doesn't exist.
+    /// 
+    /// `mode` says how to carry out the sort; see [`ExecutionMode`] for the
+    /// options. [`ExecutionMode::DryRun`] returns the results as usual, but without
+    /// actually sorting the files, to check that the sort does what is intended.
+    /// Otherwise, the directories the files are sorted into are created first
+    /// (see [`Sorter::create_dirs`]), and the target is locked for the whole
+    /// sort, so that a second sort into it fails with
+    /// [`SortError::AlreadyRunning`] (see [`lock`]).
+    /// For example:
+    /// 
+    /// ```ignore
+    /// use sorterylib::prelude::*;
+    /// 
+    /// fn main() {
+    /// 
+    ///     // The sorter instance
+    ///     let sorter = Sorter { ... };
+    /// 
+    ///     // Dry run, without actually sorting the files
+    ///     sorter.sort_report(ExecutionMode::DryRun);
+    /// 
+    ///     // Acutally sort the files
+    ///     sorter.sort_report(ExecutionMode::Execute);
+    /// }
+    /// ```
+    pub fn sort_report(&self, mode: ExecutionMode) -> Result<SortReport, SortError> {
+        self.sort_base(mode, callback_fn)
+    }
+
+    /// Return `report` as the tuple returned by [`Sorter::sort`], panicking if
+    /// the sort couldn't run or any file failed.
+    fn into_tuple(report: Result<SortReport, SortError>) -> (usize, Vec<File>, Vec<File>) {
+        let report = match report {
+            Ok(report) => report,
+            Err(error) => {
+                println!("{}", error);
+                panic!("Sorting results error.");
+            },
+        };
+        if let Some((file, error)) = report.failures.first() {
+            println!("{}", error);
+            panic!("Failed to sort {}.", file);
+        }
+        (report.count(), report.old, report.new)
+    }
+    
+    /// The base sorting algorithm. This a private function, called by [`Sorter::sort`],
+    /// [`Sorter::sort_report`], and [`Sorter::sort_with_callback`].
+    fn sort_base(
+        &self,
+        mode: ExecutionMode,
+        mut callback: impl FnMut(&Progress)) -> Result<SortReport, SortError> {
+
+        // The variable for discerning when next to call the callback
+        let mut last_percent: usize = 0;
 
-        // The paths to use for testing
-        let current_dir = env::current_dir().expect("Failed to get current dir.");
-        let json_path = current_dir.join(Path::new("template.json"));
-        let source = File::from(&current_dir).join(Path::new("testing"));
-        let target = source.join(Path::new("target"));
+        // Make sure we can sort at all, and that no other sort into the target is
+        // running, before doing anything. Then get the sorting results. Sorts
+        // that can't run are over already.
+        let planned = self.validate()
+            .and_then(|()| Sorter::validate_mode(mode))
+            .and_then(|()| self.lock_target(mode))
+            .and_then(|lock| Ok((self.get_sorting_results()?, lock)));
+        let ((mut report, snapshots), _lock) = match planned {
+            Ok(planned) => planned,
+            Err(error) => {
+                if mode.moves_files() {
+                    notify::send_all(&self.notifications, &notify::Summary::of_error(&error));
+                }
+                return Err(error);
+            },
+        };
+        let count = report.count();
+        self.observers.on_start(count);
+        #[cfg(feature = "metrics")]
+        metrics::record_start(count);
 
-        // Get the string from the json file
-        let json_string = fs::read_to_string(json_path).expect("Failed to read json file.");
-        
-        // Create a Sorter instance for testing
-        let sorter1 = Sorter {
-            source: source.copy(),
-            target: target.copy(),
-            date_format: String::from("%Y-%m-%d %Hh%Mm%Ss"),
-            date_type: String::from("m"),
-            preserve_name: false,
-            exclude_type: vec![String::from("png")],
-            only_type: vec![String::from("json"), String::from("py")]
+        // Sort all the files in the vectors, or dry-run if specified. Files that
+        // fail are recorded, and in lenient mode the rest are still sorted. In
+        // strict mode, we stop at the first failure.
+        let (old, mut new) = (std::mem::take(&mut report.old), std::mem::take(&mut report.new));
+
+        // Files that are already in the target are removed from the source, if
+        // we're supposed to
+        if mode.moves_files() && self.remove_identical {
+            for (path, _) in &report.present {
+                if let Err(error) = fs::remove_file(path.as_path()) {
+                    report.failures.push((path.copy(), SortError::from(error)));
+                }
+            }
+        }
+
+        // Create the directories the files are sorted into before moving any of
+        // them, once each, rather than for every file, if we're supposed to
+        let dirs = execution::destination_dirs(&new);
+        let unmade = match (mode.moves_files(), self.create_dirs) {
+            (true, true) => execution::create_dirs(&dirs, |dir| self.prepare_created_dir(dir)),
+            (true, false) => dirs.into_iter().filter(|dir| !dir.is_dir()).map(|dir| (dir, io::ErrorKind::NotFound)).collect(),
+            (false, _) => HashMap::new(),
         };
+        let links = if mode.moves_files() { links::hard_links(&old) } else { HashMap::new() };
+        let revalidation = Revalidation::new(snapshots, &old, &new, &report.warnings, &self.sidecar_types, self.skip_open_files);
+        let transfers = self.get_transfers(&old, &revalidation);
+        let mut declined: HashSet<usize> = HashSet::new();
+        let mut handled = vec![false; count];
+        let mut throttle = ProgressThrottle::new(self.progress_interval);
+        let mut moved = Vec::new();
+        let mut unsorted = Vec::new();
+        let mut done = 0;
+        let on_done = |i: usize, status: FileStatus, error: Option<SortError>| {
+            match status {
+                FileStatus::Moved if self.symlinks == SymlinkPolicy::Relink => moved.push(i),
+                FileStatus::Skipped => {
+                    report.skipped.push(old[i].copy());
+                    declined.insert(i);
+                },
+                FileStatus::InUse => {
+                    report.in_use.push(old[i].copy());
+                    declined.insert(i);
+                },
+                FileStatus::Failed => match error {
+                    Some(error @ (SortError::IoError(_) | SortError::DirectoryNotCreated(..))) if self.quarantine_dir.is_some() => {
+                        unsorted.push((i, error));
+                    },
+                    error => report.failures.extend(error.map(|error| (old[i].copy(), error))),
+                },
+                _ => (),
+            }
+            handled[i] = true;
 
-        // Create a Sorter instance from the json string for testing
-        let sorter2 = Sorter::from_json(json_string, source.copy(), target.copy());
+            // Calculate the percent, tell the observers about the file, and run
+            // the callback if necessary, as often as `progress_interval` allows,
+            // but always for the last file. Replanned files are reported with
+            // their new destinations.
+            let failed = status == FileStatus::Failed && self.strictness == Strictness::Strict;
+            if throttle.allows(done, failed || self.cancel.is_cancelled() || done + 1 == count) {
+                let replanned = match self.stale_policy {
+                    StalePolicy::Replan => revalidation.replans.lock().unwrap_or_else(PoisonError::into_inner).moved.get(&i).cloned(),
+                    _ => None,
+                };
+                let current_percent = ((100_f32 / count as f32) * done as f32) as usize;
+                let progress = Progress {
+                    done,
+                    total: count,
+                    percent: current_percent,
+                    current: Some((&old[i], replanned.as_ref().unwrap_or(&new[i]), status))
+                };
+                self.observers.on_file(&progress);
 
-        // Compare the two Sorter instances to make sure that the JSON was parsed
-        // correctly, and that the values match up.
-        assert_eq!(sorter1, sorter2);
+                if current_percent > last_percent {
+                    // Run the callback, with the file that was just processed
+                    callback(&progress);
+                    last_percent = current_percent;
+                }
+            }
+            done += 1;
 
-        // Test the sorting algorithm
-        sorter1.sort(true);
-    }
-}
+            // Stop at the first failure of a strict sort, or once it's cancelled
+            !(failed || self.cancel.is_cancelled())
+        };
 
-/// This is a private function used in case the user doesn't supply a progress
-/// update callback.
-fn callback_fn(_data: (usize, usize, usize)) { }
+        // In verified sorts, the files are hashed ahead of being moved, on
+        // threads of their own, so that hashing overlaps with the moves
+        let hashed = match (mode, self.hash_workers) {
+            (ExecutionMode::ExecuteVerified, 1..) => &old[..],
+            _ => &[],
+        };
+        let hashes = checksum::HashQueue::new(hashed, self.hash_algorithm);
+        thread::scope(|scope| {
+            for _ in 0..self.hash_workers.min(hashed.len()) {
+                scope.spawn(|| hashes.work());
+            }
+            self.execute_plan(&old, &new, &transfers, &hashes, mode, &unmade, &links, &revalidation, on_done);
+            hashes.stop();
+        });
 
-/// The trait used by [`Sorter`] to either parse a JSON string or a JSON file
-pub trait FromJson<T> {
-    fn from_json(json: T, source: File, target: File) -> Sorter;
-}
+        // The hashes of the files that were checked end to end are recorded in
+        // the target
+        #[cfg(feature = "blake3")]
+        if mode == ExecutionMode::ExecuteIntegrity {
+            if let Err(error) = integrity::record(&self.target, &hashes.kept()) {
+                report.failures.push((self.target.join(String::from(manifest::INTEGRITY_MANIFEST_NAME)), SortError::from(error)));
+            }
+        }
 
-/// The sorter struct that sorts the files. There are two ways to create an instance
-/// of [`Sorter`]: passing the individual fields, and using [`Sorter::from_json`].
-#[derive(Debug, PartialEq)]
-pub struct Sorter {
-    /// An instance of [`File`] specifying the directory from which to get
-    /// all the files to sort
-    pub source: File,
-    /// An instance of [`File`] specifying the directory to sort all the
-    /// files into.
-    pub target: File,
-    /// A [`String`] representing the date format. Uses the standard `strftime` format.
-    /// See [`chrono::format::strftime`] for formatting information.
-    pub date_format: String,
-    /// A [`String`] representing the date type to sort by. Must be one of `String::from("a")`
-    /// (accessed) `String::from("c")` (created), or `String::from("m")` (modified).
-    /// Note that sorting by creation date is not available on all filesystems.
-    pub date_type: String,
-    /// If [`true`], then the sorter adds the old file name onto the end of the new
-    /// one. For example, `test.txt` would be renamed to something like `2021-04-22 test.txt`.
-    /// If [`false`], then ignores the old file name (would rename `test.txt` to `2021-04-22.txt`).
-    pub preserve_name: bool,
-    /// A [`Vec<String>`] containing all the file extensions to be ignored during
-    /// sorting. For example, if `vec![String::from("jpg")]` is passed, than all files
-    /// ending in `.jpg` won't be sorted.
-    pub exclude_type: Vec<String>,
-    /// A [`Vec<String>`] containing all the file extensions to be exclusively sorted.
-    /// For example, if `vec![String::from("png")] is passed, than *only* files ending
-    /// in `.png` will be sorted. All other files will be ignored. This option overrides
-    /// `exclude_type`.
-    pub only_type: Vec<String>
-}
-impl Sorter {
+        // Files that changed since they were planned were left where they are,
+        // or sorted somewhere else, and files whose destinations were taken in
+        // the meantime were given sequential names
+        let replans = revalidation.replans.into_inner().unwrap_or_else(PoisonError::into_inner);
+        let mut skipped: Vec<usize> = replans.skipped.into_iter().collect();
+        skipped.sort_unstable();
+        for i in skipped {
+            report.warnings.push((old[i].copy(), SortWarning::Changed));
+        }
+        let mut replanned: Vec<(usize, File)> = replans.moved.into_iter().collect();
+        replanned.sort_unstable_by_key(|(i, _)| *i);
+        for (i, destination) in replanned {
+            let planned = std::mem::replace(&mut new[i], destination);
+            let warning = match replans.collided.contains(&i) {
+                true => SortWarning::DestinationExists(planned),
+                false => SortWarning::Replanned(planned),
+            };
+            report.warnings.push((old[i].copy(), warning));
+        }
 
-    /// Return a [`DateTime`] instance representing the creation, modification,
-    /// or access time of `path` according to `date_type`.
-    /// 
-    /// `date_type` must be one of `"c"` (created), `"a"` (accessed), or `"m"` (modified).
-    /// Note that creation time is not available on all filesystems.
-    fn get_datetime(&self, path: &File, date_type: &str) -> DateTime<Local> {
-        let secs: i64;
-        if date_type == "m" {
-            secs = self.get_epoch_secs_modified(path);
-        } else if date_type == "a" {
-            secs = self.get_epoch_secs_access(path);
-        } else {
-            secs = self.get_epoch_secs_creation(path);
+        // Files that failed to be moved to their destinations are moved into the
+        // quarantine instead, if they are still where they were
+        if !unsorted.is_empty() {
+            let mut taken: HashSet<File> = new.iter().map(File::copy).collect();
+            for (i, error) in unsorted {
+                let destination = self.get_named_path(self.quarantine_dir.as_deref().unwrap_or_default(), &old[i], &taken);
+                let quarantined = old[i].exists() && !new[i].exists() && destination.pathbuf.parent()
+                    .is_some_and(|dir| fs::create_dir_all(dir).is_ok())
+                    && self.move_file(&old[i], &destination, transfers[i], &hashes, ExecutionMode::Execute, None, None).is_ok();
+                match quarantined {
+                    true => {
+                        taken.insert(destination.copy());
+                        new[i] = destination;
+                        report.quarantined.push((old[i].copy(), error));
+                    },
+                    false => report.failures.push((old[i].copy(), error)),
+                }
+            }
         }
-        let ctime = Utc.timestamp(secs, 0);
-        let mytime = Local.from_utc_datetime(&ctime.naive_utc());
 
-        mytime
-    }
+        // Symbolic links are rewritten once every file is where it is going, so
+        // that links to files that were sorted too lead to their new paths
+        for (i, error) in links::rewrite_symlinks(&old, &new, &moved) {
+            report.failures.push((old[i].copy(), SortError::from(error)));
+        }
 
-    /// Return the access date and time of `path` as the number of seconds since the epoch.
-    /// Now works cross-platform.
-    fn get_epoch_secs_access(&self, path: &File) -> i64 {
-        let metadata = path.pathbuf.metadata().unwrap();
-        let secs: i64 = FileTime::from_last_access_time(&metadata).seconds() as i64;
+        // Call the callback for the last time
+        callback(&Progress { done: count, total: count, percent: 100, current: None });
 
-        secs
+        // Files that weren't confirmed aren't sorted at all, and neither are the
+        // ones left after a strict sort stopped
+        (report.old, report.new) = old.into_iter().zip(new).enumerate()
+            .filter(|(i, _)| handled[*i] && !declined.contains(i))
+            .map(|(_, pair)| pair)
+            .unzip();
+        if mode.moves_files() && self.persist_counters {
+            self.count_sequential_names(&mut report);
+        }
+        if mode.moves_files() && self.record_history {
+            self.add_to_history(&mut report);
+        }
+        self.observers.on_finish(&report);
+        if mode.moves_files() {
+            report.notification_failures = notify::send_all(&self.notifications, &notify::Summary::of_report(&report));
+        }
+        Ok(report)
     }
-    
-    /// Return the creation date and time of `path` as the number of seconds since the epoch.
-    /// Now works cross-platform.
-    fn get_epoch_secs_creation(&self, path: &File) -> i64 {
-        let metadata = path.pathbuf.metadata().unwrap();
-        let secs: i64 = FileTime::from_creation_time(&metadata).expect("Failed to get ctime.").seconds() as i64;
 
-        secs
+    /// Count the sequential names given in `report` in the counters kept in the
+    /// target, for later sorts. If the counters can't be kept, the counters file
+    /// is listed in the failures of `report`. See [`counters`].
+    fn count_sequential_names(&self, report: &mut SortReport) {
+        let counted = counters::Counters::load(&self.target).and_then(|mut counters| {
+            for (_, new) in report.sorted() {
+                counters.record(&self.target, new);
+            }
+            counters.save(&self.target)
+        });
+        if let Err(error) = counted {
+            report.failures.push((self.target.join(String::from(counters::COUNTERS_FILE_NAME)), SortError::from(error)));
+        }
     }
 
-    /// Return the modification date and time of `path` as the number of seconds since the epoch.
-    /// Now works cross-platform.
-    fn get_epoch_secs_modified(&self, path: &File) -> i64 {
-        let metadata = path.pathbuf.metadata().unwrap();
-        let secs: i64 = FileTime::from_last_modification_time(&metadata).seconds() as i64;
-        println!("secs: {} timestamp: {}", secs, 1641033122);
-        println!("{}", secs < 1641033122);
+    /// Record the sort of `report` in the history kept in the target. If the
+    /// history can't be kept, its file is listed in the failures of `report`.
+    /// See [`history`].
+    fn add_to_history(&self, report: &mut SortReport) {
+        let recorded = history::History::load(&self.target).and_then(|mut history| {
+            history.record(report, &self.target);
+            history.save(&self.target)
+        });
+        if let Err(error) = recorded {
+            report.failures.push((self.target.join(String::from(history::HISTORY_FILE_NAME)), SortError::from(error)));
+        }
+    }
 
-        secs
+    /// Lock the target for a sort in `mode`, if it moves files, creating it if it
+    /// doesn't exist yet. See [`lock`].
+    fn lock_target(&self, mode: ExecutionMode) -> Result<Option<lock::TargetLock>, SortError> {
+        match mode.moves_files() {
+            true => {
+                fs::create_dir_all(self.target.as_path())?;
+                lock::TargetLock::acquire(&self.target).map(Some)
+            },
+            false => Ok(None),
+        }
     }
 
-    /// Get the new directory stacks for all the files, according to the sorting algorithm.
-    fn get_new_date_path(
-        &self,
-        target: &File,
-        old_file: &File,
-        date_format: &str,
-        date_type: &str,
-        preserve_name: bool) -> File {
-        
-        // Get the time of old_file and set the names of the directories
-        let ctime = self.get_datetime(old_file, &date_type);
-        let dir = target.join(Path::new(&ctime.format("%Y/%m/").to_string()));
-
-        // Preserve the original file name, if we're supposed to.
-        let mut name_to_preserve = String::from("");
-        if preserve_name {
-            name_to_preserve = format!(
-                " {}",
-                old_file.file_stem()
-            );
+    /// Return [`true`] if files can be created in `dir`, by creating one and
+    /// removing it again. Unlike read-only permissions, this also catches
+    /// directories owned by other users, access control lists, and read-only
+    /// mounts.
+    fn is_writable(dir: &Path) -> bool {
+        let probe = dir.join(format!(".sorterylib-probe-{}", std::process::id()));
+        match fs::OpenOptions::new().write(true).create_new(true).open(&probe) {
+            Ok(_) => fs::remove_file(&probe).is_ok(),
+            Err(_) => false,
         }
+    }
 
-        // Create the new file name
-        let new_file = dir.join(Path::new(&format!(
-            "{}{}.{}",
-            &ctime.format(date_format).to_string(),
-            name_to_preserve,
-            old_file.extension()
-        )));
+    /// Set the permissions and owner of `dir`, which the sorter just created, if
+    /// we're supposed to. See [`Sorter::dir_mode`] and [`Sorter::owner`].
+    fn prepare_created_dir(&self, dir: &Path) -> io::Result<()> {
+        if let Some(mode) = self.dir_mode {
+            execution::set_mode(dir, mode)?;
+        }
+        execution::set_owner(dir, self.owner, self.group)
+    }
 
-        new_file
+    /// Return how each file of `old` gets into the target: the way of the rule it
+    /// meets, if that has one, and `self.transfer` otherwise. Sidecars go the
+    /// same way as their primaries, from `revalidation`.
+    fn get_transfers(&self, old: &[File], revalidation: &Revalidation) -> Vec<TransferMode> {
+        if self.rules.iter().all(|rule| rule.transfer.is_none()) {
+            return vec![self.transfer; old.len()];
+        }
+        let mut transfers: Vec<TransferMode> = old.iter()
+            .enumerate()
+            .map(|(i, path)| match revalidation.primaries.contains_key(&i) {
+                true => self.transfer,
+                false => rules::matching_rule(path, &self.rules).and_then(|rule| rule.transfer).unwrap_or(self.transfer),
+            })
+            .collect();
+        for (sidecar, primary) in &revalidation.primaries {
+            transfers[*sidecar] = transfers[*primary];
+        }
+        transfers
     }
 
-    /// Return a [`File`] representing the renamed version of `path`.
+    /// Carry out the plan of `old` and `new` in `mode`, getting each file into
+    /// the target by its mode in `transfers`, taking the hashes of files that
+    /// are verified from `hashes`, and passing the index, status,
+    /// and error of each file to `on_done` in the order they finish. Files sorted
+    /// into one of the directories of `unmade`, which couldn't be created, fail
+    /// without being moved. Files that are hard links to files before them, in
+    /// `links` (see [`links::hard_links`]), are linked to their new paths rather
+    /// than copied. Files that move are checked again before they are (see
+    /// [`Sorter::revalidate`]), against `revalidation`. Returning
+    /// [`false`] from `on_done` stops the sort, though files that are already
+    /// being moved are still passed to it.
     /// 
-    /// This function is called only if `path` already exists, but can't/shouldn't
-    /// be replaced. The naming logic: if `/path/to/file` already exists, return
-    /// `/path/to/file_2`. If `/path/to/file_2` already exists, return `/path/to/file_3`, etc.
-    fn get_sequential_name(&self, path: &File, vec: &Vec<File>) -> File {
+    /// With more than one of `self.workers`, the files are moved on that many
+    /// threads, in the groups of [`execution::destination_groups`], so that each
+    /// directory's files are still moved in order. Dry runs and interactive sorts
+    /// always run on the calling thread.
+    #[allow(clippy::too_many_arguments)]
+    fn execute_plan(
+        &self,
+        old: &[File],
+        new: &[File],
+        transfers: &[TransferMode],
+        hashes: &checksum::HashQueue,
+        mode: ExecutionMode,
+        unmade: &HashMap<&Path, io::ErrorKind>,
+        links: &HashMap<usize, usize>,
+        revalidation: &Revalidation,
+        mut on_done: impl FnMut(usize, FileStatus, Option<SortError>) -> bool) {
 
-        let mut num = 2;
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        if self.backend == execution::Backend::IoUring && mode == ExecutionMode::Execute
+            && transfers.iter().all(|transfer| *transfer == TransferMode::Move) {
+            return self.execute_plan_uring(old, new, hashes, unmade, links, revalidation, on_done);
+        }
 
-        loop {
+        let leader = |i: usize| links.get(&i).map(|leader| &new[*leader]);
+        let execute = |i: usize| {
+            let checked = if mode.moves_files() { self.revalidate(i, old, new, revalidation) } else { Ok(new[i].copy()) };
+            match checked {
+                Ok(destination) => self.execute_file(&old[i], &destination, transfers[i], hashes, mode, unmade, leader(i), None),
+                Err(outcome) => outcome,
+            }
+        };
+        if self.workers <= 1 || mode == ExecutionMode::Interactive || !mode.moves_files() {
+            for i in 0..old.len() {
+                let (status, error) = execute(i);
+                if !on_done(i, status, error) {
+                    break;
+                }
+            }
+            return;
+        }
 
-            // Create the new path name
-            let mut new_pathbuf = path.to_path_buf();
-            new_pathbuf.set_file_name(&format!(
-                "{}_{}.{}",
-                path.pathbuf.file_stem().unwrap().to_str().unwrap(),
-                num,
-                path.pathbuf.extension().unwrap().to_str().unwrap()
-            ));
-            let new_file = File::from(&new_pathbuf);
+        // Each worker takes the next group of files until there are none left,
+        // and sends back what happened to each file
+        let groups = execution::destination_groups(new, links);
+        let (next, stop) = (AtomicUsize::new(0), AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            for _ in 0..self.workers.min(groups.len()) {
+                let (sender, groups, next, stop, execute) = (sender.clone(), &groups, &next, &stop, &execute);
+                scope.spawn(move || {
+                    while let Some(group) = groups.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        for &i in group {
+                            if stop.load(Ordering::Relaxed) {
+                                return;
+                            }
+                            let (status, error) = execute(i);
+                            if sender.send((i, status, error)).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+            drop(sender);
+            for (i, status, error) in receiver {
+                if !on_done(i, status, error) {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            }
+        });
+    }
 
-            // Check if it exists, and if so, continue the loop
-            if !vec.contains(&new_file) {
-                return new_file;
+    /// The same as [`Sorter::execute_plan`] in [`ExecutionMode::Execute`] mode, but
+    /// renames the files in batches with io_uring. Only the files that io_uring
+    /// fails to rename, as when they are going to another filesystem, are moved
+    /// the usual way. Files are checked again before their batch is renamed.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    #[allow(clippy::too_many_arguments)]
+    fn execute_plan_uring(
+        &self,
+        old: &[File],
+        new: &[File],
+        hashes: &checksum::HashQueue,
+        unmade: &HashMap<&Path, io::ErrorKind>,
+        links: &HashMap<usize, usize>,
+        revalidation: &Revalidation,
+        mut on_done: impl FnMut(usize, FileStatus, Option<SortError>) -> bool) {
+
+        let is_unmade = |i: usize| new[i].as_path().parent().is_some_and(|dir| unmade.contains_key(dir));
+        for start in (0..old.len()).step_by(uring::BATCH_SIZE) {
+            let batch = start..old.len().min(start + uring::BATCH_SIZE);
+            let checked: Vec<_> = batch.clone().map(|i| self.revalidate(i, old, new, revalidation)).collect();
+            let pairs: Vec<(usize, &Path, &Path)> = batch.clone()
+                .zip(&checked)
+                .filter(|(i, _)| !is_unmade(*i) && !links::is_file_symlink(old[*i].as_path()))
+                .filter_map(|(i, checked)| checked.as_ref().ok().map(|new| (i, old[i].as_path(), new.as_path())))
+                .collect();
+            let results = uring::rename_all(&pairs.iter().map(|(_, old, new)| (*old, *new)).collect::<Vec<_>>());
+            let mut renamed: HashMap<usize, io::Result<()>> = pairs.iter().map(|(i, ..)| *i).zip(results).collect();
+
+            // The whole batch has been renamed by now, so every file in it is
+            // passed on, even after the sort is stopped
+            let mut stop = false;
+            for (i, checked) in batch.zip(checked) {
+                let (status, error) = match checked {
+                    Ok(destination) => {
+                        // Files that io_uring failed to rename are moved the usual way
+                        let result = renamed.remove(&i).filter(|result| result.is_ok());
+                        let leader = links.get(&i).map(|leader| &new[*leader]);
+                        self.execute_file(&old[i], &destination, TransferMode::Move, hashes, ExecutionMode::Execute, unmade, leader, result)
+                    },
+                    Err(outcome) => outcome,
+                };
+                stop |= !on_done(i, status, error);
+            }
+            if stop {
+                return;
             }
-            num += 1;
         }
     }
 
-    /// Get the full sorting results for all the files according to the sorting algorithm.
-    fn get_sorting_results(
+    /// Check the file at `i` in the plan of `old` and `new` again before it is
+    /// moved, against its snapshot in `revalidation`, and handle it according to
+    /// `self.stale_policy` if it changed (see [`StalePolicy`]). Sidecars follow
+    /// their primaries: they are left where they are along with them, or sorted
+    /// to wherever they were replanned to. Destinations that were taken in the
+    /// target since the sort was planned, by files that aren't being replaced
+    /// (see `self.conflict_policy`), are checked on disk and given sequential
+    /// names, so that nothing is overwritten. Return the path to move the file
+    /// to, or what happened to it if it isn't moved.
+    fn revalidate(
         &self,
-        source: &File,
-        target: &File,
-        date_format: &str,
-        date_type: &str,
-        preserve_name: &bool,
-        exclude_type: (&str, bool),
-        mut only_type: (&str, bool)) -> Result<(usize, Vec<File>, Vec<File>), PathDoesNotExistError> {
-
-        // Return error messages if either source or target don't exist
-        if !source.exists() {
-            return Err(PathDoesNotExistError {
-                path: source.to_string(),
-            })
+        i: usize,
+        old: &[File],
+        new: &[File],
+        revalidation: &Revalidation) -> Result<File, (FileStatus, Option<SortError>)> {
+
+        // Files opened since the sort was planned are left where they are, and
+        // so are their sidecars
+        if let Some(open_files) = &revalidation.open_files {
+            let mut open_files = open_files.lock().unwrap_or_else(PoisonError::into_inner);
+            open_files.refresh();
+            if open_files.contains(&old[i]) {
+                revalidation.replans.lock().unwrap_or_else(PoisonError::into_inner).skipped.insert(i);
+                return Err((FileStatus::InUse, None));
+            }
         }
-        if !target.exists() {
-            return Err(PathDoesNotExistError {
-                path: target.to_string()
-            })
+        let stale = !matches!(revalidation.snapshots.get(i), Some(Some(snapshot)) if !snapshot.is_stale(&old[i], self.stale_tolerance));
+        let primary = revalidation.primaries.get(&i).copied();
+        let free = |destination: &File| revalidation.replacing.contains(&i) || !destination.exists();
+        if !stale && primary.is_none() && free(&new[i]) {
+            return Ok(new[i].copy());
         }
 
-        // Make sure that the only_type bool is false if it's str is empty
-        if only_type.0 == "" { only_type.1 = false; }
-
-        // The vector to return: a tuple of (old_filename, new_filename)
-        let mut vec_old: Vec<File> = Vec::new();
-        let mut vec_new: Vec<File> = Vec::new();
-
-        // Count the number of items we are going to sort
-        let mut items_to_sort = 0;
-        for entry in WalkDir::new(source.to_string()) {
-
-            let entry = entry.unwrap();
-            if !entry.metadata().expect("Failed to get dir metadata").is_dir() {
-                if self.is_sortable(&File::from(entry.path()), &exclude_type, &only_type) {
-                    items_to_sort += 1;
+        let mut replans = revalidation.replans.lock().unwrap_or_else(PoisonError::into_inner);
+        let skip = match self.stale_policy {
+            StalePolicy::Skip => stale,
+            StalePolicy::Replan => stale && Snapshot::of(&old[i]).is_none(),
+            StalePolicy::Error => false,
+        };
+        if skip || primary.is_some_and(|primary| replans.skipped.contains(&primary)) {
+            replans.skipped.insert(i);
+            return Err((FileStatus::Skipped, None));
+        }
+        if stale && self.stale_policy == StalePolicy::Error {
+            return Err((FileStatus::Failed, Some(SortError::FileChanged(old[i].copy()))));
+        }
+        let mut destination = match primary.and_then(|primary| replans.moved.get(&primary).map(|moved| (primary, moved.copy()))) {
+            Some((primary, moved)) => {
+                if replans.collided.contains(&primary) {
+                    replans.collided.insert(i);
                 }
-            }
+                sidecars::sidecar_destination(&old[i], &old[primary], &moved)
+            },
+            None if stale && primary.is_none() => {
+                self.replan(&old[i], &new[i], &replans.taken).map_err(|error| (FileStatus::Failed, Some(error)))?
+            },
+            None => new[i].copy(),
+        };
+        if !free(&destination) {
+            destination = self.get_sequential_name(&destination, &replans.taken);
+            replans.collided.insert(i);
         }
-        
-        // Sort the everything, excluding the directories
-        for entry in WalkDir::new(source.to_string()) {
-            
-            let entry = entry.unwrap();
-            if !entry.metadata().expect("Failed to get dir metadata").is_dir() {
+        if destination != new[i] {
+            replans.taken.insert(destination.copy());
+            replans.moved.insert(i, destination.copy());
+        }
+        Ok(destination)
+    }
 
-                // The File instance we are sorting
-                let path = File::from(entry.path());
+    /// Return the new destination of `old`, planned to go to `new`, after it
+    /// changed: dated again, and sorted on its own, with a sequential name if the
+    /// destination is in the target or in `taken` already. The directory of the
+    /// destination is created, if we're supposed to create directories.
+    fn replan(&self, old: &File, new: &File, taken: &HashSet<File>) -> Result<File, SortError> {
+        let (time, _) = self.get_file_datetime(old)?;
+        let placeholders = template::placeholders(&self.dir_format);
+        let suspicious = self.audit_date(old, &time).is_some();
+        let (dir, destination) = self.get_destination(old, &time, &placeholders, None, None, suspicious)?;
+        if destination == *new {
+            return Ok(destination);
+        }
+        if self.create_dirs {
+            if let Some((_, kind)) = execution::create_dirs(&[dir.as_path()], |dir| self.prepare_created_dir(dir)).into_iter().next() {
+                return Err(SortError::DirectoryNotCreated(dir, kind));
+            }
+        }
+        match destination.exists() || taken.contains(&destination) {
+            true => Ok(self.get_sequential_name(&destination, taken)),
+            false => Ok(destination),
+        }
+    }
 
-                // Make sure that we sort according to the exclude-type and
-                // only-type arguments
-                if self.is_sortable(&File::from(entry.path()), &exclude_type, &only_type) {
+    /// Carry out the plan for a single file, moving `old` to `new` by `transfer`
+    /// if `mode` moves files (and, in interactive mode, if the move is confirmed), unless its
+    /// directory is one of `unmade`, which couldn't be created (or don't exist,
+    /// without `self.create_dirs`). Return what happened to the file, with the
+    /// error if it failed. If the file is a hard link, `leader` is the new path
+    /// of the first file of its links. If the file was already renamed in a
+    /// batch (see [`execution::Backend`]), `renamed` is the result.
+    #[allow(clippy::too_many_arguments)]
+    fn execute_file(
+        &self,
+        old: &File,
+        new: &File,
+        transfer: TransferMode,
+        hashes: &checksum::HashQueue,
+        mode: ExecutionMode,
+        unmade: &HashMap<&Path, io::ErrorKind>,
+        leader: Option<&File>,
+        renamed: Option<io::Result<()>>) -> (FileStatus, Option<SortError>) {
 
-                    let mut new_file = self.get_new_date_path(&target, &path, date_format, date_type, *preserve_name);
+        #[cfg(feature = "metrics")]
+        let (started, size) = (std::time::Instant::now(), fs::metadata(old.as_path()).map_or(0, |m| m.len()));
 
-                    // Get the sequential file name if new_file already exists
-                    if vec_new.contains(&new_file) {
-                        new_file = self.get_sequential_name(&new_file, &vec_new);
-                    }
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "execute_file", path = %old, destination = %new, status = tracing::field::Empty
+        ).entered();
 
-                    // Push the new and old file names to their respective vectors
-                    vec_old.push(path.copy());
-                    vec_new.push(new_file);
-                }
+        let (status, error) = if mode == ExecutionMode::Interactive
+            && !execution::confirm(old, new, &mut io::stdin().lock(), &mut io::stdout()).unwrap_or(false) {
+            (FileStatus::Skipped, None)
+        } else if mode.moves_files() {
+            let result = match new.as_path().parent().and_then(|dir| unmade.get_key_value(dir)) {
+                Some((dir, _)) if !self.create_dirs => {
+                    Err(SortError::from(PathDoesNotExistError { path: dir.display().to_string() }))
+                },
+                Some((dir, kind)) => Err(SortError::DirectoryNotCreated(File::from(*dir), *kind)),
+                None => self.move_file(old, new, transfer, hashes, mode, leader, renamed),
+            };
+            match result {
+                Ok(()) => (FileStatus::Moved, None),
+                Err(error) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(%error, "failed to move file");
+                    (FileStatus::Failed, Some(error))
+                },
             }
+        } else {
+            (FileStatus::Planned, None)
+        };
+        #[cfg(feature = "metrics")]
+        metrics::record_file(status, size, started.elapsed());
+        #[cfg(feature = "tracing")]
+        {
+            span.record("status", tracing::field::debug(status));
+            tracing::debug!("processed file");
         }
-        Ok((items_to_sort, vec_old, vec_new))
+        (status, error)
     }
 
-    /// Return [`true`] if:
-    /// 1) `path`'s type is in `only_type.0` and `only_type.1` is [`true`]
-    /// 2) `path`'s type is not in `exclude_type.0`, and `only_type.1` is [`false`]
+    /// Set `dir_format` and `date_format` to the date tree of `granularity`, and
+    /// its matching file names. For example:
     /// 
-    /// "Type" refers to the file extension, as in `"jpg"`, `"png"`, etc. `exclude_type`
-    /// and `only_type` correspond with `exclude_type` and `only_type` in [`get_sorting_results`],
-    /// respectively.
-    fn is_sortable(&self, path: &File, exclude_type: &(&str, bool), only_type: &(&str, bool)) -> bool {
-
-        if self.is_type(path, only_type.0) && only_type.1 {
-            return true;
-        } else if !self.is_type(path, exclude_type.0) && !only_type.1 {
-            return true;
-        } else {
-            return false;
-        }
+    /// ```ignore
+    /// use sorterylib::prelude::*;
+    /// 
+    /// let mut sorter = Sorter { ... };
+    /// sorter.set_granularity(Granularity::YearMonth);
+    /// sorter.sort_report(ExecutionMode::Execute)?;
+    /// ```
+    pub fn set_granularity(&mut self, granularity: Granularity) {
+        self.dir_format = String::from(granularity.dir_format());
+        self.date_format = String::from(granularity.date_format());
     }
 
-    /// Return [`true`] if `path`'s type is one of the types in `types`.
-    /// "Type" refers to the file extension, as in `"jpg"`, `"png"`, etc.
-    fn is_type(&self, path: &File, types: &str) -> bool {
-        let mut to_return: bool = false;
-        for t in types.split("-") {
-            if path.extension() == t {
-                to_return = true;
-            }
-        }
-        to_return
+    /// Register `observer` to follow every sort of this [`Sorter`], along with any
+    /// observers that are already registered. Keep a clone of the [`Arc`] to get
+    /// at the observer after the sort. For example:
+    /// 
+    /// ```ignore
+    /// use sorterylib::prelude::*;
+    /// use std::sync::Arc;
+    /// 
+    /// let mut sorter = Sorter { ... };
+    /// let progress_bar = Arc::new(MyProgressBar::new());
+    /// sorter.register_observer(Arc::new(MyLogger));
+    /// sorter.register_observer(progress_bar.clone());
+    /// sorter.sort_report(ExecutionMode::Execute)?;
+    /// ```
+    pub fn register_observer(&mut self, observer: Arc<dyn Observer>) {
+        self.observers.register(observer);
     }
 
-    /// The method that runs the sorting algorithm. Returns the sorting results as
-    /// a tuple of ([`usize`], [`Vec<String>`], [`Vec<String>`]), where `results.0`
-    /// is the number of items sorted, `results.1` contains all the old file names,
-    /// and `results.2` contains all the new file names. The two vectors correspond
-    /// index-wise, so `results.1[0]` is renamed to `results.2[0]`, etc.
-    /// 
-    /// If `dry_run` is [`true`], return the results as usual, but without acutally
-    /// sorting the files. Can be used to verify that the sorting algorithm is working
-    /// as intended. For example:
+    /// Register `provider` to fill in placeholders of its own in `dir_format`
+    /// (and the `dir_format` of `rules`), after any providers that are already
+    /// registered. For example:
     /// 
     /// ```ignore
     /// use sorterylib::prelude::*;
+    /// use std::sync::Arc;
     /// 
-    /// fn main() {
-    /// 
-    ///     // The sorter instance
-    ///     let sorter = Sorter { ... };
+    /// let mut sorter = Sorter { dir_format: String::from("{client}/%Y/"), ... };
+    /// sorter.register_provider(Arc::new(ClientLookup::from_csv("clients.csv")?));
+    /// sorter.sort_report(ExecutionMode::Execute)?;
+    /// ```
+    pub fn register_provider(&mut self, provider: Arc<dyn MetadataProvider>) {
+        self.providers.register(provider);
+    }
+
+    /// Return the kinds of storage the source and target are on, like network
+    /// shares or USB drives. See [`MountInfo`].
+    pub fn mounts(&self) -> MountInfo {
+        MountInfo::of(self.source.as_path(), self.target.as_path())
+    }
+
+    /// Adapt the sorter to the storage the source and target are on (see
+    /// [`Sorter::mounts`]): move several files at once to and from the network,
+    /// and one at a time to and from removable media. Return the [`MountInfo`],
+    /// whose [`MountInfo::execution_mode`] is the mode to sort with, verifying
+    /// copies across the network. For example:
     /// 
-    ///     // Dry run, without actually sorting the files
-    ///     sorter.sort(true);
+    /// ```ignore
+    /// use sorterylib::prelude::*;
     /// 
-    ///     // Acutally sort the files
-    ///     sorter.sort(false);
-    /// }
+    /// let mut sorter = Sorter { ... };
+    /// let mounts = sorter.adapt_to_mounts();
+    /// sorter.sort_report(mounts.execution_mode())?;
     /// ```
-    pub fn sort(&self, dry_run: bool) -> (usize, Vec<File>, Vec<File>) {
-        self.sort_base(dry_run, callback_fn)
+    pub fn adapt_to_mounts(&mut self) -> MountInfo {
+        let mounts = self.mounts();
+        if let Some(workers) = mounts.workers() {
+            self.workers = workers;
+        }
+        mounts
     }
-    
-    /// The base sorting algorithm. This a private function, called by [`Sorter::sort`]
-    /// and [`Sorter::sort_with_callback`].
-    fn sort_base(&self, dry_run: bool, mut callback: impl FnMut((usize, usize, usize))) -> (usize, Vec<File>, Vec<File>) {
-
-        // The variables for calculating percent completed and discerning when next
-        // to call the callback
-        let mut current_percent: usize = 0;
-        let mut last_percent: usize = current_percent;
-
-        // Convert the exclude_type and only_type values to the tuples that
-        // self.get_sorting_results() takes
-        let exclude_type: (&str, bool) = (
-            &self.exclude_type.join("-"),
-            self.exclude_type.len() > 0
-        );
-        let only_type: (&str, bool) = (
-            &self.only_type.join("-"),
-            self.only_type.len() > 0
-        );
-
-        // Get the sorting results
-        let results = self.get_sorting_results(
-            &self.source,
-            &self.target,
-            self.date_format.as_str(),
-            self.date_type.as_str(),
-            &self.preserve_name,
-            exclude_type,
-            only_type
-        );
-
-        match results {
-            Ok(ref result) => result,
-            Err(error) => {
-                println!("{}", error);
-                panic!("Sorting results error.");
-            },
-        };
-        let results = results.unwrap();
-
-        // Make another tuple, so the vectors aren't consumed
-        let r: (usize, &Vec<File>, &Vec<File>) = (results.0, &results.1, &results.2);
 
-        // Loop through all the files in the vectors and sort them, or dry-run if specified
-        for i in 0..r.0 {
+    /// Move, copy, or link `old` to `new`, according to `transfer`, unless it was
+    /// already renamed with the result `renamed`, and stamp it with its original
+    /// location, and give it to its new owner, if we're supposed to. Hard links
+    /// whose `leader` was copied are linked to its copy instead (see [`links`]).
+    /// In [`ExecutionMode::ExecuteVerified`] mode, also make sure that its
+    /// contents are the same after the move as before, as hashed in `hashes`,
+    /// and in `ExecutionMode::ExecuteIntegrity` mode, check it end to end (see
+    /// the `integrity` module) and keep its hash in `hashes`. Symbolic links are
+    /// handled according to `self.symlinks`; the links themselves are only moved
+    /// or copied, and are rewritten later (see [`links::rewrite_symlinks`]).
+    #[allow(clippy::too_many_arguments)]
+    fn move_file(
+        &self,
+        old: &File,
+        new: &File,
+        transfer: TransferMode,
+        hashes: &checksum::HashQueue,
+        mode: ExecutionMode,
+        leader: Option<&File>,
+        renamed: Option<io::Result<()>>) -> Result<(), SortError> {
 
-            // Only actually sort the files if dry_run is not true.
-            if !dry_run {
-                fs::rename(
-                    r.1[i].to_path_buf(),
-                    r.2[i].to_path_buf()
-                ).expect("Failed to rename file.");
+        let remove_old = transfer == TransferMode::Move;
+        let symlink = renamed.is_none() && links::is_file_symlink(old.as_path());
+        if symlink && self.symlinks == SymlinkPolicy::Relink {
+            return Ok(links::copy_symlink(old.as_path(), new.as_path(), remove_old)?);
+        }
+        let hash = match mode {
+            ExecutionMode::ExecuteVerified => Some(hashes.take(old)?),
+            _ => None,
+        };
+        let progress = |copied, total| {
+            self.observers.on_bytes(&ByteProgress { file: old, copied, total });
+            !self.cancel.is_cancelled()
+        };
+        let relinked = match (leader, &renamed) {
+            (Some(leader), None) => links::relink(old.as_path(), new.as_path(), leader.as_path(), remove_old)?,
+            _ => false,
+        };
+        #[cfg(feature = "blake3")]
+        let mut checked = None;
+        let result = match (renamed, transfer) {
+            _ if relinked => Ok(()),
+            // Dereferenced links are replaced with copies of their files
+            (None, _) if symlink => transfer::copy_file(old.as_path(), new.as_path(), progress)
+                .and_then(|()| if remove_old { fs::remove_file(old.as_path()) } else { Ok(()) }),
+            #[cfg(feature = "blake3")]
+            (None, transfer) if mode == ExecutionMode::ExecuteIntegrity => {
+                integrity::transfer(old.as_path(), new.as_path(), transfer, progress).map(|hashes| checked = Some(hashes))
+            },
+            (Some(result), _) => result,
+            (None, TransferMode::Move) => transfer::move_file(old.as_path(), new.as_path(), progress),
+            (None, TransferMode::Copy) => transfer::copy_file(old.as_path(), new.as_path(), progress),
+            (None, TransferMode::Hardlink) => fs::hard_link(old.as_path(), new.as_path()),
+        };
+        match result {
+            Err(error) if error.kind() == io::ErrorKind::Interrupted && self.cancel.is_cancelled() => {
+                return Err(SortError::Cancelled(old.copy()));
+            },
+            result => result?,
+        }
+        #[cfg(feature = "blake3")]
+        if mode == ExecutionMode::ExecuteIntegrity {
+            // Files that weren't copied, like hard links to copies, are only
+            // hashed in the target
+            let written = match checked {
+                Some((read, written)) if read != written => return Err(SortError::VerificationFailed(new.copy())),
+                Some((_, written)) => written,
+                None => checksum::hash_file(new, HashAlgorithm::Blake3)?,
+            };
+            hashes.keep(new, written);
+        }
+        if self.strip_quarantine {
+            transfer::strip_quarantine(new.as_path())?;
+        }
+        origin::record_origin(old, new, &self.origin_record)?;
+        if self.owner.is_some() || self.group.is_some() {
+            let sidecar = origin::sidecar_path(new);
+            for path in [new, &sidecar].into_iter().filter(|path| path.exists()) {
+                execution::set_owner(path.as_path(), self.owner, self.group)?;
             }
-
-            // Calculate the percent, and run the callback if necessary
-            current_percent = ((100 as f32 / r.0 as f32) * i as f32) as usize;
-
-            if current_percent > last_percent {
-                // Run the callback, calculating the completion percent
-                callback(
-                    (
-                        i,
-                        r.0,
-                        ((100 as f32 / r.0 as f32) * i as f32) as usize
-                    )
-                );
+        }
+        if let Some(hash) = hash {
+            if checksum::hash_file(new, self.hash_algorithm)? != hash {
+                return Err(SortError::VerificationFailed(new.copy()));
             }
-
-            last_percent = current_percent;
-
         }
+        Ok(())
+    }
 
-        // Call the callback for the last time
-        callback(
-            (
-                r.0,
-                r.0,
-                100
-            )
-        );
-        (results.0, results.1, results.2)
+    /// The same as [`Sorter::sort_report`], but also takes a function argument that
+    /// is called every time the progress percentage is increased by one, and once
+    /// more when the sort is done. With a [`Sorter::progress_interval`], it is
+    /// called less often, but still once the sort is done. The callback gets
+    /// the [`Progress`] of the sort, including the file that was just processed
+    /// and what happened to it, so that it can show something like "Moving
+    /// IMG_1234.jpg to 2023/07/".
+    /// 
+    /// Example:
+    /// 
+    /// ```ignore
+    /// use sorterylib::prelude::*;
+    /// 
+    /// fn callback(progress: &Progress) {
+    ///     match progress.current {
+    ///         Some((old, new, status)) => println!("{}%: {:?} {} to {}", progress.percent, status, old, new),
+    ///         None => println!("Done."),
+    ///     }
+    /// }
+    /// 
+    /// fn main() {
+    ///     let sorter = Sorter { ... };
+    ///     let report = sorter.sort_report_with_callback(ExecutionMode::Execute, callback);
+    /// }
+    /// ```
+    pub fn sort_report_with_callback(
+        &self, mode: ExecutionMode,
+        callback: impl FnMut(&Progress)) -> Result<SortReport, SortError> {
+
+        self.sort_base(mode, callback)
     }
 
     /// The same as [`Sorter::sort`], but also takes a function argument that is
@@ -466,6 +4435,8 @@ impl Sorter {
     /// of items sorted, and `arg.2` is the percent completed. The callback function
     /// must not return anything.
     /// 
+    /// `mode` is an [`ExecutionMode`], or the old `dry_run` [`bool`].
+    /// 
     /// Example:
     /// 
     /// ```ignore
@@ -481,16 +4452,23 @@ impl Sorter {
     ///     let sorter = Sorter { ... };
     /// 
     ///     // Dry run, without actually sorting the files
-    ///     sorter.sort_with_callback(true, callback);
+    ///     sorter.sort_with_callback(ExecutionMode::DryRun, callback);
     /// 
     ///     // Acutally sort the files
-    ///     sorter.sort_with_callback(false, callback);
+    ///     sorter.sort_with_callback(ExecutionMode::Execute, callback);
     /// }
+    /// ```
+    /// 
+    /// Panics if the sort can't run, or if any file fails to sort. Use
+    /// [`Sorter::sort_report_with_callback`] to handle these errors instead, and to
+    /// get a [`SortReport`] rather than a tuple. This will be removed in the next
+    /// release.
+    #[deprecated(since = "0.4.0", note = "use `Sorter::sort_report_with_callback` instead")]
     pub fn sort_with_callback(
-        &self, dry_run: bool,
-        callback: impl FnMut((usize, usize, usize))) -> (usize, Vec<File>, Vec<File>) {
+        &self, mode: impl Into<ExecutionMode>,
+        mut callback: impl FnMut((usize, usize, usize))) -> (usize, Vec<File>, Vec<File>) {
 
-        self.sort_base(dry_run, callback)
+        Sorter::into_tuple(self.sort_base(mode.into(), |progress| callback(progress.counters())))
     }
 }
 impl FromJson<File> for Sorter {
@@ -501,11 +4479,63 @@ impl FromJson<File> for Sorter {
     /// 
     /// ```ignore
     /// {
+    ///     "audit_dates": false,
+    ///     "backend": "std",
+    ///     "burst_interval": null,
+    ///     "categories": [],
+    ///     "clock_offset": "",
+    ///     "conflict_policy": "rename",
+    ///     "create_dirs": true,
     ///     "date_format": "%Y-%m-%d %Hh%Mm%Ss",
+    ///     "date_sources": [],
     ///     "date_type": "m",
+    ///     "dir_format": "%Y/%m/",
+    ///     "dir_mode": null,
+    ///     "event_gap": null,
+    ///     "exclude_content_type": [],
     ///     "exclude_type": ["png"],
+    ///     "extensionless": "keep",
+    ///     "group": null,
+    ///     "hash_algorithm": "sha256",
+    ///     "hash_workers": 0,
+    ///     "keep_newest": null,
+    ///     "keep_top_folder": false,
+    ///     "location_grid": 1.0,
+    ///     "min_age": null,
+    ///     "min_size": null,
+    ///     "missing_date": "fallback",
+    ///     "notifications": [],
+    ///     "only_content_type": [],
     ///     "only_type": ["json", "py"],
-    ///     "preserve_name": false
+    ///     "origin_record": "off",
+    ///     "owner": null,
+    ///     "persist_counters": false,
+    ///     "progress_interval": {},
+    ///     "preserve_name": false,
+    ///     "quarantine_dir": null,
+    ///     "record_history": false,
+    ///     "regions": [],
+    ///     "remove_identical": false,
+    ///     "reparse_points": "skip",
+    ///     "rules": [],
+    ///     "screenshot_dir": null,
+    ///     "sidecar_types": [],
+    ///     "skip_identical": "off",
+    ///     "skip_open_files": false,
+    ///     "skip_partial_downloads": true,
+    ///     "skip_placeholders": true,
+    ///     "stale_policy": "skip",
+    ///     "stale_tolerance": 0,
+    ///     "strictness": "lenient",
+    ///     "strip_quarantine": false,
+    ///     "suspicious_dir": null,
+    ///     "symlinks": "relink",
+    ///     "time_tie_break": false,
+    ///     "tiny_dir": null,
+    ///     "transfer": "move",
+    ///     "video_filter": {},
+    ///     "workers": 1,
+    ///     "year_start": 1
     /// }
     /// ```
     /// 
@@ -541,15 +4571,7 @@ impl FromJson<File> for Sorter {
         // Get the data from the JSON string
         let data = ConfigData::from_json(&json_string);
 
-        Sorter {
-            source: source,
-            target: target,
-            date_format: data.date_format,
-            date_type: data.date_type,
-            preserve_name: data.preserve_name,
-            exclude_type: data.exclude_type,
-            only_type: data.only_type
-        }
+        Sorter::from_config(data, source, target)
     }
 }
 impl FromJson<String> for Sorter {
@@ -583,14 +4605,6 @@ impl FromJson<String> for Sorter {
         // Get the data from the JSON string
         let data = ConfigData::from_json(&json_string);
 
-        Sorter {
-            source: source,
-            target: target,
-            date_format: data.date_format,
-            date_type: data.date_type,
-            preserve_name: data.preserve_name,
-            exclude_type: data.exclude_type,
-            only_type: data.only_type
-        }
+        Sorter::from_config(data, source, target)
     }
 }
\ No newline at end of file
@@ -0,0 +1,277 @@
+//! Finding the files in the source that are hard links to each other, so that
+//! their contents aren't duplicated by sorting them.
+//!
+//! Hard links are several names for the same file. Renaming them keeps them
+//! linked, but copying them, as in [`TransferMode::Copy`](crate::transfer::TransferMode::Copy)
+//! or when moving them to another filesystem, would make a separate copy for
+//! each name. Instead, the first of them is copied, and the others are linked
+//! to its copy. Hard links are only detected on Unix.
+//!
+//! Symbolic links to files are sorted according to a [`SymlinkPolicy`]. Links
+//! to directories are handled by a [`ReparsePolicy`](crate::reparse::ReparsePolicy)
+//! instead.
+
+use crate::structs::File;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+#[cfg(test)]
+/// Tests for hard links. Each test is named after the function it tests, prefixed
+/// with test.
+mod tests {
+
+    use std::{collections::HashMap, env, fs};
+    use super::{hard_links, relink};
+    use crate::structs::{File, Join};
+
+    #[test]
+    #[cfg(unix)]
+    /// Test [`hard_links`] and [`relink`]
+    fn test_relink() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_relink"));
+        fs::create_dir_all(dir.to_path_buf()).expect("Failed to create test dir.");
+        let files: Vec<File> = ["a.jpg", "b.jpg", "c.jpg"].into_iter().map(|name| dir.join(String::from(name))).collect();
+        fs::write(files[0].to_path_buf(), "a").expect("Failed to write test file.");
+        fs::write(files[1].to_path_buf(), "b").expect("Failed to write test file.");
+        fs::hard_link(files[0].to_path_buf(), files[2].to_path_buf()).expect("Failed to link test file.");
+        assert_eq!(hard_links(&files), HashMap::from([(2, 0)]));
+
+        // A leader that was renamed is still linked, so there is nothing to do
+        let (copy, linked) = (dir.join(String::from("copy.jpg")), dir.join(String::from("linked.jpg")));
+        assert!(!relink(files[2].as_path(), linked.as_path(), files[0].as_path(), false).unwrap());
+
+        // A leader that was copied gets the other names linked to the copy
+        fs::copy(files[0].to_path_buf(), copy.to_path_buf()).expect("Failed to copy test file.");
+        assert!(relink(files[2].as_path(), linked.as_path(), copy.as_path(), true).unwrap());
+        assert!(!files[2].exists());
+        let inode = |file: &File| fs::metadata(file.to_path_buf()).unwrap().ino();
+        assert_eq!(inode(&linked), inode(&copy));
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    /// Test [`super::normalize`] and [`super::relative_path`]
+    fn test_relative_path() {
+        use std::path::Path;
+        use super::{normalize, relative_path};
+
+        assert_eq!(normalize(Path::new("/x/a/./../b/c.txt")), Path::new("/x/b/c.txt"));
+        assert_eq!(relative_path(Path::new("/x/a"), Path::new("/x/b/c.txt")), Path::new("../b/c.txt"));
+        assert_eq!(relative_path(Path::new("/x"), Path::new("/x/c.txt")), Path::new("c.txt"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// Test [`copy_symlink`] and [`rewrite_symlinks`]
+    fn test_rewrite_symlinks() {
+        use std::path::Path;
+        use super::{copy_symlink, is_file_symlink, rewrite_symlinks};
+
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_rewrite_symlinks"));
+        fs::create_dir_all(dir.join(String::from("source")).to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(dir.join(String::from("target/2023")).to_path_buf()).expect("Failed to create test dir.");
+        let old: Vec<File> = ["source/a.txt", "source/b.txt", "source/link.txt"].into_iter().map(|name| dir.join(String::from(name))).collect();
+        let new: Vec<File> = ["target/2023/a.txt", "source/b.txt", "target/2023/link.txt"].into_iter().map(|name| dir.join(String::from(name))).collect();
+        fs::write(old[0].to_path_buf(), "a").expect("Failed to write test file.");
+        std::os::unix::fs::symlink("a.txt", old[2].to_path_buf()).expect("Failed to create test link.");
+        assert!(is_file_symlink(old[2].as_path()) && !is_file_symlink(old[0].as_path()));
+
+        // The link follows the file it leads to
+        fs::rename(old[0].to_path_buf(), new[0].to_path_buf()).expect("Failed to move test file.");
+        copy_symlink(old[2].as_path(), new[2].as_path(), true).expect("Failed to move test link.");
+        assert!(rewrite_symlinks(&old, &new, &[0, 2]).is_empty());
+        assert_eq!(fs::read_link(new[2].to_path_buf()).unwrap(), Path::new("a.txt"));
+
+        // And leads back to files that weren't moved
+        std::os::unix::fs::symlink("b.txt", old[2].to_path_buf()).expect("Failed to create test link.");
+        fs::remove_file(new[2].to_path_buf()).expect("Failed to remove test link.");
+        copy_symlink(old[2].as_path(), new[2].as_path(), true).expect("Failed to move test link.");
+        assert!(rewrite_symlinks(&old, &new, &[2]).is_empty());
+        assert_eq!(fs::read_link(new[2].to_path_buf()).unwrap(), Path::new("../../source/b.txt"));
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+}
+
+/// What to do with symbolic links to files in the source.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkPolicy {
+    /// Leave them where they are.
+    Skip,
+    /// Sort the links themselves, and rewrite where they lead so that they
+    /// still lead to the same file from their new location, or to its new path
+    /// if it is sorted too. Links with absolute paths stay absolute, and ones
+    /// with relative paths stay relative. This is the default.
+    #[default]
+    Relink,
+    /// Sort the files they lead to in their place, replacing each link with a
+    /// copy of its file. The files they lead to are left where they are.
+    Dereference
+}
+
+/// Return [`true`] if `path` is a symbolic link that doesn't lead to a
+/// directory: one to a file, or one that is broken.
+pub fn is_file_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+        && !fs::metadata(path).is_ok_and(|metadata| metadata.is_dir())
+}
+
+/// Make a symbolic link at `new` that leads to the same place as the one at
+/// `old`, and remove `old` if `remove_old` is [`true`], moving it.
+pub fn copy_symlink(old: &Path, new: &Path, remove_old: bool) -> io::Result<()> {
+    symlink(&fs::read_link(old)?, new)?;
+    if remove_old {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// Rewrite the symbolic links of `new` that were moved (by their indices, in
+/// `moved`) from their paths in `old`, so that they lead to the same files as
+/// before, or to their new paths, if they were moved too. Return the errors of
+/// the ones that couldn't be rewritten, by their indices.
+pub fn rewrite_symlinks(old: &[File], new: &[File], moved: &[usize]) -> Vec<(usize, io::Error)> {
+    let sorted: HashMap<&Path, &File> = moved.iter().map(|i| (old[*i].as_path(), &new[*i])).collect();
+    let mut errors = Vec::new();
+    for &i in moved {
+        if !fs::symlink_metadata(new[i].as_path()).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            continue;
+        }
+        let result = fs::read_link(new[i].as_path()).and_then(|link| {
+            let (old_dir, new_dir) = (parent(old[i].as_path()), parent(new[i].as_path()));
+            let target = normalize(&old_dir.join(&link));
+            let target = sorted.get(target.as_path()).map_or(target, |file| file.to_path_buf());
+            let rewritten = if link.is_absolute() { target } else { relative_path(&normalize(new_dir), &target) };
+            if rewritten == link {
+                return Ok(());
+            }
+            fs::remove_file(new[i].as_path())?;
+            symlink(&rewritten, new[i].as_path())
+        });
+        if let Err(error) = result {
+            errors.push((i, error));
+        }
+    }
+    errors
+}
+
+/// Return the directory `path` is in, or the current one if it has none.
+fn parent(path: &Path) -> &Path {
+    path.parent().unwrap_or(Path::new("."))
+}
+
+/// Return `path` with its `.` and `..` components worked out, without looking
+/// at the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Return the relative path from the directory `from` to `to`, both of which
+/// are normalized, as in `../b/c.txt` from `/x/a` to `/x/b/c.txt`.
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let (from, to): (Vec<Component>, Vec<Component>) = (from.components().collect(), to.components().collect());
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut relative: PathBuf = from[common..].iter().map(|_| Component::ParentDir).collect();
+    relative.extend(&to[common..]);
+    relative
+}
+
+#[cfg(unix)]
+/// Make a symbolic link at `link` that leads to `target`.
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+/// Make a symbolic link at `link` that leads to `target`, which, unlike on
+/// Unix, has to be told whether it leads to a file or to a directory.
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    if parent(link).join(target).is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+/// Symbolic links can't be made on this platform.
+fn symlink(_target: &Path, _link: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "symbolic links are not supported"))
+}
+
+/// Return the files of `files` that are hard links to files before them, by
+/// their indices, mapped to the index of the first file of their links.
+#[cfg(unix)]
+pub fn hard_links(files: &[File]) -> HashMap<usize, usize> {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut first: HashMap<(u64, u64), usize> = HashMap::new();
+    let mut links = HashMap::new();
+    for (i, file) in files.iter().enumerate() {
+        let Ok(metadata) = fs::symlink_metadata(file.as_path()) else {
+            continue;
+        };
+        if metadata.is_file() && metadata.nlink() > 1 {
+            let leader = *first.entry((metadata.dev(), metadata.ino())).or_insert(i);
+            if leader != i {
+                links.insert(i, leader);
+            }
+        }
+    }
+    links
+}
+
+/// Hard links are only detected on Unix, so no files are linked.
+#[cfg(not(unix))]
+pub fn hard_links(_files: &[File]) -> HashMap<usize, usize> {
+    HashMap::new()
+}
+
+/// Link `new` to `leader`, the new path of the file `old` is a hard link to, if
+/// `leader` was copied, rather than renamed, and remove `old` if `remove_old` is
+/// [`true`], moving it. Return [`false`] without doing anything if `leader`
+/// wasn't copied, or if it can't be linked to, as from another filesystem, so
+/// that `old` is moved or copied the usual way.
+pub fn relink(old: &Path, new: &Path, leader: &Path, remove_old: bool) -> io::Result<bool> {
+    if !leader.exists() || same_file(old, leader) || fs::hard_link(leader, new).is_err() {
+        return Ok(false);
+    }
+    if remove_old {
+        fs::remove_file(old)?;
+    }
+    Ok(true)
+}
+
+#[cfg(unix)]
+/// Return [`true`] if `a` and `b` are the same file.
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => (a.dev(), a.ino()) == (b.dev(), b.ino()),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+/// Return [`true`] if `a` and `b` are the same file.
+fn same_file(a: &Path, b: &Path) -> bool {
+    matches!((fs::canonicalize(a), fs::canonicalize(b)), (Ok(a), Ok(b)) if a == b)
+}
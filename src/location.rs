@@ -0,0 +1,122 @@
+//! Coarse, offline naming of the places where photos were taken.
+//!
+//! The `{location}` template placeholder is filled in with the name of the first
+//! user-supplied [`Region`] containing a photo's GPS coordinates, or with the
+//! photo's cell in a latitude/longitude grid if no region contains it. No online
+//! service is needed. GPS coordinates are read from EXIF metadata, so this needs
+//! the `exif` feature.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+/// Tests for locations. Each test is named after the function or struct it tests,
+/// prefixed with test.
+mod tests {
+
+    use super::{grid_cell, location_name, Region};
+
+    /// Return a rough square around Rome
+    fn rome() -> Region {
+        Region {
+            name: String::from("Rome"),
+            polygon: vec![(41.7, 12.3), (41.7, 12.7), (42.1, 12.7), (42.1, 12.3)]
+        }
+    }
+
+    #[test]
+    /// Test [`Region`]
+    fn test_region() {
+        assert!(rome().contains(41.9, 12.5));
+        assert!(!rome().contains(45.4, 12.3));
+        assert!(!Region { name: String::from("Empty"), polygon: Vec::new() }.contains(0.0, 0.0));
+    }
+
+    #[test]
+    /// Test [`grid_cell`]
+    fn test_grid_cell() {
+        assert_eq!(grid_cell(41.9, 12.5, 1.0), "41N_12E");
+        assert_eq!(grid_cell(-33.9, -70.6, 1.0), "34S_71W");
+        assert_eq!(grid_cell(41.9, 12.5, 0.5), "41.5N_12.5E");
+        assert_eq!(grid_cell(41.9, 12.5, 0.1), "41.9N_12.5E");
+        assert_eq!(grid_cell(41.95, 12.55, 0.1), "41.9N_12.5E");
+        assert_eq!(grid_cell(-33.9, -70.6, 0.1), "33.9S_70.6W");
+        assert_eq!(grid_cell(41.9, 12.5, 0.25), "41.75N_12.50E");
+    }
+
+    #[test]
+    /// Test [`location_name`]
+    fn test_location_name() {
+        let regions = vec![rome()];
+        assert_eq!(location_name(41.9, 12.5, &regions, 1.0), "Rome");
+        assert_eq!(location_name(45.4, 12.3, &regions, 1.0), "45N_12E");
+    }
+}
+
+/// The value of the `{location}` placeholder for files without GPS coordinates.
+pub const UNKNOWN_LOCATION: &str = "unknown_location";
+
+/// A named region on the map, used to name the locations of photos taken inside it.
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Region {
+    /// The name of the region, as in `"Rome"`.
+    pub name: String,
+    /// The corners of the region's outline, as `(latitude, longitude)` pairs in
+    /// decimal degrees. The outline is closed automatically.
+    pub polygon: Vec<(f64, f64)>
+}
+impl Region {
+
+    /// Return [`true`] if the point at `lat`, `lon` is inside our polygon.
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+
+        // Cast a ray from the point, and count the edges it crosses
+        let mut inside = false;
+        let mut j = match self.polygon.len() {
+            0 => return false,
+            n => n - 1,
+        };
+        for i in 0..self.polygon.len() {
+            let (lat_i, lon_i) = self.polygon[i];
+            let (lat_j, lon_j) = self.polygon[j];
+            if (lat_i > lat) != (lat_j > lat)
+                && lon < (lon_j - lon_i) * (lat - lat_i) / (lat_j - lat_i) + lon_i {
+                inside = !inside;
+            }
+            j = i;
+        }
+        inside
+    }
+}
+
+/// Return the name of the cell of a `size`-degree latitude/longitude grid that
+/// contains the point at `lat`, `lon`, as in `41N_12E`. Cells are named after
+/// their south-west corner, with as many decimal places as `size` has.
+pub fn grid_cell(lat: f64, lon: f64, size: f64) -> String {
+
+    // The grid is scaled to whole numbers, so that sizes floats can't hold
+    // exactly, like 0.1, still give the exact corners of their cells
+    let decimals = size.to_string().split_once('.').map_or(0, |(_, fraction)| fraction.len());
+    let scale = 10_f64.powi(decimals as i32);
+    let step = (size * scale).round();
+    let corner = |degrees: f64| (degrees * scale / step).floor() * step / scale;
+    let (lat, lon) = (corner(lat), corner(lon));
+    format!(
+        "{:.*}{}_{:.*}{}",
+        decimals,
+        lat.abs(),
+        if lat < 0.0 { "S" } else { "N" },
+        decimals,
+        lon.abs(),
+        if lon < 0.0 { "W" } else { "E" }
+    )
+}
+
+/// Return the location name of the point at `lat`, `lon`: the name of the first of
+/// `regions` that contains it, or its cell in a `grid`-degree grid otherwise.
+pub fn location_name(lat: f64, lon: f64, regions: &[Region], grid: f64) -> String {
+    match regions.iter().find(|r| r.contains(lat, lon)) {
+        Some(region) => region.name.clone(),
+        None => grid_cell(lat, lon, grid),
+    }
+}
@@ -0,0 +1,69 @@
+//! Keeping sorts into the same target from running at once. See [`TargetLock`].
+//!
+//! Two sorts into one target at the same time, as when a scheduled sort runs
+//! into the last one, or a sort is started twice, would plan their sequential
+//! names from the same target, and race to take them. Every sort that moves
+//! files holds an advisory lock on [`LOCK_FILE_NAME`] in the target while it
+//! runs, and a sort that finds it held fails right away with
+//! [`SortError::AlreadyRunning`], rather than waiting. The lock is released
+//! when the sort is over, and by the operating system if the sort crashes, so
+//! the target is never left locked. The lock file itself stays in the target.
+
+use crate::errors::SortError;
+use crate::structs::{File, Join};
+use std::{fs, io};
+
+#[cfg(test)]
+/// Tests for locking targets. Each test is named after the struct it tests,
+/// prefixed with test.
+mod tests {
+
+    use super::{TargetLock, LOCK_FILE_NAME};
+    use crate::errors::SortError;
+    use crate::testing::Sandbox;
+
+    #[test]
+    /// Test [`TargetLock`]
+    fn test_targetlock() {
+        let sandbox = Sandbox::new(&[]).expect("Failed to create sandbox.");
+        let target = sandbox.target();
+
+        // Only one lock is held at once, and the file stays after it is released
+        let lock = TargetLock::acquire(&target).expect("Failed to lock target.");
+        match TargetLock::acquire(&target) {
+            Err(SortError::AlreadyRunning(path)) => assert_eq!(path, sandbox.path(&format!("target/{}", LOCK_FILE_NAME))),
+            result => panic!("Expected AlreadyRunning, got {:?}", result),
+        }
+        drop(lock);
+        TargetLock::acquire(&target).expect("Failed to lock target again.");
+        assert_eq!(sandbox.files(), vec![format!("target/{}", LOCK_FILE_NAME)]);
+    }
+}
+
+/// The name of the lock file in the target.
+pub const LOCK_FILE_NAME: &str = ".sortery.lock";
+
+/// The lock on a target, held by a sort into it until this is dropped.
+#[derive(Debug)]
+pub struct TargetLock {
+    /// The open lock file, or [`None`] if the filesystem doesn't support locks.
+    _file: Option<fs::File>
+}
+impl TargetLock {
+
+    /// Lock `target`, creating its lock file if there isn't one yet. Fail with
+    /// [`SortError::AlreadyRunning`] if another sort holds the lock. On
+    /// filesystems that don't support locks, the target isn't locked at all.
+    pub fn acquire(target: &File) -> Result<TargetLock, SortError> {
+        let path = target.join(String::from(LOCK_FILE_NAME));
+        let file = fs::OpenOptions::new().create(true).truncate(false).write(true).open(path.as_path())?;
+        match file.try_lock() {
+            Ok(()) => Ok(TargetLock { _file: Some(file) }),
+            Err(fs::TryLockError::WouldBlock) => Err(SortError::AlreadyRunning(path)),
+            Err(fs::TryLockError::Error(error)) if error.kind() == io::ErrorKind::Unsupported => {
+                Ok(TargetLock { _file: None })
+            },
+            Err(fs::TryLockError::Error(error)) => Err(SortError::from(error)),
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Manifests of sorted files, for checking that an archive is still intact.
+//!
+//! A [`Manifest`] records the size and hash of every file in a directory tree.
+//! It can be saved as JSON after sorting, and checked against the tree later
+//! with [`Sorter::verify`](crate::Sorter::verify), which reports every file that
+//! went missing, changed, or appeared since.
+
+use crate::checksum::{self, HashAlgorithm};
+use crate::counters::COUNTERS_FILE_NAME;
+use crate::history::HISTORY_FILE_NAME;
+use crate::lock::LOCK_FILE_NAME;
+use crate::structs::{File, Join};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use walkdir::WalkDir;
+
+#[cfg(test)]
+/// Tests for manifests. Each test is named after the function or struct it tests,
+/// prefixed with test.
+mod tests {
+
+    use std::{env, fs};
+    use super::{Manifest, VerificationReport};
+    use crate::checksum::HashAlgorithm;
+    use crate::structs::{File, Join};
+
+    #[test]
+    /// Test [`Manifest`] and [`Manifest::verify`]
+    fn test_manifest() {
+
+        // A small tree to make a manifest of
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_manifest"));
+        fs::create_dir_all(dir.join(String::from("2022")).to_path_buf()).expect("Failed to create test dir.");
+        for name in ["2022/a.txt", "2022/b.txt", "2022/c.txt"] {
+            fs::write(dir.join(String::from(name)).to_path_buf(), name).expect("Failed to write test file.");
+        }
+        let manifest = Manifest::of_dir(&dir, HashAlgorithm::Xxh3, 2).expect("Failed to make manifest.");
+        assert_eq!(manifest.entries.len(), 3);
+        assert_eq!(manifest.entries[0].path, "2022/a.txt");
+
+        // Saving and loading gives the same manifest
+        let path = File::from(env::temp_dir()).join(String::from("sorterylib_test_manifest.json"));
+        manifest.save(&path).expect("Failed to save manifest.");
+        assert_eq!(Manifest::load(&path).expect("Failed to load manifest."), manifest);
+        fs::remove_file(path.to_path_buf()).expect("Failed to remove test file.");
+        assert!(manifest.verify(&dir, 0).expect("Failed to verify.").is_ok());
+
+        // Remove one file, change another, and add a new one
+        fs::remove_file(dir.join(String::from("2022/a.txt")).to_path_buf()).expect("Failed to remove test file.");
+        fs::write(dir.join(String::from("2022/b.txt")).to_path_buf(), "changed").expect("Failed to write test file.");
+        fs::write(dir.join(String::from("2022/c.txt")).to_path_buf(), "2022/c.tx!").expect("Failed to write test file.");
+        fs::write(dir.join(String::from("new.txt")).to_path_buf(), "new").expect("Failed to write test file.");
+        let report = manifest.verify(&dir, 2).expect("Failed to verify.");
+        assert_eq!(report, VerificationReport {
+            checked: 3,
+            missing: vec![dir.join(String::from("2022/a.txt"))],
+            size_mismatches: vec![dir.join(String::from("2022/b.txt"))],
+            hash_mismatches: vec![dir.join(String::from("2022/c.txt"))],
+            unexpected: vec![dir.join(String::from("new.txt"))]
+        });
+        assert!(!report.is_ok());
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+}
+
+/// The name of the manifest that sorts in
+/// [`ExecutionMode::ExecuteIntegrity`](crate::execution::ExecutionMode) mode keep
+/// in the target. See the `integrity` module, which needs the `blake3` feature.
+pub const INTEGRITY_MANIFEST_NAME: &str = ".sortery.integrity.json";
+
+/// The record of one file in a [`Manifest`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// The path of the file, relative to the root of the manifest, with `/`
+    /// separators.
+    pub path: String,
+    /// The size of the file, in bytes.
+    pub size: u64,
+    /// The hash of the contents of the file.
+    pub hash: String
+}
+
+/// The sizes and hashes of all the files in a directory tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub struct Manifest {
+    /// The algorithm the files were hashed with.
+    pub algorithm: HashAlgorithm,
+    /// The files, sorted by path.
+    pub entries: Vec<ManifestEntry>
+}
+impl Manifest {
+
+    /// Return a manifest of all the files in `dir` and its subdirectories, hashed
+    /// with `algorithm` on `workers` threads besides the calling one (see
+    /// [`checksum::hash_files`]).
+    pub fn of_dir(dir: &File, algorithm: HashAlgorithm, workers: usize) -> io::Result<Manifest> {
+        let paths = walk_files(dir)?;
+        let mut entries = Vec::new();
+        for (path, hash) in paths.iter().zip(checksum::hash_files(&paths, algorithm, workers)) {
+            entries.push(ManifestEntry {
+                path: relative_path(dir, path),
+                size: path.pathbuf.metadata()?.len(),
+                hash: hash?
+            });
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(Manifest { algorithm, entries })
+    }
+
+    /// Return the manifest saved as JSON in `path`.
+    pub fn load(path: &File) -> io::Result<Manifest> {
+        let json = fs::read_to_string(&path.pathbuf)?;
+        serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Save the manifest as JSON in `path`.
+    pub fn save(&self, path: &File) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(&path.pathbuf, json)
+    }
+
+    /// Check the files in `dir` against the manifest, hashing them on `workers`
+    /// threads besides the calling one, and return a [`VerificationReport`] of
+    /// everything that doesn't match.
+    pub fn verify(&self, dir: &File, workers: usize) -> io::Result<VerificationReport> {
+
+        // Only the files that are still the same size are hashed
+        let mut report = VerificationReport::default();
+        let mut expected = HashSet::new();
+        let mut same_size = Vec::new();
+        for entry in &self.entries {
+            expected.insert(entry.path.as_str());
+            let path = dir.join(entry.path.clone());
+            report.checked += 1;
+            match path.pathbuf.metadata() {
+                Err(_) => report.missing.push(path),
+                Ok(metadata) if metadata.len() != entry.size => report.size_mismatches.push(path),
+                Ok(_) => same_size.push((path, entry)),
+            }
+        }
+        let paths: Vec<File> = same_size.iter().map(|(path, _)| path.copy()).collect();
+        for ((path, entry), hash) in same_size.into_iter().zip(checksum::hash_files(&paths, self.algorithm, workers)) {
+            if hash? != entry.hash {
+                report.hash_mismatches.push(path);
+            }
+        }
+
+        // Look for files that aren't in the manifest
+        for path in walk_files(dir)? {
+            if !expected.contains(relative_path(dir, &path).as_str()) {
+                report.unexpected.push(path);
+            }
+        }
+        report.unexpected.sort_by(|a, b| a.pathbuf.cmp(&b.pathbuf));
+        Ok(report)
+    }
+}
+
+/// The results of checking a directory tree against a [`Manifest`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct VerificationReport {
+    /// The number of files in the manifest that were checked.
+    pub checked: usize,
+    /// The files in the manifest that don't exist anymore.
+    pub missing: Vec<File>,
+    /// The files whose sizes don't match the manifest.
+    pub size_mismatches: Vec<File>,
+    /// The files whose sizes match the manifest, but whose hashes don't.
+    pub hash_mismatches: Vec<File>,
+    /// The files that aren't in the manifest.
+    pub unexpected: Vec<File>
+}
+impl VerificationReport {
+
+    /// Return [`true`] if every file matched the manifest, and there were no
+    /// unexpected files.
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty()
+            && self.size_mismatches.is_empty()
+            && self.hash_mismatches.is_empty()
+            && self.unexpected.is_empty()
+    }
+}
+
+/// Return all the files in `dir` and its subdirectories, except the lock,
+/// counters, history, and integrity manifest files of the sorts into `dir` (see
+/// [`lock`](crate::lock), [`counters`](crate::counters),
+/// [`history`](crate::history), and [`INTEGRITY_MANIFEST_NAME`]).
+pub(crate) fn walk_files(dir: &File) -> io::Result<Vec<File>> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(&dir.pathbuf) {
+        let entry = entry?;
+        if entry.depth() == 1 && [LOCK_FILE_NAME, COUNTERS_FILE_NAME, HISTORY_FILE_NAME, INTEGRITY_MANIFEST_NAME].iter().any(|name| entry.file_name() == *name) {
+            continue;
+        }
+        if entry.file_type().is_file() {
+            files.push(File::from(entry.path()));
+        }
+    }
+    Ok(files)
+}
+
+/// Return the path of `path` relative to `dir`, with `/` separators.
+pub(crate) fn relative_path(dir: &File, path: &File) -> String {
+    let relative = path.pathbuf.strip_prefix(&dir.pathbuf).unwrap_or(&path.pathbuf);
+    relative.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
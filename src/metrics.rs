@@ -0,0 +1,72 @@
+//! Metrics of sorts, emitted through the [`metrics`](::metrics) facade. Needs the
+//! `metrics` feature.
+//!
+//! Every sort records what it does with the recorder that is installed, like a
+//! Prometheus exporter; without one, nothing is recorded. The metrics are:
+//!
+//! - `sorterylib_files_planned_total`: the files that each sort set out to sort
+//! - `sorterylib_files_moved_total`: the files that were moved
+//! - `sorterylib_files_skipped_total`: the files whose moves weren't confirmed
+//! - `sorterylib_errors_total`: the files that failed to sort
+//! - `sorterylib_bytes_moved_total`: the size of the files that were moved
+//! - `sorterylib_file_duration_seconds`: a histogram of how long each file took
+
+use crate::report::FileStatus;
+use ::metrics::{counter, histogram};
+use std::time::Duration;
+
+#[cfg(test)]
+/// Tests for metrics. Each test is named after the function it tests, prefixed
+/// with test.
+mod tests {
+
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use std::time::Duration;
+    use super::{record_file, record_start};
+    use crate::report::FileStatus;
+
+    #[test]
+    /// Test [`record_start`] and [`record_file`]
+    fn test_record_file() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        ::metrics::with_local_recorder(&recorder, || {
+            record_start(3);
+            record_file(FileStatus::Moved, 100, Duration::from_millis(5));
+            record_file(FileStatus::Moved, 20, Duration::from_millis(5));
+            record_file(FileStatus::Failed, 50, Duration::from_millis(1));
+        });
+
+        let metrics: Vec<(String, DebugValue)> = snapshotter.snapshot().into_vec().into_iter()
+            .map(|(key, _, _, value)| (key.key().name().to_string(), value))
+            .collect();
+        let value = |name: &str| metrics.iter().find(|(n, _)| n == name).map(|(_, v)| v);
+        assert_eq!(value("sorterylib_files_planned_total"), Some(&DebugValue::Counter(3)));
+        assert_eq!(value("sorterylib_files_moved_total"), Some(&DebugValue::Counter(2)));
+        assert_eq!(value("sorterylib_bytes_moved_total"), Some(&DebugValue::Counter(120)));
+        assert_eq!(value("sorterylib_errors_total"), Some(&DebugValue::Counter(1)));
+        assert_eq!(value("sorterylib_files_skipped_total"), None);
+        assert!(matches!(value("sorterylib_file_duration_seconds"), Some(DebugValue::Histogram(h)) if h.len() == 3));
+    }
+}
+
+/// Record that a sort set out to sort `total` files.
+pub fn record_start(total: usize) {
+    counter!("sorterylib_files_planned_total").increment(total as u64);
+}
+
+/// Record that a file of `size` bytes was processed with the result `status`,
+/// taking `elapsed`. Files that were only planned, in a dry run, just count
+/// towards the duration.
+pub fn record_file(status: FileStatus, size: u64, elapsed: Duration) {
+    match status {
+        FileStatus::Moved => {
+            counter!("sorterylib_files_moved_total").increment(1);
+            counter!("sorterylib_bytes_moved_total").increment(size);
+        },
+        FileStatus::Skipped | FileStatus::InUse => counter!("sorterylib_files_skipped_total").increment(1),
+        FileStatus::Failed => counter!("sorterylib_errors_total").increment(1),
+        FileStatus::Planned => (),
+    }
+    histogram!("sorterylib_file_duration_seconds").record(elapsed.as_secs_f64());
+}
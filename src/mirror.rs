@@ -0,0 +1,149 @@
+//! Mirroring the source into the target. See [`Sorter::mirror`](crate::Sorter::mirror).
+//!
+//! A mirror keeps the target's dated tree in step with a source that keeps
+//! changing, like a camera folder synced from a phone. Every mirror copies the
+//! new files of the source into the target, and leaves out the ones that are
+//! already there. The files in the target whose originals, as recorded by
+//! [`Sorter::origin_record`](crate::Sorter::origin_record), have disappeared
+//! from the source are orphans, which are either flagged in the report or pruned
+//! from the target. See [`OrphanPolicy`].
+
+use crate::errors::SortError;
+use crate::origin::{self, ORIGIN_SIDECAR_SUFFIX};
+use crate::report::{SortReport, SortWarning};
+use crate::structs::File;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::{fs, io};
+use walkdir::WalkDir;
+
+#[cfg(test)]
+/// Tests for mirroring. Each test is named after the function it tests, prefixed
+/// with test.
+mod tests {
+
+    use std::{env, fs};
+    use super::{find_orphans, prune};
+    use crate::origin::{self, OriginRecord};
+    use crate::structs::{File, Join};
+
+    #[test]
+    /// Test [`find_orphans`] and [`prune`]
+    fn test_find_orphans() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_find_orphans"));
+        let (source, target) = (dir.join(String::from("source")), dir.join(String::from("target")));
+        fs::create_dir_all(source.to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(target.to_path_buf()).expect("Failed to create test dir.");
+
+        // Copies of a file that is still in the source, of one that isn't, and
+        // of one from somewhere else entirely
+        let [kept, gone, other] = ["kept.jpg", "gone.jpg", "other.jpg"].map(|name| target.join(String::from(name)));
+        fs::write(source.join(String::from("kept.jpg")).to_path_buf(), "kept").expect("Failed to write test file.");
+        for (copy, origin) in [(&kept, &source), (&gone, &source), (&other, &dir)] {
+            fs::write(copy.to_path_buf(), "copy").expect("Failed to write test file.");
+            let name = copy.pathbuf.file_name().unwrap().to_string_lossy().to_string();
+            origin::record_origin(&origin.join(name), copy, &OriginRecord::Sidecar).expect("Failed to record origin.");
+        }
+
+        let orphans = find_orphans(&source, &target).expect("Failed to find orphans.");
+        assert_eq!(orphans, vec![(gone.copy(), source.join(String::from("gone.jpg")))]);
+        prune(&gone).expect("Failed to prune orphan.");
+        assert!(!gone.exists() && !origin::sidecar_path(&gone).exists());
+        assert!(find_orphans(&source, &target).expect("Failed to find orphans.").is_empty());
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+}
+
+/// What to do with orphans: files in the target whose originals have
+/// disappeared from the source.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanPolicy {
+    /// List them in the [`MirrorReport`], and leave them in the target. This is
+    /// the default.
+    #[default]
+    Flag,
+    /// Remove them from the target, along with their origin sidecars, and list
+    /// them in the [`MirrorReport`] as pruned. Nothing is removed in a dry run.
+    Prune
+}
+
+/// What a mirror did, or would do in a dry run.
+#[derive(Debug, Default)]
+pub struct MirrorReport {
+    /// The report of copying the files of the source into the target.
+    pub sort: SortReport,
+    /// The orphans in the target, with the paths of their originals that have
+    /// disappeared from the source.
+    pub orphans: Vec<(File, File)>,
+    /// The orphans that were removed from the target, with
+    /// [`OrphanPolicy::Prune`].
+    pub pruned: Vec<File>,
+    /// The orphans that couldn't be removed from the target.
+    pub failures: Vec<(File, SortError)>
+}
+impl MirrorReport {
+
+    /// Return the old and new paths of the files that were copied into the
+    /// target, without replacing a file there.
+    pub fn added(&self) -> impl Iterator<Item = (&File, &File)> {
+        self.sort.sorted().filter(|(old, _)| !self.replaced(old))
+    }
+
+    /// Return the old and new paths of the files that replaced older versions
+    /// of themselves in the target, with
+    /// [`ConflictPolicy::Update`](crate::conflicts::ConflictPolicy::Update).
+    pub fn updated(&self) -> impl Iterator<Item = (&File, &File)> {
+        self.sort.sorted().filter(|(old, _)| self.replaced(old))
+    }
+
+    /// Return the old paths of the files that were already in the target, with
+    /// the files in the target.
+    pub fn unchanged(&self) -> impl Iterator<Item = (&File, &File)> {
+        self.sort.present.iter().map(|(old, new)| (old, new))
+    }
+
+    /// Return [`true`] if no files failed to be copied or pruned.
+    pub fn is_ok(&self) -> bool {
+        self.sort.is_ok() && self.failures.is_empty()
+    }
+
+    /// Return [`true`] if `old` replaced a file in the target.
+    fn replaced(&self, old: &File) -> bool {
+        self.sort.warnings.iter().any(|(file, warning)| file == old && matches!(warning, SortWarning::Replaced(_)))
+    }
+}
+
+/// Return the files in `target` whose recorded originals were in `source`, but
+/// aren't anymore, with the paths of their originals, sorted by path. Files
+/// without recorded originals, or with originals elsewhere, aren't orphans.
+pub fn find_orphans(source: &File, target: &File) -> io::Result<Vec<(File, File)>> {
+    let mut orphans = Vec::new();
+    for entry in WalkDir::new(&target.pathbuf) {
+        let entry = entry?;
+        if !entry.file_type().is_file() || entry.file_name().to_string_lossy().ends_with(ORIGIN_SIDECAR_SUFFIX) {
+            continue;
+        }
+        let file = File::from(entry.path());
+        if let Some(original) = origin::read_origin(&file) {
+            if Path::new(&original).starts_with(&source.pathbuf) && !Path::new(&original).exists() {
+                orphans.push((file, File::from(original.as_str())));
+            }
+        }
+    }
+    orphans.sort_by(|a, b| a.0.pathbuf.cmp(&b.0.pathbuf));
+    Ok(orphans)
+}
+
+/// Remove the orphan `file` from the target, along with its origin sidecar, if
+/// it has one.
+pub fn prune(file: &File) -> io::Result<()> {
+    fs::remove_file(file.as_path())?;
+    let sidecar = origin::sidecar_path(file);
+    if sidecar.exists() {
+        fs::remove_file(sidecar.as_path())?;
+    }
+    Ok(())
+}
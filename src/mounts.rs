@@ -0,0 +1,269 @@
+//! Detecting what kind of storage the source and target are on, so that sorts
+//! can adapt to it. See [`MountInfo`] and
+//! [`Sorter::adapt_to_mounts`](crate::Sorter::adapt_to_mounts).
+//!
+//! How this works depends on the platform. On Linux, the mount of a path is
+//! looked up in `/proc/self/mountinfo`: network filesystems are recognized by
+//! their types (see [`NETWORK_FILESYSTEMS`]), and removable media by their
+//! block devices in `/sys`, including USB drives that don't call themselves
+//! removable. On Windows, the type of the drive is asked for, and UNC paths are
+//! network shares. On other platforms, the kind of mount is unknown.
+
+use crate::execution::ExecutionMode;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+#[cfg(target_os = "linux")]
+use std::path::PathBuf;
+
+#[cfg(test)]
+/// Tests for detecting mounts. Each test is named after the function or struct
+/// it tests, prefixed with test.
+mod tests {
+
+    use std::env;
+    use super::{MountInfo, MountKind};
+    use crate::execution::ExecutionMode;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    /// Test [`super::parse_mountinfo`] and [`super::kind_of`]
+    fn test_parse_mountinfo() {
+        use std::path::Path;
+        use super::{kind_of, parse_mountinfo};
+
+        let line = "36 35 98:0 / /mnt/my\\040photos rw,noatime master:1 - nfs4 nas:/photos rw";
+        let (point, fs_type, source) = parse_mountinfo(line).unwrap();
+        assert_eq!(point, Path::new("/mnt/my photos"));
+        assert_eq!(kind_of(fs_type, source), MountKind::Network);
+        assert_eq!(kind_of("cifs", "//nas/photos"), MountKind::Network);
+        assert_eq!(kind_of("tmpfs", "tmpfs"), MountKind::Local);
+    }
+
+    #[test]
+    /// Test [`MountInfo`]
+    fn test_mount_info() {
+        let info = MountInfo::of(&env::temp_dir(), &env::temp_dir());
+        assert!(info.same_device);
+        assert_ne!(info.source, MountKind::Network);
+
+        // Networks are verified and moved to in parallel, and removable media
+        // get one file at a time
+        let network = MountInfo { source: MountKind::Local, target: MountKind::Network, same_device: false };
+        assert_eq!(network.execution_mode(), ExecutionMode::ExecuteVerified);
+        assert_eq!(network.workers(), Some(super::NETWORK_WORKERS));
+        let usb = MountInfo { target: MountKind::Removable, ..network };
+        assert_eq!(usb.execution_mode(), ExecutionMode::Execute);
+        assert_eq!(usb.workers(), Some(1));
+    }
+}
+
+/// The types of the network filesystems recognized on Linux, including the
+/// FUSE ones for SSH, WebDAV, and cloud storage.
+#[cfg(target_os = "linux")]
+pub const NETWORK_FILESYSTEMS: [&str; 16] = [
+    "9p", "afs", "ceph", "cifs", "davfs", "fuse.davfs2", "fuse.rclone", "fuse.s3fs",
+    "fuse.sshfs", "glusterfs", "ncpfs", "nfs", "nfs4", "smb3", "smbfs", "sshfs"
+];
+
+/// The number of files moved at once by [`Sorter::adapt_to_mounts`](crate::Sorter::adapt_to_mounts)
+/// when the source or target is on the network, where moves are bound by
+/// latency rather than bandwidth.
+pub const NETWORK_WORKERS: usize = 4;
+
+/// The kind of storage a path is on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MountKind {
+    /// A disk of this computer.
+    Local,
+    /// A network share, like an NFS or SMB share on a NAS.
+    Network,
+    /// Removable media, like a USB drive, an SD card, or a CD.
+    Removable,
+    /// The kind couldn't be detected, as on platforms where it isn't supported.
+    Unknown
+}
+
+/// The kinds of storage the source and target of a sort are on, and what they
+/// mean for the sort.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MountInfo {
+    /// The kind of storage the source is on.
+    pub source: MountKind,
+    /// The kind of storage the target is on.
+    pub target: MountKind,
+    /// If [`true`], the source and target are on the same device, so files are
+    /// moved by renaming them, and can be hard linked. If [`false`], they are
+    /// copied, and can't be.
+    pub same_device: bool
+}
+impl MountInfo {
+
+    /// Return the [`MountInfo`] of sorting from `source` to `target`.
+    pub fn of(source: &Path, target: &Path) -> MountInfo {
+        MountInfo { source: mount_kind(source), target: mount_kind(target), same_device: same_device(source, target) }
+    }
+
+    /// Return [`true`] if either the source or the target is of `kind`.
+    pub fn either_is(&self, kind: MountKind) -> bool {
+        self.source == kind || self.target == kind
+    }
+
+    /// Return the [`ExecutionMode`] to sort with: [`ExecutionMode::ExecuteVerified`]
+    /// if the files are copied to or from the network, where they are most
+    /// likely to be damaged on the way, and [`ExecutionMode::Execute`] otherwise.
+    pub fn execution_mode(&self) -> ExecutionMode {
+        if !self.same_device && self.either_is(MountKind::Network) {
+            ExecutionMode::ExecuteVerified
+        } else {
+            ExecutionMode::Execute
+        }
+    }
+
+    /// Return the number of files to move at once, if the storage calls for a
+    /// particular one: `1` for removable media, which are slowed down by
+    /// moving several files at once, and [`NETWORK_WORKERS`] for the network.
+    /// Return [`None`] for local disks.
+    pub fn workers(&self) -> Option<usize> {
+        if self.either_is(MountKind::Removable) {
+            Some(1)
+        } else if self.either_is(MountKind::Network) {
+            Some(NETWORK_WORKERS)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+/// Return the kind of storage `path` is on.
+pub fn mount_kind(path: &Path) -> MountKind {
+    let (Ok(path), Ok(mountinfo)) = (fs::canonicalize(path), fs::read_to_string("/proc/self/mountinfo")) else {
+        return MountKind::Unknown;
+    };
+    // The mount a path is on is the deepest one it is inside
+    mountinfo.lines()
+        .filter_map(parse_mountinfo)
+        .filter(|(point, _, _)| path.starts_with(point))
+        .max_by_key(|(point, _, _)| point.as_os_str().len())
+        .map_or(MountKind::Unknown, |(_, fs_type, source)| kind_of(fs_type, source))
+}
+
+#[cfg(windows)]
+/// Return the kind of storage `path` is on.
+pub fn mount_kind(path: &Path) -> MountKind {
+    use std::path::{Component, Prefix};
+    use windows_sys::Win32::Storage::FileSystem::GetDriveTypeW;
+
+    // The types of drives, as returned by `GetDriveTypeW`
+    const DRIVE_REMOVABLE: u32 = 2;
+    const DRIVE_FIXED: u32 = 3;
+    const DRIVE_REMOTE: u32 = 4;
+    const DRIVE_CDROM: u32 = 5;
+    const DRIVE_RAMDISK: u32 = 6;
+
+    let Ok(path) = fs::canonicalize(path) else {
+        return MountKind::Unknown;
+    };
+    let letter = match path.components().next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::UNC(..) | Prefix::VerbatimUNC(..) => return MountKind::Network,
+            Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => letter,
+            _ => return MountKind::Unknown,
+        },
+        _ => return MountKind::Unknown,
+    };
+    let root: Vec<u16> = format!("{}:\\", letter as char).encode_utf16().chain([0]).collect();
+    // SAFETY: `root` is NUL-terminated
+    match unsafe { GetDriveTypeW(root.as_ptr()) } {
+        DRIVE_FIXED | DRIVE_RAMDISK => MountKind::Local,
+        DRIVE_REMOTE => MountKind::Network,
+        DRIVE_REMOVABLE | DRIVE_CDROM => MountKind::Removable,
+        _ => MountKind::Unknown,
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+/// The kinds of mounts can't be detected on this platform.
+pub fn mount_kind(_path: &Path) -> MountKind {
+    MountKind::Unknown
+}
+
+#[cfg(target_os = "linux")]
+/// Return the mount point, filesystem type, and source of a line of
+/// `/proc/self/mountinfo`, which look like
+/// `36 35 98:0 / /mnt/photos rw,noatime master:1 - nfs4 nas:/photos rw`.
+fn parse_mountinfo(line: &str) -> Option<(PathBuf, &str, &str)> {
+    let (mount, filesystem) = line.split_once(" - ")?;
+    let point = mount.split(' ').nth(4)?;
+    let mut filesystem = filesystem.split(' ');
+    Some((PathBuf::from(unescape(point)), filesystem.next()?, filesystem.next()?))
+}
+
+#[cfg(target_os = "linux")]
+/// Return `field` of `/proc/self/mountinfo` with its octal escapes, as in `\040`
+/// for a space, turned back into the characters.
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(start) = rest.find('\\') {
+        unescaped.push_str(&rest[..start]);
+        match rest.get(start + 1..start + 4).and_then(|code| u8::from_str_radix(code, 8).ok()) {
+            Some(byte) => {
+                unescaped.push(byte as char);
+                rest = &rest[start + 4..];
+            },
+            None => {
+                unescaped.push('\\');
+                rest = &rest[start + 1..];
+            },
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+#[cfg(target_os = "linux")]
+/// Return the kind of storage of a mount of `fs_type` from `source`.
+fn kind_of(fs_type: &str, source: &str) -> MountKind {
+    if NETWORK_FILESYSTEMS.contains(&fs_type) || source.starts_with("//") {
+        return MountKind::Network;
+    }
+    match source.strip_prefix("/dev/") {
+        Some(device) if is_removable(device) => MountKind::Removable,
+        _ => MountKind::Local,
+    }
+}
+
+#[cfg(target_os = "linux")]
+/// Return [`true`] if the block `device`, as in `sdb1`, is removable, or is
+/// connected by USB.
+fn is_removable(device: &str) -> bool {
+    let Ok(path) = fs::canonicalize(Path::new("/sys/class/block").join(device)) else {
+        return false;
+    };
+    // Partitions don't say whether they are removable, their disks do
+    let removable = |dir: &Path| fs::read_to_string(dir.join("removable")).is_ok_and(|value| value.trim() == "1");
+    path.to_string_lossy().contains("/usb") || removable(&path) || path.parent().is_some_and(removable)
+}
+
+#[cfg(unix)]
+/// Return [`true`] if `a` and `b` are on the same device.
+fn same_device(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+/// Return [`true`] if `a` and `b` are on the same drive or share.
+fn same_device(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a.components().next() == b.components().next(),
+        _ => false,
+    }
+}
@@ -0,0 +1,308 @@
+//! Telling someone how a sort went once it's over. See [`Notification`] and
+//! [`Sorter::notifications`](crate::Sorter::notifications).
+//!
+//! Sorts that run unattended, as from a scheduler, have nobody watching them
+//! fail. Each [`Notification`] either runs a command, which gets a JSON
+//! [`Summary`] of the sort on its standard input, or posts the summary to a
+//! webhook. Webhooks need the `webhook` feature.
+
+use chrono::Local;
+use crate::errors::SortError;
+use crate::report::SortReport;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+#[cfg(test)]
+/// Tests for notifications. Each test is named after the function or struct it
+/// tests, prefixed with test.
+mod tests {
+
+    use std::{env, fs, io};
+    use super::{Hook, Notification, NotifyOn, Status, Summary};
+    use crate::errors::SortError;
+    use crate::report::SortReport;
+    use crate::structs::{File, Join};
+
+    #[test]
+    /// Test [`Summary`]
+    fn test_summary() {
+        let mut report = SortReport {
+            old: vec![File::from("a.jpg"), File::from("b.jpg")],
+            new: vec![File::from("target/a.jpg"), File::from("target/b.jpg")],
+            ..Default::default()
+        };
+        assert_eq!(Summary::of_report(&report).status, Status::Ok);
+        report.failures.push((File::from("b.jpg"), SortError::from(io::Error::other("gone"))));
+        let summary = Summary::of_report(&report);
+        assert_eq!((summary.status, summary.sorted, summary.failed), (Status::Failures, 1, 1));
+        assert_eq!(summary.failures[0].path, "b.jpg");
+
+        // Files that failed before they were planned aren't among the sorted ones
+        report.failures.push((File::from("undated.jpg"), SortError::from(io::Error::other("no date"))));
+        report.failures.push((File::from("album"), SortError::from(io::Error::other("unreadable"))));
+        let summary = Summary::of_report(&report);
+        assert_eq!((summary.sorted, summary.failed), (1, 3));
+        let planning = SortReport { failures: vec![(File::from("album"), SortError::from(io::Error::other("unreadable")))], ..Default::default() };
+        let summary = Summary::of_report(&planning);
+        assert_eq!((summary.sorted, summary.failed), (0, 1));
+
+        let error = SortError::InvalidConfig(String::from("bad"));
+        let summary = Summary::of_error(&error);
+        assert_eq!(summary.status, Status::Error);
+        assert_eq!(summary.error, Some(error.to_string()));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// Test [`Notification::send`] with a command
+    fn test_notification() {
+        let path = File::from(env::temp_dir()).join(String::from("sorterylib_test_notification.json"));
+        let notification = Notification {
+            hook: Hook::Command(vec![String::from("sh"), String::from("-c"), format!("cat > '{}'", path)]),
+            on: NotifyOn::Problems
+        };
+
+        let json = format!(r#"{{"command": ["sh", "-c", "cat > '{}'"], "on": "problems"}}"#, path);
+        assert_eq!(serde_json::from_str::<Notification>(&json).unwrap(), notification);
+
+        // Only problems are sent
+        let ok = Summary::of_report(&SortReport::default());
+        notification.send(&ok).expect("Failed to send notification.");
+        assert!(!path.exists());
+        let error = Summary::of_error(&SortError::InvalidConfig(String::from("bad")));
+        notification.send(&error).expect("Failed to send notification.");
+        let sent: Summary = serde_json::from_str(&fs::read_to_string(path.to_path_buf()).unwrap()).unwrap();
+        assert_eq!(sent, error);
+
+        // Commands that fail are errors
+        let failing = Notification { hook: Hook::Command(vec![String::from("false")]), on: NotifyOn::Always };
+        assert!(failing.send(&ok).is_err());
+
+        fs::remove_file(path.to_path_buf()).expect("Failed to remove test file.");
+    }
+
+    #[test]
+    #[cfg(feature = "webhook")]
+    /// Test [`Notification::send`] with a webhook
+    fn test_webhook() {
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::net::TcpListener;
+
+        // A server that answers one request, and hands back its body
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind test server.");
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            reader.get_mut().write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n").unwrap();
+            String::from_utf8(body).unwrap()
+        });
+
+        let summary = Summary::of_report(&SortReport::default());
+        let notification = Notification { hook: Hook::Webhook(url), on: NotifyOn::Always };
+        notification.send(&summary).expect("Failed to send notification.");
+        assert_eq!(serde_json::from_str::<Summary>(&server.join().unwrap()).unwrap(), summary);
+    }
+}
+
+/// Where a [`Notification`] is sent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Hook {
+    /// Run this command, a program followed by its arguments, with the JSON
+    /// [`Summary`] on its standard input, and its status in the
+    /// `SORTERY_STATUS` environment variable. Commands that exit unsuccessfully
+    /// count as failed notifications.
+    Command(Vec<String>),
+    /// Post the JSON [`Summary`] to this URL. Responses with error statuses
+    /// count as failed notifications. Needs the `webhook` feature.
+    Webhook(String)
+}
+
+/// Which sorts a [`Notification`] is sent for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyOn {
+    /// Every sort. This is the default.
+    #[default]
+    Always,
+    /// Only the sorts that couldn't run, or where files failed to sort.
+    Problems
+}
+
+/// A notification sent once a sort is over. In JSON configuration, it looks like
+/// `{"command": ["notify-send", "Sortery"], "on": "problems"}` or
+/// `{"webhook": "https://example.com/hooks/sortery"}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub struct Notification {
+    /// Where the notification is sent.
+    #[serde(flatten)]
+    pub hook: Hook,
+    /// Which sorts the notification is sent for.
+    #[serde(default)]
+    pub on: NotifyOn
+}
+impl Notification {
+
+    /// Send `summary`, if the notification is for sorts like it.
+    pub fn send(&self, summary: &Summary) -> io::Result<()> {
+        if self.on == NotifyOn::Problems && summary.status == Status::Ok {
+            return Ok(());
+        }
+        let json = serde_json::to_string(summary).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        match &self.hook {
+            Hook::Command(command) => run_command(command, summary.status, &json),
+            Hook::Webhook(url) => post(url, &json),
+        }
+    }
+}
+
+/// How a sort went, in a [`Summary`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// Every file was sorted.
+    Ok,
+    /// Some files failed to sort.
+    Failures,
+    /// The sort couldn't run at all.
+    Error
+}
+
+/// A file that failed to sort, in a [`Summary`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub struct FailureSummary {
+    /// The path of the file.
+    pub path: String,
+    /// The message of its error.
+    pub error: String
+}
+
+/// The summary of a sort that a [`Notification`] sends, as JSON.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub struct Summary {
+    /// How the sort went.
+    pub status: Status,
+    /// The number of files sorted without failing.
+    pub sorted: usize,
+    /// The number of files that failed, either to sort or before they could be
+    /// planned, like those in directories that couldn't be read.
+    pub failed: usize,
+    /// The number of warnings about files.
+    pub warnings: usize,
+    /// The number of files that weren't confirmed, were open in other processes,
+    /// or were already in the target.
+    pub skipped: usize,
+    /// The files that failed to sort.
+    pub failures: Vec<FailureSummary>,
+    /// The error the sort couldn't run because of, if it couldn't.
+    pub error: Option<String>,
+    /// When the sort finished, in RFC 3339 format.
+    pub finished_at: String
+}
+impl Summary {
+
+    /// Return the summary of the sort that `report` is the report of.
+    pub fn of_report(report: &SortReport) -> Summary {
+        Summary {
+            status: if report.is_ok() { Status::Ok } else { Status::Failures },
+            sorted: report.sorted().count(),
+            failed: report.failures.len(),
+            warnings: report.warnings.len(),
+            skipped: report.skipped.len() + report.in_use.len() + report.present.len(),
+            failures: report.failures.iter()
+                .map(|(path, error)| FailureSummary { path: path.to_string(), error: error.to_string() })
+                .collect(),
+            error: None,
+            finished_at: Local::now().to_rfc3339()
+        }
+    }
+
+    /// Return the summary of a sort that couldn't run because of `error`.
+    pub fn of_error(error: &SortError) -> Summary {
+        Summary {
+            status: Status::Error,
+            sorted: 0,
+            failed: 0,
+            warnings: 0,
+            skipped: 0,
+            failures: Vec::new(),
+            error: Some(error.to_string()),
+            finished_at: Local::now().to_rfc3339()
+        }
+    }
+}
+
+/// Send `summary` with every notification in `notifications`, and return the
+/// errors of the ones that failed, by their indices.
+pub fn send_all(notifications: &[Notification], summary: &Summary) -> Vec<(usize, io::Error)> {
+    notifications.iter()
+        .enumerate()
+        .filter_map(|(i, notification)| notification.send(summary).err().map(|error| (i, error)))
+        .collect()
+}
+
+/// Run `command` with `json` on its standard input.
+fn run_command(command: &[String], status: Status, json: &str) -> io::Result<()> {
+    let (program, args) = command.split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the command is empty"))?;
+    let status = match status {
+        Status::Ok => "ok",
+        Status::Failures => "failures",
+        Status::Error => "error",
+    };
+    let mut child = Command::new(program)
+        .args(args)
+        .env("SORTERY_STATUS", status)
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    // Commands don't have to read the summary
+    if let Some(mut stdin) = child.stdin.take() {
+        match stdin.write_all(json.as_bytes()) {
+            Err(error) if error.kind() != io::ErrorKind::BrokenPipe => return Err(error),
+            _ => (),
+        }
+    }
+    let exit = child.wait()?;
+    if !exit.success() {
+        return Err(io::Error::other(format!("the command exited with {}", exit)));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "webhook")]
+/// Post `json` to `url`.
+fn post(url: &str, json: &str) -> io::Result<()> {
+    ureq::post(url)
+        .header("Content-Type", "application/json")
+        .send(json)
+        .map(|_| ())
+        .map_err(io::Error::other)
+}
+
+#[cfg(not(feature = "webhook"))]
+/// Webhooks need the `webhook` feature.
+fn post(_url: &str, _json: &str) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "webhooks need the `webhook` feature"))
+}
@@ -0,0 +1,281 @@
+//! Watching sorts as they happen.
+//!
+//! An [`Observer`] is told when a sort starts, about every file it processes,
+//! and when it is done. Any number of observers can be registered on a
+//! [`Sorter`](crate::Sorter) with
+//! [`Sorter::register_observer`](crate::Sorter::register_observer), so that a
+//! logger, a progress bar, and a metrics exporter can all follow the same sort
+//! without a callback that passes everything on to each of them.
+//!
+//! Sorts of millions of files would tell the observers about each of them, which
+//! takes longer than it's worth. [`Sorter::progress_interval`](crate::Sorter::progress_interval)
+//! reports the progress only every so many files, or every so often, instead
+//! (see [`ProgressInterval`]).
+
+use crate::report::{ByteProgress, Progress, SortReport};
+use serde::{Deserialize, Serialize};
+use std::{fmt, sync::{Arc, atomic::{AtomicBool, Ordering}}};
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+/// Tests for observers. Each test is named after the struct it tests, prefixed
+/// with test.
+mod tests {
+
+    use std::sync::{Arc, Mutex};
+    use super::{CancelToken, Observer, Observers, ProgressInterval, ProgressThrottle};
+    use crate::report::{Progress, SortReport};
+
+    /// An [`Observer`] that writes down what it is told
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+    impl Observer for Recorder {
+        fn on_start(&self, total: usize) {
+            self.0.lock().unwrap().push(format!("start {}", total));
+        }
+        fn on_file(&self, progress: &Progress) {
+            self.0.lock().unwrap().push(format!("file {}", progress.done));
+        }
+        fn on_finish(&self, report: &SortReport) {
+            self.0.lock().unwrap().push(format!("finish {}", report.count()));
+        }
+    }
+
+    #[test]
+    /// Test [`Observers`]
+    fn test_observers() {
+        let (first, second) = (Arc::new(Recorder::default()), Arc::new(Recorder::default()));
+        let mut observers = Observers::default();
+        assert!(observers.is_empty());
+        observers.register(first.clone());
+        observers.register(second.clone());
+        assert_eq!(observers.len(), 2);
+
+        // Every observer gets every event
+        observers.on_start(1);
+        observers.on_file(&Progress { done: 0, total: 1, percent: 0, current: None });
+        observers.on_finish(&SortReport::default());
+        for recorder in [&first, &second] {
+            assert_eq!(*recorder.0.lock().unwrap(), vec!["start 1", "file 0", "finish 0"]);
+        }
+
+        // Observers are only equal if they hold the same observers
+        let mut other = Observers::default();
+        other.register(first.clone());
+        other.register(second.clone());
+        assert_eq!(observers, other);
+        assert_ne!(observers, Observers::default());
+        assert_eq!(format!("{:?}", observers), "Observers(2)");
+    }
+
+    #[test]
+    /// Test [`ProgressThrottle`]
+    fn test_progress_throttle() {
+        let reported = |interval: ProgressInterval| {
+            let mut throttle = ProgressThrottle::new(interval);
+            (0..10).filter(|done| throttle.allows(*done, *done == 9)).collect::<Vec<usize>>()
+        };
+
+        // The first and last files are always reported
+        assert_eq!(reported(ProgressInterval::default()), (0..10).collect::<Vec<usize>>());
+        assert_eq!(reported(ProgressInterval { files: Some(4), millis: None }), vec![0, 4, 8, 9]);
+        assert_eq!(reported(ProgressInterval { files: None, millis: Some(60_000) }), vec![0, 9]);
+        assert_eq!(reported(ProgressInterval { files: Some(4), millis: Some(60_000) }), vec![0, 9]);
+        assert!(ProgressInterval::default().is_empty());
+        assert!(!ProgressInterval { files: Some(4), millis: None }.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_token() {
+        let token = CancelToken::default();
+        let clone = token.clone();
+        assert!(!token.is_cancelled());
+        clone.cancel();
+        assert!(token.is_cancelled());
+        token.reset();
+        assert!(!clone.is_cancelled());
+        assert_eq!(token, CancelToken::default());
+        clone.cancel();
+        assert_ne!(token, CancelToken::default());
+    }
+}
+
+/// Something that follows sorts as they happen. All the methods do nothing by
+/// default, so only the ones that are needed have to be implemented.
+///
+/// Observers are shared, so they take `&self`; ones that keep state should keep
+/// it behind a [`Mutex`](std::sync::Mutex) or in atomics.
+pub trait Observer: Send + Sync {
+
+    /// Called once the files to sort are known, with their number.
+    fn on_start(&self, _total: usize) { }
+
+    /// Called after each file is processed. Unlike the callback of
+    /// [`Sorter::sort_report_with_callback`](crate::Sorter::sort_report_with_callback),
+    /// this is called for every file, not just when the percent completed goes
+    /// up, unless [`Sorter::progress_interval`](crate::Sorter::progress_interval)
+    /// says otherwise. `progress.current` is always [`Some`].
+    fn on_file(&self, _progress: &Progress) { }
+
+    /// Called after each chunk of a large file is copied (see
+    /// [`transfer::LARGE_FILE_SIZE`](crate::transfer::LARGE_FILE_SIZE)), so that a
+    /// single long copy still shows progress. This is called on the thread
+    /// copying the file, which isn't the sorting thread when there are several
+    /// [`Sorter::workers`](crate::Sorter::workers).
+    fn on_bytes(&self, _progress: &ByteProgress) { }
+
+    /// Called once the sort is done, with its report.
+    fn on_finish(&self, _report: &SortReport) { }
+}
+
+/// The observers registered on a [`Sorter`](crate::Sorter), each of which is told
+/// about every event in the order they were registered.
+#[derive(Clone, Default)]
+pub struct Observers(Vec<Arc<dyn Observer>>);
+impl Observers {
+
+    /// Add `observer` to the observers.
+    pub fn register(&mut self, observer: Arc<dyn Observer>) {
+        self.0.push(observer);
+    }
+
+    /// Return the number of observers.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Return [`true`] if there are no observers.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Call [`Observer::on_start`] on all the observers.
+    pub fn on_start(&self, total: usize) {
+        self.0.iter().for_each(|o| o.on_start(total));
+    }
+
+    /// Call [`Observer::on_file`] on all the observers.
+    pub fn on_file(&self, progress: &Progress) {
+        self.0.iter().for_each(|o| o.on_file(progress));
+    }
+
+    /// Call [`Observer::on_bytes`] on all the observers.
+    pub fn on_bytes(&self, progress: &ByteProgress) {
+        self.0.iter().for_each(|o| o.on_bytes(progress));
+    }
+
+    /// Call [`Observer::on_finish`] on all the observers.
+    pub fn on_finish(&self, report: &SortReport) {
+        self.0.iter().for_each(|o| o.on_finish(report));
+    }
+}
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Observers({})", self.0.len())
+    }
+}
+impl PartialEq for Observers {
+
+    /// Observers are equal if they hold the very same observers, in the same order.
+    fn eq(&self, other: &Observers) -> bool {
+        self.0.len() == other.0.len() && self.0.iter().zip(&other.0).all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+}
+
+/// How often the progress of a sort is reported, to the observers and to the
+/// callback of [`Sorter::sort_report_with_callback`](crate::Sorter::sort_report_with_callback).
+/// With both limits, progress is only reported once both are met. The first
+/// and last files are always reported, as is the end of the sort. In JSON
+/// configuration, it looks like `{"files": 1000, "millis": 250}`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub struct ProgressInterval {
+    /// The least number of files between two reports. [`None`] by default,
+    /// which is no limit.
+    #[serde(default)]
+    pub files: Option<usize>,
+    /// The least time between two reports, in milliseconds. [`None`] by
+    /// default, which is no limit.
+    #[serde(default)]
+    pub millis: Option<u64>
+}
+impl ProgressInterval {
+
+    /// Return [`true`] if there are no limits, so that every file is reported.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_none() && self.millis.is_none()
+    }
+}
+
+/// When the progress of a sort was last reported, to report it only as often as
+/// a [`ProgressInterval`] allows.
+#[derive(Clone, Debug)]
+pub struct ProgressThrottle {
+    interval: ProgressInterval,
+    last: Option<(usize, Instant)>
+}
+impl ProgressThrottle {
+
+    /// Return a new [`ProgressThrottle`] that hasn't reported anything yet.
+    pub fn new(interval: ProgressInterval) -> ProgressThrottle {
+        ProgressThrottle { interval, last: None }
+    }
+
+    /// Return [`true`] if the progress should be reported now that `done` files
+    /// were done before the current one, and remember that it was. `last` is
+    /// whether the current file is the last one, which is always reported.
+    pub fn allows(&mut self, done: usize, last: bool) -> bool {
+        let now = Instant::now();
+        let allowed = match self.last {
+            None => true,
+            _ if last => true,
+            Some((files, time)) => {
+                self.interval.files.is_none_or(|n| done - files >= n)
+                    && self.interval.millis.is_none_or(|ms| now.duration_since(time) >= Duration::from_millis(ms))
+            },
+        };
+        if allowed {
+            self.last = Some((done, now));
+        }
+        allowed
+    }
+}
+
+/// A way to cancel a sort from another thread, as from the cancel button of an
+/// interface. Clones share the same state, so keep a clone of the
+/// [`Sorter::cancel`](crate::Sorter::cancel) token and call [`CancelToken::cancel`]
+/// on it while the sort runs. No more files are started after that, and a large
+/// file that is being copied is stopped between two chunks and removed from the
+/// target; the files that were already sorted stay sorted.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+impl CancelToken {
+
+    /// Cancel the sorts using this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Return [`true`] if the sorts using this token have been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Un-cancel the token, so that it can be used for another sort.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+impl fmt::Debug for CancelToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CancelToken({})", self.is_cancelled())
+    }
+}
+impl PartialEq for CancelToken {
+
+    /// Tokens are equal if they are both cancelled, or both not, so that
+    /// [`Sorter`](crate::Sorter)s with the same configuration are equal.
+    fn eq(&self, other: &CancelToken) -> bool {
+        self.is_cancelled() == other.is_cancelled()
+    }
+}
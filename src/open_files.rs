@@ -0,0 +1,126 @@
+//! Detecting files that are open in other processes, so that they aren't moved
+//! while they are being written. See
+//! [`Sorter::skip_open_files`](crate::Sorter::skip_open_files).
+//!
+//! How this works depends on the platform. On Linux, the open files of every
+//! other process we can see are listed in `/proc`. On Windows, a file is open if
+//! it can't be opened without sharing. On other platforms, no files are detected
+//! as open.
+
+use crate::structs::File;
+#[cfg(target_os = "linux")]
+use std::{collections::HashSet, fs, path::PathBuf, time::{Duration, Instant}};
+
+#[cfg(test)]
+/// Tests for detecting open files. Each test is named after the function or struct
+/// it tests, prefixed with test.
+mod tests {
+
+    use std::{env, fs, process::{Command, Stdio}};
+    use super::OpenFiles;
+    use crate::structs::{File, Join};
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    /// Test [`OpenFiles`]
+    fn test_open_files() {
+
+        let path = File::from(env::temp_dir()).join(String::from("sorterylib_test_open_files.txt"));
+        fs::write(path.to_path_buf(), "test").expect("Failed to write test file.");
+
+        // Files open in this process don't count
+        let handle = fs::File::open(path.to_path_buf()).expect("Failed to open test file.");
+        assert!(!OpenFiles::scan().contains(&path));
+
+        // The file is only open while another process holds on to it
+        let mut child = Command::new("sleep").arg("60").stdin(Stdio::from(handle)).spawn().expect("Failed to start test process.");
+        assert!(OpenFiles::scan().contains(&path));
+        child.kill().expect("Failed to stop test process.");
+        child.wait().expect("Failed to stop test process.");
+        assert!(!OpenFiles::scan().contains(&path));
+
+        fs::remove_file(path.to_path_buf()).expect("Failed to remove test file.");
+    }
+}
+
+#[cfg(target_os = "linux")]
+/// How old a scan can get before [`OpenFiles::refresh`] scans again.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The files that are open in other processes, as of when [`OpenFiles::scan`]
+/// was called.
+#[derive(Debug, Default)]
+pub struct OpenFiles {
+    /// The canonical paths of all the files open in the other processes we can
+    /// see.
+    #[cfg(target_os = "linux")]
+    paths: HashSet<PathBuf>,
+    /// When the files were scanned, or [`None`] if they haven't been yet.
+    #[cfg(target_os = "linux")]
+    scanned_at: Option<Instant>
+}
+impl OpenFiles {
+
+    /// Return the files that are open right now. On platforms where files are
+    /// checked one by one, this does nothing.
+    pub fn scan() -> OpenFiles {
+        #[cfg(target_os = "linux")]
+        {
+            // The processes are the numbered directories, leaving out this one,
+            // which `/proc/self` leads to as well
+            let own = std::process::id();
+            let mut paths = HashSet::new();
+            for process in fs::read_dir("/proc").into_iter().flatten().flatten() {
+                let other = process.file_name().to_str().is_some_and(|name| name.parse::<u32>().is_ok_and(|pid| pid != own));
+                if !other {
+                    continue;
+                }
+                let fds = match fs::read_dir(process.path().join("fd")) {
+                    Ok(fds) => fds,
+                    Err(_) => continue,
+                };
+                paths.extend(fds.flatten().filter_map(|fd| fs::read_link(fd.path()).ok()));
+            }
+            OpenFiles { paths, scanned_at: Some(Instant::now()) }
+        }
+        #[cfg(not(target_os = "linux"))]
+        OpenFiles::default()
+    }
+
+    /// Scan again if the files haven't been scanned in the last second, so that
+    /// files opened since are found without scanning for every file. On platforms where files are checked one by one,
+    /// this does nothing.
+    pub fn refresh(&mut self) {
+        #[cfg(target_os = "linux")]
+        if self.scanned_at.is_none_or(|at| at.elapsed() >= RESCAN_INTERVAL) {
+            *self = OpenFiles::scan();
+        }
+    }
+
+    /// Return [`true`] if `path` is open in another process.
+    pub fn contains(&self, path: &File) -> bool {
+        #[cfg(target_os = "linux")]
+        {
+            match fs::canonicalize(&path.pathbuf) {
+                Ok(path) => self.paths.contains(&path),
+                Err(_) => false,
+            }
+        }
+        #[cfg(windows)]
+        {
+            use std::os::windows::fs::OpenOptionsExt;
+
+            // ERROR_SHARING_VIOLATION
+            const SHARING_VIOLATION: i32 = 32;
+            match std::fs::OpenOptions::new().read(true).share_mode(0).open(&path.pathbuf) {
+                Err(error) => error.raw_os_error() == Some(SHARING_VIOLATION),
+                Ok(_) => false,
+            }
+        }
+        #[cfg(not(any(target_os = "linux", windows)))]
+        {
+            let _ = path;
+            false
+        }
+    }
+}
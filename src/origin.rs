@@ -0,0 +1,140 @@
+//! Recording the original location of sorted files.
+//!
+//! When [`Sorter::origin_record`](crate::Sorter::origin_record) is set, every
+//! file that gets moved is stamped with the path it was moved from. The stamp is
+//! either an extended attribute named [`ORIGIN_XATTR`] (on filesystems that
+//! support them), or a small JSON sidecar file next to the sorted file, named
+//! after it with [`ORIGIN_SIDECAR_SUFFIX`] appended.
+
+use chrono::Local;
+use crate::structs::File;
+use serde::{Deserialize, Serialize};
+use std::{fs, io};
+
+#[cfg(test)]
+/// Tests for recording the original path. Each test is named after the function
+/// it tests, prefixed with test.
+mod tests {
+
+    use std::{env, fs};
+    use super::{OriginRecord, read_origin, record_origin, sidecar_path};
+    use crate::structs::{File, Join};
+
+    #[test]
+    /// Test [`record_origin`] and [`read_origin`] using a sidecar file
+    fn test_record_origin() {
+
+        // The directory and files to use for testing
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_record_origin"));
+        fs::create_dir_all(dir.to_path_buf()).expect("Failed to create test dir.");
+        let old = File::from("/path/to/old/test.jpg");
+        let new = dir.join(String::from("2022 test.jpg"));
+        fs::write(new.to_path_buf(), "").expect("Failed to create test file.");
+
+        // Nothing should be written if recording is off
+        record_origin(&old, &new, &OriginRecord::Off).expect("Failed to record origin.");
+        assert!(!sidecar_path(&new).exists());
+        assert_eq!(read_origin(&new), None);
+
+        // Write the sidecar, and make sure we can read it back
+        record_origin(&old, &new, &OriginRecord::Sidecar).expect("Failed to record origin.");
+        assert!(sidecar_path(&new).exists());
+        assert_eq!(read_origin(&new), Some(String::from("/path/to/old/test.jpg")));
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+}
+
+/// The name of the extended attribute used by [`OriginRecord::Xattr`].
+pub const ORIGIN_XATTR: &str = "user.sortery.origin";
+
+/// The suffix appended to a sorted file's name to get the name of its origin
+/// sidecar file, as in `2022 test.jpg.origin.json`.
+pub const ORIGIN_SIDECAR_SUFFIX: &str = ".origin.json";
+
+/// How to record the original location of each sorted file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OriginRecord {
+    /// Don't record the original location.
+    #[default]
+    Off,
+    /// Store the original path in the [`ORIGIN_XATTR`] extended attribute.
+    /// Fails on platforms and filesystems without extended attribute support.
+    Xattr,
+    /// Write the original path to a `.origin.json` sidecar file.
+    Sidecar,
+    /// Use an extended attribute where supported, and a sidecar file otherwise.
+    Auto
+}
+
+/// The contents of an origin sidecar file.
+#[derive(Debug, Serialize, Deserialize)]
+struct OriginData {
+    original_path: String,
+    sorted_at: String
+}
+
+/// Return a [`File`] representing the origin sidecar file of `file`.
+pub fn sidecar_path(file: &File) -> File {
+    let mut name = file.pathbuf.file_name().unwrap_or_default().to_os_string();
+    name.push(ORIGIN_SIDECAR_SUFFIX);
+    File::from(file.pathbuf.with_file_name(name))
+}
+
+/// Return the original path recorded for `file`, checking the extended attribute
+/// first and then the sidecar file. Returns [`None`] if no origin was recorded.
+pub fn read_origin(file: &File) -> Option<String> {
+    if let Some(path) = read_xattr(file) {
+        return Some(path);
+    }
+    let json = fs::read_to_string(sidecar_path(file).to_path_buf()).ok()?;
+    let data: OriginData = serde_json::from_str(&json).ok()?;
+    Some(data.original_path)
+}
+
+/// Record `old` as the original location of `new`, the way `how` says to.
+pub fn record_origin(old: &File, new: &File, how: &OriginRecord) -> io::Result<()> {
+    match how {
+        OriginRecord::Off => Ok(()),
+        OriginRecord::Xattr => write_xattr(old, new),
+        OriginRecord::Sidecar => write_sidecar(old, new),
+        OriginRecord::Auto => write_xattr(old, new).or_else(|_| write_sidecar(old, new)),
+    }
+}
+
+/// Write the origin sidecar file of `new`.
+fn write_sidecar(old: &File, new: &File) -> io::Result<()> {
+    let data = OriginData {
+        original_path: old.to_string(),
+        sorted_at: Local::now().to_rfc3339()
+    };
+    let json = serde_json::to_string_pretty(&data)?;
+    fs::write(sidecar_path(new).to_path_buf(), json)
+}
+
+#[cfg(unix)]
+/// Read the [`ORIGIN_XATTR`] extended attribute of `file`, if it has one.
+fn read_xattr(file: &File) -> Option<String> {
+    let value = xattr::get(&file.pathbuf, ORIGIN_XATTR).ok()??;
+    String::from_utf8(value).ok()
+}
+
+#[cfg(not(unix))]
+/// Extended attributes are not supported on this platform.
+fn read_xattr(_file: &File) -> Option<String> {
+    None
+}
+
+#[cfg(unix)]
+/// Set the [`ORIGIN_XATTR`] extended attribute of `new` to the path of `old`.
+fn write_xattr(old: &File, new: &File) -> io::Result<()> {
+    xattr::set(&new.pathbuf, ORIGIN_XATTR, old.to_string().as_bytes())
+}
+
+#[cfg(not(unix))]
+/// Extended attributes are not supported on this platform.
+fn write_xattr(_old: &File, _new: &File) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "extended attributes are not supported"))
+}
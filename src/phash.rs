@@ -0,0 +1,79 @@
+//! Perceptual hashing of images, for finding visually identical images that
+//! exact hashing misses, like re-saved or resized copies. Needs the `phash` feature.
+//!
+//! The hash used is a difference hash (dHash): the image is shrunk to 9×8 grayscale
+//! pixels, and each of the 64 bits records whether a pixel is brighter than its
+//! right-hand neighbour. Visually identical images get hashes that differ in only
+//! a few bits, whatever their encoding or resolution.
+
+use crate::structs::File;
+use image::imageops::FilterType;
+
+#[cfg(test)]
+/// Tests for perceptual hashing. Each test is named after the function it tests,
+/// prefixed with test.
+mod tests {
+
+    use image::{imageops::FilterType, GrayImage, Luma};
+    use std::{env, fs};
+    use super::{dhash, distance};
+    use crate::structs::{File, Join};
+
+    #[test]
+    /// Test [`dhash`] and [`distance`]
+    fn test_dhash() {
+
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_dhash"));
+        fs::create_dir_all(dir.to_path_buf()).expect("Failed to create test dir.");
+
+        // A gradient with a bright block, a smaller JPEG copy of it, and a mirror image
+        let gradient = GrayImage::from_fn(256, 128, |x, y| {
+            let block = if (64..128).contains(&x) && y < 64 { 100 } else { 0 };
+            Luma([(x / 2 + block) as u8])
+        });
+        let original = dir.join(String::from("original.png"));
+        let resaved = dir.join(String::from("resaved.jpg"));
+        let flipped = dir.join(String::from("flipped.png"));
+        gradient.save(original.to_path_buf()).expect("Failed to save image.");
+        image::imageops::resize(&gradient, 128, 64, FilterType::Triangle)
+            .save(resaved.to_path_buf()).expect("Failed to save image.");
+        image::imageops::flip_horizontal(&gradient)
+            .save(flipped.to_path_buf()).expect("Failed to save image.");
+
+        let original = dhash(&original).expect("Failed to hash image.");
+        assert!(distance(original, dhash(&resaved).expect("Failed to hash image.")) <= 4);
+        assert!(distance(original, dhash(&flipped).expect("Failed to hash image.")) > 10);
+
+        // Files that aren't images don't have a hash
+        assert_eq!(dhash(&File::from("Cargo.toml")), None);
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+}
+
+/// The largest [`distance`] between the hashes of two images for them to count as
+/// visually identical, by default.
+pub const DEFAULT_MAX_DISTANCE: u32 = 4;
+
+/// Return the difference hash of the image `path`, or [`None`] if it can't be
+/// read as an image.
+pub fn dhash(path: &File) -> Option<u64> {
+    let image = image::open(&path.pathbuf).ok()?.grayscale();
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    Some(hash)
+}
+
+/// Return the number of bits that differ between the hashes `a` and `b`.
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
@@ -0,0 +1,168 @@
+//! Sort plans that can be kept and compared. See [`SortPlan`].
+//!
+//! A dry run plans a sort without moving anything. Keeping its plan, and
+//! comparing it with the plan of a later dry run, shows exactly what changed in
+//! between, as after tweaking the configuration, before the sort is run for real.
+
+use crate::report::SortReport;
+use crate::structs::File;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::{fs, io};
+
+#[cfg(test)]
+/// Tests for sort plans. Each test is named after the method or struct it
+/// tests, prefixed with test.
+mod tests {
+
+    use std::io;
+    use super::{PlanChange, PlanEntry, SortPlan};
+    use crate::errors::SortError;
+    use crate::report::SortReport;
+    use crate::structs::File;
+    use crate::testing::Sandbox;
+
+    /// Return the plan of moving each file of `moves` from the first path to the
+    /// second one
+    fn plan(moves: &[(&str, &str)]) -> SortPlan {
+        SortPlan::of(&SortReport {
+            old: moves.iter().map(|(old, _)| File::from(*old)).collect(),
+            new: moves.iter().map(|(_, new)| File::from(*new)).collect(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    /// Test [`SortPlan::of`]
+    fn test_sortplan_of() {
+        let report = SortReport {
+            old: vec![File::from("a.jpg"), File::from("b.jpg")],
+            new: vec![File::from("2023/a.jpg"), File::from("2023/b.jpg")],
+            failures: vec![(File::from("b.jpg"), SortError::from(io::Error::other("full")))],
+            ..Default::default()
+        };
+        let entry = PlanEntry { old: String::from("a.jpg"), new: String::from("2023/a.jpg") };
+        assert_eq!(SortPlan::of(&report).entries, vec![entry]);
+    }
+
+    #[test]
+    /// Test [`SortPlan::diff`]
+    fn test_sortplan_diff() {
+        let before = plan(&[("a.jpg", "2023/a.jpg"), ("b.jpg", "2023/b.jpg"), ("c.jpg", "2023/c.jpg")]);
+        let after = plan(&[("d.jpg", "2024/d.jpg"), ("c.jpg", "2023/07/c.jpg"), ("a.jpg", "2023/a.jpg")]);
+        let diff = before.diff(&after);
+        assert_eq!(diff.added, vec![PlanEntry { old: String::from("d.jpg"), new: String::from("2024/d.jpg") }]);
+        assert_eq!(diff.removed, vec![PlanEntry { old: String::from("b.jpg"), new: String::from("2023/b.jpg") }]);
+        assert_eq!(diff.changed, vec![PlanChange {
+            old: String::from("c.jpg"),
+            before: String::from("2023/c.jpg"),
+            after: String::from("2023/07/c.jpg")
+        }]);
+        assert!(!diff.is_empty());
+        assert!(after.diff(&after).is_empty());
+    }
+
+    #[test]
+    /// Test [`SortPlan::save`] and [`SortPlan::load`]
+    fn test_sortplan_save() {
+        let sandbox = Sandbox::new(&[]).expect("Failed to create sandbox.");
+        let path = sandbox.path("plan.json");
+        let plan = plan(&[("a.jpg", "2023/a.jpg")]);
+        plan.save(&path).expect("Failed to save plan.");
+        assert_eq!(SortPlan::load(&path).expect("Failed to load plan."), plan);
+        assert!(SortPlan::load(&sandbox.path("missing.json")).is_err());
+    }
+}
+
+/// A file in a [`SortPlan`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub struct PlanEntry {
+    /// The path the file is sorted from.
+    pub old: String,
+    /// The path the file is sorted to.
+    pub new: String
+}
+
+/// A file that is sorted to different places by two [`SortPlan`]s.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlanChange {
+    /// The path the file is sorted from.
+    pub old: String,
+    /// The path the file is sorted to by the first plan.
+    pub before: String,
+    /// The path the file is sorted to by the second plan.
+    pub after: String
+}
+
+/// What changed from one [`SortPlan`] to another. See [`SortPlan::diff`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PlanDiff {
+    /// The files that only the second plan sorts.
+    pub added: Vec<PlanEntry>,
+    /// The files that only the first plan sorts.
+    pub removed: Vec<PlanEntry>,
+    /// The files that the plans sort to different places.
+    pub changed: Vec<PlanChange>
+}
+impl PlanDiff {
+
+    /// Return [`true`] if the plans sort the same files to the same places.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Where a sort moves each file, as planned by a dry run.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub struct SortPlan {
+    /// The files sorted, in the order they are sorted.
+    pub entries: Vec<PlanEntry>
+}
+impl SortPlan {
+
+    /// Return the plan of the files sorted in `report`, leaving out the ones
+    /// that failed.
+    pub fn of(report: &SortReport) -> SortPlan {
+        let entries = report.sorted()
+            .map(|(old, new)| PlanEntry { old: old.to_string(), new: new.to_string() })
+            .collect();
+        SortPlan { entries }
+    }
+
+    /// Return what changed from this plan to `other`: the files that only
+    /// `other` sorts, the ones that only this plan sorts, and the ones they sort
+    /// to different places, each in the order of the plan that sorts them.
+    pub fn diff(&self, other: &SortPlan) -> PlanDiff {
+        let ours: BTreeMap<&str, &str> = self.entries.iter().map(|e| (e.old.as_str(), e.new.as_str())).collect();
+        let theirs: BTreeSet<&str> = other.entries.iter().map(|e| e.old.as_str()).collect();
+        let mut diff = PlanDiff::default();
+        for entry in &other.entries {
+            match ours.get(entry.old.as_str()) {
+                None => diff.added.push(entry.clone()),
+                Some(before) if *before != entry.new => diff.changed.push(PlanChange {
+                    old: entry.old.clone(),
+                    before: String::from(*before),
+                    after: entry.new.clone()
+                }),
+                Some(_) => (),
+            }
+        }
+        diff.removed = self.entries.iter().filter(|e| !theirs.contains(e.old.as_str())).cloned().collect();
+        diff
+    }
+
+    /// Save the plan as JSON in `path`, to compare it with later plans.
+    pub fn save(&self, path: &File) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(&path.pathbuf, json)
+    }
+
+    /// Return the plan saved as JSON in `path`. Fails with
+    /// [`io::ErrorKind::InvalidData`] if it isn't a plan.
+    pub fn load(path: &File) -> io::Result<SortPlan> {
+        let json = fs::read_to_string(&path.pathbuf)?;
+        serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
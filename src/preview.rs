@@ -0,0 +1,147 @@
+//! The tree of directories a sort makes in the target, for showing in a tree
+//! widget. See [`TreeNode`] and [`SortReport::tree`](crate::report::SortReport::tree).
+//!
+//! A [`SortReport`], like the one of a dry run, lists the new path of every file
+//! on its own. A preview puts them together into the directories they end up
+//! in, each with the number and size of the files in it and below it, so that
+//! interfaces can show the result of a sort before it is run.
+
+use crate::report::SortReport;
+use crate::structs::{File, Join};
+use std::fs;
+use std::path::Component;
+
+#[cfg(test)]
+/// Tests for previews. Each test is named after the function it tests, prefixed
+/// with test.
+mod tests {
+
+    use std::fs;
+    use super::tree;
+    use crate::report::SortReport;
+    use crate::testing::{Entry, Sandbox};
+
+    #[test]
+    /// Test [`tree`]
+    fn test_tree() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/a.jpg").contents("aa"),
+            Entry::file("source/b.jpg").contents("bbb"),
+            Entry::file("source/c.jpg").contents("c"),
+        ]).expect("Failed to create sandbox.");
+        let (source, target) = (sandbox.source(), sandbox.target());
+        let report = SortReport {
+            old: ["a.jpg", "b.jpg", "c.jpg"].map(|name| sandbox.path(&format!("source/{}", name))).to_vec(),
+            new: ["2023/07/a.jpg", "2023/06/b.jpg", "2024/c.jpg"].map(|path| sandbox.path(&format!("target/{}", path))).to_vec(),
+            ..Default::default()
+        };
+        fs::remove_file(source.to_path_buf().join("c.jpg")).unwrap();
+        fs::create_dir_all(target.to_path_buf().join("2024")).unwrap();
+        fs::write(target.to_path_buf().join("2024/c.jpg"), "c").unwrap();
+
+        // Directories come in order, with the counts and sizes of everything
+        // below them, even of files that were already moved
+        let root = tree(&report, &target);
+        assert_eq!((root.path.copy(), root.count, root.size), (target.copy(), 3, 6));
+        let names: Vec<&str> = root.dirs.iter().map(|dir| dir.name.as_str()).collect();
+        assert_eq!(names, vec!["2023", "2024"]);
+        let months: Vec<(&str, usize, u64)> = root.dirs[0].dirs.iter().map(|dir| (dir.name.as_str(), dir.count, dir.size)).collect();
+        assert_eq!(months, vec![("06", 1, 3), ("07", 1, 2)]);
+        assert_eq!(root.dirs[1].files[0].name, "c.jpg");
+        assert_eq!(root.find("2023/07").unwrap().files[0].old, source.to_path_buf().join("a.jpg").into());
+    }
+}
+
+/// A file in a [`TreeNode`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeFile {
+    /// The new name of the file.
+    pub name: String,
+    /// The path the file is sorted from.
+    pub old: File,
+    /// The path the file is sorted to.
+    pub new: File,
+    /// The size of the file, in bytes, or `0` if it can't be read.
+    pub size: u64
+}
+
+/// A directory in the target, with the directories and files sorted into it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeNode {
+    /// The name of the directory. The root is named after the target.
+    pub name: String,
+    /// The path of the directory.
+    pub path: File,
+    /// The directories in the directory, sorted by name.
+    pub dirs: Vec<TreeNode>,
+    /// The files sorted into the directory itself, sorted by name.
+    pub files: Vec<TreeFile>,
+    /// The number of files sorted into the directory and the ones below it.
+    pub count: usize,
+    /// The total size of the files sorted into the directory and the ones
+    /// below it, in bytes.
+    pub size: u64
+}
+impl TreeNode {
+
+    /// Return an empty node for the directory at `path`.
+    fn new(path: File) -> TreeNode {
+        TreeNode { name: path.file_name(), path, dirs: Vec::new(), files: Vec::new(), count: 0, size: 0 }
+    }
+
+    /// Return the directory at `path` below this one, relative to it, with `/`
+    /// separators, as in `"2023/07"`.
+    pub fn find(&self, path: &str) -> Option<&TreeNode> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |node, name| node.dirs.iter().find(|dir| dir.name == name))
+    }
+
+    /// Add `file` at the directory `dirs` below this one.
+    fn add(&mut self, dirs: &[String], file: TreeFile) {
+        self.count += 1;
+        self.size += file.size;
+        match dirs.split_first() {
+            None => self.files.push(file),
+            Some((name, rest)) => {
+                let i = match self.dirs.iter().position(|dir| &dir.name == name) {
+                    Some(i) => i,
+                    None => {
+                        self.dirs.push(TreeNode::new(self.path.join(name.clone())));
+                        self.dirs.len() - 1
+                    },
+                };
+                self.dirs[i].add(rest, file);
+            },
+        }
+    }
+
+    /// Sort the directories and files of this node and the ones below it by name.
+    fn sort(&mut self) {
+        self.dirs.sort_by(|a, b| a.name.cmp(&b.name));
+        self.files.sort_by(|a, b| a.name.cmp(&b.name));
+        self.dirs.iter_mut().for_each(TreeNode::sort);
+    }
+}
+
+/// Return the tree of the directories in `target` that the files sorted in
+/// `report` are sorted into. Files that failed to sort are left out. Files
+/// sorted outside of `target` are put in the tree by their whole paths.
+pub fn tree(report: &SortReport, target: &File) -> TreeNode {
+    let mut root = TreeNode::new(target.copy());
+    for (old, new) in report.sorted() {
+
+        // The file is wherever it is, depending on whether it has been moved yet
+        let size = [old, new].iter().find_map(|path| fs::metadata(path.as_path()).ok()).map_or(0, |m| m.len());
+        let relative = new.pathbuf.strip_prefix(&target.pathbuf).unwrap_or(&new.pathbuf);
+        let dirs: Vec<String> = relative.parent()
+            .into_iter()
+            .flat_map(|parent| parent.components())
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect();
+        root.add(&dirs, TreeFile { name: new.file_name(), old: old.copy(), new: new.copy(), size });
+    }
+    root.sort();
+    root
+}
@@ -0,0 +1,135 @@
+//! Template placeholders filled in from outside the library. See
+//! [`MetadataProvider`].
+//!
+//! The built-in placeholders of [`Sorter::dir_format`](crate::Sorter::dir_format)
+//! only know what is in the files themselves. A [`MetadataProvider`] fills in
+//! placeholders of its own from what only the application knows, like the client
+//! or the project code of a file, looked up in a database or a CSV file, or
+//! parsed out of its name, so that `"{client}/{project}/%Y/"` sorts each
+//! client's files into a tree of their own. Any number of providers can be
+//! registered on a [`Sorter`](crate::Sorter) with
+//! [`Sorter::register_provider`](crate::Sorter::register_provider).
+
+use crate::structs::File;
+use std::collections::HashMap;
+use std::{fmt, sync::Arc};
+
+#[cfg(test)]
+/// Tests for metadata providers. Each test is named after the struct it tests,
+/// prefixed with test.
+mod tests {
+
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use super::{MetadataProvider, Providers};
+    use crate::structs::File;
+
+    /// A [`MetadataProvider`] that gives every file the same values
+    struct Fixed(&'static [(&'static str, &'static str)]);
+    impl MetadataProvider for Fixed {
+        fn variables(&self, _path: &File, names: &[&str]) -> HashMap<String, String> {
+            self.0.iter()
+                .filter(|(name, _)| names.contains(name))
+                .map(|(name, value)| (String::from(*name), String::from(*value)))
+                .collect()
+        }
+    }
+
+    #[test]
+    /// Test [`Providers`]
+    fn test_providers() {
+        let (first, second): (Arc<dyn MetadataProvider>, Arc<dyn MetadataProvider>) = (
+            Arc::new(Fixed(&[("client", "Acme")])),
+            Arc::new(Fixed(&[("client", "Other"), ("project", "P-042")])),
+        );
+        let mut providers = Providers::default();
+        assert!(providers.is_empty());
+        providers.register(first.clone());
+        providers.register(second.clone());
+        assert_eq!(providers.len(), 2);
+
+        // The first provider to fill in a placeholder wins, and only the
+        // placeholders asked for are filled in
+        let vars = providers.variables(&File::from("a/report.pdf"), &["client", "project"]);
+        assert_eq!(vars["client"], "Acme");
+        assert_eq!(vars["project"], "P-042");
+        assert_eq!(providers.variables(&File::from("a/report.pdf"), &["project"]).len(), 1);
+
+        // Providers are only equal if they hold the same providers
+        let mut other = Providers::default();
+        other.register(first);
+        other.register(second);
+        assert_eq!(providers, other);
+        assert_ne!(providers, Providers::default());
+        assert_eq!(format!("{:?}", providers), "Providers(2)");
+    }
+}
+
+/// Something that fills in template placeholders of its own for each file, from
+/// what only the application knows.
+///
+/// Providers are shared between the threads of a sort, so they take `&self`;
+/// ones that cache what they look up should keep it behind a
+/// [`Mutex`](std::sync::Mutex).
+pub trait MetadataProvider: Send + Sync {
+
+    /// Return the values of the placeholders of `names` that this provider
+    /// knows, for `path`, by their names. `names` are only the placeholders that
+    /// no built-in placeholder fills in. Placeholders that no provider fills in
+    /// are left as they are, so a provider should give the files it knows
+    /// nothing about a value too, like `unknown_client`.
+    fn variables(&self, path: &File, names: &[&str]) -> HashMap<String, String>;
+}
+
+/// The metadata providers registered on a [`Sorter`](crate::Sorter), which are
+/// asked for the values of placeholders in the order they were registered.
+#[derive(Clone, Default)]
+pub struct Providers(Vec<Arc<dyn MetadataProvider>>);
+impl Providers {
+
+    /// Add `provider` to the providers.
+    pub fn register(&mut self, provider: Arc<dyn MetadataProvider>) {
+        self.0.push(provider);
+    }
+
+    /// Return the number of providers.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Return [`true`] if there are no providers.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Return the values of the placeholders of `names` for `path`, from the
+    /// first provider that fills in each of them. Later providers are only asked
+    /// for the placeholders that are still missing.
+    pub fn variables(&self, path: &File, names: &[&str]) -> HashMap<String, String> {
+        let mut vars: HashMap<String, String> = HashMap::new();
+        for provider in &self.0 {
+            let missing: Vec<&str> = names.iter().copied().filter(|name| !vars.contains_key(*name)).collect();
+            if missing.is_empty() {
+                break;
+            }
+            for (name, value) in provider.variables(path, &missing) {
+                if missing.contains(&name.as_str()) {
+                    vars.insert(name, value);
+                }
+            }
+        }
+        vars
+    }
+}
+impl fmt::Debug for Providers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Providers({})", self.0.len())
+    }
+}
+impl PartialEq for Providers {
+
+    /// Providers are equal if they hold the very same providers, in the same order.
+    fn eq(&self, other: &Providers) -> bool {
+        self.0.len() == other.0.len() && self.0.iter().zip(&other.0).all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+}
@@ -0,0 +1,185 @@
+//! Walking the source without surprises from links to directories and cloud
+//! placeholders. See [`ReparsePolicy`] and
+//! [`Sorter::skip_placeholders`](crate::Sorter::skip_placeholders).
+//!
+//! On Windows, directory junctions and symbolic links to directories are
+//! reparse points: entries that stand in for a directory somewhere else. They
+//! are symbolic links to directories on other platforms. Moving one moves the
+//! link rather than the files it leads to, and following one can lead out of the
+//! source, or around in circles.
+//!
+//! Files synced by OneDrive, and other cloud storage using the Windows cloud
+//! files API, can be placeholders: only their metadata is on disk, and their
+//! contents are downloaded as soon as anything reads them. Placeholders are only
+//! detected on Windows.
+
+use crate::counters::COUNTERS_FILE_NAME;
+use crate::history::HISTORY_FILE_NAME;
+use crate::lock::LOCK_FILE_NAME;
+use crate::manifest::INTEGRITY_MANIFEST_NAME;
+use crate::structs::File;
+use serde::{Deserialize, Serialize};
+use std::{fs, io};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+#[cfg(test)]
+/// Tests for walking the source. Each test is named after the function it tests,
+/// prefixed with test.
+mod tests {
+
+    use std::{env, fs, io};
+    use super::{is_placeholder, source_files, ReparsePolicy};
+    use crate::structs::{File, Join};
+
+    #[test]
+    #[cfg(unix)]
+    /// Test [`source_files`]
+    fn test_source_files() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_source_files"));
+        let (source, elsewhere) = (dir.join(String::from("source")), dir.join(String::from("elsewhere")));
+        fs::create_dir_all(source.to_path_buf()).expect("Failed to create test dir.");
+        fs::create_dir_all(elsewhere.to_path_buf()).expect("Failed to create test dir.");
+        fs::write(source.join(String::from("a.txt")).to_path_buf(), "a").expect("Failed to write test file.");
+        fs::write(elsewhere.join(String::from("b.txt")).to_path_buf(), "b").expect("Failed to write test file.");
+        let (link, cycle) = (source.join(String::from("link")), source.join(String::from("cycle")));
+        std::os::unix::fs::symlink(elsewhere.to_path_buf(), link.to_path_buf()).expect("Failed to create test link.");
+        std::os::unix::fs::symlink(source.to_path_buf(), cycle.to_path_buf()).expect("Failed to create test link.");
+        // A socket is one of the special files
+        let socket = source.join(String::from("app.sock"));
+        let _listener = std::os::unix::net::UnixListener::bind(socket.to_path_buf()).expect("Failed to create test socket.");
+
+        let (mut skipped, special, unreadable) = source_files(source.as_path(), ReparsePolicy::Skip);
+        skipped.sort_by(|a, b| a.pathbuf.cmp(&b.pathbuf));
+        assert_eq!(skipped, vec![source.join(String::from("a.txt"))]);
+        assert_eq!(special, vec![socket]);
+        assert!(unreadable.is_empty());
+
+        // A source that can't be read is reported, instead of panicking
+        let missing = dir.join(String::from("missing"));
+        let (files, _, unreadable) = source_files(missing.as_path(), ReparsePolicy::Skip);
+        assert!(files.is_empty());
+        assert!(matches!(&unreadable[..], [(path, error)] if *path == missing && error.kind() == io::ErrorKind::NotFound));
+
+        let (mut files, _, _) = source_files(source.as_path(), ReparsePolicy::File);
+        files.sort_by(|a, b| a.pathbuf.cmp(&b.pathbuf));
+        assert_eq!(files, vec![source.join(String::from("a.txt")), cycle.clone(), link.clone()]);
+
+        // Links back into the source aren't followed, so nothing is found twice
+        let (mut followed, _, _) = source_files(source.as_path(), ReparsePolicy::Follow);
+        followed.sort_by(|a, b| a.pathbuf.cmp(&b.pathbuf));
+        assert_eq!(followed, vec![source.join(String::from("a.txt")), link.join(String::from("b.txt"))]);
+
+        assert!(!is_placeholder(source.join(String::from("a.txt")).as_path()));
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+}
+
+/// The attributes of files whose contents aren't on disk: offline, recalled
+/// when opened, and recalled when read. OneDrive's "online-only" files have the
+/// last one.
+#[cfg(windows)]
+const PLACEHOLDER_ATTRIBUTES: u32 = 0x1000 | 0x40000 | 0x400000;
+
+/// What to do with links to directories in the source: directory junctions and
+/// symbolic links to directories on Windows, and symbolic links to directories
+/// elsewhere.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReparsePolicy {
+    /// Leave them where they are, along with the files they lead to. This is the
+    /// default.
+    #[default]
+    Skip,
+    /// Sort the links themselves, like files, dated by the links rather than by
+    /// the directories they lead to.
+    File,
+    /// Sort the files in the directories they lead to, as if they were in the
+    /// source. Links that lead back into the source, or into a directory that
+    /// has already been walked, aren't followed, so no file is sorted twice.
+    Follow
+}
+
+/// Return all the files in `source`, excluding the directories, and handling
+/// the links to directories according to `links`. Special files, like FIFOs,
+/// sockets, and device nodes, which can't be sorted like files, are returned
+/// separately, after the files, and so are the paths that couldn't be read, with
+/// their errors. The lock, counters, history, and integrity manifest files of
+/// sorts into `source`, if it has been a target (see [`lock`](crate::lock),
+/// [`counters`](crate::counters), [`history`](crate::history), and
+/// [`INTEGRITY_MANIFEST_NAME`]), are left out.
+pub fn source_files(source: &Path, links: ReparsePolicy) -> (Vec<File>, Vec<File>, Vec<(File, io::Error)>) {
+    let (mut files, mut special, mut unreadable) = (Vec::new(), Vec::new(), Vec::new());
+    let mut walked: Vec<PathBuf> = fs::canonicalize(source).into_iter().collect();
+    let mut roots = vec![source.to_path_buf()];
+    while let Some(root) = roots.pop() {
+        for entry in WalkDir::new(&root) {
+
+            // The roots are directories, even the ones reached by links. Those
+            // that can't be read, or have gone since, are reported rather than
+            // walked.
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    let path = error.path().unwrap_or(&root).to_path_buf();
+                    unreadable.push((File::from(path), io::Error::from(error)));
+                    continue;
+                },
+            };
+            let state = [LOCK_FILE_NAME, COUNTERS_FILE_NAME, HISTORY_FILE_NAME, INTEGRITY_MANIFEST_NAME].iter().any(|name| entry.file_name() == *name);
+            if entry.depth() == 0 || (entry.depth() == 1 && root == source && state) {
+                continue;
+            }
+            if entry.path_is_symlink() && is_dir_link(entry.path()) {
+                match links {
+                    ReparsePolicy::Skip => (),
+                    ReparsePolicy::File => files.push(File::from(entry.path())),
+                    ReparsePolicy::Follow => {
+                        if let Ok(target) = fs::canonicalize(entry.path()) {
+                            if !walked.iter().any(|dir| target.starts_with(dir)) {
+                                walked.push(target);
+                                roots.push(entry.path().to_path_buf());
+                            }
+                        }
+                    },
+                }
+            } else if is_special(entry.file_type()) {
+                special.push(File::from(entry.path()));
+            } else {
+                match entry.metadata() {
+                    Ok(metadata) if metadata.is_dir() => (),
+                    Ok(_) => files.push(File::from(entry.path())),
+                    Err(error) => unreadable.push((File::from(entry.path()), io::Error::from(error))),
+                }
+            }
+        }
+    }
+    (files, special, unreadable)
+}
+
+/// Return [`true`] if `file_type` is neither a file, a directory, nor a
+/// symbolic link.
+fn is_special(file_type: fs::FileType) -> bool {
+    !file_type.is_file() && !file_type.is_dir() && !file_type.is_symlink()
+}
+
+/// Return [`true`] if `path` is a link that leads to a directory.
+fn is_dir_link(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_dir())
+}
+
+#[cfg(windows)]
+/// Return [`true`] if `path` is a cloud placeholder, whose contents would be
+/// downloaded by reading it.
+pub fn is_placeholder(path: &Path) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_attributes() & PLACEHOLDER_ATTRIBUTES != 0)
+}
+
+#[cfg(not(windows))]
+/// Cloud placeholders are only detected on Windows, so no file is one.
+pub fn is_placeholder(_path: &Path) -> bool {
+    false
+}
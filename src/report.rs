@@ -0,0 +1,345 @@
+//! The results of sorting. See [`Sorter::sort_report`](crate::Sorter::sort_report).
+
+use crate::audit::SuspiciousDate;
+use crate::errors::SortError;
+use crate::plan::SortPlan;
+use crate::preview::{self, TreeNode};
+use crate::structs::File;
+use std::{collections::{HashMap, HashSet}, fmt, io};
+
+#[cfg(test)]
+/// Tests for sort reports. Each test is named after the method it tests, prefixed
+/// with test.
+mod tests {
+
+    use std::io;
+    use super::{FileStatus, Progress, SortReport, SortWarning};
+    use crate::errors::SortError;
+    use crate::structs::File;
+
+    /// Return a report of two files, the second of which failed
+    fn report() -> SortReport {
+        SortReport {
+            old: vec![File::from("a.jpg"), File::from("b.jpg")],
+            new: vec![File::from("2023/a.jpg"), File::from("2023/b.jpg")],
+            failures: vec![(File::from("b.jpg"), SortError::from(io::Error::other("full")))],
+            warnings: vec![(File::from("a.jpg"), SortWarning::DateTypeFallback('m'))],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    /// Test [`SortReport::entries`]
+    fn test_sortreport_entries() {
+        let report = report();
+        let entries = report.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].old, entries[0].new), (&File::from("a.jpg"), &File::from("2023/a.jpg")));
+        assert!(entries[0].error.is_none());
+        assert_eq!(entries[0].warnings, vec![&SortWarning::DateTypeFallback('m')]);
+        assert!(matches!(entries[1].error, Some(SortError::IoError(_))));
+        assert!(entries[1].warnings.is_empty());
+    }
+
+    #[test]
+    /// Test [`Progress::counters`]
+    fn test_progress_counters() {
+        let (old, new) = (File::from("a.jpg"), File::from("2023/a.jpg"));
+        let progress = Progress { done: 1, total: 4, percent: 25, current: Some((&old, &new, FileStatus::Moved)) };
+        assert_eq!(progress.counters(), (1, 4, 25));
+    }
+
+    #[test]
+    /// Test [`SortReport::sorted`]
+    fn test_sortreport_sorted() {
+        let report = report();
+        assert_eq!(report.sorted().collect::<Vec<_>>(), vec![(&File::from("a.jpg"), &File::from("2023/a.jpg"))]);
+        assert_eq!(report.count(), 2);
+        assert!(!report.is_ok());
+    }
+}
+
+/// An anomaly that was worked around while sorting a file, in lenient mode.
+/// See [`Strictness`](crate::errors::Strictness).
+#[derive(Debug, PartialEq)]
+pub enum SortWarning {
+    /// The destination of the file was already in the target, so the file was
+    /// given a sequential name instead. Destinations are checked again right
+    /// before files are moved, so this is also the destination that was taken
+    /// after the sort was planned.
+    DestinationExists(File),
+    /// The first date type in [`Sorter::date_type`](crate::Sorter::date_type)
+    /// wasn't available for the file, so it was sorted by this fallback date type
+    /// instead, as in `'m'`.
+    DateTypeFallback(char),
+    /// The file is a hard link to this file, which is sorted before it, so it
+    /// is linked to that file's new path rather than copied again.
+    HardLinked(File),
+    /// The destination of the file was already in the target, but older, so it
+    /// was replaced with the file (see
+    /// [`Sorter::conflict_policy`](crate::Sorter::conflict_policy)).
+    Replaced(File),
+    /// The file changed or disappeared after the sort was planned, or it is a
+    /// sidecar whose primary did, so it was left where it is (see
+    /// [`Sorter::stale_policy`](crate::Sorter::stale_policy)).
+    Changed,
+    /// The file changed after the sort was planned, or it is a sidecar whose
+    /// primary did, so it was sorted again, to somewhere else than this
+    /// destination it was planned to go to (see
+    /// [`Sorter::stale_policy`](crate::Sorter::stale_policy)).
+    Replanned(File),
+    /// The date of the file can't be right, so it was sorted into
+    /// [`Sorter::suspicious_dir`](crate::Sorter::suspicious_dir), if there is
+    /// one, or by its date anyway (see
+    /// [`Sorter::audit_dates`](crate::Sorter::audit_dates)).
+    SuspiciousDate(SuspiciousDate)
+}
+
+/// A part of a [`Sorter`](crate::Sorter)'s configuration that is probably not
+/// what was meant, because it contradicts another part. See
+/// [`Sorter::config_warnings`](crate::Sorter::config_warnings).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigWarning {
+    /// The option is ignored, because the option of the same kind that lets
+    /// only some types through overrides it, as `only_type` does
+    /// `exclude_type`. Holds the name of the ignored option.
+    Overridden(&'static str),
+    /// The type is both in the option named first and in the option named second,
+    /// as in `("only_type", "exclude_type", "jpg")`. The first one wins.
+    Contradicts(&'static str, &'static str, String)
+}
+impl fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigWarning::Overridden(option) => write!(
+                f, "{} is ignored, because it is overridden by {}",
+                option, option.replace("exclude_", "only_")),
+            ConfigWarning::Contradicts(winner, loser, t) => write!(
+                f, "{:?} is in both {} and {}; {} wins", t, winner, loser, winner),
+        }
+    }
+}
+
+/// About how many files a sort would process, and how big they are. See
+/// [`Sorter::estimate`](crate::Sorter::estimate).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Estimate {
+    /// The number of files, including sidecars.
+    pub files: usize,
+    /// The total size of the files, in bytes.
+    pub bytes: u64
+}
+impl fmt::Display for Estimate {
+
+    /// Write the estimate as in `about 42000 files, 380.2 GB`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+        let (mut size, mut unit) = (self.bytes as f64, 0);
+        while size >= 1000.0 && unit < UNITS.len() - 1 {
+            size /= 1000.0;
+            unit += 1;
+        }
+        match unit {
+            0 => write!(f, "about {} files, {} B", self.files, self.bytes),
+            _ => write!(f, "about {} files, {:.1} {}", self.files, size, UNITS[unit]),
+        }
+    }
+}
+
+/// A configuration of a [`Sorter`](crate::Sorter) that works, but is risky. See
+/// [`Sorter::check`](crate::Sorter::check).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Risk {
+    /// The target is inside the source, so sorted files can be sorted again by
+    /// the next sort.
+    TargetInSource,
+    /// The date format has no date in it, so the new names don't say when the
+    /// files are from.
+    NoDateInFormat,
+    /// The old names aren't kept, and the date format doesn't tell apart files
+    /// from the same second, so all the files from the same day, minute, or so
+    /// get sequential names.
+    NameCollisions,
+    /// The files are copied to or from a network share, where they are most
+    /// likely to be damaged on the way, so they should be sorted with
+    /// [`ExecutionMode::ExecuteVerified`](crate::execution::ExecutionMode::ExecuteVerified).
+    UnverifiedNetworkCopy
+}
+impl fmt::Display for Risk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Risk::TargetInSource => write!(f, "the target is inside the source, so sorted files can be sorted again"),
+            Risk::NoDateInFormat => write!(f, "date_format has no date in it"),
+            Risk::NameCollisions => write!(
+                f, "date_format doesn't have the seconds and preserve_name is off, so many files will get sequential names"),
+            Risk::UnverifiedNetworkCopy => write!(
+                f, "files are copied over the network, so they should be sorted with verification"),
+        }
+    }
+}
+
+/// One file of a [`SortReport`], and what happened to it.
+#[derive(Debug)]
+pub struct SortEntry<'r> {
+    /// The old path of the file.
+    pub old: &'r File,
+    /// The new path of the file.
+    pub new: &'r File,
+    /// Why the file failed to sort, if it did.
+    pub error: Option<&'r SortError>,
+    /// The anomalies that were worked around while sorting the file.
+    pub warnings: Vec<&'r SortWarning>
+}
+
+/// What happened to a file during a sort.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileStatus {
+    /// The file was moved to its new path.
+    Moved,
+    /// The file would be moved to its new path, but this is a dry run.
+    Planned,
+    /// The move wasn't confirmed, or the file changed after the sort was planned
+    /// (see [`Sorter::stale_policy`](crate::Sorter::stale_policy)), so the file
+    /// was left where it is.
+    Skipped,
+    /// The file couldn't be moved. The error is in the [`SortReport`].
+    Failed,
+    /// The file was open in another process right before it would have been
+    /// moved (see [`Sorter::skip_open_files`](crate::Sorter::skip_open_files)),
+    /// so it was left where it is.
+    InUse
+}
+
+/// How far along a sort is, passed to the callback of
+/// [`Sorter::sort_report_with_callback`](crate::Sorter::sort_report_with_callback).
+#[derive(Clone, Copy, Debug)]
+pub struct Progress<'a> {
+    /// The number of files sorted before the current one.
+    pub done: usize,
+    /// The number of files to sort.
+    pub total: usize,
+    /// The percent completed.
+    pub percent: usize,
+    /// The old and new paths of the file that was just processed, and what
+    /// happened to it, or [`None`] once the sort is done.
+    pub current: Option<(&'a File, &'a File, FileStatus)>
+}
+impl Progress<'_> {
+
+    /// Return the counters as `(done, total, percent)`, the way the callback of
+    /// [`Sorter::sort_with_callback`](crate::Sorter::sort_with_callback) takes them.
+    pub fn counters(&self) -> (usize, usize, usize) {
+        (self.done, self.total, self.percent)
+    }
+}
+
+/// How far along the copy of a single large file is, passed to
+/// [`Observer::on_bytes`](crate::observers::Observer::on_bytes).
+#[derive(Clone, Copy, Debug)]
+pub struct ByteProgress<'a> {
+    /// The file being copied.
+    pub file: &'a File,
+    /// The number of bytes copied so far.
+    pub copied: u64,
+    /// The size of the file, in bytes.
+    pub total: u64
+}
+
+/// What a sort did, or would do in a dry run.
+#[derive(Debug, Default)]
+pub struct SortReport {
+    /// The old paths of all the files sorted.
+    pub old: Vec<File>,
+    /// The new paths of all the files sorted, index-wise with `old`, so `old[0]`
+    /// is moved to `new[0]`, etc.
+    pub new: Vec<File>,
+    /// The files that couldn't be sorted, by their old paths, and why. The other
+    /// files are still sorted, unless the sort is strict.
+    pub failures: Vec<(File, SortError)>,
+    /// The anomalies that were worked around, by the old paths of their files.
+    pub warnings: Vec<(File, SortWarning)>,
+    /// The files that were left where they are, because their moves weren't
+    /// confirmed in [`ExecutionMode::Interactive`](crate::execution::ExecutionMode::Interactive) mode,
+    /// because they changed after the sort was planned (see
+    /// [`Sorter::stale_policy`](crate::Sorter::stale_policy)), or because they
+    /// have no date (see [`Sorter::missing_date`](crate::Sorter::missing_date)).
+    pub skipped: Vec<File>,
+    /// The files that were left where they are because they were open in other
+    /// processes, while the sort was planned or right before they would have been
+    /// moved (see [`Sorter::skip_open_files`](crate::Sorter::skip_open_files)).
+    pub in_use: Vec<File>,
+    /// The special files in the source, like FIFOs, sockets, and device nodes,
+    /// which were left where they are, since they aren't really files to sort.
+    pub special: Vec<File>,
+    /// The files that were already in the target (see
+    /// [`Sorter::skip_identical`](crate::Sorter::skip_identical)), by their old
+    /// paths, with the identical files in the target, or, with
+    /// [`ConflictPolicy::Update`](crate::conflicts::ConflictPolicy::Update), the
+    /// files in the target that are as new. They weren't sorted.
+    pub present: Vec<(File, File)>,
+    /// The files that couldn't be sorted, by their old paths, and why, which
+    /// were moved into [`Sorter::quarantine_dir`](crate::Sorter::quarantine_dir)
+    /// instead (or would be, in a dry run). They are also in `old` and `new`,
+    /// with their paths in the quarantine.
+    pub quarantined: Vec<(File, SortError)>,
+    /// The parts of the configuration that contradict each other.
+    pub config_warnings: Vec<ConfigWarning>,
+    /// The notifications (see [`Sorter::notifications`](crate::Sorter::notifications))
+    /// that couldn't be sent, by their indices, with their errors.
+    pub notification_failures: Vec<(usize, io::Error)>
+}
+impl SortReport {
+
+    /// Return the number of files sorted, including the ones that failed.
+    pub fn count(&self) -> usize {
+        self.old.len()
+    }
+
+    /// Return [`true`] if no files failed to sort.
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Return an entry for each file sorted, in order, with its old and new paths,
+    /// its error if it failed, and its warnings.
+    pub fn entries(&self) -> Vec<SortEntry<'_>> {
+
+        // The errors and warnings are looked up by path, rather than searched
+        // for every file
+        let mut errors: HashMap<&File, &SortError> = HashMap::new();
+        for (path, error) in &self.failures {
+            errors.entry(path).or_insert(error);
+        }
+        let mut warnings: HashMap<&File, Vec<&SortWarning>> = HashMap::new();
+        for (path, warning) in &self.warnings {
+            warnings.entry(path).or_default().push(warning);
+        }
+        self.old.iter().zip(&self.new)
+            .map(|(old, new)| SortEntry {
+                old,
+                new,
+                error: errors.get(old).copied(),
+                warnings: warnings.get(old).cloned().unwrap_or_default()
+            })
+            .collect()
+    }
+
+    /// Return the tree of the directories in `target` that the files are sorted
+    /// into, with the number and size of the files in each, for showing the
+    /// result of a sort, as of a dry run, in a tree widget. See [`preview`].
+    pub fn tree(&self, target: &File) -> TreeNode {
+        preview::tree(self, target)
+    }
+
+    /// Return the plan of the files sorted, as of a dry run, to keep and compare
+    /// with later plans. See [`SortPlan`].
+    pub fn plan(&self) -> SortPlan {
+        SortPlan::of(self)
+    }
+
+    /// Return the old and new paths of the files that were sorted without failing.
+    pub fn sorted(&self) -> impl Iterator<Item = (&File, &File)> {
+        let failed: HashSet<&File> = self.failures.iter().map(|(path, _)| path).collect();
+        self.old.iter().zip(&self.new).filter(move |(old, _)| !failed.contains(old))
+    }
+}
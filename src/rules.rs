@@ -0,0 +1,134 @@
+//! Sorting some files into trees of their own, by what they are about. See
+//! [`Rule`].
+//!
+//! A sort puts every file into the tree of its `dir_format`. Rules pick files
+//! out of that by conditions on the files themselves, and sort them into their
+//! own trees instead, as in photos tagged `family` going to `Family/%Y/`, or
+//! documents with `invoice` in their names going to `Finance/%Y/`. The first
+//! rule that a file meets decides where it goes, and, if the rule says so, how
+//! it gets there: photos can be copied to a NAS while junk downloads are moved
+//! to a local archive, all in one sort.
+
+use crate::structs::File;
+use crate::transfer::TransferMode;
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+/// Tests for rules. Each test is named after the function or struct it tests,
+/// prefixed with test.
+mod tests {
+
+    use super::{matching_rule, Rule};
+    use crate::structs::File;
+
+    #[test]
+    /// Test [`Rule`], [`Rule::matches_name`], and [`matching_rule`]
+    fn test_matching_rule() {
+
+        // Rules without conditions never match
+        let rules = [Rule::new("Everything/", &[]), Rule::new("Family/%Y/", &["Family"])];
+        assert_eq!(matching_rule(&File::from("a/notes.txt"), &rules), None);
+
+        // Names match by their stems, ignoring case
+        let finance = Rule { name_keywords: vec![String::from("invoice"), String::from("receipt")], ..Rule::new("Finance/%Y/", &[]) };
+        let rules = [finance, Rule::new("Family/%Y/", &["Family"])];
+        assert_eq!(matching_rule(&File::from("a/Invoice-2023-07.pdf"), &rules), Some(&rules[0]));
+        assert_eq!(matching_rule(&File::from("a/Amazon Receipt.PDF"), &rules), Some(&rules[0]));
+        assert_eq!(matching_rule(&File::from("a/notes.receipt"), &rules), None);
+
+        #[cfg(feature = "metadata")]
+        {
+            use crate::testing::{Entry, Sandbox};
+            let sandbox = Sandbox::new(&[
+                Entry::file("source/a.cr2").contents("raw"),
+                Entry::file("source/a.xmp").contents(
+                    "<x:xmpmeta><dc:subject><rdf:Bag><rdf:li>family</rdf:li></rdf:Bag></dc:subject></x:xmpmeta>"
+                ),
+            ]).expect("Failed to create sandbox.");
+            assert_eq!(matching_rule(&sandbox.path("source/a.cr2"), &rules), Some(&rules[1]));
+        }
+    }
+}
+
+/// A rule for sorting the files that meet its conditions into a tree of their
+/// own. A file has to meet all of the rule's conditions, but only one keyword
+/// of each. In JSON configuration, it looks like
+/// `{"dir_format": "Family/%Y/", "tags": ["family"]}`, or
+/// `{"dir_format": "Finance/%Y/", "name_keywords": ["invoice", "receipt"]}`,
+/// or `{"dir_format": "/mnt/nas/Photos/%Y/", "tags": ["family"], "transfer": "copy"}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub struct Rule {
+    /// The format of the directories to sort the files into, like
+    /// [`Sorter::dir_format`](crate::Sorter::dir_format), with the same
+    /// placeholders. It is relative to the target, unless it is absolute, which
+    /// sorts the files into another target altogether.
+    pub dir_format: String,
+    /// The keywords that the files are tagged with in their metadata, as by
+    /// Lightroom (see the `keywords` module). Files tagged with any of
+    /// them meet this condition, ignoring case. Needs the `metadata` feature;
+    /// without it, files have no tags. Empty by default, which is no condition.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Keywords that the names of the files contain, without their extensions,
+    /// as in `invoice` for `Invoice-2023-07.pdf`. Files whose names contain any
+    /// of them meet this condition, ignoring case. Empty by default, which is no
+    /// condition.
+    #[serde(default)]
+    pub name_keywords: Vec<String>,
+    /// How the files get into the rule's tree, instead of
+    /// [`Sorter::transfer`](crate::Sorter::transfer). Their sidecars go the same
+    /// way. [`None`] by default, which is the sorter's `transfer`.
+    #[serde(default)]
+    pub transfer: Option<TransferMode>
+}
+impl Rule {
+
+    /// Return a new [`Rule`] sorting files tagged with any of `tags` into
+    /// `dir_format`.
+    pub fn new(dir_format: &str, tags: &[&str]) -> Rule {
+        Rule {
+            dir_format: String::from(dir_format),
+            tags: tags.iter().map(|t| String::from(*t)).collect(),
+            name_keywords: Vec::new(),
+            transfer: None
+        }
+    }
+
+    /// Return [`true`] if the rule has no conditions, and so matches nothing.
+    pub fn is_empty(&self) -> bool {
+        self.tags.iter().chain(&self.name_keywords).all(|keyword| keyword.is_empty())
+    }
+
+    /// Return [`true`] if the name of `path` contains one of our
+    /// `name_keywords`, ignoring case, or if there are none.
+    pub fn matches_name(&self, path: &File) -> bool {
+        let stem = path.file_stem().to_lowercase();
+        self.name_keywords.is_empty()
+            || self.name_keywords.iter().any(|keyword| !keyword.is_empty() && stem.contains(&keyword.to_lowercase()))
+    }
+}
+
+/// Return the first of `rules` whose conditions `path` meets, if there is one.
+/// The metadata of `path` is only read if a rule needs it, and only once.
+pub fn matching_rule<'a>(path: &File, rules: &'a [Rule]) -> Option<&'a Rule> {
+    let mut tags: Option<Vec<String>> = None;
+    rules.iter().filter(|rule| !rule.is_empty()).find(|rule| {
+        rule.matches_name(path) && (rule.tags.is_empty() || {
+            let tags = tags.get_or_insert_with(|| read_tags(path));
+            rule.tags.iter().any(|tag| tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+        })
+    })
+}
+
+/// Return the keywords `path` is tagged with.
+#[cfg(feature = "metadata")]
+fn read_tags(path: &File) -> Vec<String> {
+    crate::keywords::read(path)
+}
+
+/// Return no keywords, since they can't be read without the `metadata` feature.
+#[cfg(not(feature = "metadata"))]
+fn read_tags(_path: &File) -> Vec<String> {
+    Vec::new()
+}
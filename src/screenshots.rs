@@ -0,0 +1,142 @@
+//! Heuristic detection of screenshots.
+//!
+//! Screenshots are recognized by their file names (as in `Screenshot_20230714-103012.png`
+//! or `Screen Shot 2023-07-14 at 10.30.12.png`), by the text metadata that screenshot
+//! tools write into PNG files, and, with the `exif` feature, by the EXIF software tag.
+//! See [`Sorter::screenshot_dir`](crate::Sorter::screenshot_dir) for routing them
+//! to a separate tree.
+
+use crate::structs::File;
+use std::fs;
+use std::io::{self, Read};
+
+#[cfg(test)]
+/// Tests for screenshot detection. Each test is named after the function it tests,
+/// prefixed with test.
+mod tests {
+
+    use std::{env, fs};
+    use super::{is_screenshot, is_screenshot_name, png_text};
+    use crate::structs::{File, Join};
+
+    /// Return the bytes of a PNG chunk of type `kind` holding `data`. The CRC is
+    /// left empty, since we don't check it.
+    fn chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut bytes = (data.len() as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(kind);
+        bytes.extend_from_slice(data);
+        bytes.extend_from_slice(&[0; 4]);
+        bytes
+    }
+
+    #[test]
+    /// Test [`is_screenshot_name`]
+    fn test_is_screenshot_name() {
+        assert!(is_screenshot_name(&File::from("Screenshot_20230714-103012.png")));
+        assert!(is_screenshot_name(&File::from("Screen Shot 2023-07-14 at 10.30.12.png")));
+        assert!(is_screenshot_name(&File::from("Bildschirmfoto vom 2023-07-14.png")));
+        assert!(!is_screenshot_name(&File::from("IMG_1234.jpg")));
+    }
+
+    #[test]
+    /// Test [`png_text`] and [`is_screenshot`] with PNG metadata
+    fn test_png_text() {
+
+        // A PNG file with a software tag from a screenshot tool
+        let mut bytes = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+        bytes.extend(chunk(b"IHDR", &[0; 13]));
+        bytes.extend(chunk(b"tEXt", b"Software\0gnome-screenshot"));
+        bytes.extend(chunk(b"IDAT", &[0; 4]));
+
+        let path = File::from(env::temp_dir()).join(String::from("sorterylib_test_png_text.png"));
+        fs::write(path.to_path_buf(), &bytes).expect("Failed to write test file.");
+
+        assert_eq!(png_text(&path).expect("Failed to read PNG."), vec![String::from("Software gnome-screenshot")]);
+        assert!(is_screenshot(&path));
+
+        fs::remove_file(path.to_path_buf()).expect("Failed to remove test file.");
+    }
+}
+
+/// Lowercase fragments of the file names that screenshot tools give their files,
+/// in a few languages.
+pub const SCREENSHOT_NAMES: [&str; 9] = [
+    "screenshot",
+    "screen shot",
+    "screen_shot",
+    "bildschirmfoto",
+    "capture d'écran",
+    "capture d’écran",
+    "captura de pantalla",
+    "schermafbeelding",
+    "istantanea"
+];
+
+/// The longest PNG chunk we read, so that corrupt files can't make us allocate
+/// huge buffers.
+const MAX_CHUNK_LENGTH: usize = 1 << 20;
+
+/// Return [`true`] if `path` looks like a screenshot, judging by its file name,
+/// its PNG text metadata, and (with the `exif` feature) its EXIF software tag.
+pub fn is_screenshot(path: &File) -> bool {
+    if is_screenshot_name(path) {
+        return true;
+    }
+    if path.extension().eq_ignore_ascii_case("png") {
+        if let Ok(text) = png_text(path) {
+            if text.iter().any(|t| t.to_lowercase().contains("screenshot")) {
+                return true;
+            }
+        }
+    }
+    #[cfg(feature = "exif")]
+    if let Some(software) = crate::exif::read(path).and_then(|d| d.software) {
+        if software.to_lowercase().contains("screenshot") {
+            return true;
+        }
+    }
+    false
+}
+
+/// Return [`true`] if the file name of `path` contains one of [`SCREENSHOT_NAMES`].
+pub fn is_screenshot_name(path: &File) -> bool {
+    let name = path.file_name().to_lowercase();
+    SCREENSHOT_NAMES.iter().any(|n| name.contains(n))
+}
+
+/// Return the uncompressed text metadata (`tEXt` and `iTXt` chunks) of the PNG
+/// file `path`, as `"<keyword> <text>"` strings. Only the chunks before the image
+/// data are read.
+pub fn png_text(path: &File) -> io::Result<Vec<String>> {
+
+    let mut file = fs::File::open(&path.pathbuf)?;
+    let mut signature = [0; 8];
+    file.read_exact(&mut signature)?;
+    if signature != [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'] {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a PNG file"));
+    }
+
+    let mut text = Vec::new();
+    loop {
+
+        // Each chunk is its length, type, data, and CRC
+        let mut header = [0; 8];
+        if file.read_exact(&mut header).is_err() {
+            break;
+        }
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let kind = &header[4..8];
+        if kind == b"IDAT" || kind == b"IEND" || length > MAX_CHUNK_LENGTH {
+            break;
+        }
+
+        let mut data = vec![0; length + 4];
+        file.read_exact(&mut data)?;
+        data.truncate(length);
+        if kind == b"tEXt" || kind == b"iTXt" {
+            let readable: Vec<u8> = data.into_iter().map(|b| if b == 0 { b' ' } else { b }).collect();
+            text.push(String::from_utf8_lossy(&readable).split_whitespace().collect::<Vec<&str>>().join(" "));
+        }
+    }
+    Ok(text)
+}
@@ -0,0 +1,140 @@
+//! Pairing of metadata sidecar files with their primary files.
+//!
+//! Photos often come with sidecar files holding extra metadata, like `.xmp`
+//! files from photo editors, `.json` files from Google Takeout, or `.aae` files
+//! from Apple devices. A sidecar is named either after its primary's stem
+//! (`IMG_1234.xmp`) or after its primary's full file name (`IMG_1234.jpg.json`).
+//! When [`Sorter::sidecar_types`](crate::Sorter::sidecar_types) is set, sidecars
+//! follow their primary file into the same destination directory and get the
+//! same new stem, instead of being sorted on their own.
+
+use crate::structs::File;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::Path;
+
+#[cfg(test)]
+/// Tests for sidecar pairing. Each test is named after the function it tests,
+/// prefixed with test.
+mod tests {
+
+    use super::{pair_sidecars, sidecar_destination};
+    use crate::structs::File;
+
+    #[test]
+    /// Test [`pair_sidecars`]
+    fn test_pair_sidecars() {
+
+        // The files to pair up
+        let files = vec![
+            File::from("/src/IMG_1.jpg"),
+            File::from("/src/IMG_1.xmp"),
+            File::from("/src/IMG_1.jpg.json"),
+            File::from("/src/IMG_2.HEIC"),
+            File::from("/src/IMG_2.AAE"),
+            File::from("/src/other/IMG_1.xmp"),
+            File::from("/src/lonely.json")
+        ];
+        let types = vec![String::from("xmp"), String::from("json"), String::from("aae")];
+        let pairs = pair_sidecars(&files, &types);
+
+        assert_eq!(pairs.get(&0), Some(&vec![1, 2]));
+        assert_eq!(pairs.get(&3), Some(&vec![4]));
+        assert_eq!(pairs.len(), 2);
+
+        // Pairing is off if there are no sidecar types
+        assert!(pair_sidecars(&files, &[]).is_empty());
+    }
+
+    #[test]
+    /// Test [`sidecar_destination`]
+    fn test_sidecar_destination() {
+        let primary = File::from("/src/IMG_1.jpg");
+        let new_primary = File::from("/target/2022/01/2022-01-01 10h32m02s.jpg");
+
+        assert_eq!(
+            sidecar_destination(&File::from("/src/IMG_1.xmp"), &primary, &new_primary),
+            File::from("/target/2022/01/2022-01-01 10h32m02s.xmp")
+        );
+        assert_eq!(
+            sidecar_destination(&File::from("/src/IMG_1.jpg.json"), &primary, &new_primary),
+            File::from("/target/2022/01/2022-01-01 10h32m02s.jpg.json")
+        );
+    }
+}
+
+/// The sidecar types commonly produced by photo software: `xmp`, `json`, and `aae`.
+pub const DEFAULT_SIDECAR_TYPES: [&str; 3] = ["xmp", "json", "aae"];
+
+/// Return [`true`] if the extension of `path` is one of `sidecar_types`, ignoring case.
+fn is_sidecar_type(path: &Path, sidecar_types: &[String]) -> bool {
+    match path.extension() {
+        None => false,
+        Some(ext) => sidecar_types.iter().any(|t| ext.eq_ignore_ascii_case(t)),
+    }
+}
+
+/// Pair the sidecar files in `files` with their primaries. Returns a map from
+/// the index of each primary file to the indices of its sidecars, in the order
+/// they appear in `files`. Files that aren't keys or values in the map aren't
+/// paired, and are sorted like any other file.
+///
+/// A sidecar is a file whose extension is one of `sidecar_types` (ignoring case),
+/// that sits in the same directory as its primary, and whose name minus the
+/// sidecar extension is either the primary's full file name or its stem.
+pub fn pair_sidecars(files: &[File], sidecar_types: &[String]) -> HashMap<usize, Vec<usize>> {
+
+    let mut pairs: HashMap<usize, Vec<usize>> = HashMap::new();
+    if sidecar_types.is_empty() {
+        return pairs;
+    }
+
+    // Index the possible primaries by their full names and by their stems
+    let mut by_name: HashMap<&Path, usize> = HashMap::new();
+    let mut by_stem: HashMap<(&Path, OsString), usize> = HashMap::new();
+    for (i, file) in files.iter().enumerate() {
+        if is_sidecar_type(&file.pathbuf, sidecar_types) {
+            continue;
+        }
+        by_name.insert(file.as_path(), i);
+        if let (Some(parent), Some(stem)) = (file.pathbuf.parent(), file.pathbuf.file_stem()) {
+            by_stem.entry((parent, stem.to_os_string())).or_insert(i);
+        }
+    }
+
+    // Look up the primary of each sidecar, first by full name, then by stem
+    for (i, file) in files.iter().enumerate() {
+        if !is_sidecar_type(&file.pathbuf, sidecar_types) {
+            continue;
+        }
+        let (parent, stem) = match (file.pathbuf.parent(), file.pathbuf.file_stem()) {
+            (Some(parent), Some(stem)) => (parent, stem),
+            _ => continue,
+        };
+        let primary = by_name.get(parent.join(stem).as_path())
+            .or_else(|| by_stem.get(&(parent, stem.to_os_string())));
+        if let Some(primary) = primary {
+            pairs.entry(*primary).or_default().push(i);
+        }
+    }
+    pairs
+}
+
+/// Return the destination of `sidecar`, given that its primary file `primary`
+/// is being sorted to `new_primary`. The sidecar keeps the naming style it had
+/// next to the old primary: `IMG_1.xmp` becomes `<new stem>.xmp`, and
+/// `IMG_1.jpg.json` becomes `<new name>.json`.
+pub fn sidecar_destination(sidecar: &File, primary: &File, new_primary: &File) -> File {
+
+    let mut name = if sidecar.pathbuf.file_stem() == primary.pathbuf.file_name() {
+        new_primary.pathbuf.file_name()
+    } else {
+        new_primary.pathbuf.file_stem()
+    }.unwrap_or_default().to_os_string();
+
+    if let Some(ext) = sidecar.pathbuf.extension() {
+        name.push(".");
+        name.push(ext);
+    }
+    File::from(new_primary.pathbuf.with_file_name(name))
+}
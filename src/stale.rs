@@ -0,0 +1,173 @@
+//! Files that changed between planning a sort and moving them. See
+//! [`StalePolicy`].
+//!
+//! A sort is planned from the files as they were when the source was walked.
+//! On a busy source, like a folder that a camera or a sync client is still
+//! writing to, files can change or disappear before their turn to be moved
+//! comes. Each file's size and modification time are kept with the plan, as a
+//! [`Snapshot`], and checked again right before the file is moved, so that the
+//! sort doesn't act on what it remembers of a file that isn't like that anymore.
+//! Their destinations are checked again too, in case something else took them
+//! in the meantime.
+
+use crate::open_files::OpenFiles;
+use crate::report::SortWarning;
+use crate::sidecars;
+use crate::structs::File;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+#[cfg(test)]
+/// Tests for stale files. Each test is named after the function or struct it
+/// tests, prefixed with test.
+mod tests {
+
+    use std::fs;
+    use std::time::Duration;
+    use super::Snapshot;
+    use crate::testing::{Entry, Sandbox};
+
+    #[test]
+    /// Test [`Snapshot`]
+    fn test_snapshot() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/a.jpg").contents("aaaa").modified_at(1_600_000_000),
+        ]).expect("Failed to create sandbox.");
+        let path = sandbox.path("source/a.jpg");
+        let snapshot = Snapshot::of(&path).expect("Failed to take snapshot.");
+        assert!(!snapshot.is_stale(&path, Duration::ZERO));
+
+        // Modification times may drift as far as the tolerance, sizes not at all
+        let time = |seconds| filetime::FileTime::from_unix_time(seconds, 0);
+        filetime::set_file_mtime(path.to_path_buf(), time(1_600_000_002)).unwrap();
+        assert!(snapshot.is_stale(&path, Duration::from_secs(1)));
+        assert!(!snapshot.is_stale(&path, Duration::from_secs(2)));
+        fs::write(path.to_path_buf(), "aaaaa").unwrap();
+        filetime::set_file_mtime(path.to_path_buf(), time(1_600_000_000)).unwrap();
+        assert!(snapshot.is_stale(&path, Duration::from_secs(2)));
+
+        fs::remove_file(path.to_path_buf()).unwrap();
+        assert!(snapshot.is_stale(&path, Duration::from_secs(2)));
+        assert_eq!(Snapshot::of(&path), None);
+    }
+}
+
+/// What to do with a file that changed or disappeared after the sort was
+/// planned, found when it is about to be moved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StalePolicy {
+    /// Leave the file where it is, and list it in the report as skipped, with a
+    /// [`SortWarning::Changed`]. This is the default.
+    #[default]
+    Skip,
+    /// Date the file again, and sort it to wherever that date takes it, on its
+    /// own rather than with the event or burst it was grouped into, with a
+    /// [`SortWarning::Replanned`]. Its sidecars go along with it. Files that
+    /// disappeared are skipped.
+    Replan,
+    /// Fail the file with a
+    /// [`SortError::FileChanged`](crate::errors::SortError::FileChanged), which
+    /// stops a strict sort.
+    Error
+}
+
+/// The size and modification time of a file, as it was when a sort was planned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    /// The size of the file, in bytes.
+    pub size: u64,
+    /// When the file was last modified, if the filesystem records it.
+    pub modified: Option<SystemTime>
+}
+impl Snapshot {
+
+    /// Return the snapshot of `path` as it is now, or [`None`] if it can't be
+    /// read. Symbolic links are taken as they are, not followed.
+    pub fn of(path: &File) -> Option<Snapshot> {
+        let metadata = fs::symlink_metadata(path.as_path()).ok()?;
+        Some(Snapshot { size: metadata.len(), modified: metadata.modified().ok() })
+    }
+
+    /// Return [`true`] if `path` is gone, or isn't like this snapshot anymore:
+    /// if its size is different, or its modification time is more than
+    /// `tolerance` away.
+    pub fn is_stale(&self, path: &File, tolerance: Duration) -> bool {
+        let Some(now) = Snapshot::of(path) else {
+            return true;
+        };
+        let drift = match (self.modified, now.modified) {
+            (Some(then), Some(now)) => then.duration_since(now).or_else(|_| now.duration_since(then)).unwrap_or_default(),
+            _ => Duration::ZERO,
+        };
+        now.size != self.size || drift > tolerance
+    }
+}
+
+/// The files replanned during a sort (see [`StalePolicy::Replan`]), shared by
+/// the threads that move files.
+#[derive(Debug, Default)]
+pub(crate) struct Replans {
+    /// The destinations of the plan, and of the files replanned so far.
+    pub taken: HashSet<File>,
+    /// The new destinations of the replanned files, by their indices in the plan.
+    pub moved: HashMap<usize, File>,
+    /// The files of `moved` that were given sequential names because their
+    /// destinations were taken after the sort was planned, rather than
+    /// replanned, and the sidecars that followed them.
+    pub collided: HashSet<usize>,
+    /// The files that were left where they are, by their indices in the plan.
+    pub skipped: HashSet<usize>
+}
+
+/// What is needed to check the files of a plan again before moving them.
+#[derive(Debug)]
+pub(crate) struct Revalidation {
+    /// The snapshots of the files of the plan, by their indices, or [`None`]
+    /// for files that couldn't be read while planning.
+    pub snapshots: Vec<Option<Snapshot>>,
+    /// The indices of the primaries of the sidecars in the plan, by the indices
+    /// of the sidecars.
+    pub primaries: HashMap<usize, usize>,
+    /// The indices of the files that replace the files at their destinations
+    /// (see [`SortWarning::Replaced`]).
+    pub replacing: HashSet<usize>,
+    /// The files replanned so far.
+    pub replans: Mutex<Replans>,
+    /// The files open in other processes, checked again right before each file
+    /// is moved, or [`None`] if open files aren't skipped.
+    pub open_files: Option<Mutex<OpenFiles>>
+}
+impl Revalidation {
+
+    /// Return what is needed to check the files of the plan of `old` and `new`
+    /// again, with the `snapshots` taken of them while planning, the `warnings`
+    /// of the plan, and the sidecars of `sidecar_types` paired with their
+    /// primaries. If `skip_open_files` is [`true`], files open in other processes
+    /// are left where they are.
+    pub fn new(
+        snapshots: Vec<Option<Snapshot>>,
+        old: &[File],
+        new: &[File],
+        warnings: &[(File, SortWarning)],
+        sidecar_types: &[String],
+        skip_open_files: bool) -> Revalidation {
+
+        let primaries = sidecars::pair_sidecars(old, sidecar_types)
+            .into_iter()
+            .flat_map(|(primary, sidecars)| sidecars.into_iter().map(move |sidecar| (sidecar, primary)))
+            .collect();
+        let replaced: HashSet<&File> = warnings.iter()
+            .filter(|(_, warning)| matches!(warning, SortWarning::Replaced(_)))
+            .map(|(path, _)| path)
+            .collect();
+        let replacing = (0..old.len()).filter(|i| replaced.contains(&old[*i])).collect();
+        let replans = Replans { taken: new.iter().map(File::copy).collect(), ..Default::default() };
+        let open_files = skip_open_files.then(|| Mutex::new(OpenFiles::default()));
+        Revalidation { snapshots, primaries, replacing, replans: Mutex::new(replans), open_files }
+    }
+}
@@ -1,18 +1,39 @@
 //! Commonly-used structs.
 
+use crate::categories::Category;
+use crate::checksum::HashAlgorithm;
+use crate::conflicts::{ConflictPolicy, IdenticalCheck};
+use crate::content_types::ExtensionlessPolicy;
+use crate::dates::{DateSource, MissingDatePolicy};
+use crate::errors::{InvalidPathError, Strictness};
+use crate::execution::Backend;
+use crate::notify::Notification;
+use crate::observers::ProgressInterval;
+use crate::links::SymlinkPolicy;
+use crate::location::Region;
+use crate::origin::OriginRecord;
+use crate::reparse::ReparsePolicy;
+use crate::rules::Rule;
+use crate::stale::StalePolicy;
+use crate::transfer::TransferMode;
+use crate::video::VideoFilter;
 use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::ffi::{OsStr, OsString};
 use std::fmt;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 
 #[cfg(test)]
 /// Tests for the structs. Each test is named after the function and/or struct
 /// it tests, prefixed with test.
 mod tests {
 
-    use std::{env, fs, path::Path};
+    use std::{env, ffi::OsStr, fs, path::{Path, PathBuf}, sync::Arc};
     use super::{ConfigData, File, Join};
     
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     /// Test the [`ConfigData`] struct
     fn test_configdata() {
         
@@ -44,17 +65,47 @@ mod tests {
 
         // Test the methods
         assert!(!file.exists());
-        assert_eq!(file.copy(), File { pathbuf: path.to_path_buf() });
-        assert_eq!(File::from(path), File { pathbuf: path.to_path_buf() });
-        assert_eq!(File::from(&path.to_path_buf()), File { pathbuf: path.to_path_buf() });
+        assert_eq!(file.copy(), File { pathbuf: Arc::from(path) });
+        assert_eq!(File::from(path), File { pathbuf: Arc::from(path) });
+        assert_eq!(File::from(&path.to_path_buf()), File { pathbuf: Arc::from(path) });
         assert_eq!(file.extension(), String::from("txt"));
         assert_eq!(file.file_name(), String::from("my_file.txt"));
         assert_eq!(file.file_stem(), String::from("my_file"));
-        assert_eq!(file.join(path), File { pathbuf: joined_path.to_path_buf() });
-        assert_eq!(file.join(String::from("my_file.txt")), File { pathbuf: joined_path.to_path_buf() });
-        assert_eq!(File::new("my_file.txt"), File { pathbuf: path.to_path_buf() });
+        assert_eq!(file.join(path), File { pathbuf: Arc::from(joined_path) });
+        assert_eq!(file.join(String::from("my_file.txt")), File { pathbuf: Arc::from(joined_path) });
+        assert_eq!(File::new("my_file.txt"), File { pathbuf: Arc::from(path) });
         assert_eq!(file.to_path_buf(), path.to_path_buf());
+        assert_eq!(file.as_path(), path);
         assert_eq!(file.to_string(), String::from("my_file.txt"));
+        assert_eq!(file.extension_os(), "txt");
+        assert_eq!(file.file_stem_os(), "my_file");
+    }
+
+    #[test]
+    /// Test [`File::try_new`] and [`TryFrom`] for [`File`]
+    fn test_file_try_new() {
+        let expected: PathBuf = ["uploads", "photo.jpg"].iter().collect();
+        assert_eq!(File::try_new("uploads//./photo.jpg").expect("Failed to validate path.").to_path_buf(), expected);
+        assert_eq!(File::try_from(OsStr::new("uploads/photo.jpg")).expect("Failed to validate path.").to_path_buf(), expected);
+        assert_eq!(File::try_new("").unwrap_err().reason, "the path is empty");
+        assert_eq!(File::try_new("photo\0.jpg").unwrap_err().reason, "the path contains a NUL character");
+        assert_eq!(File::try_new("../../etc/passwd").unwrap_err().reason, "the path leads out of its directory");
+        assert_eq!(File::try_new("uploads/../../photo.jpg").unwrap_err().reason, "the path leads out of its directory");
+        assert_eq!(File::try_new("/etc/passwd").unwrap_err().reason, "the path isn't relative");
+        #[cfg(windows)]
+        assert_eq!(File::try_new("C:photo.jpg").unwrap_err().reason, "the path isn't relative");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// Test the [`File`] struct with a name that isn't valid UTF-8
+    fn test_file_non_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let file = File::from(Path::new(OsStr::from_bytes(b"caf\xe9.jpg")));
+        assert_eq!(file.extension(), String::from("jpg"));
+        assert_eq!(file.file_stem(), String::from("caf\u{fffd}"));
+        assert_eq!(file.file_stem_os().as_bytes(), b"caf\xe9");
     }
 }
 
@@ -63,11 +114,115 @@ mod tests {
 #[derive(Debug)]
 #[derive(Serialize, Deserialize)]
 pub struct ConfigData {
+    #[serde(default)]
+    pub audit_dates: bool,
+    #[serde(default)]
+    pub backend: Backend,
+    #[serde(default)]
+    pub burst_interval: Option<u64>,
+    #[serde(default)]
+    pub categories: Vec<Category>,
+    #[serde(default)]
+    pub clock_offset: String,
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    #[serde(default = "default_true")]
+    pub create_dirs: bool,
     pub date_format: String,
+    #[serde(default)]
+    pub date_sources: Vec<DateSource>,
     pub date_type: String,
+    #[serde(default = "default_dir_format")]
+    pub dir_format: String,
+    #[serde(default)]
+    pub dir_mode: Option<u32>,
+    #[serde(default)]
+    pub event_gap: Option<u64>,
+    #[serde(default)]
+    pub exclude_content_type: Vec<String>,
     pub exclude_type: Vec<String>,
+    #[serde(default)]
+    pub extensionless: ExtensionlessPolicy,
+    #[serde(default)]
+    pub group: Option<u32>,
+    #[serde(default)]
+    pub hash_algorithm: HashAlgorithm,
+    #[serde(default)]
+    pub hash_workers: usize,
+    #[serde(default)]
+    pub keep_newest: Option<usize>,
+    #[serde(default)]
+    pub keep_top_folder: bool,
+    #[serde(default = "default_location_grid")]
+    pub location_grid: f64,
+    #[serde(default)]
+    pub min_age: Option<u64>,
+    #[serde(default)]
+    pub min_size: Option<u64>,
+    #[serde(default)]
+    pub missing_date: MissingDatePolicy,
+    #[serde(default)]
+    pub notifications: Vec<Notification>,
+    #[serde(default)]
+    pub only_content_type: Vec<String>,
     pub only_type: Vec<String>,
-    pub preserve_name: bool
+    #[serde(default)]
+    pub origin_record: OriginRecord,
+    #[serde(default)]
+    pub owner: Option<u32>,
+    #[serde(default)]
+    pub persist_counters: bool,
+    #[serde(default)]
+    pub progress_interval: ProgressInterval,
+    pub preserve_name: bool,
+    #[serde(default)]
+    pub quarantine_dir: Option<String>,
+    #[serde(default)]
+    pub record_history: bool,
+    #[serde(default)]
+    pub regions: Vec<Region>,
+    #[serde(default)]
+    pub remove_identical: bool,
+    #[serde(default)]
+    pub reparse_points: ReparsePolicy,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub screenshot_dir: Option<String>,
+    #[serde(default)]
+    pub sidecar_types: Vec<String>,
+    #[serde(default)]
+    pub skip_identical: IdenticalCheck,
+    #[serde(default)]
+    pub skip_open_files: bool,
+    #[serde(default = "default_true")]
+    pub skip_partial_downloads: bool,
+    #[serde(default = "default_true")]
+    pub skip_placeholders: bool,
+    #[serde(default)]
+    pub stale_policy: StalePolicy,
+    #[serde(default)]
+    pub stale_tolerance: u64,
+    #[serde(default)]
+    pub strictness: Strictness,
+    #[serde(default)]
+    pub strip_quarantine: bool,
+    #[serde(default)]
+    pub suspicious_dir: Option<String>,
+    #[serde(default)]
+    pub symlinks: SymlinkPolicy,
+    #[serde(default)]
+    pub time_tie_break: bool,
+    #[serde(default)]
+    pub tiny_dir: Option<String>,
+    #[serde(default)]
+    pub transfer: TransferMode,
+    #[serde(default)]
+    pub video_filter: VideoFilter,
+    #[serde(default = "default_workers")]
+    pub workers: usize,
+    #[serde(default = "default_year_start")]
+    pub year_start: u32
 }
 impl ConfigData {
 
@@ -96,18 +251,35 @@ impl ConfigData {
     /// 
     /// **NOTE:** the backslashes are only needed when defining a [`String`] from
     /// a string literal like this. The JSON file will not need them.
-    pub fn from_json(json: &String) -> ConfigData {
+    pub fn from_json(json: &str) -> ConfigData {
+        serde_json::from_str(json).expect("Failed to parse json.")
+    }
+}
 
-        let json_data: ConfigData = serde_json::from_str(json.as_str()).expect("Failed to parse json.");
+/// The default `dir_format` of [`ConfigData`], for JSON without one.
+fn default_dir_format() -> String {
+    String::from("%Y/%m/")
+}
 
-        ConfigData {
-            date_format: json_data.date_format,
-            date_type: json_data.date_type,
-            exclude_type: json_data.exclude_type,
-            only_type: json_data.only_type,
-            preserve_name: json_data.preserve_name
-        }
-    }
+/// The default `location_grid` of [`ConfigData`], for JSON without one.
+fn default_location_grid() -> f64 {
+    1.0
+}
+
+/// The default of the [`ConfigData`] options that are on unless turned off, for
+/// JSON without them.
+fn default_true() -> bool {
+    true
+}
+
+/// The default `workers` of [`ConfigData`], for JSON without one.
+fn default_workers() -> usize {
+    1
+}
+
+/// The default `year_start` of [`ConfigData`], for JSON without one.
+fn default_year_start() -> u32 {
+    1
 }
 
 /// Traits used by [`File`]
@@ -119,29 +291,50 @@ pub trait Join<T> {
 /// formatter `"{}"` (used with [`println!`]), can be created from many different
 /// types, and can be joined with many different types. It underlies all file-related
 /// operations in `SorteryLib`.
-#[derive(Debug)]
-#[derive(PartialEq)]
+/// 
+/// The path is shared behind an [`Arc`], so cloning a [`File`] (or calling
+/// [`File::copy`]) doesn't copy the path, and files can be sent across threads
+/// and kept in plans and reports for next to nothing.
+#[derive(Debug, Clone)]
+#[derive(PartialEq, Eq, Hash)]
 pub struct File {
-    pub pathbuf: PathBuf,
+    pub pathbuf: Arc<Path>,
 }
 impl File {
 
     /// Returns an instance of [`File`] with the same path as ours. Used to resolve
-    /// ownership problems.
+    /// ownership problems. This is the same as [`Clone::clone`], and shares the
+    /// path rather than copying it.
     pub fn copy(&self) -> File {
-        File { pathbuf: PathBuf::from(&self.pathbuf) }
+        self.clone()
+    }
+
+    /// Return our path as a [`Path`], without copying it.
+    pub fn as_path(&self) -> &Path {
+        &self.pathbuf
     }
 
     /// Return [`true`] if our path exists, [`false`] if it does not.
     pub fn exists(&self) -> bool {
-        if self.pathbuf.exists() {
-            return true;
-        } else {
-            return false;
-        }
+        self.pathbuf.exists()
+    }
+
+    /// Return the extension of our path as an [`OsStr`], exactly as it is on disk,
+    /// or an empty [`OsStr`] if there is none. Unlike [`File::extension`], this
+    /// is lossless for names that aren't valid UTF-8.
+    pub fn extension_os(&self) -> &OsStr {
+        self.pathbuf.extension().unwrap_or_default()
+    }
+
+    /// Return the file stem of our path as an [`OsStr`], exactly as it is on disk,
+    /// or an empty [`OsStr`] if there is none. Unlike [`File::file_stem`], this
+    /// is lossless for names that aren't valid UTF-8.
+    pub fn file_stem_os(&self) -> &OsStr {
+        self.pathbuf.file_stem().unwrap_or_default()
     }
 
-    /// Return a [`String`] representing the extension of our path. For example:
+    /// Return a [`String`] representing the extension of our path. Parts of the
+    /// name that aren't valid UTF-8 are replaced with `�`. For example:
     /// 
     /// ```
     /// use sorterylib::prelude::*;
@@ -152,13 +345,14 @@ impl File {
     /// }
     /// ```
     pub fn extension(&self) -> String {
-        match self.pathbuf.as_path().extension() {
-            None => return String::from(""),
-            s => return String::from(s.unwrap().to_str().unwrap()),
+        match self.pathbuf.extension() {
+            None => String::from(""),
+            Some(s) => s.to_string_lossy().into_owned(),
         }
     }
 
-    /// Return a [`String`] representing the file name of our path. For example:
+    /// Return a [`String`] representing the file name of our path. Parts of the
+    /// name that aren't valid UTF-8 are replaced with `�`. For example:
     /// 
     /// ```
     /// use sorterylib::prelude::*;
@@ -169,13 +363,14 @@ impl File {
     /// }
     /// ```
     pub fn file_name(&self) -> String {
-        match self.pathbuf.as_path().file_name() {
-            None => return String::from(""),
-            s => return String::from(s.unwrap().to_str().unwrap()),
+        match self.pathbuf.file_name() {
+            None => String::from(""),
+            Some(s) => s.to_string_lossy().into_owned(),
         }
     }
 
-    /// Return a [`String`] representing the file stem of our path. For example:
+    /// Return a [`String`] representing the file stem of our path. Parts of the
+    /// name that aren't valid UTF-8 are replaced with `�`. For example:
     /// 
     /// ```
     /// use sorterylib::prelude::*;
@@ -186,10 +381,47 @@ impl File {
     /// }
     /// ```
     pub fn file_stem(&self) -> String {
-        match self.pathbuf.as_path().file_stem() {
-            None => return String::from(""),
-            s => return String::from(s.unwrap().to_str().unwrap()),
+        match self.pathbuf.file_stem() {
+            None => String::from(""),
+            Some(s) => s.to_string_lossy().into_owned(),
+        }
+    }
+
+    /// Return a new instance of [`File`] from `path`, after checking that it is
+    /// valid: that it isn't empty, doesn't contain NUL characters, and is
+    /// relative, without any `..` components to lead out of the directory it is
+    /// joined to. The path is normalized, with repeated separators and `.`
+    /// components removed, and the platform's own separators. Use this instead
+    /// of [`File::from`] for paths from untrusted input, like web uploads. For
+    /// example:
+    /// 
+    /// ```
+    /// use sorterylib::prelude::*;
+    /// 
+    /// fn main() {
+    ///     assert!(File::try_new("uploads//photo.jpg").is_ok());
+    ///     assert!(File::try_new("").is_err());
+    ///     assert!(File::try_new("../../etc/passwd").is_err());
+    /// }
+    /// ```
+    pub fn try_new(path: impl AsRef<Path>) -> Result<File, InvalidPathError> {
+        let path = path.as_ref();
+        let error = |reason| InvalidPathError { path: path.display().to_string(), reason };
+        if path.as_os_str().as_encoded_bytes().contains(&0) {
+            return Err(error("the path contains a NUL character"));
+        }
+        for component in path.components() {
+            match component {
+                Component::ParentDir => return Err(error("the path leads out of its directory")),
+                Component::RootDir | Component::Prefix(_) => return Err(error("the path isn't relative")),
+                _ => (),
+            }
+        }
+        let pathbuf: PathBuf = path.components().collect();
+        if pathbuf.as_os_str().is_empty() {
+            return Err(error("the path is empty"));
         }
+        Ok(File::from(pathbuf))
     }
 
     /// DEPRECATED: Please use [`File::from`] instead.
@@ -203,7 +435,7 @@ impl File {
     /// }
     /// ```
     pub fn new(from: &str) -> File {
-        File { pathbuf: PathBuf::from(from) }
+        File::from(Path::new(from))
     }
 
     /// Return an instance of [`PathBuf`] representing our path. For example:
@@ -218,7 +450,7 @@ impl File {
     /// }
     /// ```
     pub fn to_path_buf(&self) -> PathBuf {
-        PathBuf::from(&self.pathbuf)
+        self.pathbuf.to_path_buf()
     }
     
     /// Return a [`String`] representing our path. For example:
@@ -230,77 +462,98 @@ impl File {
     ///     let file = File::from("test.txt");
     ///     assert_eq!(file.to_string(), String::from("test.txt"));
     /// }
+    /// ```
+    #[allow(clippy::inherent_to_string_shadow_display)]
     pub fn to_string(&self) -> String {
         self.pathbuf.display().to_string()
     }
 }
+impl AsRef<Path> for File {
+    fn as_ref(&self) -> &Path {
+        &self.pathbuf
+    }
+}
+impl Borrow<Path> for File {
+    /// Borrow our path, so that collections of [`File`]s can be searched by
+    /// [`Path`] without creating a [`File`] first.
+    fn borrow(&self) -> &Path {
+        &self.pathbuf
+    }
+}
 impl fmt::Display for File {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.pathbuf.display())
     }
 }
-impl<'f> From<&'f Path> for File {
+impl From<&Path> for File {
     /// Return a new instance of [`File`], with `path` as the path.
     fn from(path: &Path) -> File {
-        File { pathbuf: path.to_path_buf() }
+        File { pathbuf: Arc::from(path) }
     }
 }
 impl From<PathBuf> for File {
     /// Return a new instance of [`File`], with `path` as the path.
     fn from(path: PathBuf) -> File {
-        File { pathbuf: path }
+        File { pathbuf: Arc::from(path) }
     }
 }
-impl<'f> From<&'f PathBuf> for File {
+impl From<&PathBuf> for File {
     /// Return a new instance of [`File`], with `path` as the path.
     fn from(path: &PathBuf) -> File {
-        File { pathbuf: path.to_path_buf() }
+        File { pathbuf: Arc::from(path.as_path()) }
     }
 }
-impl<'f> From<&'f str> for File {
+impl From<&str> for File {
     /// Return a new instance of [`File`], with `path` as the path
     fn from(path: &str) -> File {
-        File { pathbuf: PathBuf::from(path) }
+        File { pathbuf: Arc::from(Path::new(path)) }
     }
 }
 impl From<String> for File {
     /// Return a new instance of [`File`], with `path` as the path
     fn from(path: String) -> File {
-        File { pathbuf: PathBuf::from(path) }
+        File { pathbuf: Arc::from(PathBuf::from(path)) }
+    }
+}
+impl TryFrom<&OsStr> for File {
+    type Error = InvalidPathError;
+
+    /// Return a new instance of [`File`], with `path` as the path, if it is valid.
+    /// See [`File::try_new`].
+    fn try_from(path: &OsStr) -> Result<File, InvalidPathError> {
+        File::try_new(path)
+    }
+}
+impl TryFrom<OsString> for File {
+    type Error = InvalidPathError;
+
+    /// Return a new instance of [`File`], with `path` as the path, if it is valid.
+    /// See [`File::try_new`].
+    fn try_from(path: OsString) -> Result<File, InvalidPathError> {
+        File::try_new(path)
     }
 }
 impl Join<File> for File {
     /// Return an instance of [`File`] representing the joining of our path and `path`.
     fn join(&self, path: File) -> File {
-        let join_start = self.to_path_buf();
-        let join_end = path.to_path_buf();
-        let pathbuf = join_start.join(join_end);
-        File { pathbuf: pathbuf }
+        File::from(self.pathbuf.join(&path.pathbuf))
     }
 }
-impl<'j> Join<&'j Path> for File {
+impl Join<&Path> for File {
     /// Return an instance of [`File`] representing the joining of our path and `path`.
     fn join(&self, path: &Path) -> File {
-        let join_start = self.to_path_buf();
-        let join_end = path.to_path_buf();
-        let pathbuf = join_start.join(join_end);
-        File { pathbuf: pathbuf }
+        File::from(self.pathbuf.join(path))
     }
 }
-impl<'j> Join<&'j PathBuf> for File {
+impl Join<&PathBuf> for File {
     /// Return an instance of [`File`] representing the joining of our path and `path`.
     fn join(&self, path: &PathBuf) -> File {
-        let join_start = self.to_path_buf();
-        let pathbuf = join_start.join(path);
-        File { pathbuf: pathbuf }
+        File::from(self.pathbuf.join(path))
     }
 }
 impl Join<String> for File {
     /// Return an instance of [`File`] representing the joining of our path and `path`.
     fn join(&self, path: String) -> File {
-        let join_start = self.to_path_buf();
-        let join_end = PathBuf::from(path);
-        let pathbuf = join_start.join(join_end);
-        File { pathbuf: pathbuf }
+        File::from(self.pathbuf.join(path))
     }
-}
\ No newline at end of file
+}
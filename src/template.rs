@@ -0,0 +1,224 @@
+//! Destination directory templates.
+//!
+//! A template is a `strftime` format string (see [`chrono::format::strftime`])
+//! that can also contain `{name}` placeholders, like `"%Y/%m/{location}/"`. The
+//! placeholders are filled in with each file's own values before the date is
+//! formatted. See [`Sorter::dir_format`](crate::Sorter::dir_format) for the
+//! available placeholders.
+//!
+//! Each directory of a template is a layer of classification, so layers are
+//! stacked just by writing them in order: `"{extension}/%Y/%m/"` classifies
+//! files by type first, and by date below that. The common date trees come
+//! ready-made as [`Granularity`] presets, for those who'd rather not write
+//! `strftime` formats.
+
+use crate::structs::File;
+use chrono::{DateTime, Datelike, Local};
+use std::collections::HashMap;
+use std::path::Component;
+
+#[cfg(test)]
+/// Tests for templates. Each test is named after the function it tests, prefixed
+/// with test.
+mod tests {
+
+    use chrono::{Local, TimeZone};
+    use std::collections::HashMap;
+    use super::{date_vars, expand, parent_level, parent_name, placeholders};
+    use crate::structs::File;
+
+    #[test]
+    /// Test [`date_vars`]
+    fn test_date_vars() {
+        let date = Local.with_ymd_and_hms(2023, 8, 14, 12, 0, 0).unwrap();
+        assert_eq!(date_vars(&date, 1)["quarter"], "Q3");
+        assert_eq!(date_vars(&date, 7)["quarter"], "Q1");
+        assert_eq!(date_vars(&date, 10)["quarter"], "Q4");
+        assert_eq!(date_vars(&date, 9)["quarter"], "Q4");
+
+        assert_eq!(date_vars(&date, 1)["fiscal_year"], "2023");
+        assert_eq!(date_vars(&date, 8)["fiscal_year"], "2023-2024");
+        assert_eq!(date_vars(&date, 9)["fiscal_year"], "2022-2023");
+    }
+
+    #[test]
+    /// Test [`parent_level`] and [`parent_name`]
+    fn test_parent_name() {
+        assert_eq!(parent_level("parent"), Some(1));
+        assert_eq!(parent_level("parent:3"), Some(3));
+        assert_eq!(parent_level("parent:0"), None);
+        assert_eq!(parent_level("parents"), None);
+
+        // Only the folders inside the source are counted
+        let source = File::from("/photos/import");
+        let path = File::from("/photos/import/Italy 2019/Rome/IMG_1234.jpg");
+        assert_eq!(parent_name(&path, &source, 1), Some(String::from("Rome")));
+        assert_eq!(parent_name(&path, &source, 2), Some(String::from("Italy 2019")));
+        assert_eq!(parent_name(&path, &source, 3), None);
+        assert_eq!(parent_name(&File::from("/photos/import/a.jpg"), &source, 1), None);
+        assert_eq!(parent_name(&File::from("/elsewhere/Album/a.jpg"), &source, 1), Some(String::from("Album")));
+    }
+
+    #[test]
+    /// Test [`placeholders`]
+    fn test_placeholders() {
+        assert_eq!(placeholders("%Y/{location}/%m/{camera}"), vec!["location", "camera"]);
+        assert!(placeholders("%Y/%m/").is_empty());
+        assert!(placeholders("%Y/{unclosed").is_empty());
+    }
+
+    #[test]
+    /// Test [`expand`]
+    fn test_expand() {
+        let mut vars = HashMap::new();
+        vars.insert("location", String::from("Rome"));
+        vars.insert("odd", String::from("100%/done"));
+        vars.insert("camera", String::from(".."));
+        vars.insert("empty", String::new());
+
+        assert_eq!(expand("%Y/%m/{location}/", &vars), "%Y/%m/Rome/");
+        assert_eq!(expand("%Y/{odd}", &vars), "%Y/100%%_done");
+        assert_eq!(expand("%Y/{unknown}/", &vars), "%Y/{unknown}/");
+
+        // Values can't lead out of the folder they are in
+        assert_eq!(expand("{camera}/{camera}/%Y/", &vars), "_/_/%Y/");
+        assert_eq!(expand("%Y/{empty}/", &vars), "%Y/_/");
+    }
+}
+
+/// A ready-made date tree, with a file name format to match, for
+/// [`Sorter::set_granularity`](crate::Sorter::set_granularity). The names always
+/// hold the full date, so that files still say when they're from once they are
+/// out of their folders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Granularity {
+    /// A folder for each year, as in `2023/2023-07-14.jpg`.
+    Year,
+    /// A folder for each month, in a folder for its year, as in
+    /// `2023/07/2023-07-14.jpg`.
+    YearMonth,
+    /// A folder for each day, down the year and month folders, as in
+    /// `2023/07/14/2023-07-14 09h30m00s.jpg`.
+    YearMonthDay,
+    /// No folders at all, as in `2023-07-14 09h30m00s.jpg`.
+    Flat
+}
+impl Granularity {
+
+    /// Return the [`Sorter::dir_format`](crate::Sorter::dir_format) of the tree.
+    pub fn dir_format(&self) -> &'static str {
+        match self {
+            Granularity::Year => "%Y/",
+            Granularity::YearMonth => "%Y/%m/",
+            Granularity::YearMonthDay => "%Y/%m/%d/",
+            Granularity::Flat => "",
+        }
+    }
+
+    /// Return the [`Sorter::date_format`](crate::Sorter::date_format) of the
+    /// names in the tree: the day for year and month folders, and the time too
+    /// when there can be many files with the same day in one folder.
+    pub fn date_format(&self) -> &'static str {
+        match self {
+            Granularity::Year | Granularity::YearMonth => "%Y-%m-%d",
+            Granularity::YearMonthDay | Granularity::Flat => "%Y-%m-%d %Hh%Mm%Ss",
+        }
+    }
+}
+
+/// The value of the `{extension}` placeholder for files without an extension.
+pub const NO_EXTENSION: &str = "no_extension";
+
+/// The value of the `{parent}` placeholder for files that aren't that deep in
+/// any folders of the source.
+pub const NO_PARENT: &str = "no_parent";
+
+/// The names of the placeholders that only depend on the date. See [`date_vars`].
+pub const DATE_PLACEHOLDERS: [&str; 2] = ["quarter", "fiscal_year"];
+
+/// Return the values of the placeholders that only depend on the date `date`.
+/// `year_start` is the month (1 to 12) that starts the year, for fiscal or
+/// school years; see [`Sorter::year_start`](crate::Sorter::year_start).
+pub fn date_vars(date: &DateTime<Local>, year_start: u32) -> HashMap<&'static str, String> {
+    let mut vars = HashMap::new();
+    let months_into_year = (date.month() as i32 - year_start as i32).rem_euclid(12);
+    vars.insert("quarter", format!("Q{}", months_into_year / 3 + 1));
+
+    // Years that don't start in January are named after both calendar years
+    let first_year = if date.month() >= year_start { date.year() } else { date.year() - 1 };
+    vars.insert("fiscal_year", if year_start <= 1 {
+        first_year.to_string()
+    } else {
+        format!("{}-{}", first_year, first_year + 1)
+    });
+    vars
+}
+
+/// Return which ancestor of a file the placeholder `name` stands for, counting
+/// its own folder as `1`, if it is a `{parent}` placeholder: `1` for `parent`,
+/// and `n` for `parent:n`.
+pub fn parent_level(name: &str) -> Option<usize> {
+    match name.strip_prefix("parent")? {
+        "" => Some(1),
+        level => level.strip_prefix(':')?.parse().ok().filter(|n| *n >= 1),
+    }
+}
+
+/// Return the name of the `level`th folder above `path`, counting its own
+/// folder as `1`, or [`None`] if it isn't that deep in folders inside `source`.
+/// Files that aren't in `source` at all count all of the folders above them.
+pub fn parent_name(path: &File, source: &File, level: usize) -> Option<String> {
+    let relative = path.pathbuf.strip_prefix(&source.pathbuf).unwrap_or(&path.pathbuf);
+    let folders: Vec<String> = relative.parent()?
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect();
+    folders.len().checked_sub(level).map(|n| folders[n].clone())
+}
+
+/// Return the names of all the `{name}` placeholders in `template`, in order.
+pub fn placeholders(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        match rest[start..].find('}') {
+            Some(end) => {
+                names.push(&rest[start + 1..start + end]);
+                rest = &rest[start + end + 1..];
+            },
+            None => break,
+        }
+    }
+    names
+}
+
+/// Return `template` with all of its placeholders replaced with their values in
+/// `vars`. Placeholders without a value are left as they are.
+///
+/// The values are made safe for use in a path and a `strftime` format string:
+/// path separators are replaced with `_`, as are values that would be a `.` or
+/// `..` folder, or none at all, and `%` is escaped as `%%`.
+pub fn expand(template: &str, vars: &HashMap<&str, String>) -> String {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        expanded.push_str(&rest[..start]);
+        match vars.get(&rest[start + 1..end]) {
+            Some(value) => match value.replace(['/', '\\'], "_") {
+                value if matches!(value.as_str(), "" | "." | "..") => expanded.push('_'),
+                value => expanded.push_str(&value.replace('%', "%%")),
+            },
+            None => expanded.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
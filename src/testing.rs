@@ -0,0 +1,364 @@
+//! Building directory trees to test and benchmark sorts in. See [`Sandbox`] and
+//! [`Workload`].
+//!
+//! A [`Sandbox`] is a fresh directory in the temporary directory, with a
+//! `source` and a `target` directory in it, and the files described by a list
+//! of [`Entry`]s, down to their contents and modification times. It is removed
+//! when it is dropped, so tests of a configuration don't depend on any files
+//! being around, and don't leave any behind:
+//!
+//! ```
+//! use sorterylib::prelude::*;
+//! use sorterylib::testing::{Entry, Sandbox};
+//!
+//! let sandbox = Sandbox::new(&[
+//!     Entry::file("source/a.jpg").modified_at(1_656_676_800),
+//!     Entry::file("source/notes.txt").contents("notes"),
+//! ]).expect("Failed to create sandbox.");
+//! let sorter = Sorter {
+//!     source: sandbox.source(),
+//!     target: sandbox.target(),
+//!     date_format: String::from("%Y"),
+//!     preserve_name: true,
+//!     ..Default::default()
+//! };
+//! sorter.sort_report(ExecutionMode::Execute).expect("Failed to sort.");
+//! assert!(sandbox.files().contains(&String::from("target/2022/07/2022 a.jpg")));
+//! ```
+//!
+//! A [`Workload`] fills a directory with many synthetic files instead, of sizes
+//! and dates drawn at random, to measure sorts on before pointing them at real
+//! data.
+
+use crate::structs::{File, Join};
+use filetime::FileTime;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::io::Write;
+use std::time::SystemTime;
+use std::{env, fs, io, process};
+use walkdir::WalkDir;
+
+#[cfg(test)]
+/// Tests for sandboxes. Each test is named after the struct it tests, prefixed
+/// with test.
+mod tests {
+
+    use std::fs;
+    use super::{Entry, Sandbox, Sizes, Workload};
+
+    #[test]
+    /// Test [`Sandbox`]
+    fn test_sandbox() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/a.txt").contents("a").modified_at(1_600_000_000),
+            Entry::file("source/nested/b.txt"),
+            Entry::dir("source/empty"),
+        ]).expect("Failed to create sandbox.");
+        let other = Sandbox::new(&[]).expect("Failed to create sandbox.");
+        assert_ne!(sandbox.root(), other.root());
+
+        assert!(sandbox.target().pathbuf.is_dir() && sandbox.path("source/empty").pathbuf.is_dir());
+        assert_eq!(sandbox.files(), vec!["source/a.txt", "source/nested/b.txt"]);
+        let a = sandbox.path("source/a.txt");
+        assert_eq!(fs::read_to_string(a.to_path_buf()).unwrap(), "a");
+        let modified = filetime::FileTime::from_last_modification_time(&a.pathbuf.metadata().unwrap());
+        assert_eq!(modified.unix_seconds(), 1_600_000_000);
+
+        // The whole tree is removed once the sandbox is dropped
+        let root = sandbox.root().copy();
+        drop(sandbox);
+        assert!(!root.exists());
+    }
+
+    #[test]
+    /// Test [`Workload`]
+    fn test_workload() {
+        let sandbox = Sandbox::new(&[]).expect("Failed to create sandbox.");
+        let workload = Workload {
+            files: 20,
+            dirs: 3,
+            sizes: Sizes::Uniform(10, 100),
+            start: 1_600_000_000,
+            spread: 3600,
+            extensions: vec![String::from("jpg"), String::from("mp4")],
+            ..Default::default()
+        };
+        workload.generate(&sandbox.source()).expect("Failed to generate workload.");
+
+        let files = sandbox.files();
+        assert_eq!(files.len(), 20);
+        assert!(files.contains(&String::from("source/dir_1/IMG_000001.mp4")));
+        for file in files.iter().map(|file| sandbox.path(file)) {
+            let metadata = file.pathbuf.metadata().unwrap();
+            assert!((10..100).contains(&metadata.len()));
+            let modified = filetime::FileTime::from_last_modification_time(&metadata).unix_seconds();
+            assert!((1_600_000_000..1_600_003_600).contains(&modified));
+        }
+
+        // The same workload is generated the same way every time
+        let other = Sandbox::new(&[]).expect("Failed to create sandbox.");
+        workload.generate(&other.source()).expect("Failed to generate workload.");
+        let size = |sandbox: &Sandbox| sandbox.path("source/dir_2/IMG_000005.mp4").pathbuf.metadata().unwrap().len();
+        assert_eq!(size(&sandbox), size(&other));
+    }
+}
+
+/// The number of sandboxes created by this process so far, to name each one
+/// differently.
+static SANDBOXES: AtomicUsize = AtomicUsize::new(0);
+
+/// A file or directory in a [`Sandbox`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// The path of the entry, relative to the root of the sandbox, with `/`
+    /// separators, as in `"source/2022/a.jpg"`. Its parent directories are
+    /// created as needed.
+    pub path: String,
+    /// If [`true`], the entry is a directory, and `contents` and `modified` are
+    /// ignored.
+    pub dir: bool,
+    /// The contents of the file.
+    pub contents: Vec<u8>,
+    /// The modification time of the file. If [`None`], it is left at the time
+    /// the file was created.
+    pub modified: Option<SystemTime>
+}
+impl Entry {
+
+    /// Return an empty file at `path`.
+    pub fn file(path: &str) -> Entry {
+        Entry { path: String::from(path), dir: false, contents: Vec::new(), modified: None }
+    }
+
+    /// Return a directory at `path`.
+    pub fn dir(path: &str) -> Entry {
+        Entry { dir: true, ..Entry::file(path) }
+    }
+
+    /// Return the entry with `contents`.
+    pub fn contents(self, contents: impl AsRef<[u8]>) -> Entry {
+        Entry { contents: contents.as_ref().to_vec(), ..self }
+    }
+
+    /// Return the entry modified at `modified`.
+    pub fn modified(self, modified: SystemTime) -> Entry {
+        Entry { modified: Some(modified), ..self }
+    }
+
+    /// Return the entry modified at `seconds` since the Unix epoch.
+    pub fn modified_at(self, seconds: i64) -> Entry {
+        self.modified(FileTime::from_unix_time(seconds, 0).into())
+    }
+}
+
+/// How the sizes of the files of a [`Workload`] are distributed, in bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Sizes {
+    /// Every file is this big.
+    Fixed(u64),
+    /// Sizes are drawn uniformly from the first size, included, to the second
+    /// one, excluded.
+    Uniform(u64, u64),
+    /// Sizes are drawn from the sizes given, each with the weight given, as in
+    /// `vec![(4_000_000, 9), (200_000_000, 1)]` for mostly photos and some videos.
+    Weighted(Vec<(u64, u32)>)
+}
+
+/// A synthetic tree of files, for benchmarking sorts. Files are named
+/// `IMG_000000.jpg`, `IMG_000001.jpg`, and so on, with their sizes and
+/// modification times drawn at random, but the same way every time for the same
+/// `seed`. Create them with [`Workload::generate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Workload {
+    /// The number of files. Defaults to `1000`.
+    pub files: usize,
+    /// The number of directories the files are spread over, named `dir_0`,
+    /// `dir_1`, and so on. Defaults to `0`, which puts them all in one directory.
+    pub dirs: usize,
+    /// The sizes of the files. Defaults to [`Sizes::Fixed`] empty files, which
+    /// are the fastest to generate when only planning is measured.
+    pub sizes: Sizes,
+    /// The earliest modification time, in seconds since the Unix epoch.
+    /// Defaults to the start of 2020.
+    pub start: i64,
+    /// The number of seconds after `start` that the modification times are
+    /// spread over. Defaults to a year.
+    pub spread: u64,
+    /// The number of consecutive files that share each modification time, as
+    /// in a burst of photos, so that their names collide. Defaults to `1`.
+    pub per_timestamp: usize,
+    /// The extensions of the files, used in turn. Defaults to `jpg`.
+    pub extensions: Vec<String>,
+    /// The seed the sizes and times are drawn with. Defaults to `0`.
+    pub seed: u64
+}
+impl Default for Workload {
+
+    /// Return a workload of a thousand empty JPEG files, from 2020.
+    fn default() -> Workload {
+        Workload {
+            files: 1000,
+            dirs: 0,
+            sizes: Sizes::Fixed(0),
+            start: 1_577_836_800,
+            spread: 365 * 86_400,
+            per_timestamp: 1,
+            extensions: vec![String::from("jpg")],
+            seed: 0
+        }
+    }
+}
+impl Workload {
+
+    /// Create the files of the workload in `dir`, which is created if it doesn't
+    /// exist yet.
+    pub fn generate(&self, dir: &File) -> io::Result<()> {
+        let mut random = SplitMix64(self.seed);
+        let mut filler = vec![0; 64 * 1024];
+        filler.iter_mut().for_each(|byte| *byte = random.next() as u8);
+        let mut modified = self.start;
+        for i in 0..self.files {
+            let parent = match self.dirs {
+                0 => dir.copy(),
+                dirs => dir.join(format!("dir_{}", i % dirs)),
+            };
+            fs::create_dir_all(parent.as_path())?;
+            let extension = self.extensions.get(i % self.extensions.len().max(1)).map_or("", String::as_str);
+            let path = parent.join(format!("IMG_{:06}.{}", i, extension));
+
+            // Every file starts with its index, so that no two are identical
+            let size = self.size(&mut random);
+            let mut file = io::BufWriter::new(fs::File::create(path.as_path())?);
+            let index = (i as u64).to_le_bytes();
+            let head = size.min(index.len() as u64) as usize;
+            file.write_all(&index[..head])?;
+            let mut left = size - head as u64;
+            while left > 0 {
+                let chunk = left.min(filler.len() as u64) as usize;
+                file.write_all(&filler[..chunk])?;
+                left -= chunk as u64;
+            }
+            file.flush()?;
+            drop(file);
+
+            if i % self.per_timestamp.max(1) == 0 {
+                modified = self.start + (random.next() % self.spread.max(1)) as i64;
+            }
+            filetime::set_file_mtime(path.as_path(), FileTime::from_unix_time(modified, 0))?;
+        }
+        Ok(())
+    }
+
+    /// Return the size of the next file.
+    fn size(&self, random: &mut SplitMix64) -> u64 {
+        match &self.sizes {
+            Sizes::Fixed(size) => *size,
+            Sizes::Uniform(min, max) => min + random.next() % (max.saturating_sub(*min)).max(1),
+            Sizes::Weighted(sizes) => {
+                let total: u64 = sizes.iter().map(|(_, weight)| *weight as u64).sum();
+                let mut pick = random.next() % total.max(1);
+                for (size, weight) in sizes {
+                    if pick < *weight as u64 {
+                        return *size;
+                    }
+                    pick -= *weight as u64;
+                }
+                0
+            },
+        }
+    }
+}
+
+/// The SplitMix64 generator, which is all the randomness a [`Workload`] needs.
+struct SplitMix64(u64);
+impl SplitMix64 {
+
+    /// Return the next number.
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// A directory tree in the temporary directory, removed when dropped. See the
+/// [module documentation](self).
+#[derive(Debug)]
+pub struct Sandbox {
+    root: File
+}
+impl Sandbox {
+
+    /// Return a new sandbox, with an empty `source` and `target` directory, and
+    /// `entries` in it.
+    pub fn new(entries: &[Entry]) -> io::Result<Sandbox> {
+        let name = format!("sorterylib_sandbox_{}_{}", process::id(), SANDBOXES.fetch_add(1, Ordering::Relaxed));
+        let sandbox = Sandbox { root: File::from(env::temp_dir()).join(name) };
+        if sandbox.root.exists() {
+            fs::remove_dir_all(sandbox.root.as_path())?;
+        }
+        for dir in ["source", "target"] {
+            fs::create_dir_all(sandbox.path(dir).as_path())?;
+        }
+        for entry in entries {
+            let path = sandbox.path(&entry.path);
+            if entry.dir {
+                fs::create_dir_all(path.as_path())?;
+                continue;
+            }
+            if let Some(parent) = path.pathbuf.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path.as_path(), &entry.contents)?;
+            if let Some(modified) = entry.modified {
+                filetime::set_file_mtime(path.as_path(), FileTime::from(modified))?;
+            }
+        }
+        Ok(sandbox)
+    }
+
+    /// Return the root directory of the sandbox.
+    pub fn root(&self) -> &File {
+        &self.root
+    }
+
+    /// Return the `source` directory of the sandbox.
+    pub fn source(&self) -> File {
+        self.path("source")
+    }
+
+    /// Return the `target` directory of the sandbox.
+    pub fn target(&self) -> File {
+        self.path("target")
+    }
+
+    /// Return the file at `path`, which is relative to the root of the sandbox,
+    /// with `/` separators.
+    pub fn path(&self, path: &str) -> File {
+        path.split('/').filter(|part| !part.is_empty()).fold(self.root.copy(), |dir, part| dir.join(String::from(part)))
+    }
+
+    /// Return the paths of all the files in the sandbox, relative to its root,
+    /// with `/` separators, sorted.
+    pub fn files(&self) -> Vec<String> {
+        let mut files: Vec<String> = WalkDir::new(self.root.as_path())
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| !entry.file_type().is_dir())
+            .filter_map(|entry| entry.path().strip_prefix(self.root.as_path()).ok().map(|path| {
+                path.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")
+            }))
+            .collect();
+        files.sort();
+        files
+    }
+}
+impl Drop for Sandbox {
+
+    /// Remove the sandbox, ignoring errors, since nothing can be done about them.
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(self.root.as_path());
+    }
+}
@@ -0,0 +1,477 @@
+//! Getting files from the source into the target: moving them, copying them, or
+//! linking them. See [`TransferMode`].
+//!
+//! Copies keep the modification and access times of the originals, since those
+//! are what files are sorted by. Large files are copied in chunks, reporting
+//! their progress after each one, so that a long copy can be followed and
+//! cancelled. Sparse files, like disk images and virtual machine disks, stay
+//! sparse: their holes are skipped rather than written out as zeros, so a 10 GB
+//! image with 1 GB of data takes 1 GB in the target too. The holes are found by
+//! reading the file and skipping the chunks that are all zeros, so a copy still
+//! reads the whole file, holes included. This needs a filesystem that supports
+//! sparse files, as most Unix ones and NTFS do; elsewhere, sparse files are
+//! copied in full.
+//!
+//! On Windows, copies also keep the alternate data streams of NTFS files, like
+//! the `Zone.Identifier` that marks downloads, and the metadata some
+//! applications attach to files, so that nothing attached to a file is lost.
+//! On Unix, they keep the extended attributes that organize files (see
+//! [`PRESERVED_XATTRS`]), like the Finder tags and labels of macOS.
+
+use filetime::FileTime;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+#[cfg(test)]
+/// Tests for transfers. Each test is named after the function it tests, prefixed
+/// with test.
+mod tests {
+
+    use std::{env, fs, io::{ErrorKind, Seek, SeekFrom, Write}};
+    use super::{copy_file, is_sparse, mark_sparse, move_file, strip_quarantine, CHUNK_SIZE, LARGE_FILE_SIZE, QUARANTINE_XATTR};
+    use crate::structs::{File, Join};
+
+    #[test]
+    /// Test [`copy_file`] and [`move_file`]
+    fn test_copy_file() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_copy_file"));
+        fs::create_dir_all(dir.to_path_buf()).expect("Failed to create test dir.");
+        let (old, copy, moved) = (dir.join(String::from("old.txt")), dir.join(String::from("copy.txt")), dir.join(String::from("moved.txt")));
+        fs::write(old.to_path_buf(), "old").expect("Failed to write test file.");
+        let time = filetime::FileTime::from_unix_time(1_600_000_000, 0);
+        filetime::set_file_mtime(old.to_path_buf(), time).expect("Failed to set test file time.");
+
+        // The copy has the same contents and times, and the original is kept
+        copy_file(old.as_path(), copy.as_path(), |_, _| true).expect("Failed to copy file.");
+        assert_eq!(fs::read_to_string(copy.to_path_buf()).unwrap(), "old");
+        let modified = filetime::FileTime::from_last_modification_time(&fs::metadata(copy.to_path_buf()).unwrap());
+        assert_eq!(modified, time);
+        assert!(old.exists());
+
+        move_file(old.as_path(), moved.as_path(), |_, _| true).expect("Failed to move file.");
+        assert!(!old.exists());
+        assert_eq!(fs::read_to_string(moved.to_path_buf()).unwrap(), "old");
+
+        // Moves to another filesystem, as to the tmpfs of /dev/shm, copy the
+        // file and remove the original
+        #[cfg(target_os = "linux")]
+        {
+            let shm = File::from("/dev/shm/sorterylib_test_copy_file.txt");
+            move_file(moved.as_path(), shm.as_path(), |_, _| true).expect("Failed to move file.");
+            assert!(!moved.exists());
+            assert_eq!(fs::read_to_string(shm.to_path_buf()).unwrap(), "old");
+            let modified = filetime::FileTime::from_last_modification_time(&fs::metadata(shm.to_path_buf()).unwrap());
+            assert_eq!(modified, time);
+            fs::remove_file(shm.to_path_buf()).expect("Failed to remove test file.");
+        }
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// Test that [`copy_file`] keeps the [`PRESERVED_XATTRS`]
+    fn test_copy_file_xattrs() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_copy_file_xattrs"));
+        fs::create_dir_all(dir.to_path_buf()).expect("Failed to create test dir.");
+        let (old, new) = (dir.join(String::from("old.jpg")), dir.join(String::from("new.jpg")));
+        fs::write(old.to_path_buf(), "old").expect("Failed to write test file.");
+
+        // Only check where the filesystem supports extended attributes
+        if xattr::set(old.to_path_buf(), "user.xdg.tags", b"holiday").is_ok() {
+            xattr::set(old.to_path_buf(), "user.other", b"other").expect("Failed to set test attribute.");
+            copy_file(old.as_path(), new.as_path(), |_, _| true).expect("Failed to copy file.");
+            assert_eq!(xattr::get(new.to_path_buf(), "user.xdg.tags").unwrap(), Some(b"holiday".to_vec()));
+            assert_eq!(xattr::get(new.to_path_buf(), "user.other").unwrap(), None);
+        }
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// Test [`strip_quarantine`]
+    fn test_strip_quarantine() {
+        let path = File::from(env::temp_dir()).join(String::from("sorterylib_test_strip_quarantine.zip"));
+        fs::write(path.to_path_buf(), "zip").expect("Failed to write test file.");
+
+        // Files without the attribute are fine, and it is only removed where
+        // the filesystem supports setting it
+        strip_quarantine(path.as_path()).expect("Failed to strip quarantine.");
+        if xattr::set(path.to_path_buf(), QUARANTINE_XATTR, b"0081;00000000;Safari;").is_ok() {
+            strip_quarantine(path.as_path()).expect("Failed to strip quarantine.");
+            assert_eq!(xattr::get(path.to_path_buf(), QUARANTINE_XATTR).unwrap(), None);
+        }
+
+        fs::remove_file(path.to_path_buf()).expect("Failed to remove test file.");
+    }
+
+    #[test]
+    /// Test [`copy_file`] with a sparse file
+    fn test_copy_file_sparse() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_copy_file_sparse"));
+        fs::create_dir_all(dir.to_path_buf()).expect("Failed to create test dir.");
+        let (old, new) = (dir.join(String::from("disk.img")), dir.join(String::from("copy.img")));
+
+        // 64 MiB, with data only at the start and the end
+        let mut file = fs::File::create(old.to_path_buf()).expect("Failed to create test file.");
+        mark_sparse(&file).expect("Failed to make test file sparse.");
+        file.write_all(b"start").unwrap();
+        file.seek(SeekFrom::Start(64 * 1024 * 1024 - 3)).unwrap();
+        file.write_all(b"end").unwrap();
+        drop(file);
+
+        copy_file(old.as_path(), new.as_path(), |_, _| true).expect("Failed to copy file.");
+        assert_eq!(fs::read(old.to_path_buf()).unwrap(), fs::read(new.to_path_buf()).unwrap());
+        // Only check that the holes were kept where the filesystem kept them
+        // in the original
+        if is_sparse(&fs::metadata(old.to_path_buf()).unwrap()) {
+            assert!(is_sparse(&fs::metadata(new.to_path_buf()).unwrap()));
+        }
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+
+    #[test]
+    /// Test [`copy_file`] with progress and cancellation
+    fn test_copy_file_progress() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_copy_file_progress"));
+        fs::create_dir_all(dir.to_path_buf()).expect("Failed to create test dir.");
+        let (old, new) = (dir.join(String::from("video.mp4")), dir.join(String::from("copy.mp4")));
+        fs::write(old.to_path_buf(), vec![1; LARGE_FILE_SIZE as usize + 1]).expect("Failed to write test file.");
+
+        // Progress is reported after every chunk, up to the whole file
+        let mut events = Vec::new();
+        copy_file(old.as_path(), new.as_path(), |copied, total| {
+            events.push((copied, total));
+            true
+        }).expect("Failed to copy file.");
+        assert_eq!(events.len(), LARGE_FILE_SIZE as usize / CHUNK_SIZE + 1);
+        assert_eq!(events.last(), Some(&(LARGE_FILE_SIZE + 1, LARGE_FILE_SIZE + 1)));
+        fs::remove_file(new.to_path_buf()).expect("Failed to remove test file.");
+
+        // Cancelled copies are cleaned up
+        let error = copy_file(old.as_path(), new.as_path(), |copied, _| copied < 2 * CHUNK_SIZE as u64).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Interrupted);
+        assert!(!new.exists());
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+}
+
+/// The size of the chunks that large and sparse files are copied in. Chunks of
+/// sparse files that are all zeros are skipped, leaving holes.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The size, in bytes, from which files are copied in chunks, reporting their
+/// progress after each one. Smaller files are copied in one go, which is faster.
+pub const LARGE_FILE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// The prefixes of the names of the extended attributes kept by copies on Unix:
+/// the Spotlight metadata of macOS, which holds the Finder tags
+/// (`com.apple.metadata:_kMDItemUserTags`), the Finder's own info, which holds
+/// its color labels, and the freedesktop.org tags and comments of Linux desktops.
+/// The quarantine attribute is kept too, so that copies behave like moves (see
+/// [`strip_quarantine`]).
+pub const PRESERVED_XATTRS: [&str; 4] = ["com.apple.metadata:", "com.apple.FinderInfo", "user.xdg.", QUARANTINE_XATTR];
+
+/// The name of the extended attribute that macOS marks downloaded files with,
+/// so that they are checked by Gatekeeper when they are first opened.
+pub const QUARANTINE_XATTR: &str = "com.apple.quarantine";
+
+/// The name of the alternate data stream that Windows marks downloaded files
+/// with, its "Mark of the Web", so that they are opened with care.
+pub const ZONE_IDENTIFIER_STREAM: &str = ":Zone.Identifier";
+
+/// How files get from the source into the target.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferMode {
+    /// Move the files, so that they are no longer in the source. Files are
+    /// renamed where the source and the target are on the same filesystem, and
+    /// copied and then removed where they aren't. This is the default.
+    #[default]
+    Move,
+    /// Copy the files, leaving the originals in the source.
+    Copy,
+    /// Hard link the files into the target, leaving the originals in the
+    /// source, without copying their contents, so that they take no more space.
+    /// The source and the target have to be on the same filesystem; files fail
+    /// to sort otherwise. A link is the same file as its original, so changing
+    /// one, as by giving it a new owner, changes the other.
+    Hardlink
+}
+
+/// Move `old` to `new`: rename it if we can, and copy it and remove the original
+/// if they are on different filesystems. The copy is flushed to disk before the
+/// original is removed, so that a crash in between can't lose both (see
+/// [`copy_file_inspected`]). See [`copy_file`] for `progress`.
+pub fn move_file(old: &Path, new: &Path, progress: impl FnMut(u64, u64) -> bool) -> io::Result<()> {
+    match fs::rename(old, new) {
+        Err(error) if error.kind() == io::ErrorKind::CrossesDevices => {
+            copy_file_inspected(old, new, |_| (), progress)?;
+            fs::remove_file(old)
+        },
+        result => result,
+    }
+}
+
+/// Copy `old` to `new`, keeping its permissions, its modification and access
+/// times, and, if it is sparse, its holes.
+/// 
+/// Large files (see [`LARGE_FILE_SIZE`]) and sparse ones are copied in chunks,
+/// and `progress` is called after each chunk with the number of bytes copied
+/// so far and the size of the file. If it returns [`false`], the copy stops, the
+/// partial copy is removed, and an [`io::ErrorKind::Interrupted`] error is
+/// returned.
+pub fn copy_file(old: &Path, new: &Path, progress: impl FnMut(u64, u64) -> bool) -> io::Result<()> {
+    let metadata = fs::metadata(old)?;
+    let sparse = is_sparse(&metadata);
+    if sparse || metadata.len() >= LARGE_FILE_SIZE {
+        // Unlike `fs::copy`, this doesn't copy the alternate data streams on
+        // Windows by itself
+        let result = copy_chunked(old, new, &metadata, sparse, |_| (), progress).and_then(|_| copy_streams(old, new));
+        if let Err(error) = result {
+            let _ = fs::remove_file(new);
+            return Err(error);
+        }
+    } else {
+        fs::copy(old, new)?;
+    }
+    copy_xattrs(old, new)?;
+    filetime::set_file_times(
+        new,
+        FileTime::from_last_access_time(&metadata),
+        FileTime::from_last_modification_time(&metadata))
+}
+
+/// Copy `old` to `new` like [`copy_file`], but always in chunks, whatever its
+/// size, passing each chunk to `inspect` as it is read, as to hash the file on
+/// its way. The copy is flushed to disk before this returns.
+pub fn copy_file_inspected(
+    old: &Path,
+    new: &Path,
+    inspect: impl FnMut(&[u8]),
+    progress: impl FnMut(u64, u64) -> bool) -> io::Result<()> {
+
+    let metadata = fs::metadata(old)?;
+    let result = copy_chunked(old, new, &metadata, is_sparse(&metadata), inspect, progress)
+        .and_then(|writer| writer.sync_all())
+        .and_then(|()| copy_streams(old, new))
+        .and_then(|()| copy_xattrs(old, new))
+        .and_then(|()| filetime::set_file_times(
+            new,
+            FileTime::from_last_access_time(&metadata),
+            FileTime::from_last_modification_time(&metadata)));
+    if result.is_err() {
+        let _ = fs::remove_file(new);
+    }
+    result
+}
+
+/// Remove the marks that downloaded files get from `path`: the
+/// [`QUARANTINE_XATTR`] on Unix, and the [`ZONE_IDENTIFIER_STREAM`] on Windows.
+/// Files without them are left alone.
+pub fn strip_quarantine(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    match xattr::get(path, QUARANTINE_XATTR) {
+        Ok(Some(_)) => xattr::remove(path, QUARANTINE_XATTR),
+        Err(error) if error.kind() != io::ErrorKind::Unsupported => Err(error),
+        _ => Ok(()),
+    }
+    #[cfg(windows)]
+    {
+        let mut stream = path.as_os_str().to_owned();
+        stream.push(ZONE_IDENTIFIER_STREAM);
+        match fs::remove_file(stream) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+    #[cfg(not(any(unix, windows)))]
+    Ok(())
+}
+
+#[cfg(unix)]
+/// Copy the extended attributes of `old` named with one of the
+/// [`PRESERVED_XATTRS`] to `new`. Filesystems without extended attributes, like
+/// FAT on a USB drive, are skipped, since there is nowhere to keep them.
+fn copy_xattrs(old: &Path, new: &Path) -> io::Result<()> {
+    let names = match xattr::list(old) {
+        Ok(names) => names,
+        Err(error) if error.kind() == io::ErrorKind::Unsupported => return Ok(()),
+        Err(error) => return Err(error),
+    };
+    for name in names {
+        let preserved = name.to_str().is_some_and(|name| PRESERVED_XATTRS.iter().any(|prefix| name.starts_with(prefix)));
+        if !preserved {
+            continue;
+        }
+        if let Some(value) = xattr::get(old, &name)? {
+            match xattr::set(new, &name, &value) {
+                Err(error) if error.kind() == io::ErrorKind::Unsupported => return Ok(()),
+                result => result?,
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+/// Extended attributes are not supported on this platform.
+fn copy_xattrs(_old: &Path, _new: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(windows)]
+/// Copy the alternate data streams of `old` to `new`, leaving out the main one,
+/// which has already been copied.
+fn copy_streams(old: &Path, new: &Path) -> io::Result<()> {
+    use std::ffi::{OsStr, OsString};
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+    use windows_sys::Win32::Foundation::{GetLastError, ERROR_HANDLE_EOF, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Storage::FileSystem::{
+        FindClose, FindFirstStreamW, FindNextStreamW, FindStreamInfoStandard, WIN32_FIND_STREAM_DATA
+    };
+
+    let wide: Vec<u16> = old.as_os_str().encode_wide().chain([0]).collect();
+    let mut data = WIN32_FIND_STREAM_DATA::default();
+    // SAFETY: `wide` is NUL-terminated, and `data` is the struct asked for
+    let handle = unsafe { FindFirstStreamW(wide.as_ptr(), FindStreamInfoStandard, (&mut data as *mut WIN32_FIND_STREAM_DATA).cast(), 0) };
+    if handle == INVALID_HANDLE_VALUE {
+        // Filesystems without streams, like FAT, have nothing to copy
+        return match unsafe { GetLastError() } {
+            ERROR_HANDLE_EOF => Ok(()),
+            code => Err(io::Error::from_raw_os_error(code as i32)),
+        };
+    }
+
+    let mut result = Ok(());
+    loop {
+        // Stream names look like `:Zone.Identifier:$DATA`, and the main one is
+        // `::$DATA`
+        let length = data.cStreamName.iter().position(|c| *c == 0).unwrap_or(data.cStreamName.len());
+        let name = OsString::from_wide(&data.cStreamName[..length]);
+        if name != OsStr::new("::$DATA") {
+            let (mut from, mut to) = (old.as_os_str().to_owned(), new.as_os_str().to_owned());
+            from.push(&name);
+            to.push(&name);
+            let copied = fs::File::open(from)
+                .and_then(|mut reader| fs::File::create(to).and_then(|mut writer| io::copy(&mut reader, &mut writer)));
+            if let Err(error) = copied {
+                result = Err(error);
+                break;
+            }
+        }
+        // SAFETY: `handle` is open until it is closed below
+        if unsafe { FindNextStreamW(handle, (&mut data as *mut WIN32_FIND_STREAM_DATA).cast()) } == 0 {
+            break;
+        }
+    }
+    // SAFETY: `handle` is a valid stream search handle
+    unsafe { FindClose(handle) };
+    result
+}
+
+#[cfg(not(windows))]
+/// Only NTFS has alternate data streams, so there is nothing to copy.
+fn copy_streams(_old: &Path, _new: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Copy `old`, whose metadata is `metadata`, to `new` in chunks, passing each
+/// one to `inspect` and calling `progress` after it. If `sparse` is [`true`],
+/// seek over the chunks that are all zeros instead of writing them, so that
+/// they are left as holes. Return the copy, still open.
+fn copy_chunked(
+    old: &Path,
+    new: &Path,
+    metadata: &fs::Metadata,
+    sparse: bool,
+    mut inspect: impl FnMut(&[u8]),
+    mut progress: impl FnMut(u64, u64) -> bool) -> io::Result<fs::File> {
+
+    let mut reader = fs::File::open(old)?;
+    let mut writer = fs::File::create(new)?;
+    if sparse {
+        mark_sparse(&writer)?;
+    }
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut copied = 0;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        let chunk = &buffer[..read];
+        inspect(chunk);
+        if sparse && chunk.iter().all(|byte| *byte == 0) {
+            writer.seek(SeekFrom::Current(read as i64))?;
+        } else {
+            writer.write_all(chunk)?;
+        }
+        copied += read as u64;
+        if !progress(copied, metadata.len()) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "the copy was cancelled"));
+        }
+    }
+
+    // A hole at the end is only kept by setting the length
+    writer.set_len(metadata.len())?;
+    writer.set_permissions(metadata.permissions())?;
+    Ok(writer)
+}
+
+#[cfg(unix)]
+/// Return [`true`] if the file with `metadata` is sparse: if it takes up less
+/// space on disk than its length.
+pub fn is_sparse(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512 < metadata.len()
+}
+
+#[cfg(windows)]
+/// Return [`true`] if the file with `metadata` is sparse: if NTFS marks it as
+/// one.
+pub fn is_sparse(metadata: &fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    use windows_sys::Win32::Storage::FileSystem::FILE_ATTRIBUTE_SPARSE_FILE;
+    metadata.file_attributes() & FILE_ATTRIBUTE_SPARSE_FILE != 0
+}
+
+#[cfg(not(any(unix, windows)))]
+/// Sparse files can't be detected on this platform, so they are copied in full.
+pub fn is_sparse(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(windows)]
+/// Mark `file` as sparse, so that the parts of it that are seeked over are left
+/// as holes instead of being filled with zeros.
+fn mark_sparse(file: &fs::File) -> io::Result<()> {
+    use std::os::windows::io::AsRawHandle;
+    use std::ptr;
+    use windows_sys::Win32::System::Ioctl::FSCTL_SET_SPARSE;
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+
+    let mut returned = 0;
+    // SAFETY: the handle is open for as long as `file` is borrowed, and
+    // FSCTL_SET_SPARSE without an input buffer marks the file as sparse
+    let marked = unsafe {
+        DeviceIoControl(file.as_raw_handle(), FSCTL_SET_SPARSE, ptr::null(), 0, ptr::null_mut(), 0, &mut returned, ptr::null_mut())
+    };
+    match marked {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(windows))]
+/// Files here get holes wherever they are seeked over without being marked
+/// first, so there is nothing to do.
+fn mark_sparse(_file: &fs::File) -> io::Result<()> {
+    Ok(())
+}
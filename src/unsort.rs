@@ -0,0 +1,117 @@
+//! Flattening a sorted target back out. See
+//! [`Sorter::unsort`](crate::Sorter::unsort).
+//!
+//! Sometimes a way of organizing files turns out to be a mistake. Unsorting
+//! moves every file in the target's tree back into a single flat directory,
+//! where it can be sorted again some other way, and removes the directories it
+//! leaves empty. Files can get back the names they had before they were sorted,
+//! if their original locations were recorded (see
+//! [`Sorter::origin_record`](crate::Sorter::origin_record)).
+
+use crate::manifest;
+use crate::origin::{self, ORIGIN_SIDECAR_SUFFIX};
+use crate::structs::File;
+use std::ffi::OsString;
+use std::path::Path;
+use std::{fs, io};
+use walkdir::WalkDir;
+
+#[cfg(test)]
+/// Tests for unsorting. Each test is named after the function it tests, prefixed
+/// with test.
+mod tests {
+
+    use std::fs;
+    use super::{flat_name, remove_empty_dirs, target_files, UnsortNames};
+    use crate::origin::{self, OriginRecord};
+    use crate::testing::{Entry, Sandbox};
+
+    #[test]
+    /// Test [`target_files`] and [`flat_name`]
+    fn test_flat_name() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("target/2023/07/2023 a.jpg"),
+            Entry::file("target/2023/b.jpg"),
+        ]).expect("Failed to create sandbox.");
+        let a = sandbox.path("target/2023/07/2023 a.jpg");
+        origin::record_origin(&sandbox.path("source/a.jpg"), &a, &OriginRecord::Sidecar).expect("Failed to record origin.");
+
+        // The origin sidecars go along with their files, rather than on their own
+        let files = target_files(&sandbox.target()).expect("Failed to walk target.");
+        assert_eq!(files, vec![a.copy(), sandbox.path("target/2023/b.jpg")]);
+        assert_eq!(flat_name(&a, UnsortNames::Keep), "2023 a.jpg");
+        assert_eq!(flat_name(&a, UnsortNames::Original), "a.jpg");
+        assert_eq!(flat_name(&files[1], UnsortNames::Original), "b.jpg");
+    }
+
+    #[test]
+    /// Test [`remove_empty_dirs`]
+    fn test_remove_empty_dirs() {
+        let sandbox = Sandbox::new(&[
+            Entry::dir("target/2022/06"),
+            Entry::file("target/2023/07/a.jpg"),
+        ]).expect("Failed to create sandbox.");
+        fs::remove_file(sandbox.path("target/2023/07/a.jpg").to_path_buf()).unwrap();
+        fs::create_dir_all(sandbox.path("target/2024").to_path_buf()).unwrap();
+        fs::write(sandbox.path("target/2024/b.jpg").to_path_buf(), "").unwrap();
+
+        let removed = remove_empty_dirs(&sandbox.target());
+        assert_eq!(removed.len(), 4);
+        assert_eq!(sandbox.files(), vec![String::from("target/2024/b.jpg")]);
+        assert!(sandbox.target().exists());
+    }
+}
+
+/// What to name the files that are unsorted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnsortNames {
+    /// Keep the names the files have in the target. This is the default.
+    #[default]
+    Keep,
+    /// Give the files back the names they had before they were sorted, as
+    /// recorded by [`Sorter::origin_record`](crate::Sorter::origin_record).
+    /// Files without a record keep the names they have.
+    Original
+}
+
+/// Return all the files in the tree of `target`, sorted by path, except its lock
+/// and counters files, and the origin sidecars (see [`origin::sidecar_path`]),
+/// which go along with their files.
+pub fn target_files(target: &File) -> io::Result<Vec<File>> {
+    let mut files: Vec<File> = manifest::walk_files(target)?
+        .into_iter()
+        .filter(|file| !file.file_name().ends_with(ORIGIN_SIDECAR_SUFFIX) || !is_origin_sidecar(file))
+        .collect();
+    files.sort_by(|a, b| a.pathbuf.cmp(&b.pathbuf));
+    Ok(files)
+}
+
+/// Return the name `file` is given in the flat directory, according to `names`.
+pub fn flat_name(file: &File, names: UnsortNames) -> OsString {
+    let original = match names {
+        UnsortNames::Original => origin::read_origin(file)
+            .and_then(|original| Path::new(&original).file_name().map(|name| name.to_os_string())),
+        UnsortNames::Keep => None,
+    };
+    original.unwrap_or_else(|| file.pathbuf.file_name().unwrap_or_default().to_os_string())
+}
+
+/// Remove the empty directories in the tree of `root`, deepest first, so that
+/// directories holding only empty directories go too. `root` itself is kept.
+/// Return the directories that were removed.
+pub fn remove_empty_dirs(root: &File) -> Vec<File> {
+    let mut removed = Vec::new();
+    for entry in WalkDir::new(&root.pathbuf).min_depth(1).contents_first(true).into_iter().flatten() {
+        if entry.file_type().is_dir() && fs::remove_dir(entry.path()).is_ok() {
+            removed.push(File::from(entry.path()));
+        }
+    }
+    removed
+}
+
+/// Return [`true`] if `file` is the origin sidecar of a file next to it.
+fn is_origin_sidecar(file: &File) -> bool {
+    let name = file.file_name();
+    let primary = file.pathbuf.with_file_name(&name[..name.len() - ORIGIN_SIDECAR_SUFFIX.len()]);
+    primary.is_file()
+}
@@ -0,0 +1,147 @@
+//! Renaming files in batches with io_uring, on Linux. See
+//! [`Backend::IoUring`](crate::execution::Backend::IoUring).
+//!
+//! Moving hundreds of thousands of small files on fast storage is bound by the
+//! cost of the system calls rather than by the storage itself. io_uring hands
+//! the kernel a whole batch of renames with a single system call, instead of one
+//! call for each file. This needs Linux 5.11 or later, and the `io-uring` feature.
+
+use io_uring::{opcode, types, IoUring, Probe};
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+#[cfg(test)]
+/// Tests for io_uring renames. Each test is named after the function it tests,
+/// prefixed with test.
+mod tests {
+
+    use std::{env, fs, path::Path};
+    use super::{is_supported, rename_all};
+    use crate::structs::{File, Join};
+
+    #[test]
+    /// Test [`rename_all`]
+    fn test_rename_all() {
+        let dir = File::from(env::temp_dir()).join(String::from("sorterylib_test_rename_all"));
+        fs::create_dir_all(dir.to_path_buf()).expect("Failed to create test dir.");
+        let (a, b) = (dir.join(String::from("a.txt")), dir.join(String::from("b.txt")));
+        fs::write(a.to_path_buf(), "a").expect("Failed to write test file.");
+        let missing = dir.join(String::from("missing.txt"));
+
+        // io_uring can be unavailable, as in some containers, in which case
+        // every rename fails as unsupported
+        let pairs: Vec<(&Path, &Path)> = vec![(a.as_path(), b.as_path()), (missing.as_path(), a.as_path())];
+        let results = rename_all(&pairs);
+        assert_eq!(results.len(), 2);
+        if is_supported() {
+            assert!(results[0].is_ok());
+            assert_eq!(results[1].as_ref().unwrap_err().kind(), std::io::ErrorKind::NotFound);
+            assert!(!a.exists() && b.exists());
+        } else {
+            assert!(results.iter().all(|result| result.as_ref().unwrap_err().kind() == std::io::ErrorKind::Unsupported));
+        }
+
+        fs::remove_dir_all(dir.to_path_buf()).expect("Failed to remove test dir.");
+    }
+}
+
+/// The number of renames handed to the kernel at once.
+pub const BATCH_SIZE: usize = 256;
+
+/// Return [`true`] if io_uring can rename files here, which it can't on kernels
+/// before 5.11 or where it is disabled, as in some containers.
+pub fn is_supported() -> bool {
+    ring(1).is_ok()
+}
+
+/// Return a new io_uring with room for `entries` renames, or an
+/// [`io::ErrorKind::Unsupported`] error if io_uring, or renaming with it, isn't
+/// available.
+fn ring(entries: usize) -> io::Result<IoUring> {
+    let unsupported = |_| io::Error::new(io::ErrorKind::Unsupported, "io_uring is not available");
+    let ring: IoUring = IoUring::new(entries.next_power_of_two() as u32).map_err(unsupported)?;
+    let mut probe = Probe::new();
+    ring.submitter().register_probe(&mut probe).map_err(unsupported)?;
+    if !probe.is_supported(opcode::RenameAt::CODE) {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "io_uring can't rename files on this kernel"));
+    }
+    Ok(ring)
+}
+
+/// Rename each of `pairs` from its first path to its second, handing them all
+/// to the kernel at once, and return the result of each rename, in order. Like
+/// [`std::fs::rename`], existing files are replaced. The renames of a batch can
+/// happen in any order.
+///
+/// Renames that io_uring couldn't even try fail too: with an
+/// [`io::ErrorKind::Unsupported`] error if io_uring, or renaming with it, isn't
+/// available (see [`is_supported`]), or with whatever went wrong handing them
+/// to the kernel. Either way, the caller can rename those files the usual way.
+pub fn rename_all(pairs: &[(&Path, &Path)]) -> Vec<io::Result<()>> {
+    if pairs.is_empty() {
+        return Vec::new();
+    }
+    let mut ring = match ring(pairs.len()) {
+        Ok(ring) => ring,
+        Err(error) => return pairs.iter().map(|_| Err(copy_error(&error))).collect(),
+    };
+    let mut results: Vec<Option<io::Result<()>>> = pairs.iter().map(|_| None).collect();
+
+    // The paths have to outlive the submission, until the renames complete
+    let paths: Vec<io::Result<(CString, CString)>> = pairs.iter().map(|(old, new)| Ok((c_path(old)?, c_path(new)?))).collect();
+    let mut submitted = 0;
+    for (i, paths) in paths.iter().enumerate() {
+        let (old, new) = match paths {
+            Ok(paths) => paths,
+            Err(error) => {
+                results[i] = Some(Err(copy_error(error)));
+                continue;
+            },
+        };
+        let entry = opcode::RenameAt::new(types::Fd(libc::AT_FDCWD), old.as_ptr(), types::Fd(libc::AT_FDCWD), new.as_ptr())
+            .build()
+            .user_data(i as u64);
+        // SAFETY: the paths live until every submitted rename has completed,
+        // below, or forever if that can't be waited for
+        match unsafe { ring.submission().push(&entry) } {
+            Ok(()) => submitted += 1,
+            Err(_) => results[i] = Some(Err(io::Error::other("the io_uring submission queue is full"))),
+        }
+    }
+
+    // Wait for every rename the kernel was handed, even if something goes wrong
+    // along the way, since it may still be reading their paths
+    let mut completed = 0;
+    while completed < submitted {
+        if let Err(error) = ring.submit_and_wait(1) {
+            if error.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            for result in results.iter_mut().filter(|result| result.is_none()) {
+                *result = Some(Err(copy_error(&error)));
+            }
+            std::mem::forget(paths);
+            std::mem::forget(ring);
+            break;
+        }
+        for completion in ring.completion() {
+            let result = completion.result();
+            results[completion.user_data() as usize] = Some(if result < 0 { Err(io::Error::from_raw_os_error(-result)) } else { Ok(()) });
+            completed += 1;
+        }
+    }
+    results.into_iter().flatten().collect()
+}
+
+/// Return a new error like `error`, which can't be cloned, for each of the
+/// renames that it stopped.
+fn copy_error(error: &io::Error) -> io::Error {
+    io::Error::new(error.kind(), error.to_string())
+}
+
+/// Return `path` as a C string, for the kernel.
+fn c_path(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes()).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "the path contains a NUL character"))
+}
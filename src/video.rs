@@ -0,0 +1,282 @@
+//! The lengths and resolutions of videos, for sorting short clips, 4K footage,
+//! and screen recordings into different places. See [`VideoInfo`] and
+//! [`VideoFilter`].
+//!
+//! Videos are probed by reading the headers of their MP4 and QuickTime (MOV)
+//! containers, the ones cameras and phones record, without reading the video
+//! itself: the movie header for the length, and the track headers for the
+//! resolution. Other containers, like Matroska, aren't probed.
+
+use crate::structs::File;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::time::Duration;
+
+#[cfg(test)]
+/// Tests for video properties. Each test is named after the function or struct
+/// it tests, prefixed with test.
+mod tests {
+
+    use std::time::Duration;
+    use super::{VideoFilter, VideoInfo};
+    use crate::testing::{Entry, Sandbox};
+
+    /// Return an MP4 box of the type `kind`, holding `contents`
+    fn mp4_box(kind: &[u8; 4], contents: &[u8]) -> Vec<u8> {
+        let mut bytes = ((contents.len() + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(kind);
+        bytes.extend_from_slice(contents);
+        bytes
+    }
+
+    /// Return an MP4 file of a video `seconds` long, of `width` by `height`
+    /// pixels, turned sideways if `rotated`
+    fn mp4(seconds: u32, width: u32, height: u32, rotated: bool) -> Vec<u8> {
+        let mut mvhd = vec![0; 12];
+        mvhd.extend_from_slice(&1000_u32.to_be_bytes());
+        mvhd.extend_from_slice(&(seconds * 1000).to_be_bytes());
+        mvhd.resize(100, 0);
+
+        // The matrix of a rotated track swaps its axes
+        let mut tkhd = vec![0; 40];
+        let [a, b] = if rotated { [0, 0x10000_u32] } else { [0x10000, 0] };
+        for value in [a, b, 0, b.wrapping_neg(), a, 0, 0, 0, 0x40000000] {
+            tkhd.extend_from_slice(&value.to_be_bytes());
+        }
+        tkhd.extend_from_slice(&(width << 16).to_be_bytes());
+        tkhd.extend_from_slice(&(height << 16).to_be_bytes());
+        let sound = mp4_box(b"trak", &mp4_box(b"tkhd", &[0; 84]));
+        let video = mp4_box(b"trak", &mp4_box(b"tkhd", &tkhd));
+
+        let mut file = mp4_box(b"ftyp", b"isom");
+        file.extend(mp4_box(b"mdat", &[0; 64]));
+        file.extend(mp4_box(b"moov", &[mp4_box(b"mvhd", &mvhd), sound, video].concat()));
+        file
+    }
+
+    #[test]
+    /// Test [`VideoInfo`]
+    fn test_videoinfo() {
+        let sandbox = Sandbox::new(&[
+            Entry::file("source/clip.mp4").contents(mp4(12, 3840, 2160, false)),
+            Entry::file("source/upright.mov").contents(mp4(300, 1920, 1080, true)),
+            Entry::file("source/screen.mp4").contents(mp4(60, 2880, 1800, false)),
+            Entry::file("source/broken.mp4").contents("not a video"),
+        ]).expect("Failed to create sandbox.");
+        let read = |name: &str| VideoInfo::read(&sandbox.path(&format!("source/{}", name)));
+
+        let clip = read("clip.mp4").expect("Failed to read video.");
+        assert_eq!(clip, VideoInfo { duration: Duration::from_secs(12), width: 3840, height: 2160 });
+        assert_eq!((clip.resolution(), clip.length(), clip.class()), ("4k", "clip", "footage"));
+        let upright = read("upright.mov").expect("Failed to read video.");
+        assert_eq!((upright.width, upright.height), (1080, 1920));
+        assert_eq!((upright.resolution(), upright.length()), ("1080p", "long"));
+        assert_eq!(read("screen.mp4").expect("Failed to read video.").class(), "screen_recording");
+        assert_eq!(read("broken.mp4"), None);
+    }
+
+    #[test]
+    /// Test [`VideoFilter`]
+    fn test_videofilter() {
+        let video = VideoInfo { duration: Duration::from_secs(12), width: 3840, height: 2160 };
+        assert!(VideoFilter::default().allows(&video));
+        assert!(VideoFilter { min_duration: Some(10), min_height: Some(2160), ..Default::default() }.allows(&video));
+        assert!(!VideoFilter { max_duration: Some(10), ..Default::default() }.allows(&video));
+        assert!(!VideoFilter { max_height: Some(1080), ..Default::default() }.allows(&video));
+        assert!(!VideoFilter { screen_recordings: Some(true), ..Default::default() }.allows(&video));
+    }
+}
+
+/// The value of the video placeholders for files that aren't videos, or can't
+/// be probed.
+pub const UNKNOWN_VIDEO: &str = "unknown";
+
+/// Videos shorter than this many seconds are clips.
+pub const CLIP_LENGTH: u64 = 30;
+
+/// Videos shorter than this many seconds, but not clips, are short.
+pub const SHORT_LENGTH: u64 = 5 * 60;
+
+/// The resolutions that cameras record, by the shorter side, in pixels. Videos
+/// of other resolutions are screen recordings.
+pub const CAMERA_RESOLUTIONS: [u32; 7] = [480, 540, 720, 1080, 1440, 2160, 4320];
+
+/// The length and resolution of a video.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VideoInfo {
+    /// How long the video is.
+    pub duration: Duration,
+    /// The width of the video, in pixels, as it is played.
+    pub width: u32,
+    /// The height of the video, in pixels, as it is played.
+    pub height: u32
+}
+impl VideoInfo {
+
+    /// Return the length and resolution of the video at `path`, or [`None`] if
+    /// it isn't an MP4 or QuickTime video, or can't be read. Videos recorded
+    /// upright, whose tracks are turned sideways, are as tall as they are played.
+    pub fn read(path: &File) -> Option<VideoInfo> {
+        let mut file = fs::File::open(path.as_path()).ok()?;
+        let end = file.metadata().ok()?.len();
+        let moov = find_box(&mut file, 0, end, b"moov").ok()??;
+        let mvhd = read_box(&mut file, moov, b"mvhd").ok()??;
+
+        // The movie header's duration is in units of its timescale
+        let (timescale, duration) = match mvhd.first()? {
+            1 => (be_u32(&mvhd, 20)?, u64::from_be_bytes(mvhd.get(24..32)?.try_into().ok()?)),
+            _ => (be_u32(&mvhd, 12)?, be_u32(&mvhd, 16)? as u64),
+        };
+        let duration = Duration::from_millis(duration.checked_mul(1000)? / (timescale.max(1) as u64));
+
+        // The video track is the first one with a size; sound tracks have none
+        let mut start = moov.0;
+        while let Some(trak) = find_box(&mut file, start, moov.1, b"trak").ok()? {
+            start = trak.1;
+            let tkhd = match read_box(&mut file, trak, b"tkhd").ok()? {
+                Some(tkhd) => tkhd,
+                None => continue,
+            };
+            let at = if tkhd.first() == Some(&1) { 52 } else { 40 };
+            let (width, height) = (be_u32(&tkhd, at + 36)? >> 16, be_u32(&tkhd, at + 40)? >> 16);
+            if width == 0 || height == 0 {
+                continue;
+            }
+            let sideways = be_u32(&tkhd, at)? == 0 && be_u32(&tkhd, at + 4)? != 0;
+            return match sideways {
+                true => Some(VideoInfo { duration, width: height, height: width }),
+                false => Some(VideoInfo { duration, width, height }),
+            };
+        }
+        None
+    }
+
+    /// Return the resolution of the video, by its shorter side: `4k`, `1080p`,
+    /// `720p`, or `sd` for anything smaller.
+    pub fn resolution(&self) -> &'static str {
+        match self.width.min(self.height) {
+            2160.. => "4k",
+            1080.. => "1080p",
+            720.. => "720p",
+            _ => "sd",
+        }
+    }
+
+    /// Return the length of the video: `clip` if it is shorter than
+    /// [`CLIP_LENGTH`], `short` if it is shorter than [`SHORT_LENGTH`], and
+    /// `long` otherwise.
+    pub fn length(&self) -> &'static str {
+        match self.duration.as_secs() {
+            secs if secs < CLIP_LENGTH => "clip",
+            secs if secs < SHORT_LENGTH => "short",
+            _ => "long",
+        }
+    }
+
+    /// Return what the video probably is: `footage` if it has one of the
+    /// resolutions of [`CAMERA_RESOLUTIONS`], and `screen_recording` otherwise.
+    pub fn class(&self) -> &'static str {
+        match self.is_screen_recording() {
+            true => "screen_recording",
+            false => "footage",
+        }
+    }
+
+    /// Return [`true`] if the video doesn't have a camera's resolution (see
+    /// [`CAMERA_RESOLUTIONS`]), so it was probably recorded from a screen.
+    pub fn is_screen_recording(&self) -> bool {
+        !CAMERA_RESOLUTIONS.contains(&self.width.min(self.height))
+    }
+}
+
+/// The conditions videos have to meet to be sorted. Files that aren't videos, or
+/// can't be probed (see [`VideoInfo::read`]), are sorted as usual. In JSON
+/// configuration, it looks like `{"min_duration": 30, "min_height": 2160}`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub struct VideoFilter {
+    /// If [`Some`], videos shorter than this many seconds are left out.
+    #[serde(default)]
+    pub min_duration: Option<u64>,
+    /// If [`Some`], videos longer than this many seconds are left out.
+    #[serde(default)]
+    pub max_duration: Option<u64>,
+    /// If [`Some`], videos whose shorter side is less than this many pixels are
+    /// left out, so `Some(2160)` only lets 4K videos through.
+    #[serde(default)]
+    pub min_height: Option<u32>,
+    /// If [`Some`], videos whose shorter side is more than this many pixels are
+    /// left out.
+    #[serde(default)]
+    pub max_height: Option<u32>,
+    /// If [`Some`], only screen recordings are sorted, with `Some(true)`, or
+    /// only videos that aren't, with `Some(false)` (see
+    /// [`VideoInfo::is_screen_recording`]).
+    #[serde(default)]
+    pub screen_recordings: Option<bool>
+}
+impl VideoFilter {
+
+    /// Return [`true`] if the filter has no conditions, so nothing needs to be
+    /// probed.
+    pub fn is_empty(&self) -> bool {
+        *self == VideoFilter::default()
+    }
+
+    /// Return [`true`] if `video` meets all the conditions.
+    pub fn allows(&self, video: &VideoInfo) -> bool {
+        let (secs, side) = (video.duration.as_secs(), video.width.min(video.height));
+        self.min_duration.is_none_or(|min| secs >= min)
+            && self.max_duration.is_none_or(|max| secs <= max)
+            && self.min_height.is_none_or(|min| side >= min)
+            && self.max_height.is_none_or(|max| side <= max)
+            && self.screen_recordings.is_none_or(|screen| video.is_screen_recording() == screen)
+    }
+}
+
+/// Return the start and end of the contents of the first box of the type `kind`
+/// between `start` and `end` in `file`, or [`None`] if there isn't one.
+fn find_box(file: &mut fs::File, start: u64, end: u64, kind: &[u8; 4]) -> io::Result<Option<(u64, u64)>> {
+    let mut at = start;
+    while at + 8 <= end {
+        let mut header = [0; 16];
+        file.seek(SeekFrom::Start(at))?;
+        file.read_exact(&mut header[..8])?;
+        let (size, header_size) = match u32::from_be_bytes(header[..4].try_into().unwrap()) {
+            0 => (end - at, 8),
+            1 => {
+                file.read_exact(&mut header[8..])?;
+                (u64::from_be_bytes(header[8..].try_into().unwrap()), 16)
+            },
+            size => (size as u64, 8),
+        };
+        if size < header_size || at + size > end {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid box size"));
+        }
+        if &header[4..8] == kind {
+            return Ok(Some((at + header_size, at + size)));
+        }
+        at += size;
+    }
+    Ok(None)
+}
+
+/// Return the contents of the first box of the type `kind` inside `parent` in
+/// `file`, or [`None`] if there isn't one. Only small boxes, like headers, are
+/// read.
+fn read_box(file: &mut fs::File, parent: (u64, u64), kind: &[u8; 4]) -> io::Result<Option<Vec<u8>>> {
+    let (start, end) = match find_box(file, parent.0, parent.1, kind)? {
+        Some(found) if found.1 - found.0 <= 1024 => found,
+        _ => return Ok(None),
+    };
+    let mut contents = vec![0; (end - start) as usize];
+    file.seek(SeekFrom::Start(start))?;
+    file.read_exact(&mut contents)?;
+    Ok(Some(contents))
+}
+
+/// Return the big-endian [`u32`] at `at` in `bytes`, if it is long enough.
+fn be_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}